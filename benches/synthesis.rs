@@ -0,0 +1,57 @@
+//! Synthesis benchmarks
+//!
+//! `cargo bench` times single-sample waveform generation and full mixes of a
+//! 256-sample buffer with a growing number of held notes, so changes to the
+//! synthesis code can be compared run to run.
+
+use codebeats::audio_engine::AudioState;
+use codebeats::waveforms::{Waveform, get_all_waveforms};
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+
+const SAMPLE_RATE: f32 = 44100.0;
+const BUFFER_SIZE: usize = 256;
+
+fn waveforms() -> Vec<(&'static str, Waveform)> {
+    get_all_waveforms()
+        .into_iter()
+        .filter_map(|(name, _)| Some((name, name.parse().ok()?)))
+        .collect()
+}
+
+fn bench_waveforms(c: &mut Criterion) {
+    let mut group = c.benchmark_group("waveform");
+    for (name, waveform) in waveforms() {
+        group.bench_function(name, |b| {
+            let mut phase = 0.0;
+            b.iter(|| {
+                phase = (phase + 440.0 / SAMPLE_RATE) % 1.0;
+                waveform.generate_sample(black_box(phase), 440.0, SAMPLE_RATE)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_mix(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mix");
+    for (name, waveform) in waveforms() {
+        for voices in [1, 8, 32] {
+            let mut state = AudioState::new(SAMPLE_RATE, waveform, 0.5, 1200.0);
+            for voice in 0..voices {
+                // Spread the voices over four octaves from A2, as `codebeats benchmark` does
+                let frequency = 110.0 * 2.0_f32.powf((voice % 48) as f32 / 12.0);
+                state.start_note_with_id(&format!("voice-{}", voice), frequency, 0.5);
+            }
+            let mut buffer = vec![0.0; BUFFER_SIZE];
+            group.bench_with_input(BenchmarkId::new(name, voices), &voices, |b, _| {
+                b.iter(|| {
+                    state.fill_block(black_box(&mut buffer));
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_waveforms, bench_mix);
+criterion_main!(benches);
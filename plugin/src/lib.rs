@@ -0,0 +1,325 @@
+//! CodeBeats as a CLAP/VST3 instrument
+//!
+//! Plays MIDI notes from the host through `codebeats::instrument::Instrument`,
+//! so the waveforms and embedded configs sound the same as in the standalone
+//! app. A note plays the config key mapped to its pitch (with that key's
+//! volume, waveform, envelope and samples); notes no key plays use the
+//! selected waveform.
+
+use codebeats::audio_engine::MAX_FILTER_CUTOFF;
+use codebeats::embedded_configs;
+use codebeats::instrument::{Instrument, PreparedConfig};
+use codebeats::waveforms::{Waveform, get_all_waveforms};
+use nih_plug::prelude::*;
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+
+/// Config parameter value that plays the waveform alone (always index 0)
+const NO_CONFIG: &str = "none";
+
+/// Work done away from the audio thread
+enum Task {
+    /// Load the config at an index of `config_names`, with its samples
+    LoadConfig { index: i32, sample_rate: f32 },
+}
+
+struct CodeBeatsPlugin {
+    params: Arc<CodeBeatsParams>,
+    instrument: Option<Instrument>,
+    /// Waveforms in the order of the waveform parameter
+    waveforms: Vec<Option<Waveform>>,
+    /// Waveform and config the instrument was last asked for, to spot parameter changes
+    waveform: Option<i32>,
+    config: Option<i32>,
+    /// Config loaded by the background task, waiting to be switched to
+    loaded_config: Arc<Mutex<Option<(i32, PreparedConfig)>>>,
+}
+
+#[derive(Params)]
+struct CodeBeatsParams {
+    /// Index into `get_all_waveforms`
+    #[id = "waveform"]
+    waveform: IntParam,
+    /// Index into `config_names`
+    #[id = "config"]
+    config: IntParam,
+    #[id = "volume"]
+    volume: FloatParam,
+    #[id = "cutoff"]
+    cutoff: FloatParam,
+}
+
+/// Names the config parameter chooses from: none, then the embedded configs
+fn config_names() -> Vec<&'static str> {
+    std::iter::once(NO_CONFIG)
+        .chain(embedded_configs::get_config_names())
+        .collect()
+}
+
+/// Name at an index, or an empty string past the end
+fn name_at(names: &[&'static str], index: i32) -> &'static str {
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| names.get(index))
+        .copied()
+        .unwrap_or_default()
+}
+
+impl Default for CodeBeatsParams {
+    fn default() -> Self {
+        let waveforms: Vec<&'static str> = get_all_waveforms()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let configs = config_names();
+
+        Self {
+            waveform: IntParam::new(
+                "Waveform",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: waveforms.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(move |index| {
+                name_at(&waveforms, index).to_string()
+            })),
+            config: IntParam::new(
+                "Config",
+                0,
+                IntRange::Linear {
+                    min: 0,
+                    max: configs.len() as i32 - 1,
+                },
+            )
+            .with_value_to_string(Arc::new(move |index| name_at(&configs, index).to_string())),
+            // The engine ramps volume and cutoff changes itself
+            volume: FloatParam::new("Volume", 0.7, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage())
+                .with_unit("%"),
+            // At the top of the range the filter is bypassed
+            cutoff: FloatParam::new(
+                "Filter Cutoff",
+                MAX_FILTER_CUTOFF,
+                FloatRange::Skewed {
+                    min: 100.0,
+                    max: MAX_FILTER_CUTOFF,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+        }
+    }
+}
+
+impl Default for CodeBeatsPlugin {
+    fn default() -> Self {
+        Self {
+            params: Arc::new(CodeBeatsParams::default()),
+            instrument: None,
+            waveforms: get_all_waveforms()
+                .into_iter()
+                .map(|(name, _)| name.parse().ok())
+                .collect(),
+            waveform: None,
+            config: None,
+            loaded_config: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl CodeBeatsPlugin {
+    /// Bring the instrument in line with the parameters
+    ///
+    /// A new config is loaded (samples and all) by `load` off the audio
+    /// thread; the instrument switches to it once it is ready.
+    fn apply_params(&mut self, load: impl FnOnce(Task)) {
+        let Some(ref mut instrument) = self.instrument else {
+            return;
+        };
+
+        let waveform = self.params.waveform.value();
+        if self.waveform != Some(waveform) {
+            if let Some(parsed) = usize::try_from(waveform)
+                .ok()
+                .and_then(|index| self.waveforms.get(index).copied().flatten())
+            {
+                instrument.state_mut().set_waveform(parsed);
+            }
+            self.waveform = Some(waveform);
+        }
+
+        let config = self.params.config.value();
+        if self.config != Some(config) {
+            // Index 0 is `NO_CONFIG`
+            if config == 0 {
+                instrument.clear_config();
+            } else {
+                load(Task::LoadConfig {
+                    index: config,
+                    sample_rate: instrument.state_mut().sample_rate(),
+                });
+            }
+            self.config = Some(config);
+        }
+
+        // Skip the switch while the background task holds the lock; it is
+        // picked up on a later block
+        if let Ok(mut loaded) = self.loaded_config.try_lock()
+            && let Some((index, prepared)) = loaded.take()
+            && self.config == Some(index)
+        {
+            instrument.apply_config(prepared);
+        }
+
+        let state = instrument.state_mut();
+        state.set_volume(self.params.volume.value());
+        state.set_filter_cutoff(self.params.cutoff.value());
+    }
+}
+
+impl Plugin for CodeBeatsPlugin {
+    const NAME: &'static str = "CodeBeats";
+    const VENDOR: &'static str = "CodeBeats";
+    const URL: &'static str = "https://github.com/guiyuanju/codebeats";
+    const EMAIL: &'static str = "";
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: None,
+            main_output_channels: NonZeroU32::new(2),
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: None,
+            main_output_channels: NonZeroU32::new(1),
+            ..AudioIOLayout::const_default()
+        },
+    ];
+
+    const MIDI_INPUT: MidiConfig = MidiConfig::Basic;
+    const SAMPLE_ACCURATE_AUTOMATION: bool = false;
+
+    type SysExMessage = ();
+    type BackgroundTask = Task;
+
+    fn params(&self) -> Arc<dyn Params> {
+        self.params.clone()
+    }
+
+    fn task_executor(&mut self) -> TaskExecutor<Self> {
+        let loaded_config = self.loaded_config.clone();
+        Box::new(move |task| match task {
+            Task::LoadConfig { index, sample_rate } => {
+                let name = name_at(&config_names(), index);
+                match embedded_configs::load_config(name) {
+                    Ok(keyboard_config) => {
+                        let prepared = Instrument::prepare_config(&keyboard_config, sample_rate);
+                        for warning in &prepared.warnings {
+                            nih_warn!("{}", warning);
+                        }
+                        *loaded_config.lock().unwrap() = Some((index, prepared));
+                    }
+                    Err(e) => nih_warn!("Could not load config '{}': {}", name, e),
+                }
+            }
+        })
+    }
+
+    fn initialize(
+        &mut self,
+        _audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        context: &mut impl InitContext<Self>,
+    ) -> bool {
+        self.instrument = Some(Instrument::new(
+            buffer_config.sample_rate,
+            Waveform::Electronic,
+            self.params.volume.value(),
+            self.params.cutoff.value(),
+        ));
+        self.waveform = None;
+        self.config = None;
+        // Not on the audio thread yet: load the config right away
+        self.apply_params(|task| context.execute(task));
+        true
+    }
+
+    fn reset(&mut self) {
+        if let Some(ref mut instrument) = self.instrument {
+            instrument.all_notes_off();
+        }
+    }
+
+    fn process(
+        &mut self,
+        buffer: &mut Buffer,
+        _aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        self.apply_params(|task| context.execute_background(task));
+        let Some(ref mut instrument) = self.instrument else {
+            return ProcessStatus::Normal;
+        };
+
+        let samples = buffer.samples();
+        let channels = buffer.as_slice();
+        let mut next_event = context.next_event();
+        let mut block_start = 0;
+
+        // Render between note events so each starts on its own sample
+        while block_start < samples {
+            while let Some(event) = next_event {
+                if event.timing() as usize > block_start {
+                    break;
+                }
+                match event {
+                    NoteEvent::NoteOn { note, velocity, .. } => instrument.note_on(note, velocity),
+                    NoteEvent::NoteOff { note, .. } => instrument.note_off(note),
+                    NoteEvent::Choke { note, .. } => instrument.note_off(note),
+                    _ => (),
+                }
+                next_event = context.next_event();
+            }
+
+            let block_end =
+                next_event.map_or(samples, |event| (event.timing() as usize).min(samples));
+            let Some((first, rest)) = channels.split_first_mut() else {
+                break;
+            };
+            instrument.process(&mut first[block_start..block_end]);
+            for channel in rest {
+                channel[block_start..block_end].copy_from_slice(&first[block_start..block_end]);
+            }
+            block_start = block_end;
+        }
+
+        ProcessStatus::KeepAlive
+    }
+}
+
+impl ClapPlugin for CodeBeatsPlugin {
+    const CLAP_ID: &'static str = "com.github.guiyuanju.codebeats";
+    const CLAP_DESCRIPTION: Option<&'static str> =
+        Some("Programming-optimized keyboard synth with CodeBeats waveforms and configs");
+    const CLAP_MANUAL_URL: Option<&'static str> = Some(Self::URL);
+    const CLAP_SUPPORT_URL: Option<&'static str> = None;
+    const CLAP_FEATURES: &'static [ClapFeature] = &[
+        ClapFeature::Instrument,
+        ClapFeature::Synthesizer,
+        ClapFeature::Mono,
+    ];
+}
+
+impl Vst3Plugin for CodeBeatsPlugin {
+    const VST3_CLASS_ID: [u8; 16] = *b"CodeBeatsSynth01";
+    const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] =
+        &[Vst3SubCategory::Instrument, Vst3SubCategory::Synth];
+}
+
+nih_export_clap!(CodeBeatsPlugin);
+nih_export_vst3!(CodeBeatsPlugin);
@@ -0,0 +1,142 @@
+//! Engine actions module
+//!
+//! This module defines the actions that configured triggers (such as input
+//! sequences, chords and runtime hotkeys) can run on the engine, beyond
+//! simply playing a sound.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Name under which the built-in fart sample can be played
+pub const FART_SAMPLE_NAME: &str = "fart";
+
+/// An action the engine can run when a trigger fires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    /// Play a named sample from the config's `samples` (or "fart")
+    PlaySample {
+        sample: String,
+        #[serde(default = "default_action_volume")]
+        volume: f32,
+    },
+    /// Switch to another waveform (e.g. "saw")
+    SetWaveform { waveform: String },
+    /// Load another keyboard configuration (embedded name or JSON file path)
+    LoadConfig { config: String },
+    /// Toggle muting of all output
+    ToggleMute,
+    /// Change the master volume by a relative amount
+    AdjustVolume { delta: f32 },
+    /// Switch to the next waveform
+    CycleWaveform,
+    /// Pause or resume the engine (paused engines ignore keys and stay silent)
+    TogglePause,
+}
+
+fn default_action_volume() -> f32 {
+    0.7
+}
+
+/// Default global shortcut for pausing and resuming the engine
+pub const DEFAULT_PAUSE_HOTKEY: &str = "Ctrl+Shift+F10";
+
+/// Runtime hotkeys: key combos (e.g. "Ctrl+Alt+M") mapped to actions
+pub type HotkeyMap = HashMap<String, Action>;
+
+/// Default hotkeys for tweaking the sound without restarting
+pub fn default_hotkeys() -> HotkeyMap {
+    HashMap::from([
+        ("Ctrl+Alt+M".to_string(), Action::ToggleMute),
+        (
+            "Ctrl+Alt+Up".to_string(),
+            Action::AdjustVolume { delta: 0.1 },
+        ),
+        (
+            "Ctrl+Alt+Down".to_string(),
+            Action::AdjustVolume { delta: -0.1 },
+        ),
+        ("Ctrl+Alt+W".to_string(), Action::CycleWaveform),
+    ])
+}
+
+/// Load a hotkey map from a JSON file
+pub fn load_hotkeys<P: AsRef<Path>>(path: P) -> Result<HotkeyMap, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let hotkeys: HotkeyMap = serde_json::from_str(&content)?;
+    Ok(hotkeys)
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::PlaySample { sample, .. } => write!(f, "play sample '{}'", sample),
+            Action::SetWaveform { waveform } => write!(f, "set waveform to {}", waveform),
+            Action::LoadConfig { config } => write!(f, "load config '{}'", config),
+            Action::ToggleMute => write!(f, "toggle mute"),
+            Action::AdjustVolume { delta } => write!(f, "adjust volume by {:+.2}", delta),
+            Action::CycleWaveform => write!(f, "cycle waveform"),
+            Action::TogglePause => write!(f, "pause/resume"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_deserialization() {
+        let json = r#"[
+            { "type": "play_sample", "sample": "chime" },
+            { "type": "set_waveform", "waveform": "saw" },
+            { "type": "load_config", "config": "rust" },
+            { "type": "toggle_mute" },
+            { "type": "adjust_volume", "delta": -0.1 }
+        ]"#;
+        let actions: Vec<Action> = serde_json::from_str(json).unwrap();
+
+        assert_eq!(
+            actions,
+            vec![
+                Action::PlaySample {
+                    sample: "chime".to_string(),
+                    volume: 0.7
+                },
+                Action::SetWaveform {
+                    waveform: "saw".to_string()
+                },
+                Action::LoadConfig {
+                    config: "rust".to_string()
+                },
+                Action::ToggleMute,
+                Action::AdjustVolume { delta: -0.1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hotkey_map_deserialization() {
+        let json = r#"{
+            "Ctrl+Shift+M": { "type": "toggle_mute" },
+            "Ctrl+Shift+W": { "type": "cycle_waveform" }
+        }"#;
+        let hotkeys: HotkeyMap = serde_json::from_str(json).unwrap();
+        assert_eq!(hotkeys["Ctrl+Shift+M"], Action::ToggleMute);
+        assert_eq!(hotkeys["Ctrl+Shift+W"], Action::CycleWaveform);
+
+        assert_eq!(default_hotkeys().len(), 4);
+    }
+
+    #[test]
+    fn test_action_display() {
+        assert_eq!(Action::ToggleMute.to_string(), "toggle mute");
+        assert_eq!(
+            Action::AdjustVolume { delta: 0.1 }.to_string(),
+            "adjust volume by +0.10"
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Focused application detection module
+//!
+//! This module reports the name of the application owning the focused window,
+//! polled on a background thread since the platform queries shell out to
+//! system tools:
+//! - macOS: `osascript` asking System Events for the frontmost process, reported
+//!   as "Name (bundle.identifier)"
+//! - Linux (X11): `xprop` reading the active window's WM_CLASS
+//! - Other platforms: not supported
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How often the focused application is polled
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Background poller tracking the focused application
+pub struct ActiveAppMonitor {
+    current: Arc<Mutex<Option<String>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Default for ActiveAppMonitor {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+impl ActiveAppMonitor {
+    /// Start polling the focused application
+    pub fn start() -> Self {
+        let current = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let current_clone = current.clone();
+        let stop_clone = stop.clone();
+        thread::spawn(move || {
+            while !stop_clone.load(Ordering::Relaxed) {
+                let app = focused_application();
+                if let Ok(mut current) = current_clone.lock() {
+                    *current = app;
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+        Self { current, stop }
+    }
+
+    /// Name of the focused application at the last poll, if known
+    pub fn current(&self) -> Option<String> {
+        self.current.lock().ok().and_then(|current| current.clone())
+    }
+}
+
+impl Drop for ActiveAppMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Query the name of the focused application
+#[cfg(target_os = "macos")]
+pub fn focused_application() -> Option<String> {
+    command_output(
+        "osascript",
+        &[
+            "-e",
+            "tell application \"System Events\" to tell (first application process whose frontmost is true) to return name & \" (\" & bundle identifier & \")\"",
+        ],
+    )
+}
+
+/// Query the name of the focused application
+#[cfg(target_os = "linux")]
+pub fn focused_application() -> Option<String> {
+    let root = command_output("xprop", &["-root", "_NET_ACTIVE_WINDOW"])?;
+    let window_id = root.split_whitespace().last()?;
+    let class = command_output("xprop", &["-id", window_id, "WM_CLASS"])?;
+    parse_wm_class(&class)
+}
+
+/// Query the name of the focused application
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub fn focused_application() -> Option<String> {
+    None
+}
+
+/// Run a command and return its trimmed stdout if it succeeded
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Extract the class name from `xprop` output like `WM_CLASS(STRING) = "code", "Code"`
+#[cfg(any(target_os = "linux", test))]
+fn parse_wm_class(output: &str) -> Option<String> {
+    let (_, values) = output.split_once('=')?;
+    values
+        .split(',')
+        .map(|value| value.trim().trim_matches('"'))
+        .rfind(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wm_class() {
+        assert_eq!(
+            parse_wm_class(r#"WM_CLASS(STRING) = "gnome-terminal-server", "Gnome-terminal""#),
+            Some("Gnome-terminal".to_string())
+        );
+        assert_eq!(
+            parse_wm_class(r#"WM_CLASS(STRING) = "slack""#),
+            Some("slack".to_string())
+        );
+        assert_eq!(parse_wm_class("WM_CLASS:  not found."), None);
+    }
+}
@@ -1,40 +1,299 @@
 //! Audio engine with ADSR envelope system and state management
 
-use crate::audio_samples::{AudioSample, SamplePlayback};
+use crate::audio_samples::{AudioSample, SamplePlayback, VelocityLayer, select_velocity_layer};
 use crate::embedded_configs;
+use crate::keyboard_config::{AdsrConfig, FilterEnvelope, KeyMapping, KeyboardConfig};
+use crate::keyboard_mapping::midi_to_frequency;
+use crate::keycode::Keycode;
+use crate::modulation::{Lfo, LfoTarget, ModMatrix, ModSource, Vibrato, wpm_source};
+use crate::soundfont::{self, SoundFontPreset, SoundFontVoice};
 use crate::waveforms::Waveform;
-use device_query::Keycode;
 use std::collections::HashMap;
-use std::time::{Duration, Instant};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Time for the output to fade out once the idle timeout has passed (seconds)
+const IDLE_FADE_OUT_TIME: f32 = 2.0;
+/// Time for the output to fade back in on the next key press (seconds)
+const IDLE_FADE_IN_TIME: f32 = 0.02;
+
+/// Time constant of the ramp that volume and filter changes follow (seconds);
+/// a change is all but complete after four of these, about 20 ms
+const PARAMETER_SMOOTHING_TIME: f32 = 0.005;
+
+/// Magnitude below which DSP state is flushed to zero (far below audibility,
+/// but well above the denormal range where float math gets very slow)
+const DENORMAL_THRESHOLD: f32 = 1e-20;
+
+/// Interval since the previous key press (seconds) at or below which a press
+/// gets full velocity
+const FAST_PRESS_INTERVAL: f32 = 0.08;
+/// Interval at or above which a press gets the lowest velocity
+const SLOW_PRESS_INTERVAL: f32 = 0.6;
+/// Volume of a note at the lowest velocity, relative to full velocity
+const MIN_VELOCITY_GAIN: f32 = 0.6;
+/// Low-pass cutoff of a note at the lowest velocity (Hz); it opens up with
+/// velocity until a full-velocity note is unfiltered
+const MIN_VELOCITY_CUTOFF: f32 = 2000.0;
+/// Longest interval between key presses counted towards the typing speed (seconds)
+const MAX_WPM_INTERVAL: f32 = 2.0;
+/// Weight of each new interval in the smoothed typing speed
+const WPM_SMOOTHING: f32 = 0.2;
+
+/// Filter cutoffs at or above this leave the output unfiltered (Hz)
+pub const MAX_FILTER_CUTOFF: f32 = 20000.0;
+/// Lowest accepted filter cutoff (Hz)
+const MIN_FILTER_CUTOFF: f32 = 20.0;
+/// Largest transposition in either direction (semitones)
+pub const MAX_TRANSPOSE: i32 = 24;
+
+/// Output level of the most recent audio buffer, shared with front ends
+///
+/// Values are stored as f32 bits in atomics so the audio callback never locks.
+#[derive(Debug, Default)]
+pub struct OutputLevel {
+    rms: AtomicU32,
+    peak: AtomicU32,
+}
+
+impl OutputLevel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measure a buffer of output samples
+    pub fn update(&self, samples: &[f32]) {
+        if samples.is_empty() {
+            return;
+        }
+
+        let peak = samples
+            .iter()
+            .fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+        let sum_squares: f32 = samples.iter().map(|sample| sample * sample).sum();
+        let rms = (sum_squares / samples.len() as f32).sqrt();
+
+        self.rms.store(rms.to_bits(), Ordering::Relaxed);
+        self.peak.store(peak.to_bits(), Ordering::Relaxed);
+    }
+
+    /// RMS level of the last buffer (1.0 = full scale)
+    pub fn rms(&self) -> f32 {
+        f32::from_bits(self.rms.load(Ordering::Relaxed))
+    }
+
+    /// Peak level of the last buffer (1.0 = full scale, above clips)
+    pub fn peak(&self) -> f32 {
+        f32::from_bits(self.peak.load(Ordering::Relaxed))
+    }
+}
+
+/// Audio dropouts detected by the output callback, shared with front ends
+///
+/// - Underrun: the device played past the end of the last buffer before the
+///   next one arrived (playback jumped ahead of the rendered audio)
+/// - Overrun: a buffer took longer to render than it lasts, so the callback
+///   can't keep up with the device
+#[derive(Debug, Default)]
+pub struct XrunCounter {
+    underruns: AtomicU64,
+    overruns: AtomicU64,
+}
+
+impl XrunCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check one buffer
+    ///
+    /// `since_previous` is the playback time between the previous buffer's start
+    /// and this one's (None for the first buffer), `previous_duration` how long
+    /// the previous buffer lasts, and `render_time` how long this buffer of
+    /// `duration` took to render.
+    pub fn record(
+        &self,
+        since_previous: Option<Duration>,
+        previous_duration: Duration,
+        render_time: Duration,
+        duration: Duration,
+    ) {
+        // Allow for jitter in the device's timestamps
+        if since_previous.is_some_and(|gap| gap > previous_duration * 3 / 2) {
+            self.underruns.fetch_add(1, Ordering::Relaxed);
+        }
+        if render_time > duration {
+            self.overruns.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Buffers the device ran out of audio before
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+
+    /// Buffers that took longer to render than they last
+    pub fn overruns(&self) -> u64 {
+        self.overruns.load(Ordering::Relaxed)
+    }
+}
+
+/// Number of recent output samples kept for scope and spectrum displays
+pub const OUTPUT_HISTORY_SIZE: usize = 4096;
+
+/// Ring buffer of the most recent output samples, shared with front ends
+#[derive(Debug)]
+pub struct OutputHistory {
+    buffer: Mutex<(Vec<f32>, usize)>,
+}
+
+impl Default for OutputHistory {
+    fn default() -> Self {
+        Self::new(OUTPUT_HISTORY_SIZE)
+    }
+}
+
+impl OutputHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buffer: Mutex::new((vec![0.0; capacity.max(1)], 0)),
+        }
+    }
+
+    /// Record output samples, skipping them if a reader holds the lock
+    pub fn push(&self, samples: &[f32]) {
+        let Ok(mut guard) = self.buffer.try_lock() else {
+            return;
+        };
+        let (buffer, write_pos) = &mut *guard;
+        for &sample in samples {
+            buffer[*write_pos] = sample;
+            *write_pos = (*write_pos + 1) % buffer.len();
+        }
+    }
+
+    /// Copy of the recent samples, oldest first
+    pub fn snapshot(&self) -> Vec<f32> {
+        let guard = self.buffer.lock().unwrap();
+        let (buffer, write_pos) = &*guard;
+        let mut samples = buffer[*write_pos..].to_vec();
+        samples.extend_from_slice(&buffer[..*write_pos]);
+        samples
+    }
+}
+
+/// Velocity of key presses from the time since the previous one: fast typing
+/// plays loud and bright, slow typing soft and dark
+///
+/// Times are seconds on the audio clock, like the rate limiter's.
+#[derive(Debug, Default)]
+pub struct VelocityTracker {
+    last_press: Option<f64>,
+    /// Smoothed interval between presses, once there have been two
+    average_interval: Option<f32>,
+}
+
+impl VelocityTracker {
+    /// Record a key press and return its velocity from 0.0 to 1.0
+    ///
+    /// The first press counts as slow.
+    pub fn record_press(&mut self, now: f64) -> f32 {
+        let interval = self
+            .last_press
+            .map_or(SLOW_PRESS_INTERVAL, |last| (now - last) as f32);
+        if self.last_press.is_some() {
+            // Pauses count as slow typing rather than wiping out the average
+            let capped = interval.min(MAX_WPM_INTERVAL);
+            self.average_interval = Some(self.average_interval.map_or(capped, |average| {
+                average + (capped - average) * WPM_SMOOTHING
+            }));
+        }
+        self.last_press = Some(now);
+        ((SLOW_PRESS_INTERVAL - interval) / (SLOW_PRESS_INTERVAL - FAST_PRESS_INTERVAL))
+            .clamp(0.0, 1.0)
+    }
+
+    /// Typing speed in words (five characters) per minute, falling off once
+    /// typing stops
+    pub fn wpm(&self, now: f64) -> f32 {
+        match (self.last_press, self.average_interval) {
+            (Some(last), Some(average)) => 12.0 / average.max((now - last) as f32).max(0.01),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Volume multiplier for a velocity from 0.0 to 1.0
+pub fn velocity_gain(velocity: f32) -> f32 {
+    MIN_VELOCITY_GAIN + (1.0 - MIN_VELOCITY_GAIN) * velocity.clamp(0.0, 1.0)
+}
+
+/// Low-pass cutoff (Hz) for a velocity from 0.0 to 1.0, rising exponentially
+/// so each step in velocity sounds alike
+pub fn velocity_cutoff(velocity: f32) -> f32 {
+    MIN_VELOCITY_CUTOFF * (MAX_FILTER_CUTOFF / MIN_VELOCITY_CUTOFF).powf(velocity.clamp(0.0, 1.0))
+}
+
+/// Largest humanize detune (cents), half a semitone
+pub const MAX_HUMANIZE_DETUNE: f32 = 50.0;
+
+/// Largest humanize delay (seconds)
+pub const MAX_HUMANIZE_TIMING: f32 = 0.05;
+
+/// Random variation of each key press, so repeated keys don't sound
+/// machine-gun identical
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Humanize {
+    /// Largest detune in either direction (cents)
+    pub detune_cents: f32,
+    /// Largest delay before a note starts (seconds)
+    pub timing_jitter: f32,
+}
+
+/// Number of keys the rate limiter tracks before forgetting idle ones
+const MAX_TRACKED_KEYS: usize = 256;
 
 /// Rate limiter to reduce volume for rapid successive key presses
+///
+/// Times are seconds on the audio clock, so rendering is independent of wall time.
 pub struct RateLimiter {
-    press_history: HashMap<String, Vec<Instant>>,
-    window_duration: Duration,
+    press_history: HashMap<String, Vec<f64>>,
+    window_duration: f64,
     volume_reduction_factor: f32,
 }
 
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RateLimiter {
     pub fn new() -> Self {
         Self {
             press_history: HashMap::new(),
-            window_duration: Duration::from_millis(500), // 500ms window
-            volume_reduction_factor: 0.7,                // Each rapid press reduces volume by 30%
+            window_duration: 0.5,         // 500ms window
+            volume_reduction_factor: 0.7, // Each rapid press reduces volume by 30%
         }
     }
 
     /// Record a key press and return volume multiplier based on recent press frequency
-    pub fn record_press_and_get_volume_multiplier(&mut self, key_id: &str) -> f32 {
-        let now = Instant::now();
+    pub fn record_press_and_get_volume_multiplier(&mut self, key_id: &str, now: f64) -> f32 {
+        // Forget keys with no press inside the window, so keys seen once
+        // (e.g. from jam peers) don't pile up
+        if self.press_history.len() >= MAX_TRACKED_KEYS {
+            let window = self.window_duration;
+            self.press_history
+                .retain(|_, history| history.last().is_some_and(|&t| now - t <= window));
+        }
 
         // Get or create press history for this key
-        let history = self
-            .press_history
-            .entry(key_id.to_string())
-            .or_insert_with(Vec::new);
+        let history = self.press_history.entry(key_id.to_string()).or_default();
 
         // Remove old presses outside the window
-        history.retain(|&press_time| now.duration_since(press_time) <= self.window_duration);
+        history.retain(|&press_time| now - press_time <= self.window_duration);
 
         // Calculate volume multiplier based on recent presses
         let rapid_press_count = history.len() as f32;
@@ -64,6 +323,19 @@ pub struct ADSRParams {
 }
 
 impl ADSRParams {
+    /// Default envelope for a waveform
+    pub fn for_waveform(waveform: Waveform) -> Self {
+        match waveform {
+            Waveform::Natural => Self::natural(),
+            Waveform::Electronic => Self::electronic(),
+            Waveform::Saw | Waveform::Square => Self::punchy(),
+            Waveform::Cyberpunk => Self::cyberpunk(),
+            Waveform::Triangle | Waveform::Harmonic { .. } => Self::electronic(),
+            Waveform::Fart => Self::fart(),
+            Waveform::Bass => Self::cyberpunk(), // Bass uses analog-style envelope
+        }
+    }
+
     pub fn natural() -> Self {
         Self {
             attack_time: 0.02,
@@ -130,6 +402,128 @@ impl ADSRParams {
             release_time: 2.0,
         }
     }
+
+    /// Create SoundFont ADSR (sample carries its own shape, short release)
+    pub fn soundfont() -> Self {
+        Self {
+            attack_time: 0.002,
+            decay_time: 0.0,
+            sustain_level: 1.0,
+            release_time: 0.2,
+        }
+    }
+}
+
+/// Per-key settings that replace the engine-wide ones for a single note
+#[derive(Clone, Debug, Default)]
+pub struct NoteOverrides {
+    /// Waveform (and its default envelope) used instead of the current one and any SoundFont
+    pub waveform: Option<Waveform>,
+    /// Envelope stages replacing those of the waveform's envelope
+    pub adsr: Option<AdsrConfig>,
+    /// Stereo position from -1.0 (left) to 1.0 (right) instead of the center
+    pub pan: Option<f32>,
+    /// Key velocity from 0.0 to 1.0 scaling the note's volume and brightness
+    /// (None plays at the given volume, unfiltered)
+    pub velocity: Option<f32>,
+    /// Pitch offset (cents)
+    pub detune_cents: f32,
+    /// Seconds of silence before the note starts
+    pub delay: f32,
+    /// Keyboard column from -1.0 (left) to 1.0 (right), for the modulation matrix
+    pub key_position: Option<f32>,
+    /// Resonant filter sweep used instead of the engine-wide one
+    pub filter_envelope: Option<FilterEnvelope>,
+}
+
+/// Zero for values so small they'd decay into denormals, which spike CPU
+/// use in recursive filters and long envelope tails
+#[inline]
+pub fn flush_denormal(value: f32) -> f32 {
+    if value.abs() < DENORMAL_THRESHOLD {
+        0.0
+    } else {
+        value
+    }
+}
+
+/// Samples between updates of a `NoteFilter`'s cutoff (and its `tan`)
+const FILTER_UPDATE_INTERVAL: u32 = 32;
+
+/// A note's own resonant low-pass following a `FilterEnvelope`
+///
+/// It's a state-variable filter in trapezoidal form, which stays stable at
+/// any cutoff and resonance. The cutoff follows the envelope at control
+/// rate, every `FILTER_UPDATE_INTERVAL` samples.
+#[derive(Debug, Clone)]
+pub struct NoteFilter {
+    envelope: FilterEnvelope,
+    sample_rate: f32,
+    /// Seconds since the note started
+    time: f32,
+    /// Cutoff multiplier set by the modulation matrix
+    pub cutoff_scale: f32,
+    /// Cutoff the coefficients were computed for
+    cutoff: f32,
+    /// Samples left until the cutoff is next updated
+    update_countdown: u32,
+    /// Damping, lower for more resonance
+    k: f32,
+    a1: f32,
+    a2: f32,
+    a3: f32,
+    ic1eq: f32,
+    ic2eq: f32,
+}
+
+impl NoteFilter {
+    pub fn new(envelope: FilterEnvelope, sample_rate: f32) -> Self {
+        let k = 2.0 - 1.95 * envelope.resonance.clamp(0.0, 1.0);
+        Self {
+            envelope,
+            sample_rate,
+            time: 0.0,
+            cutoff_scale: 1.0,
+            cutoff: 0.0,
+            update_countdown: 0,
+            k,
+            a1: 0.0,
+            a2: 0.0,
+            a3: 0.0,
+            ic1eq: 0.0,
+            ic2eq: 0.0,
+        }
+    }
+
+    /// Filter one sample, moving along the envelope
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        if self.update_countdown == 0 {
+            self.update_countdown = FILTER_UPDATE_INTERVAL;
+            let cutoff = (self.envelope.cutoff(self.time) * self.cutoff_scale)
+                .clamp(MIN_FILTER_CUTOFF, self.sample_rate * 0.45);
+            if cutoff != self.cutoff {
+                self.set_cutoff(cutoff);
+            }
+        }
+        self.update_countdown -= 1;
+        self.time += 1.0 / self.sample_rate;
+
+        let v3 = input - self.ic2eq;
+        let v1 = self.a1 * self.ic1eq + self.a2 * v3;
+        let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3;
+        self.ic1eq = flush_denormal(2.0 * v1 - self.ic1eq);
+        self.ic2eq = flush_denormal(2.0 * v2 - self.ic2eq);
+        v2
+    }
+
+    fn set_cutoff(&mut self, cutoff: f32) {
+        self.cutoff = cutoff;
+        let g = (std::f32::consts::PI * cutoff / self.sample_rate).tan();
+        self.a1 = 1.0 / (1.0 + g * (g + self.k));
+        self.a2 = g * self.a1;
+        self.a3 = g * self.a2;
+    }
 }
 
 /// Individual note state with envelope and synthesis parameters
@@ -141,9 +535,39 @@ pub struct NoteState {
     pub envelope_time: f32,
     pub adsr: ADSRParams,
     pub waveform: Waveform,
-    pub start_time: std::time::Instant,
+    /// Seconds the note has been sounding, counted in samples
+    pub hold_time: f32,
     pub current_hold_volume: f32,
     pub target_hold_volume: f32,
+    /// SoundFont voice replacing the oscillator, if a preset is loaded
+    pub soundfont_voice: Option<SoundFontVoice>,
+    /// Stereo position from -1.0 (left) to 1.0 (right)
+    pub pan: f32,
+    /// Coefficient of the note's one-pole low-pass (1.0 leaves it unfiltered)
+    pub lowpass_alpha: f32,
+    pub lowpass_state: f32,
+    /// Samples of silence left before the note starts
+    pub start_delay: usize,
+    /// Pitch wobble timed from the note's start
+    pub vibrato: Option<Vibrato>,
+    /// Position in the vibrato's cycle, from 0.0 to 1.0
+    pub vibrato_phase: f32,
+    /// Frequency multiplier set by the global LFO and the modulation matrix
+    pub pitch_bend: f32,
+    /// Low-pass cutoff (Hz) the modulation matrix's filter routes move from
+    pub cutoff: f32,
+    /// Volume multiplier and pan offset set by the modulation matrix
+    pub mod_gain: f32,
+    pub mod_pan: f32,
+    /// Keyboard column of the note's key (0.0 without one)
+    pub key_position: f32,
+    /// Latest envelope level, read by the modulation matrix
+    pub envelope_level: f32,
+    /// Resonant low-pass with its own envelope, after the one-pole low-pass
+    pub filter: Option<NoteFilter>,
+    /// Gain of a note fading out after its voice went to another note
+    /// (None while it plays normally)
+    pub steal_fade: Option<f32>,
 }
 
 impl NoteState {
@@ -156,9 +580,62 @@ impl NoteState {
             envelope_time: 0.0,
             adsr: adsr_params,
             waveform,
-            start_time: std::time::Instant::now(),
+            hold_time: 0.0,
             current_hold_volume: 1.0,
             target_hold_volume: 1.0,
+            soundfont_voice: None,
+            pan: 0.0,
+            lowpass_alpha: 1.0,
+            lowpass_state: 0.0,
+            start_delay: 0,
+            vibrato: None,
+            vibrato_phase: 0.0,
+            pitch_bend: 1.0,
+            cutoff: MAX_FILTER_CUTOFF,
+            mod_gain: 1.0,
+            mod_pan: 0.0,
+            key_position: 0.0,
+            envelope_level: 0.0,
+            filter: None,
+            steal_fade: None,
+        }
+    }
+
+    /// Create a note that plays a SoundFont voice instead of a waveform
+    pub fn with_soundfont_voice(
+        frequency: f32,
+        volume: f32,
+        adsr_params: ADSRParams,
+        voice: SoundFontVoice,
+    ) -> Self {
+        let mut note = Self::new(frequency, volume, adsr_params, Waveform::Electronic);
+        note.soundfont_voice = Some(voice);
+        note
+    }
+
+    /// Darken the note with a low-pass at `cutoff` Hz (MAX_FILTER_CUTOFF or
+    /// above leaves it unfiltered)
+    pub fn set_lowpass(&mut self, cutoff: f32, sample_rate: f32) {
+        self.lowpass_alpha = if cutoff >= MAX_FILTER_CUTOFF {
+            1.0
+        } else {
+            let cutoff = cutoff.max(MIN_FILTER_CUTOFF);
+            1.0 - (-2.0 * std::f32::consts::PI * cutoff / sample_rate).exp()
+        };
+    }
+
+    /// Run one sample through the note's low-pass and resonant filter
+    #[inline]
+    fn lowpass(&mut self, mut sample: f32) -> f32 {
+        if self.lowpass_alpha < 1.0 {
+            self.lowpass_state = flush_denormal(
+                self.lowpass_state + self.lowpass_alpha * (sample - self.lowpass_state),
+            );
+            sample = self.lowpass_state;
+        }
+        match self.filter {
+            Some(ref mut filter) => filter.process(sample),
+            None => sample,
         }
     }
 
@@ -166,7 +643,7 @@ impl NoteState {
     pub fn update_envelope(&mut self, dt: f32) -> f32 {
         self.envelope_time += dt;
 
-        match self.envelope_state {
+        let multiplier = match self.envelope_state {
             EnvelopeState::Attack => {
                 if self.envelope_time >= self.adsr.attack_time {
                     self.envelope_state = EnvelopeState::Decay;
@@ -199,35 +676,83 @@ impl NoteState {
                     self.adsr.sustain_level * (1.0 - progress * progress)
                 }
             }
-        }
+        };
+        self.envelope_level = flush_denormal(multiplier);
+        self.envelope_level
     }
 
     /// Generate audio sample for this note
     pub fn generate_sample(&mut self, sample_rate: f32, envelope_multiplier: f32) -> f32 {
-        // Generate waveform sample
-        let wave_sample = self
-            .waveform
-            .generate_sample(self.phase, self.frequency, sample_rate);
+        // Generate waveform sample (or the next SoundFont frame)
+        let wave_sample = match self.soundfont_voice {
+            Some(ref mut voice) => voice.next_sample(),
+            None => self
+                .waveform
+                .generate_sample(self.phase, self.frequency, sample_rate),
+        };
 
         // Update smooth hold duration volume
         self.update_smooth_hold_volume(sample_rate);
 
         // Apply envelope, base volume, and smooth hold duration reduction
-        let final_sample =
-            wave_sample * self.base_volume * envelope_multiplier * self.current_hold_volume;
+        let final_sample = self.lowpass(
+            wave_sample
+                * self.base_volume
+                * self.mod_gain
+                * envelope_multiplier
+                * self.current_hold_volume,
+        );
 
         // Update phase
-        self.phase += self.frequency / sample_rate;
+        self.advance_phase(sample_rate);
+
+        final_sample
+    }
+
+    /// Add this note to a block of samples
+    /// Returns true once the note has finished (the rest of the block is left as is)
+    pub fn render_block(&mut self, block: &mut [f32], sample_rate: f32) -> bool {
+        let delayed = self.start_delay.min(block.len());
+        self.start_delay -= delayed;
+        let block = &mut block[delayed..];
+
+        let dt = 1.0 / sample_rate;
+        for sample in block.iter_mut() {
+            let mut envelope_multiplier = self.update_envelope(dt);
+            if let Some(ref mut fade) = self.steal_fade {
+                *fade -= dt / STEAL_FADE_TIME;
+                if *fade <= 0.0 {
+                    return true;
+                }
+                envelope_multiplier *= *fade;
+            }
+            if self.is_finished(envelope_multiplier) {
+                return true;
+            }
+            *sample += self.generate_sample(sample_rate, envelope_multiplier);
+        }
+        false
+    }
+
+    /// Advance the oscillator by one sample, following the vibrato and pitch
+    /// bend, and the vibrato along its own cycle
+    #[inline]
+    fn advance_phase(&mut self, sample_rate: f32) {
+        let mut frequency = self.frequency * self.pitch_bend;
+        if let Some(ref vibrato) = self.vibrato {
+            frequency *= vibrato.ratio(self.vibrato_phase);
+            self.vibrato_phase = (self.vibrato_phase + vibrato.rate / sample_rate).fract();
+        }
+        self.phase += frequency / sample_rate;
         if self.phase >= 1.0 {
             self.phase -= 1.0;
         }
-
-        final_sample
     }
 
     /// Update smooth hold duration volume to prevent audio crackling
     fn update_smooth_hold_volume(&mut self, sample_rate: f32) {
-        let hold_duration = self.start_time.elapsed().as_secs_f32();
+        self.hold_time += 1.0 / sample_rate;
+        let hold_duration = self.hold_time;
 
         // Calculate target volume based on hold duration
         self.target_hold_volume = match hold_duration {
@@ -258,27 +783,306 @@ impl NoteState {
             self.envelope_state = EnvelopeState::Release;
             self.envelope_time = 0.0;
         }
+        if let Some(ref mut voice) = self.soundfont_voice {
+            voice.release();
+        }
     }
 
-    /// Check if note should be removed (fully released)
+    /// Check if note should be removed (fully released or out of sample data)
     pub fn is_finished(&self, envelope_multiplier: f32) -> bool {
-        matches!(self.envelope_state, EnvelopeState::Release) && envelope_multiplier <= 0.0
+        (matches!(self.envelope_state, EnvelopeState::Release) && envelope_multiplier <= 0.0)
+            || self
+                .soundfont_voice
+                .as_ref()
+                .is_some_and(|voice| voice.is_finished())
+    }
+}
+
+/// Left and right gains for a pan position from -1.0 (left) to 1.0 (right)
+///
+/// The far channel fades out while the near one stays at full level, so
+/// centered notes sound as they do in mono.
+pub fn pan_gains(pan: f32) -> (f32, f32) {
+    let pan = pan.clamp(-1.0, 1.0);
+    ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0))
+}
+
+/// Most notes that sound at once; another note replaces a releasing one
+/// (the oldest first), or failing that the oldest note
+pub const MAX_VOICES: usize = 64;
+
+/// Time a replaced note takes to fade out instead of stopping mid-cycle
+const STEAL_FADE_TIME: f32 = 0.005;
+
+/// What a voice plays for: a physical key, or the hash of a key ID
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum VoiceKey {
+    Keycode(Keycode),
+    Id(u64),
+}
+
+impl VoiceKey {
+    /// Key for a key ID, hashed so voices never hold a String
+    fn id(key_id: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        key_id.hash(&mut hasher);
+        VoiceKey::Id(hasher.finish())
+    }
+}
+
+/// A sounding note in a pool slot
+struct Voice {
+    key: VoiceKey,
+    note: NoteState,
+    /// Start order, for picking the oldest voice to replace
+    started: u64,
+}
+
+/// Fixed set of voice slots addressed by index
+///
+/// The slots are allocated once, so starting, rendering and finishing notes
+/// neither allocates nor moves other voices, unlike maps keyed by key ID.
+pub struct VoicePool {
+    slots: Vec<Option<Voice>>,
+    started: u64,
+    /// Notes whose slots were taken for new ones, fading out over
+    /// `STEAL_FADE_TIME` (allocated once, like the slots)
+    fading: Vec<NoteState>,
+}
+
+impl VoicePool {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: (0..capacity.max(1)).map(|_| None).collect(),
+            started: 0,
+            fading: Vec::with_capacity(capacity.max(1)),
+        }
+    }
+
+    /// Start a note, replacing the key's current note; returns the slot index
+    fn start(&mut self, key: VoiceKey, note: NoteState) -> usize {
+        let index = self
+            .index_of(key)
+            .or_else(|| self.slots.iter().position(Option::is_none))
+            .unwrap_or_else(|| self.steal());
+        self.started += 1;
+        self.slots[index] = Some(Voice {
+            key,
+            note,
+            started: self.started,
+        });
+        index
+    }
+
+    /// Slot of the voice to replace when all are in use: releasing voices
+    /// first, oldest first
+    fn index_to_replace(&self) -> usize {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| slot.as_ref().map(|voice| (index, voice)))
+            .min_by_key(|(_, voice)| {
+                let releasing = matches!(voice.note.envelope_state, EnvelopeState::Release);
+                (!releasing, voice.started)
+            })
+            .map_or(0, |(index, _)| index)
+    }
+
+    /// Free a slot for a new note when all are in use, moving its note to
+    /// `fading`; returns the slot index
+    fn steal(&mut self) -> usize {
+        let index = self.index_to_replace();
+        if let Some(mut voice) = self.slots[index].take() {
+            if self.fading.len() == self.fading.capacity() {
+                self.fading.remove(0);
+            }
+            voice.note.steal_fade = Some(1.0);
+            self.fading.push(voice.note);
+        }
+        index
+    }
+
+    fn index_of(&self, key: VoiceKey) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|slot| slot.as_ref().is_some_and(|voice| voice.key == key))
+    }
+
+    fn note_mut(&mut self, key: VoiceKey) -> Option<&mut NoteState> {
+        let index = self.index_of(key)?;
+        self.slots[index].as_mut().map(|voice| &mut voice.note)
+    }
+
+    /// The note in a slot, if it is still sounding
+    pub fn get(&self, index: usize) -> Option<&NoteState> {
+        self.slots.get(index)?.as_ref().map(|voice| &voice.note)
+    }
+
+    /// Number of sounding notes
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none) && self.fading.is_empty()
+    }
+
+    /// Start the release of every note
+    fn release_all(&mut self) {
+        for voice in self.slots.iter_mut().flatten() {
+            voice.note.release();
+        }
+    }
+
+    /// Set every note's modulation for the next block: the global LFO's
+    /// `pitch_bend` times what the matrix's routes add up to for the note
+    fn modulate(
+        &mut self,
+        pitch_bend: f32,
+        matrix: &ModMatrix,
+        lfo: [f32; 2],
+        wpm: f32,
+        sample_rate: f32,
+    ) {
+        for voice in self.slots.iter_mut().flatten() {
+            let note = &mut voice.note;
+            let outputs = matrix.evaluate(|source| match source {
+                ModSource::Lfo1 => lfo[0],
+                ModSource::Lfo2 => lfo[1],
+                ModSource::Envelope => note.envelope_level,
+                ModSource::Wpm => wpm,
+                ModSource::KeyPosition => note.key_position,
+            });
+            note.pitch_bend = pitch_bend * (outputs.pitch / 12.0).exp2();
+            note.mod_gain = (1.0 + outputs.amp).max(0.0);
+            note.mod_pan = outputs.pan;
+            note.set_lowpass(note.cutoff * outputs.filter.exp2(), sample_rate);
+            if let Some(ref mut filter) = note.filter {
+                filter.cutoff_scale = outputs.filter.exp2();
+            }
+        }
+    }
+
+    /// Add every note to a block, freeing the slots of notes that finish
+    fn render_block(&mut self, block: &mut [f32], sample_rate: f32) {
+        for slot in self.slots.iter_mut() {
+            if let Some(voice) = slot
+                && voice.note.render_block(block, sample_rate)
+            {
+                *slot = None;
+            }
+        }
+        self.fading
+            .retain_mut(|note| !note.render_block(block, sample_rate));
+    }
+
+    /// Add every note to a pair of blocks at its pan position, rendering
+    /// each note into `buffer` first
+    fn render_stereo(
+        &mut self,
+        left: &mut [f32],
+        right: &mut [f32],
+        buffer: &mut [f32],
+        sample_rate: f32,
+    ) {
+        for slot in self.slots.iter_mut() {
+            let Some(voice) = slot else {
+                continue;
+            };
+            buffer.fill(0.0);
+            let finished = voice.note.render_block(buffer, sample_rate);
+            let (left_gain, right_gain) = pan_gains(voice.note.pan + voice.note.mod_pan);
+            for ((l, r), sample) in left.iter_mut().zip(right.iter_mut()).zip(buffer.iter()) {
+                *l += sample * left_gain;
+                *r += sample * right_gain;
+            }
+            if finished {
+                *slot = None;
+            }
+        }
+        self.fading.retain_mut(|note| {
+            buffer.fill(0.0);
+            let finished = note.render_block(buffer, sample_rate);
+            let (left_gain, right_gain) = pan_gains(note.pan + note.mod_pan);
+            for ((l, r), sample) in left.iter_mut().zip(right.iter_mut()).zip(buffer.iter()) {
+                *l += sample * left_gain;
+                *r += sample * right_gain;
+            }
+            !finished
+        });
+    }
+}
+
+/// MIDI velocity used when selecting SoundFont regions for key presses
+const SOUNDFONT_VELOCITY: u8 = 100;
+
+/// A note started and released at set output times, without a key
+#[derive(Debug, Clone)]
+struct ScheduledNote {
+    key_id: String,
+    frequency: f32,
+    volume: f32,
+    /// Output times (as `sample_clock`) to start and release the note
+    start: u64,
+    stop: u64,
+    started: bool,
 }
 
 pub struct AudioState {
-    pub active_notes: HashMap<Keycode, NoteState>,
-    pub active_notes_by_id: HashMap<String, NoteState>,
+    voices: VoicePool,
     sample_rate: f32,
     current_waveform: Waveform,
     default_adsr: ADSRParams,
     master_volume: f32,
+    // Gain applied to the output, ramping towards master_volume
+    volume_gain: f32,
+    // Per-sample step of the parameter ramps
+    smoothing_coefficient: f32,
+    muted: bool,
+    // Auto-mute after a period without key presses
+    idle_timeout: Option<f32>,
+    last_activity_time: f64,
+    idle_gain: f32,
     filter_cutoff: f32,
+    // Output low-pass, bypassed until a cutoff is set at runtime
+    filter_enabled: bool,
+    // One filter state per output channel (the mono output uses the first)
+    filter_state: [f32; 2],
+    // Cutoff the filter runs at, ramping towards filter_cutoff
+    // (or MAX_FILTER_CUTOFF while bypassed)
+    smoothed_cutoff: f32,
+    // Semitones added to every note
+    transpose: i32,
     rate_limiter: RateLimiter,
+    velocity: VelocityTracker,
+    // Whether key presses get a velocity from the typing speed
+    velocity_enabled: bool,
+    humanize: Humanize,
+    // State of the xorshift generator behind the humanization, seeded the same
+    // way every time so offline renders stay reproducible
+    random_state: u32,
+    // Vibrato replacing the waveforms' own, and the LFO shared by every note
+    vibrato: Option<Vibrato>,
+    lfo: Option<Lfo>,
+    mod_matrix: ModMatrix,
+    // Resonant filter sweep of notes whose keys don't set their own
+    filter_envelope: Option<FilterEnvelope>,
+    // SoundFont instrument replacing the waveform oscillators
+    soundfont_preset: Option<SoundFontPreset>,
     // Sample playback support
     fart_sample: Option<AudioSample>,
+    key_samples: HashMap<String, Vec<VelocityLayer>>,
+    named_samples: HashMap<String, AudioSample>,
     active_sample_playbacks: Vec<SamplePlayback>,
-    global_time: f32,
+    // Melodies played without keys (e.g. notifications)
+    scheduled_notes: Vec<ScheduledNote>,
+    // Scratch blocks for stereo rendering, grown to the largest buffer seen
+    left_buffer: Vec<f32>,
+    right_buffer: Vec<f32>,
+    voice_buffer: Vec<f32>,
+    // Samples rendered so far; output times are derived from it so they keep
+    // full precision however long the engine runs
+    sample_clock: u64,
 }
 
 impl AudioState {
@@ -288,169 +1092,658 @@ impl AudioState {
         master_volume: f32,
         filter_cutoff: f32,
     ) -> Self {
-        let default_adsr = match waveform {
-            Waveform::Natural => ADSRParams::natural(),
-            Waveform::Electronic => ADSRParams::electronic(),
-            Waveform::Saw | Waveform::Square => ADSRParams::punchy(),
-            Waveform::Cyberpunk => ADSRParams::cyberpunk(),
-            Waveform::Triangle => ADSRParams::electronic(),
-            Waveform::Fart => ADSRParams::fart(),
-            Waveform::Bass => ADSRParams::cyberpunk(), // Bass uses analog-style envelope
-        };
+        let default_adsr = ADSRParams::for_waveform(waveform);
 
         // Load the embedded fart sample
         let fart_sample = AudioSample::load_from_bytes(embedded_configs::get_fart_audio_data())
             .map_err(|e| eprintln!("Warning: Could not load embedded fart sample: {}", e))
-            .ok();
+            .ok()
+            .map(|sample| sample.resampled(sample_rate as u32));
 
         Self {
-            active_notes: HashMap::new(),
-            active_notes_by_id: HashMap::new(),
+            voices: VoicePool::new(MAX_VOICES),
             sample_rate,
             current_waveform: waveform,
             default_adsr,
             master_volume,
+            volume_gain: master_volume,
+            smoothing_coefficient: 1.0 - (-1.0 / (PARAMETER_SMOOTHING_TIME * sample_rate)).exp(),
+            muted: false,
+            idle_timeout: None,
+            last_activity_time: 0.0,
+            idle_gain: 1.0,
             filter_cutoff,
+            filter_enabled: false,
+            filter_state: [0.0; 2],
+            smoothed_cutoff: MAX_FILTER_CUTOFF,
+            transpose: 0,
             rate_limiter: RateLimiter::new(),
+            velocity: VelocityTracker::default(),
+            velocity_enabled: false,
+            humanize: Humanize::default(),
+            random_state: 0x9E37_79B9,
+            vibrato: None,
+            lfo: None,
+            mod_matrix: ModMatrix::default(),
+            filter_envelope: None,
+            soundfont_preset: None,
             fart_sample,
+            key_samples: HashMap::new(),
+            named_samples: HashMap::new(),
             active_sample_playbacks: Vec::new(),
-            global_time: 0.0,
+            scheduled_notes: Vec::new(),
+            left_buffer: Vec::new(),
+            right_buffer: Vec::new(),
+            voice_buffer: Vec::new(),
+            sample_clock: 0,
         }
     }
 
     #[allow(dead_code)]
     pub fn start_note(&mut self, keycode: Keycode, frequency: f32, volume: f32) -> f32 {
-        let adjusted_volume = volume * self.master_volume;
-
         let note_state = NoteState::new(
             frequency,
-            adjusted_volume,
+            volume,
             self.default_adsr.clone(),
             self.current_waveform,
         );
-        self.active_notes.insert(keycode, note_state);
+        self.voices.start(VoiceKey::Keycode(keycode), note_state);
 
-        adjusted_volume
+        volume * self.master_volume
     }
 
     #[allow(dead_code)]
     pub fn stop_note(&mut self, keycode: Keycode) {
-        if let Some(note_state) = self.active_notes.get_mut(&keycode) {
+        if let Some(note_state) = self.voices.note_mut(VoiceKey::Keycode(keycode)) {
             note_state.release();
         }
     }
 
     pub fn stop_note_with_id(&mut self, id: &str) {
-        // For fart waveform, samples play to completion, no need to stop
-        if matches!(self.current_waveform, Waveform::Fart) {
-            return;
+        // Fart samples play to completion and never have a note entry to release
+        if let Some(note) = self.voices.note_mut(VoiceKey::id(id)) {
+            note.release();
         }
+    }
 
-        if let Some(note) = self.active_notes_by_id.get_mut(id) {
-            note.release();
+    /// Release every sounding note and looped sample so the output fades to silence
+    pub fn release_all(&mut self) {
+        self.scheduled_notes.clear();
+        self.voices.release_all();
+        let now = self.now();
+        for playback in self.active_sample_playbacks.iter_mut() {
+            playback.release(now, self.sample_rate);
         }
     }
 
+    /// Play a note `delay` seconds from now for `duration` seconds
+    ///
+    /// Scheduled notes start and stop at buffer boundaries, so melodies keep
+    /// their timing whenever the caller gets to run.
+    pub fn schedule_note(
+        &mut self,
+        key_id: &str,
+        frequency: f32,
+        volume: f32,
+        delay: f32,
+        duration: f32,
+    ) {
+        let samples = |seconds: f32| (seconds.max(0.0) * self.sample_rate) as u64;
+        let start = self.sample_clock + samples(delay);
+        self.scheduled_notes.push(ScheduledNote {
+            key_id: key_id.to_string(),
+            frequency,
+            volume,
+            start,
+            stop: start + samples(duration),
+            started: false,
+        });
+    }
+
+    /// Start and release the scheduled notes that are due
+    fn run_scheduled_notes(&mut self) {
+        if self.scheduled_notes.is_empty() {
+            return;
+        }
+        let now = self.sample_clock;
+        let mut scheduled = std::mem::take(&mut self.scheduled_notes);
+        scheduled.retain_mut(|note| {
+            if !note.started && note.start <= now {
+                self.start_note_with_id(&note.key_id, note.frequency, note.volume);
+                note.started = true;
+            }
+            if note.started && note.stop <= now {
+                self.stop_note_with_id(&note.key_id);
+                return false;
+            }
+            true
+        });
+        self.scheduled_notes = scheduled;
+    }
+
     /// Start a note with string-based identifier (for virtual keys)
     pub fn start_note_with_id(&mut self, key_id: &str, frequency: f32, volume: f32) -> f32 {
+        self.start_note_with_overrides(key_id, frequency, volume, &NoteOverrides::default())
+    }
+
+    /// Start a note with string-based identifier, using the key's own settings where given
+    /// Returns the note's volume after the master volume and rate limiting, or
+    /// 0.0 if nothing was started (a SoundFont key no region covers)
+    pub fn start_note_with_overrides(
+        &mut self,
+        key_id: &str,
+        frequency: f32,
+        volume: f32,
+        overrides: &NoteOverrides,
+    ) -> f32 {
+        let waveform = overrides.waveform.unwrap_or(self.current_waveform);
+        let semitones = self.transpose as f32 + overrides.detune_cents / 100.0;
+        let frequency = frequency * 2.0_f32.powf(semitones / 12.0);
+        let start_delay = (overrides.delay.max(0.0) * self.sample_rate) as usize;
+
         // Apply rate limiting - get volume multiplier based on recent press frequency
         let rate_limit_multiplier = self
             .rate_limiter
-            .record_press_and_get_volume_multiplier(key_id);
-        let adjusted_volume = volume * self.master_volume * rate_limit_multiplier;
+            .record_press_and_get_volume_multiplier(key_id, self.now());
+        let velocity_multiplier = overrides.velocity.map_or(1.0, velocity_gain);
+        // The master volume is applied to the output, so changes reach sounding notes
+        let adjusted_volume = volume * rate_limit_multiplier * velocity_multiplier;
+        let cutoff = overrides
+            .velocity
+            .map_or(MAX_FILTER_CUTOFF, velocity_cutoff);
+        let applied_volume = adjusted_volume * self.master_volume;
+        let filter = overrides
+            .filter_envelope
+            .as_ref()
+            .or(self.filter_envelope.as_ref())
+            .map(|envelope| NoteFilter::new(envelope.clone(), self.sample_rate));
+
+        // A loaded SoundFont preset takes over from the waveform, unless the key picks its own
+        if overrides.waveform.is_none()
+            && let Some(ref preset) = self.soundfont_preset
+        {
+            let key = soundfont::frequency_to_midi_key(frequency);
+            let Some(mut voice) = preset.start_voice(key, SOUNDFONT_VELOCITY, self.sample_rate)
+            else {
+                return 0.0;
+            };
+            // Keep the fractional transpose and detune that rounding to a key dropped
+            voice.detune(1200.0 * (frequency / midi_to_frequency(key as i32)).log2());
+            let mut note_state = NoteState::with_soundfont_voice(
+                frequency,
+                adjusted_volume,
+                ADSRParams::soundfont(),
+                voice,
+            );
+            note_state.pan = overrides.pan.unwrap_or(0.0);
+            note_state.key_position = overrides.key_position.unwrap_or(0.0);
+            note_state.cutoff = cutoff;
+            note_state.set_lowpass(cutoff, self.sample_rate);
+            note_state.filter = filter;
+            note_state.start_delay = start_delay;
+            self.voices.start(VoiceKey::id(key_id), note_state);
+            return applied_volume;
+        }
 
         // Handle fart waveform with audio sample
-        if matches!(self.current_waveform, Waveform::Fart) {
-            if let Some(ref fart_sample) = self.fart_sample {
-                let playback =
-                    SamplePlayback::new(fart_sample.clone(), self.global_time, adjusted_volume);
-                self.active_sample_playbacks.push(playback);
-                return adjusted_volume;
+        if matches!(waveform, Waveform::Fart)
+            && let Some(ref fart_sample) = self.fart_sample
+        {
+            let playback = SamplePlayback::new(fart_sample.clone(), self.now(), adjusted_volume);
+            self.active_sample_playbacks.push(playback);
+            return applied_volume;
+        }
+        // Fallback to synthetic if sample loading failed
+
+        let mut adsr = match overrides.waveform {
+            Some(waveform) => ADSRParams::for_waveform(waveform),
+            None => self.default_adsr.clone(),
+        };
+        if let Some(ref envelope) = overrides.adsr {
+            adsr = envelope.apply(adsr);
+        }
+        let mut note_state = NoteState::new(frequency, adjusted_volume, adsr, waveform);
+        note_state.pan = overrides.pan.unwrap_or(0.0);
+        note_state.key_position = overrides.key_position.unwrap_or(0.0);
+        note_state.vibrato = self.vibrato.or(Vibrato::for_waveform(waveform));
+        note_state.pitch_bend = self.lfo_pitch_bend();
+        note_state.cutoff = cutoff;
+        note_state.set_lowpass(cutoff, self.sample_rate);
+        note_state.filter = filter;
+        note_state.start_delay = start_delay;
+        self.voices.start(VoiceKey::id(key_id), note_state);
+
+        applied_volume
+    }
+
+    /// Record a key press, filling in the parts of its overrides that vary
+    /// per press: the velocity from the time since the previous press (when
+    /// enabled), and the humanization's random detune and delay
+    pub fn prepare_key_press(&mut self, overrides: &mut NoteOverrides) {
+        let velocity = self.velocity.record_press(self.now());
+        overrides.velocity = self.velocity_enabled.then_some(velocity);
+        overrides.detune_cents = (self.next_random() * 2.0 - 1.0) * self.humanize.detune_cents;
+        overrides.delay = self.next_random() * self.humanize.timing_jitter;
+    }
+
+    /// Set whether fast typing plays louder and brighter than slow typing
+    pub fn set_velocity_enabled(&mut self, enabled: bool) {
+        self.velocity_enabled = enabled;
+    }
+
+    /// Set how much key presses vary in pitch and timing
+    pub fn set_humanize(&mut self, humanize: Humanize) {
+        self.humanize = humanize;
+    }
+
+    /// Set the vibrato of new notes (None keeps each waveform's own), the
+    /// global LFO and the modulation matrix
+    pub fn set_modulation(
+        &mut self,
+        vibrato: Option<Vibrato>,
+        lfo: Option<Lfo>,
+        mod_matrix: ModMatrix,
+    ) {
+        self.vibrato = vibrato;
+        self.lfo = lfo;
+        self.mod_matrix = mod_matrix;
+    }
+
+    /// Give new notes a resonant filter sweep, unless their key sets its own
+    pub fn set_filter_envelope(&mut self, filter_envelope: Option<FilterEnvelope>) {
+        self.filter_envelope = filter_envelope;
+    }
+
+    /// Update every sounding note's modulation at the start of a block
+    fn modulate_voices(&mut self) {
+        let pitch_bend = self.lfo_pitch_bend();
+        let lfo = self.mod_matrix.lfo_values(self.now());
+        let wpm = wpm_source(self.velocity.wpm(self.now()));
+        self.voices
+            .modulate(pitch_bend, &self.mod_matrix, lfo, wpm, self.sample_rate);
+    }
+
+    /// Frequency multiplier of the global LFO at the current time
+    fn lfo_pitch_bend(&self) -> f32 {
+        match self.lfo {
+            Some(lfo) if lfo.target == LfoTarget::Pitch => {
+                (lfo.depth * lfo.value(self.now()) / 12.0).exp2()
             }
-            // Fallback to synthetic if sample loading failed
+            _ => 1.0,
         }
+    }
 
-        let note_state = NoteState::new(
-            frequency,
-            adjusted_volume,
-            self.default_adsr.clone(),
-            self.current_waveform,
-        );
-        self.active_notes_by_id
-            .insert(key_id.to_string(), note_state);
+    /// Next number of the humanization's generator, from 0.0 to 1.0
+    fn next_random(&mut self) -> f32 {
+        // xorshift32
+        let mut x = self.random_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.random_state = x;
+        x as f32 / u32::MAX as f32
+    }
+
+    /// Assign a one-shot sample to a key identifier (e.g. a drum hit)
+    pub fn set_key_sample(&mut self, key_id: &str, sample: AudioSample) {
+        self.set_key_sample_layers(key_id, vec![VelocityLayer::full_range(sample)]);
+    }
+
+    /// Assign velocity-layered one-shot samples to a key identifier
+    pub fn set_key_sample_layers(&mut self, key_id: &str, layers: Vec<VelocityLayer>) {
+        if layers.is_empty() {
+            self.key_samples.remove(key_id);
+        } else {
+            self.key_samples.insert(key_id.to_string(), layers);
+        }
+    }
+
+    /// Remove all per-key samples
+    pub fn clear_key_samples(&mut self) {
+        self.key_samples.clear();
+    }
+
+    /// Check whether a key identifier has a one-shot sample assigned
+    pub fn has_key_sample(&self, key_id: &str) -> bool {
+        self.key_samples.contains_key(key_id)
+    }
+
+    /// Trigger the one-shot sample assigned to a key, mixed with the synth voices
+    ///
+    /// The rate limiter output doubles as the hit velocity: fresh presses pick the
+    /// top layer while rapid repeats fall through to softer layers.
+    /// Returns the applied volume, or None if the key has no sample
+    pub fn start_sample_with_id(&mut self, key_id: &str, volume: f32) -> Option<f32> {
+        let layers = self.key_samples.get(key_id)?;
+        let velocity = self
+            .rate_limiter
+            .record_press_and_get_volume_multiplier(key_id, self.now());
+        let layer = select_velocity_layer(layers, velocity)?;
+        let adjusted_volume = volume * velocity;
+
+        // Samples with loop points sustain while the key is held
+        let playback = if layer.sample.loop_points.is_some() {
+            SamplePlayback {
+                key_id: Some(key_id.to_string()),
+                ..SamplePlayback::looped(layer.sample.clone(), self.now(), adjusted_volume)
+            }
+        } else {
+            SamplePlayback::new(layer.sample.clone(), self.now(), adjusted_volume)
+        };
+        self.active_sample_playbacks.push(playback);
+
+        Some(adjusted_volume * self.master_volume)
+    }
+
+    /// Register a sample that can be played by name (e.g. for a sequence)
+    pub fn set_named_sample(&mut self, name: &str, sample: AudioSample) {
+        self.named_samples.insert(name.to_string(), sample);
+    }
+
+    /// Remove all named samples
+    pub fn clear_named_samples(&mut self) {
+        self.named_samples.clear();
+    }
+
+    /// Play a named sample at the given volume
+    /// Returns false if no sample with that name is registered
+    pub fn start_named_sample(&mut self, name: &str, volume: f32) -> bool {
+        let Some(sample) = self.named_samples.get(name) else {
+            return false;
+        };
+
+        let playback = SamplePlayback::new(sample.clone(), self.now(), volume);
+        self.active_sample_playbacks.push(playback);
+        true
+    }
 
-        adjusted_volume
+    /// Release looped samples started by a key so their tails play out
+    pub fn release_sample_with_id(&mut self, key_id: &str) {
+        let now = self.now();
+        for playback in self.active_sample_playbacks.iter_mut() {
+            if playback.key_id.as_deref() == Some(key_id) {
+                playback.release(now, self.sample_rate);
+            }
+        }
     }
 
-    /// Generate a single audio sample (main synthesis loop)
+    /// Generate a single audio sample
     pub fn generate_sample(&mut self) -> f32 {
-        let mut sample = 0.0;
+        let mut block = [0.0];
+        self.fill_block(&mut block);
+        block[0]
+    }
+
+    /// Render a buffer of audio (main synthesis loop)
+    ///
+    /// Each voice and sample playback renders the whole buffer in turn, so
+    /// its state stays at hand instead of every voice being visited per sample.
+    pub fn fill_block(&mut self, block: &mut [f32]) {
         let dt = 1.0 / self.sample_rate;
-        let mut to_remove = Vec::new();
-        let mut to_remove_by_id = Vec::new();
+        self.run_scheduled_notes();
+        block.fill(0.0);
 
-        // Update global time
-        self.global_time += dt;
+        // Process each sounding note, freeing the slots of finished ones
+        self.modulate_voices();
+        self.voices.render_block(block, self.sample_rate);
+        self.mix_sample_playbacks(block);
 
-        // Process each active note (keycode-based)
-        for (keycode, note_state) in self.active_notes.iter_mut() {
-            // Update envelope
-            let envelope_multiplier = note_state.update_envelope(dt);
+        for sample in block.iter_mut() {
+            self.sample_clock += 1;
+            *sample = self.process_output([*sample; 2], dt)[0];
+        }
+    }
 
-            // Check if note should be removed
-            if note_state.is_finished(envelope_multiplier) {
-                to_remove.push(*keycode);
-                continue;
-            }
+    /// Render a pair of left and right blocks, placing each note at its pan
+    /// position (samples play in the center)
+    pub fn fill_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let dt = 1.0 / self.sample_rate;
+        self.run_scheduled_notes();
+        left.fill(0.0);
+        right.fill(0.0);
+
+        let mut buffer = std::mem::take(&mut self.voice_buffer);
+        buffer.resize(left.len(), 0.0);
+        self.modulate_voices();
+        self.voices
+            .render_stereo(left, right, &mut buffer, self.sample_rate);
+        buffer.fill(0.0);
+        self.mix_sample_playbacks(&mut buffer);
+
+        for ((l, r), sample) in left.iter_mut().zip(right.iter_mut()).zip(&buffer) {
+            self.sample_clock += 1;
+            [*l, *r] = self.process_output([*l + sample, *r + sample], dt);
+        }
+        self.voice_buffer = buffer;
+    }
 
-            // Generate sample for this note
-            let note_sample = note_state.generate_sample(self.sample_rate, envelope_multiplier);
-            sample += note_sample;
+    /// Render interleaved frames for an output with `channels` channels: the
+    /// stereo mix on the first two, silence on any others
+    pub fn fill_interleaved(&mut self, data: &mut [f32], channels: usize) {
+        if channels < 2 {
+            self.fill_block(data);
+            return;
         }
 
-        // Process each active note (string ID-based)
-        for (key_id, note_state) in self.active_notes_by_id.iter_mut() {
-            // Update envelope
-            let envelope_multiplier = note_state.update_envelope(dt);
+        // Only allocates when the device asks for a bigger buffer than before
+        let frames = data.len() / channels;
+        let mut left = std::mem::take(&mut self.left_buffer);
+        let mut right = std::mem::take(&mut self.right_buffer);
+        left.resize(frames, 0.0);
+        right.resize(frames, 0.0);
+        self.fill_stereo(&mut left, &mut right);
+
+        data.fill(0.0);
+        for (frame, (l, r)) in data.chunks_exact_mut(channels).zip(left.iter().zip(&right)) {
+            frame[0] = *l;
+            frame[1] = *r;
+        }
+        self.left_buffer = left;
+        self.right_buffer = right;
+    }
 
-            // Check if note should be removed
-            if note_state.is_finished(envelope_multiplier) {
-                to_remove_by_id.push(key_id.clone());
-                continue;
+    /// Add the active sample playbacks to a block starting at the current
+    /// time, dropping finished ones in place
+    fn mix_sample_playbacks(&mut self, block: &mut [f32]) {
+        let sample_rate = self.sample_rate;
+        let block_start = self.sample_clock;
+        self.active_sample_playbacks.retain_mut(|playback| {
+            for (frame, sample) in (1..).zip(block.iter_mut()) {
+                let time = (block_start + frame) as f64 / f64::from(sample_rate);
+                if playback.is_finished(time, sample_rate) {
+                    return false;
+                }
+                *sample += playback.get_current_sample(time, sample_rate);
             }
+            true
+        });
+    }
 
-            // Generate sample for this note
-            let note_sample = note_state.generate_sample(self.sample_rate, envelope_multiplier);
-            sample += note_sample;
+    /// Filter, mute, idle fade and master volume for one mixed frame
+    fn process_output(&mut self, mut frame: [f32; 2], dt: f32) -> [f32; 2] {
+        // One-pole low-pass on the mixed output, kept running while its cutoff
+        // ramps back up after being bypassed
+        let target_cutoff = if self.filter_enabled {
+            self.filter_cutoff
+        } else {
+            MAX_FILTER_CUTOFF
+        };
+        self.smoothed_cutoff = self.smooth(self.smoothed_cutoff, target_cutoff);
+        let mut cutoff = self.smoothed_cutoff;
+        // A filter LFO sweeps around the set cutoff, engaging the filter while bypassed
+        if let Some(lfo) = self.lfo
+            && lfo.target == LfoTarget::Filter
+        {
+            let center = if self.filter_enabled {
+                cutoff
+            } else {
+                self.filter_cutoff
+            };
+            cutoff = (center * (lfo.depth * lfo.value(self.now())).exp2())
+                .clamp(MIN_FILTER_CUTOFF, MAX_FILTER_CUTOFF);
+        }
+        if cutoff < MAX_FILTER_CUTOFF {
+            let alpha = 1.0 - (-2.0 * std::f32::consts::PI * cutoff / self.sample_rate).exp();
+            for (sample, state) in frame.iter_mut().zip(self.filter_state.iter_mut()) {
+                *state = flush_denormal(*state + alpha * (*sample - *state));
+                *sample = *state;
+            }
+        } else {
+            self.filter_state = frame;
         }
 
-        // Process active sample playbacks (for fart sounds)
-        let mut samples_to_remove = Vec::new();
-        for (i, playback) in self.active_sample_playbacks.iter_mut().enumerate() {
-            if playback.is_finished(self.global_time, self.sample_rate) {
-                samples_to_remove.push(i);
-                continue;
+        self.volume_gain = self.smooth(self.volume_gain, self.master_volume);
+
+        // Voices keep running while muted so unmuting resumes in time
+        if self.muted {
+            return [0.0; 2];
+        }
+
+        // Fade out after the idle timeout, and back in once a key is pressed
+        let idle = self
+            .idle_timeout
+            .is_some_and(|timeout| self.now() - self.last_activity_time > f64::from(timeout));
+        self.idle_gain = if idle {
+            (self.idle_gain - dt / IDLE_FADE_OUT_TIME).max(0.0)
+        } else {
+            (self.idle_gain + dt / IDLE_FADE_IN_TIME).min(1.0)
+        };
+        let lfo_gain = match self.lfo {
+            Some(lfo) if lfo.target == LfoTarget::Volume => {
+                1.0 - lfo.depth.clamp(0.0, 1.0) * (1.0 - lfo.value(self.now())) / 2.0
             }
+            _ => 1.0,
+        };
+        // Global volume adjustment - normalized for comfortable listening
+        frame.map(|sample| sample * self.idle_gain * self.volume_gain * lfo_gain)
+    }
 
-            let sample_val = playback.get_current_sample(self.global_time, self.sample_rate);
-            sample += sample_val;
+    /// One step of a parameter ramp from `current` towards `target`, landing on
+    /// the target once the remaining difference is inaudible
+    fn smooth(&self, current: f32, target: f32) -> f32 {
+        let next = current + (target - current) * self.smoothing_coefficient;
+        if (target - next).abs() <= target.abs() * 1e-4 + 1e-6 {
+            target
+        } else {
+            next
         }
+    }
+
+    /// Whether nothing is sounding, so parameter changes can't click
+    fn is_silent(&self) -> bool {
+        self.voices.is_empty() && self.active_sample_playbacks.is_empty()
+    }
+
+    /// Exchange the loaded key and named samples with another engine's, to
+    /// take on samples loaded elsewhere without loading them here
+    pub fn swap_samples(&mut self, other: &mut AudioState) {
+        std::mem::swap(&mut self.key_samples, &mut other.key_samples);
+        std::mem::swap(&mut self.named_samples, &mut other.named_samples);
+    }
 
-        // Remove finished sample playbacks (in reverse order to maintain indices)
-        for &i in samples_to_remove.iter().rev() {
-            self.active_sample_playbacks.remove(i);
+    /// Load the named, sequence, and per-key samples a keyboard config refers to,
+    /// replacing any previously loaded ones
+    /// Returns a message for each sample that could not be loaded
+    pub fn load_config_samples(&mut self, config: &KeyboardConfig) -> Vec<String> {
+        self.clear_key_samples();
+        self.clear_named_samples();
+        let mut warnings = Vec::new();
+
+        for (name, path) in &config.samples {
+            match self.load_sample(path) {
+                Ok(sample) => self.set_named_sample(name, sample),
+                Err(e) => warnings.push(format!(
+                    "Could not load sample '{}' ({}): {}",
+                    name, path, e
+                )),
+            }
         }
 
-        // Remove finished notes
-        for keycode in to_remove {
-            self.active_notes.remove(&keycode);
+        for sequence in &config.sequences {
+            if let Some(ref path) = sequence.sample {
+                match self.load_sample(path) {
+                    Ok(sample) => self.set_named_sample(&sequence.name, sample),
+                    Err(e) => warnings.push(format!(
+                        "Could not load sample '{}' for sequence {}: {}",
+                        path, sequence.name, e
+                    )),
+                }
+            }
         }
-        for key_id in to_remove_by_id {
-            self.active_notes_by_id.remove(&key_id);
+
+        self.load_mapping_samples(config.all_mappings(), &mut warnings);
+
+        warnings
+    }
+
+    /// Load the key samples of another keyboard config alongside the current
+    /// ones, under key IDs prefixed with "prefix:" (e.g. for a device's config)
+    pub fn load_prefixed_samples(&mut self, prefix: &str, config: &KeyboardConfig) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let mappings = config
+            .all_mappings()
+            .map(|(key_name, mapping)| (format!("{}:{}", prefix, key_name), mapping));
+        self.load_mapping_samples(mappings, &mut warnings);
+        warnings
+    }
+
+    /// Load the samples of key mappings by key ID
+    fn load_mapping_samples<'a>(
+        &mut self,
+        mappings: impl Iterator<Item = (String, &'a KeyMapping)>,
+        warnings: &mut Vec<String>,
+    ) {
+        let sample_rate = self.sample_rate as u32;
+        for (key_name, mapping) in mappings {
+            // Loop points are given in the file's frames, so set them before resampling
+            let mut load = |path: &str| match AudioSample::load_from_file(path) {
+                Ok(sample) if mapping.is_looped() => Some(
+                    sample
+                        .with_loop(mapping.loop_start, mapping.loop_end)
+                        .resampled(sample_rate),
+                ),
+                Ok(sample) => Some(sample.resampled(sample_rate)),
+                Err(e) => {
+                    warnings.push(format!(
+                        "Could not load sample '{}' for key {}: {}",
+                        path, key_name, e
+                    ));
+                    None
+                }
+            };
+
+            if !mapping.layers.is_empty() {
+                let layers = mapping
+                    .layers
+                    .iter()
+                    .filter_map(|layer| {
+                        Some(VelocityLayer {
+                            min_velocity: layer.min_velocity,
+                            max_velocity: layer.max_velocity,
+                            sample: load(&layer.sample)?,
+                        })
+                    })
+                    .collect();
+                self.set_key_sample_layers(&key_name, layers);
+            } else if let Some(sample) = mapping.sample.as_deref().and_then(load) {
+                self.set_key_sample(&key_name, sample);
+            }
         }
+    }
 
-        // Global volume adjustment - normalized for comfortable listening
-        sample
+    /// Load a sound file, converted to the output sample rate
+    pub fn load_sample(&self, path: &str) -> Result<AudioSample, Box<dyn std::error::Error>> {
+        Ok(AudioSample::load_from_file(path)?.resampled(self.sample_rate as u32))
+    }
+
+    /// Output sample rate in Hz
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Replace the fart sample (e.g. with a user-provided file overriding the embedded one)
+    pub fn set_fart_sample(&mut self, sample: AudioSample) {
+        self.fart_sample = Some(sample);
     }
 
     /// Get reference to fart sample for Easter egg
@@ -458,9 +1751,9 @@ impl AudioState {
         &self.fart_sample
     }
 
-    /// Get current global time for Easter egg
-    pub fn get_global_time(&self) -> f32 {
-        self.global_time
+    /// Seconds of output rendered so far
+    pub fn now(&self) -> f64 {
+        self.sample_clock as f64 / f64::from(self.sample_rate)
     }
 
     /// Add sample playback for Easter egg
@@ -468,14 +1761,97 @@ impl AudioState {
         self.active_sample_playbacks.push(playback);
     }
 
-    /// Set the waveform for the audio engine
+    /// The note sounding for a key ID, if any
+    pub fn note(&self, key_id: &str) -> Option<&NoteState> {
+        let index = self.voices.index_of(VoiceKey::id(key_id))?;
+        self.voices.get(index)
+    }
+
+    /// Number of notes sounding (samples not included)
+    pub fn active_voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Set the waveform for the audio engine, along with its default envelope
     pub fn set_waveform(&mut self, waveform: Waveform) {
         self.current_waveform = waveform;
+        self.default_adsr = ADSRParams::for_waveform(waveform);
+    }
+
+    /// Use a SoundFont preset as the sound source (None returns to the waveform)
+    pub fn set_soundfont_preset(&mut self, preset: Option<SoundFontPreset>) {
+        self.soundfont_preset = preset;
+    }
+
+    /// Get the active SoundFont preset, if any
+    pub fn get_soundfont_preset(&self) -> Option<&SoundFontPreset> {
+        self.soundfont_preset.as_ref()
     }
 
     /// Set the master volume for the audio engine
+    ///
+    /// Sounding notes and samples follow it over about 20 ms rather than jumping.
     pub fn set_volume(&mut self, volume: f32) {
         self.master_volume = volume.clamp(0.0, 1.0);
+        if self.is_silent() {
+            self.volume_gain = self.master_volume;
+        }
+    }
+
+    /// Get the master volume
+    pub fn get_volume(&self) -> f32 {
+        self.master_volume
+    }
+
+    /// Mute or unmute all output
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    /// Check whether output is muted
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Fade the output out after this many seconds without key presses (None disables)
+    pub fn set_idle_timeout(&mut self, timeout: Option<f32>) {
+        self.idle_timeout = timeout;
+    }
+
+    /// Record a key press, restarting the idle timer
+    pub fn register_activity(&mut self) {
+        self.last_activity_time = self.now();
+    }
+
+    /// Get the current idle fade gain (1.0 when active, 0.0 when fully faded)
+    pub fn get_idle_gain(&self) -> f32 {
+        self.idle_gain
+    }
+
+    /// Get the configured filter cutoff frequency
+    pub fn get_filter_cutoff(&self) -> f32 {
+        self.filter_cutoff
+    }
+
+    /// Set the output low-pass cutoff (Hz); MAX_FILTER_CUTOFF or above bypasses it
+    ///
+    /// While anything sounds the cutoff ramps to the new value over about 20 ms.
+    pub fn set_filter_cutoff(&mut self, cutoff: f32) {
+        self.filter_cutoff = cutoff.clamp(MIN_FILTER_CUTOFF, MAX_FILTER_CUTOFF);
+        self.filter_enabled = self.filter_cutoff < MAX_FILTER_CUTOFF;
+        if self.is_silent() {
+            self.smoothed_cutoff = self.filter_cutoff;
+        }
+    }
+
+    /// Transpose new notes by a number of semitones (clamped to MAX_TRANSPOSE)
+    pub fn set_transpose(&mut self, semitones: i32) {
+        self.transpose = semitones.clamp(-MAX_TRANSPOSE, MAX_TRANSPOSE);
+    }
+
+    /// Get the current transposition in semitones
+    pub fn get_transpose(&self) -> i32 {
+        self.transpose
     }
 }
 
@@ -514,6 +1890,22 @@ mod tests {
         assert_eq!(audio_state.active_sample_playbacks.len(), 0);
     }
 
+    #[test]
+    fn test_fart_sample_override() {
+        let mut state = AudioState::new(44100.0, Waveform::Fart, 1.0, 1200.0);
+        let custom = AudioSample {
+            samples: vec![0.25; 8],
+            sample_rate: 8000,
+            channels: 1,
+            loop_points: None,
+        };
+
+        state.set_fart_sample(custom);
+        let sample = state.get_fart_sample().as_ref().unwrap();
+        assert_eq!(sample.sample_rate, 8000);
+        assert_eq!(sample.samples.len(), 8);
+    }
+
     #[test]
     fn test_audio_state_creation() {
         let state = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
@@ -522,17 +1914,670 @@ mod tests {
 
     #[test]
     fn test_note_lifecycle() {
-        use device_query::Keycode;
+        use crate::keycode::Keycode;
         let mut state = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
 
         // Start note
         state.start_note(Keycode::A, 440.0, 0.5);
-        assert_eq!(state.active_notes.len(), 1);
+        assert_eq!(state.active_voice_count(), 1);
 
         // Stop note
         state.stop_note(Keycode::A);
         // Note should still exist but be in release phase
-        assert_eq!(state.active_notes.len(), 1);
+        assert_eq!(state.active_voice_count(), 1);
+    }
+
+    #[test]
+    fn test_key_sample_playback() {
+        let mut state = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
+        let kick = AudioSample {
+            samples: vec![1.0, 0.5, 0.25],
+            sample_rate: 44100,
+            channels: 1,
+            loop_points: None,
+        };
+
+        // Keys without a sample are not handled
+        assert_eq!(state.start_sample_with_id("Space", 0.5), None);
+
+        state.set_key_sample("Space", kick);
+        assert!(state.has_key_sample("Space"));
+        assert_eq!(state.start_sample_with_id("Space", 0.5), Some(0.5));
+        assert_eq!(state.active_sample_playbacks.len(), 1);
+        assert!(state.voices.is_empty());
+
+        // One-shot plays to completion and is removed
+        for _ in 0..10 {
+            state.generate_sample();
+        }
+        assert!(state.active_sample_playbacks.is_empty());
+    }
+
+    #[test]
+    fn test_velocity_layers_follow_rate_limiter() {
+        let mut state = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
+        let sample = |value| AudioSample {
+            samples: vec![value; 4],
+            sample_rate: 44100,
+            channels: 1,
+            loop_points: None,
+        };
+        state.set_key_sample_layers(
+            "Space",
+            vec![
+                VelocityLayer {
+                    min_velocity: 0.0,
+                    max_velocity: 0.8,
+                    sample: sample(0.2),
+                },
+                VelocityLayer {
+                    min_velocity: 0.8,
+                    max_velocity: 1.0,
+                    sample: sample(1.0),
+                },
+            ],
+        );
+
+        // First hit is full velocity, the rapid repeat drops to the soft layer
+        state.start_sample_with_id("Space", 1.0);
+        state.start_sample_with_id("Space", 1.0);
+        assert_eq!(state.active_sample_playbacks[0].sample.samples[0], 1.0);
+        assert_eq!(state.active_sample_playbacks[1].sample.samples[0], 0.2);
+    }
+
+    #[test]
+    fn test_looped_key_sample_released_on_key_up() {
+        let mut state = AudioState::new(100.0, Waveform::Electronic, 1.0, 1200.0);
+        let pad = AudioSample {
+            samples: vec![0.5; 10],
+            sample_rate: 100,
+            channels: 1,
+            loop_points: None,
+        }
+        .with_loop(None, None);
+        state.set_key_sample("P", pad);
+
+        state.start_sample_with_id("P", 1.0);
+        for _ in 0..100 {
+            state.generate_sample();
+        }
+        // Still sustaining well past the sample's length
+        assert_eq!(state.active_sample_playbacks.len(), 1);
+
+        state.release_sample_with_id("P");
+        for _ in 0..20 {
+            state.generate_sample();
+        }
+        assert!(state.active_sample_playbacks.is_empty());
+    }
+
+    #[test]
+    fn test_named_sample_playback() {
+        let mut state = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
+        let chime = AudioSample {
+            samples: vec![0.5; 100],
+            sample_rate: 44100,
+            channels: 1,
+            loop_points: None,
+        };
+
+        assert!(!state.start_named_sample("chime", 0.7));
+        state.set_named_sample("chime", chime);
+        assert!(state.start_named_sample("chime", 0.7));
+        assert_eq!(state.active_sample_playbacks.len(), 1);
+
+        state.clear_named_samples();
+        assert!(!state.start_named_sample("chime", 0.7));
+    }
+
+    #[test]
+    fn test_mute_silences_output() {
+        let mut state = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
+        state.start_note_with_id("A", 440.0, 0.5);
+        for _ in 0..1000 {
+            state.generate_sample();
+        }
+
+        state.set_muted(true);
+        assert!(state.is_muted());
+        assert_eq!(state.generate_sample(), 0.0);
+        assert_eq!(state.active_voice_count(), 1);
+
+        state.set_muted(false);
+        assert_ne!(state.generate_sample(), 0.0);
+    }
+
+    #[test]
+    fn test_output_level() {
+        let level = OutputLevel::new();
+        assert_eq!(level.rms(), 0.0);
+
+        level.update(&[0.5, -0.5, 0.5, -1.0]);
+        assert_eq!(level.peak(), 1.0);
+        assert!((level.rms() - (1.75_f32 / 4.0).sqrt()).abs() < 1e-6);
+
+        // Empty buffers keep the last reading
+        level.update(&[]);
+        assert_eq!(level.peak(), 1.0);
+    }
+
+    #[test]
+    fn test_xrun_counter() {
+        let counter = XrunCounter::new();
+        let buffer = Duration::from_millis(10);
+
+        // Buffers arriving on time and rendering quickly are fine
+        counter.record(None, buffer, Duration::from_millis(1), buffer);
+        counter.record(Some(buffer), buffer, Duration::from_millis(2), buffer);
+        assert_eq!((counter.underruns(), counter.overruns()), (0, 0));
+
+        // A gap in playback is an underrun, a slow render an overrun
+        counter.record(Some(buffer * 3), buffer, Duration::from_millis(1), buffer);
+        counter.record(Some(buffer), buffer, Duration::from_millis(12), buffer);
+        assert_eq!((counter.underruns(), counter.overruns()), (1, 1));
+    }
+
+    #[test]
+    fn test_output_history() {
+        let history = OutputHistory::new(4);
+        history.push(&[1.0, 2.0]);
+        assert_eq!(history.snapshot(), vec![0.0, 0.0, 1.0, 2.0]);
+
+        history.push(&[3.0, 4.0, 5.0]);
+        assert_eq!(history.snapshot(), vec![2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn test_humanize() {
+        let mut state = AudioState::new(1000.0, Waveform::Electronic, 1.0, 1200.0);
+        let mut overrides = NoteOverrides::default();
+        state.prepare_key_press(&mut overrides);
+        assert_eq!((overrides.detune_cents, overrides.delay), (0.0, 0.0));
+        assert_eq!(overrides.velocity, None);
+        state.set_velocity_enabled(true);
+        state.prepare_key_press(&mut overrides);
+        assert_eq!(overrides.velocity, Some(1.0));
+
+        state.set_humanize(Humanize {
+            detune_cents: 10.0,
+            timing_jitter: 0.02,
+        });
+        let mut detunes = Vec::new();
+        for _ in 0..50 {
+            state.prepare_key_press(&mut overrides);
+            assert!(overrides.detune_cents.abs() <= 10.0);
+            assert!((0.0..=0.02).contains(&overrides.delay));
+            detunes.push(overrides.detune_cents);
+        }
+        assert!(detunes.windows(2).all(|pair| pair[0] != pair[1]));
+
+        // The note is detuned and starts after its delay
+        overrides.detune_cents = 100.0;
+        overrides.delay = 0.01;
+        state.start_note_with_overrides("A", 440.0, 0.5, &overrides);
+        let note = state.note("A").unwrap();
+        assert!((note.frequency - 440.0 * 2.0_f32.powf(1.0 / 12.0)).abs() < 0.01);
+        let mut block = [0.0; 20];
+        state.fill_block(&mut block);
+        assert!(block[..10].iter().all(|&sample| sample == 0.0));
+        assert!(block[10..].iter().any(|&sample| sample != 0.0));
+    }
+
+    #[test]
+    fn test_modulation() {
+        let mut state = AudioState::new(1000.0, Waveform::Natural, 1.0, 1200.0);
+        state.start_note_with_id("A", 440.0, 0.5);
+        assert_eq!(
+            state.note("A").unwrap().vibrato,
+            Vibrato::for_waveform(Waveform::Natural)
+        );
+
+        // The config's vibrato replaces the waveform's, and a pitch LFO bends sounding notes
+        let vibrato = Vibrato {
+            rate: 6.0,
+            depth: 0.0,
+        };
+        let lfo = Lfo {
+            rate: 1.0,
+            depth: 12.0,
+            target: LfoTarget::Pitch,
+        };
+        state.set_modulation(Some(vibrato), Some(lfo), ModMatrix::default());
+        state.start_note_with_id("B", 440.0, 0.5);
+        assert_eq!(state.note("B").unwrap().vibrato, Some(vibrato));
+        let mut block = [0.0; 250];
+        state.fill_block(&mut block);
+        state.fill_block(&mut block[..1]);
+        // A quarter cycle in, the LFO is an octave up
+        assert!((state.note("B").unwrap().pitch_bend - 2.0).abs() < 0.01);
+        // and it still is ten hours (whole cycles) later
+        state.sample_clock += 1000 * 36_000;
+        state.fill_block(&mut block[..1]);
+        assert!((state.note("B").unwrap().pitch_bend - 2.0).abs() < 0.01);
+
+        state.set_modulation(None, None, ModMatrix::default());
+        state.fill_block(&mut block[..1]);
+        assert_eq!(state.note("B").unwrap().pitch_bend, 1.0);
+
+        // A full-depth volume LFO silences the output at the bottom of its cycle
+        let lfo = Lfo {
+            rate: 1.0,
+            depth: 1.0,
+            target: LfoTarget::Volume,
+        };
+        state.set_modulation(None, Some(lfo), ModMatrix::default());
+        let mut block = [0.0; 1000];
+        state.fill_block(&mut block);
+        assert!(block[496..500].iter().all(|sample| sample.abs() < 1e-3));
+        assert!(block[990..1000].iter().any(|sample| sample.abs() > 0.01));
+    }
+
+    #[test]
+    fn test_mod_matrix() {
+        use crate::modulation::{ModRoute, ModTarget};
+
+        let mut state = AudioState::new(1000.0, Waveform::Electronic, 1.0, 1200.0);
+        let route = |source, target, amount| ModRoute {
+            source,
+            target,
+            amount,
+        };
+        let matrix = ModMatrix {
+            routes: vec![
+                route(ModSource::KeyPosition, ModTarget::Pan, 0.5),
+                route(ModSource::Envelope, ModTarget::Filter, -4.0),
+                route(ModSource::Wpm, ModTarget::Amp, -0.5),
+            ],
+            ..ModMatrix::default()
+        };
+        state.set_modulation(None, None, matrix);
+
+        // Two presses 0.1 s apart: 120 words per minute
+        for key in ["A", "B"] {
+            let mut overrides = NoteOverrides {
+                key_position: Some(1.0),
+                ..NoteOverrides::default()
+            };
+            state.prepare_key_press(&mut overrides);
+            overrides.velocity = None;
+            state.start_note_with_overrides(key, 440.0, 0.5, &overrides);
+            let mut block = [0.0; 100];
+            state.fill_block(&mut block);
+        }
+        state.fill_block(&mut [0.0; 1]);
+
+        let note = state.note("B").unwrap();
+        assert_eq!(note.mod_pan, 0.5);
+        assert!((note.mod_gain - 0.5).abs() < 0.01);
+        // The envelope has reached its peak, closing the filter 4 octaves
+        assert!(note.envelope_level > 0.5 && note.lowpass_alpha < 1.0);
+    }
+
+    #[test]
+    fn test_note_filter() {
+        let envelope = FilterEnvelope {
+            start: 100.0,
+            peak: 6400.0,
+            sustain: 800.0,
+            attack: 0.1,
+            decay: 0.2,
+            resonance: 0.0,
+        };
+        assert_eq!(envelope.cutoff(0.0), 100.0);
+        // Halfway through the attack is halfway in pitch
+        assert!((envelope.cutoff(0.05) - 800.0).abs() < 0.1);
+        assert!((envelope.cutoff(0.1) - 6400.0).abs() < 0.1);
+        assert_eq!(envelope.cutoff(1.0), 800.0);
+
+        // Peak level of a sine through the sustained filter
+        let peak = |resonance: f32, frequency: f32| {
+            let envelope = FilterEnvelope {
+                resonance,
+                ..envelope.clone()
+            };
+            let mut filter = NoteFilter::new(envelope, 8000.0);
+            (0..8000)
+                .map(|i| {
+                    let phase = 2.0 * std::f32::consts::PI * frequency * i as f32 / 8000.0;
+                    filter.process(phase.sin())
+                })
+                .skip(4000)
+                .fold(0.0_f32, |max, sample| max.max(sample.abs()))
+        };
+        assert!(peak(0.0, 100.0) > 0.9);
+        assert!(peak(0.0, 3200.0) < 0.1);
+        assert!(peak(0.9, 800.0) > 3.0 * peak(0.0, 800.0));
+
+        // The cutoff only moves every FILTER_UPDATE_INTERVAL samples
+        let mut filter = NoteFilter::new(envelope.clone(), 8000.0);
+        filter.process(0.0);
+        assert_eq!(filter.cutoff, 100.0);
+        for _ in 1..FILTER_UPDATE_INTERVAL {
+            filter.process(0.0);
+        }
+        assert_eq!(filter.cutoff, 100.0);
+        filter.process(0.0);
+        assert!(filter.cutoff > 100.0);
+
+        // A key's own envelope wins over the engine-wide one
+        let mut state = AudioState::new(8000.0, Waveform::Saw, 1.0, 1200.0);
+        state.set_filter_envelope(Some(FilterEnvelope::default()));
+        state.start_note_with_id("A", 440.0, 0.5);
+        let overrides = NoteOverrides {
+            filter_envelope: Some(envelope.clone()),
+            ..NoteOverrides::default()
+        };
+        state.start_note_with_overrides("B", 440.0, 0.5, &overrides);
+        let filter = |key| state.note(key).unwrap().filter.as_ref().unwrap();
+        assert_eq!(filter("A").envelope, FilterEnvelope::default());
+        assert_eq!(filter("B").envelope, envelope);
+        let mut block = [0.0; 100];
+        state.fill_block(&mut block);
+        assert!(block.iter().all(|sample| sample.is_finite()));
+    }
+
+    #[test]
+    fn test_transpose() {
+        let mut state = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
+        state.set_transpose(12);
+        state.start_note_with_id("A", 440.0, 0.5);
+        assert!((state.note("A").unwrap().frequency - 880.0).abs() < 0.01);
+
+        state.set_transpose(100);
+        assert_eq!(state.get_transpose(), MAX_TRANSPOSE);
+    }
+
+    #[test]
+    fn test_waveform_override() {
+        let mut state = AudioState::new(44100.0, Waveform::Natural, 1.0, 1200.0);
+        let overrides = NoteOverrides {
+            waveform: Some(Waveform::Bass),
+            ..Default::default()
+        };
+        state.start_note_with_overrides("Space", 110.0, 0.5, &overrides);
+        state.start_note_with_id("A", 440.0, 0.5);
+
+        let space = state.note("Space").unwrap();
+        assert!(matches!(space.waveform, Waveform::Bass));
+        assert_eq!(
+            space.adsr.release_time,
+            ADSRParams::cyberpunk().release_time
+        );
+        assert!(matches!(
+            state.note("A").unwrap().waveform,
+            Waveform::Natural
+        ));
+    }
+
+    #[test]
+    fn test_adsr_override() {
+        let mut state = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
+        let overrides = NoteOverrides {
+            adsr: Some(AdsrConfig {
+                release: Some(0.01),
+                sustain: Some(2.0),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        state.start_note_with_overrides("Backspace", 440.0, 0.5, &overrides);
+
+        let adsr = &state.note("Backspace").unwrap().adsr;
+        assert_eq!(adsr.release_time, 0.01);
+        assert_eq!(adsr.sustain_level, 1.0);
+        assert_eq!(adsr.attack_time, ADSRParams::electronic().attack_time);
+
+        // The short release finishes well before the waveform's default one would
+        state.stop_note_with_id("Backspace");
+        for _ in 0..882 {
+            state.generate_sample();
+        }
+        assert!(state.voices.is_empty());
+    }
+
+    #[test]
+    fn test_filter_cutoff() {
+        // A low cutoff smooths out a bright square wave
+        let peak = |cutoff: Option<f32>| {
+            let mut state = AudioState::new(44100.0, Waveform::Square, 1.0, 1200.0);
+            if let Some(cutoff) = cutoff {
+                state.set_filter_cutoff(cutoff);
+            }
+            state.start_note_with_id("A", 2000.0, 0.5);
+            (0..4410)
+                .map(|_| state.generate_sample().abs())
+                .fold(0.0, f32::max)
+        };
+        assert!(peak(Some(100.0)) < peak(None) * 0.5);
+
+        let mut state = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
+        state.set_filter_cutoff(1.0e6);
+        assert_eq!(state.get_filter_cutoff(), MAX_FILTER_CUTOFF);
+    }
+
+    #[test]
+    fn test_parameter_smoothing() {
+        let mut state = AudioState::new(1000.0, Waveform::Electronic, 1.0, 1200.0);
+        state.start_note_with_id("A", 100.0, 0.5);
+        state.generate_sample();
+
+        // A volume change reaches sounding notes gradually rather than in one step
+        state.set_volume(0.0);
+        state.generate_sample();
+        assert!(state.volume_gain > 0.5, "{}", state.volume_gain);
+        for _ in 0..100 {
+            state.generate_sample();
+        }
+        assert_eq!(state.volume_gain, 0.0);
+
+        // So does a filter change
+        state.set_filter_cutoff(100.0);
+        state.generate_sample();
+        assert!(state.smoothed_cutoff > 10000.0);
+        for _ in 0..100 {
+            state.generate_sample();
+        }
+        assert_eq!(state.smoothed_cutoff, 100.0);
+
+        // Changing the waveform brings its envelope along
+        state.set_waveform(Waveform::Saw);
+        assert_eq!(
+            state.default_adsr.attack_time,
+            ADSRParams::punchy().attack_time
+        );
+    }
+
+    #[test]
+    fn test_voice_pool() {
+        let mut state = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
+        for i in 0..MAX_VOICES {
+            state.start_note_with_id(&format!("key{}", i), 440.0, 0.5);
+        }
+        assert_eq!(state.active_voice_count(), MAX_VOICES);
+
+        // A full pool replaces a releasing note before the oldest held one
+        state.stop_note_with_id("key5");
+        state.start_note_with_id("extra", 880.0, 0.5);
+        assert_eq!(state.active_voice_count(), MAX_VOICES);
+        assert!(state.note("key5").is_none());
+        assert!(state.note("key0").is_some());
+
+        // Then the oldest note, which fades out instead of stopping at once
+        state.start_note_with_id("another", 660.0, 0.5);
+        assert!(state.note("key0").is_none());
+        assert_eq!(state.voices.fading.len(), 2);
+        let fade_samples = (STEAL_FADE_TIME * 44100.0) as usize;
+        let mut block = vec![0.0; fade_samples - 10];
+        state.fill_block(&mut block);
+        assert_eq!(state.voices.fading.len(), 2);
+        state.fill_block(&mut [0.0; 20]);
+        assert!(state.voices.fading.is_empty());
+
+        // Pressing a sounding key restarts its note in the same slot
+        let index = state.voices.index_of(VoiceKey::id("extra")).unwrap();
+        state.start_note_with_id("extra", 440.0, 0.5);
+        assert_eq!(state.voices.index_of(VoiceKey::id("extra")), Some(index));
+        assert_eq!(state.voices.get(index).unwrap().frequency, 440.0);
+    }
+
+    #[test]
+    fn test_fill_block() {
+        let start = || {
+            let mut state = AudioState::new(44100.0, Waveform::Natural, 1.0, 1200.0);
+            state.set_filter_cutoff(2000.0);
+            state.start_note_with_id("A", 440.0, 0.5);
+            state.start_note_with_id("B", 494.0, 0.5);
+            state.add_sample_playback(SamplePlayback::new(
+                AudioSample {
+                    samples: vec![0.5; 300],
+                    sample_rate: 44100,
+                    channels: 1,
+                    loop_points: None,
+                },
+                0.0,
+                1.0,
+            ));
+            state
+        };
+
+        // Rendering blocks sounds the same as rendering sample by sample
+        let mut by_sample = start();
+        let expected: Vec<f32> = (0..1024).map(|_| by_sample.generate_sample()).collect();
+        let mut by_block = start();
+        let mut block = vec![0.0; 1024];
+        by_block.fill_block(&mut block[..256]);
+        by_block.fill_block(&mut block[256..]);
+        for (i, (a, b)) in expected.iter().zip(&block).enumerate() {
+            assert!((a - b).abs() < 1e-5, "sample {}: {} != {}", i, a, b);
+        }
+        assert!(by_block.active_sample_playbacks.is_empty());
+    }
+
+    #[test]
+    fn test_fill_interleaved() {
+        let mut mono = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
+        let mut stereo = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
+        let overrides = NoteOverrides {
+            pan: Some(-0.5),
+            ..Default::default()
+        };
+        mono.start_note_with_overrides("Q", 440.0, 0.5, &overrides);
+        stereo.start_note_with_overrides("Q", 440.0, 0.5, &overrides);
+
+        let mut block = vec![0.0; 256];
+        let mut frames = vec![1.0; 256 * 3];
+        mono.fill_block(&mut block);
+        stereo.fill_interleaved(&mut frames, 3);
+        // One frame per sample of the mono output: the left channel at full
+        // level, the right at half and the third silent
+        for (sample, frame) in block.iter().zip(frames.chunks(3)) {
+            assert!((frame[0] - sample).abs() < 1e-6);
+            assert!((frame[1] - sample * 0.5).abs() < 1e-6);
+            assert_eq!(frame[2], 0.0);
+        }
+        assert_eq!(stereo.now(), mono.now());
+
+        assert_eq!(pan_gains(0.0), (1.0, 1.0));
+        assert_eq!(pan_gains(1.0), (0.0, 1.0));
+        assert_eq!(pan_gains(-3.0), (1.0, 0.0));
+    }
+
+    #[test]
+    fn test_denormal_flush() {
+        let mut state = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
+        state.set_filter_cutoff(20.0);
+        state.start_note_with_id("A", 440.0, 0.5);
+        for _ in 0..4410 {
+            state.generate_sample();
+        }
+        state.stop_note_with_id("A");
+
+        // The filter's decaying tail goes to zero without passing through denormals
+        for _ in 0..88200 {
+            state.generate_sample();
+            assert!(!state.filter_state[0].is_subnormal());
+        }
+        assert_eq!(state.filter_state, [0.0; 2]);
+        assert_eq!(flush_denormal(1e-30), 0.0);
+        assert_eq!(flush_denormal(-0.5), -0.5);
+    }
+
+    #[test]
+    fn test_release_all() {
+        let mut state = AudioState::new(44100.0, Waveform::Electronic, 1.0, 1200.0);
+        state.start_note_with_id("A", 440.0, 0.5);
+        state.start_note_with_id("B", 494.0, 0.5);
+
+        state.release_all();
+        for _ in 0..44100 {
+            state.generate_sample();
+        }
+        assert!(state.voices.is_empty());
+    }
+
+    #[test]
+    fn test_schedule_note() {
+        let mut state = AudioState::new(1000.0, Waveform::Electronic, 1.0, 1200.0);
+        let mut block = [0.0; 100];
+        state.schedule_note("notify:0", 440.0, 0.8, 0.15, 0.2);
+
+        // Starts with the first buffer after 0.15s and is released with the first after 0.35s
+        state.fill_block(&mut block);
+        state.fill_block(&mut block);
+        assert!(state.note("notify:0").is_none());
+        state.fill_block(&mut block);
+        assert!(
+            state
+                .note("notify:0")
+                .is_some_and(|note| !matches!(note.envelope_state, EnvelopeState::Release))
+        );
+        state.fill_block(&mut block);
+        state.fill_block(&mut block);
+        // Released (and possibly already faded out)
+        assert!(
+            state
+                .note("notify:0")
+                .is_none_or(|note| matches!(note.envelope_state, EnvelopeState::Release))
+        );
+
+        state.schedule_note("notify:1", 440.0, 0.8, 0.05, 0.2);
+        state.release_all();
+        state.fill_block(&mut block);
+        state.fill_block(&mut block);
+        assert!(state.note("notify:1").is_none());
+
+        // Delays stay exact hours into a session
+        state.sample_clock = 1000 * 3600;
+        state.schedule_note("notify:2", 440.0, 0.8, 0.05, 0.2);
+        state.fill_block(&mut [0.0; 50]);
+        assert!(state.note("notify:2").is_none());
+        state.fill_block(&mut [0.0; 1]);
+        assert!(state.note("notify:2").is_some());
+    }
+
+    #[test]
+    fn test_idle_fade() {
+        let mut state = AudioState::new(1000.0, Waveform::Electronic, 1.0, 1200.0);
+        state.set_idle_timeout(Some(1.0));
+
+        // Within the timeout the output is untouched
+        for _ in 0..900 {
+            state.generate_sample();
+        }
+        assert_eq!(state.get_idle_gain(), 1.0);
+
+        // After the timeout plus the fade time the output is silent
+        for _ in 0..2200 {
+            state.generate_sample();
+        }
+        assert_eq!(state.get_idle_gain(), 0.0);
+
+        // A key press fades straight back in
+        state.register_activity();
+        for _ in 0..50 {
+            state.generate_sample();
+        }
+        assert_eq!(state.get_idle_gain(), 1.0);
     }
 
     #[test]
@@ -540,15 +2585,93 @@ mod tests {
         let mut limiter = RateLimiter::new();
 
         // First press should have full volume
-        let vol1 = limiter.record_press_and_get_volume_multiplier("test_key");
+        let vol1 = limiter.record_press_and_get_volume_multiplier("test_key", 0.0);
         assert_eq!(vol1, 1.0);
 
         // Rapid second press should have reduced volume (0.7)
-        let vol2 = limiter.record_press_and_get_volume_multiplier("test_key");
+        let vol2 = limiter.record_press_and_get_volume_multiplier("test_key", 0.1);
         assert!((vol2 - 0.7).abs() < 0.01);
 
         // Different key should start fresh
-        let vol_other = limiter.record_press_and_get_volume_multiplier("other_key");
+        let vol_other = limiter.record_press_and_get_volume_multiplier("other_key", 0.1);
         assert_eq!(vol_other, 1.0);
+
+        // Presses outside the window no longer count
+        let vol3 = limiter.record_press_and_get_volume_multiplier("test_key", 1.0);
+        assert_eq!(vol3, 1.0);
+
+        // Keys idle for longer than the window are forgotten once many are tracked
+        for i in 0..MAX_TRACKED_KEYS {
+            limiter.record_press_and_get_volume_multiplier(&format!("remote-{}", i), 2.0);
+        }
+        limiter.record_press_and_get_volume_multiplier("test_key", 5.0);
+        assert_eq!(limiter.press_history.len(), 1);
+    }
+
+    #[test]
+    fn test_clock_after_long_sessions() {
+        // Ten hours in, the clock still advances by exactly one block
+        let mut state = AudioState::new(48000.0, Waveform::Electronic, 1.0, 1200.0);
+        state.sample_clock = 48000 * 36000;
+        let start = state.now();
+        state.fill_block(&mut [0.0; 480]);
+        assert!((state.now() - start - 0.01).abs() < 1e-9);
+
+        // Presses a second apart keep their full volume
+        state.start_note_with_id("A", 440.0, 0.5);
+        state.fill_block(&mut vec![0.0; 48000]);
+        assert_eq!(state.start_note_with_id("A", 440.0, 0.5), 0.5);
+
+        // and a sample started now plays every frame in order
+        let mut state = AudioState::new(48000.0, Waveform::Electronic, 1.0, 1200.0);
+        state.sample_clock = 48000 * 36000;
+        let ramp: Vec<f32> = (0..64).map(|frame| frame as f32 / 64.0).collect();
+        let playback = SamplePlayback::new(
+            AudioSample {
+                samples: ramp.clone(),
+                sample_rate: 48000,
+                channels: 1,
+                loop_points: None,
+            },
+            state.now(),
+            1.0,
+        );
+        let frames: Vec<f32> = (1..=63)
+            .map(|frame| playback.get_current_sample(state.now() + frame as f64 / 48000.0, 48000.0))
+            .collect();
+        for (frame, expected) in frames.iter().zip(&ramp[1..]) {
+            assert!((frame - expected).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_velocity() {
+        let mut tracker = VelocityTracker::default();
+        assert_eq!(tracker.record_press(0.0), 0.0);
+        assert_eq!(tracker.record_press(0.05), 1.0);
+        let medium = tracker.record_press(0.35);
+        assert!(medium > 0.0 && medium < 1.0);
+        assert_eq!(tracker.record_press(2.0), 0.0);
+        assert_eq!(velocity_gain(0.0), MIN_VELOCITY_GAIN);
+        assert_eq!(velocity_cutoff(1.0), MAX_FILTER_CUTOFF);
+
+        // A soft press is quieter and darker than a hard one
+        let peak = |velocity| {
+            let mut state = AudioState::new(44100.0, Waveform::Saw, 1.0, 1200.0);
+            let overrides = NoteOverrides {
+                velocity: Some(velocity),
+                ..Default::default()
+            };
+            let volume = state.start_note_with_overrides("A", 220.0, 0.5, &overrides);
+            let mut block = [0.0; 2048];
+            state.fill_block(&mut block);
+            let peak = block.iter().fold(0.0_f32, |peak, s| peak.max(s.abs()));
+            (volume, peak)
+        };
+        let (soft_volume, soft_peak) = peak(0.0);
+        let (hard_volume, hard_peak) = peak(1.0);
+        assert!((soft_volume - 0.5 * MIN_VELOCITY_GAIN).abs() < 1e-6);
+        assert_eq!(hard_volume, 0.5);
+        assert!(soft_peak < hard_peak * MIN_VELOCITY_GAIN);
     }
 }
@@ -1,11 +1,20 @@
-//! Audio samples module for loading and playing WAV files
+//! Audio samples module for loading and playing sound files
 //!
-//! This module handles loading audio samples from WAV files and playing them back
-//! in the audio synthesis pipeline. Used for realistic sound effects like fart sounds.
+//! This module handles loading audio samples from WAV files (and compressed
+//! OGG/FLAC/MP3 files via symphonia) and playing them back in the audio
+//! synthesis pipeline. Used for realistic sound effects like fart sounds.
 
 use hound;
+use std::fs::File;
 use std::io::Cursor;
 use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CODEC_TYPE_NULL, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
 
 /// Audio sample data loaded from a WAV file
 #[derive(Clone, Debug)]
@@ -16,42 +25,132 @@ pub struct AudioSample {
     pub sample_rate: u32,
     /// Number of channels (1 for mono, 2 for stereo)
     pub channels: u16,
+    /// Optional sustain loop region (read from the WAV `smpl` chunk or set by config)
+    pub loop_points: Option<LoopPoints>,
+}
+
+/// Loop region of a sample, in frames (end is exclusive)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoopPoints {
+    pub start: usize,
+    pub end: usize,
 }
 
 impl AudioSample {
-    /// Load an audio sample from a WAV file
+    /// Load an audio sample from a sound file
+    ///
+    /// WAV files are read directly; other extensions (ogg, flac, mp3) are decoded
+    /// with symphonia.
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut reader = hound::WavReader::open(path)?;
-        let spec = reader.spec();
+        let path = path.as_ref();
+        let is_wav = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_none_or(|ext| ext.eq_ignore_ascii_case("wav"));
+
+        if is_wav {
+            Self::load_wav_file(path)
+        } else {
+            Self::load_compressed_file(path)
+        }
+    }
 
-        // Read all samples and convert to f32
-        let samples: Result<Vec<f32>, _> = match spec.sample_format {
-            hound::SampleFormat::Float => reader.samples::<f32>().collect(),
-            hound::SampleFormat::Int => {
-                // Convert integer samples to float
-                let bit_depth = spec.bits_per_sample;
-                let max_val = (1 << (bit_depth - 1)) as f32;
+    /// Load an audio sample from a WAV file
+    fn load_wav_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load_from_bytes(&std::fs::read(path)?)
+    }
 
-                reader
-                    .samples::<i32>()
-                    .map(|sample| sample.map(|s| s as f32 / max_val))
-                    .collect()
+    /// Decode a compressed audio file (OGG Vorbis, FLAC, MP3) into an audio sample
+    fn load_compressed_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::open(path)?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+            hint.with_extension(ext);
+        }
+
+        let probed = symphonia::default::get_probe().format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?;
+        let mut format = probed.format;
+
+        let track = format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or("No supported audio track found")?;
+        let track_id = track.id;
+        let mut sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+        let mut channels = track
+            .codec_params
+            .channels
+            .map(|c| c.count() as u16)
+            .unwrap_or(1);
+        let mut decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())?;
+
+        let mut samples = Vec::new();
+        loop {
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                // End of stream
+                Err(SymphoniaError::IoError(e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    break;
+                }
+                Err(SymphoniaError::ResetRequired) => break,
+                Err(e) => return Err(e.into()),
+            };
+            if packet.track_id() != track_id {
+                continue;
             }
-        };
 
-        let samples = samples?;
+            match decoder.decode(&packet) {
+                Ok(decoded) => {
+                    let spec = *decoded.spec();
+                    sample_rate = spec.rate;
+                    channels = spec.channels.count() as u16;
+
+                    // Interleave and convert to f32 (-1.0 to 1.0)
+                    let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+                    buffer.copy_interleaved_ref(decoded);
+                    samples.extend_from_slice(buffer.samples());
+                }
+                // Skip corrupt packets rather than failing the whole file
+                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        if sample_rate == 0 {
+            return Err("Could not determine sample rate".into());
+        }
 
         Ok(AudioSample {
             samples,
-            sample_rate: spec.sample_rate,
-            channels: spec.channels,
+            sample_rate,
+            channels,
+            loop_points: None,
         })
     }
 
     /// Load an audio sample from byte data (WAV format)
     pub fn load_from_bytes(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
-        let cursor = Cursor::new(data);
-        let mut reader = hound::WavReader::new(cursor)?;
+        let mut sample = Self::from_wav_reader(hound::WavReader::new(Cursor::new(data))?)?;
+        sample.loop_points = read_wav_loop_points(data)
+            .filter(|points| points.start < points.end && points.end <= sample.frame_count());
+        Ok(sample)
+    }
+
+    /// Read every sample from an open WAV reader and convert to f32
+    fn from_wav_reader<R: std::io::Read>(
+        mut reader: hound::WavReader<R>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let spec = reader.spec();
 
         // Read all samples and convert to f32
@@ -75,9 +174,67 @@ impl AudioSample {
             samples,
             sample_rate: spec.sample_rate,
             channels: spec.channels,
+            loop_points: None,
         })
     }
 
+    /// Set a loop region, defaulting to the existing loop or the whole sample
+    pub fn with_loop(mut self, start: Option<usize>, end: Option<usize>) -> Self {
+        let frames = self.frame_count();
+        let current = self.loop_points.unwrap_or(LoopPoints {
+            start: 0,
+            end: frames,
+        });
+        let end = end.unwrap_or(current.end).min(frames);
+        let start = start.unwrap_or(current.start);
+
+        self.loop_points = (start < end).then_some(LoopPoints { start, end });
+        self
+    }
+
+    /// Convert the sample to another sample rate (linear interpolation)
+    ///
+    /// Samples are converted to the output's rate once when loaded, so they
+    /// keep their pitch and length whatever rate the stream runs at.
+    pub fn resampled(&self, target_sample_rate: u32) -> Self {
+        if target_sample_rate == self.sample_rate || self.sample_rate == 0 {
+            return self.clone();
+        }
+
+        let channels = self.channels.max(1) as usize;
+        let frames = self.frame_count();
+        let ratio = self.sample_rate as f64 / target_sample_rate as f64;
+        let new_frames = (frames as f64 / ratio).round() as usize;
+        let mut samples = Vec::with_capacity(new_frames * channels);
+        for frame in 0..new_frames {
+            let position = frame as f64 * ratio;
+            let index = position as usize;
+            let frac = (position - index as f64) as f32;
+            let next = (index + 1).min(frames.saturating_sub(1));
+            for channel in 0..channels {
+                let current = self.samples[index * channels + channel];
+                let following = self.samples[next * channels + channel];
+                samples.push(current + (following - current) * frac);
+            }
+        }
+
+        let scale = |frame: usize| ((frame as f64 / ratio).round() as usize).min(new_frames);
+        Self {
+            samples,
+            sample_rate: target_sample_rate,
+            channels: self.channels,
+            loop_points: self.loop_points.and_then(|points| {
+                let (start, end) = (scale(points.start), scale(points.end));
+                (start < end).then_some(LoopPoints { start, end })
+            }),
+        }
+    }
+
+    /// Number of frames (samples per channel)
+    pub fn frame_count(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
     /// Get the duration of the sample in seconds
     pub fn duration(&self) -> f32 {
         self.samples.len() as f32 / (self.sample_rate as f32 * self.channels as f32)
@@ -92,15 +249,30 @@ impl AudioSample {
 
     /// Get a sample at a specific time position with proper sample rate conversion
     pub fn get_sample_at_time(&self, time_seconds: f32, target_sample_rate: f32) -> f32 {
-        if self.samples.is_empty() {
-            return 0.0;
-        }
+        self.get_sample_at_frame(self.frame_position(time_seconds, target_sample_rate))
+    }
 
+    /// Convert a playback time to a (fractional) frame position
+    pub fn frame_position(&self, time_seconds: f32, target_sample_rate: f32) -> f32 {
         // Convert time to sample position accounting for sample rate difference
         // If target rate is higher, we need to slow down the playback
         let time_scaling = self.sample_rate as f32 / target_sample_rate;
         let adjusted_time = time_seconds * time_scaling;
-        let sample_pos = adjusted_time * self.sample_rate as f32;
+        adjusted_time * self.sample_rate as f32
+    }
+
+    /// Convert a frame position back to the playback time that reaches it
+    pub fn time_for_frame(&self, frame_position: f32, target_sample_rate: f32) -> f32 {
+        let sample_rate = self.sample_rate as f32;
+        frame_position * target_sample_rate / (sample_rate * sample_rate)
+    }
+
+    /// Get an interpolated sample at a fractional frame position
+    pub fn get_sample_at_frame(&self, sample_pos: f32) -> f32 {
+        if self.samples.is_empty() || sample_pos < 0.0 {
+            return 0.0;
+        }
+
         let frame_index = sample_pos as usize;
 
         // For stereo files, each frame has multiple samples
@@ -152,32 +324,113 @@ impl AudioSample {
     }
 }
 
+/// A sample that plays when the hit velocity falls inside its range
+#[derive(Clone, Debug)]
+pub struct VelocityLayer {
+    /// Lowest velocity (0.0 to 1.0) that selects this layer
+    pub min_velocity: f32,
+    /// Highest velocity (0.0 to 1.0) that selects this layer
+    pub max_velocity: f32,
+    /// Sample played for this layer
+    pub sample: AudioSample,
+}
+
+impl VelocityLayer {
+    /// Create a layer covering the full velocity range
+    pub fn full_range(sample: AudioSample) -> Self {
+        Self {
+            min_velocity: 0.0,
+            max_velocity: 1.0,
+            sample,
+        }
+    }
+
+    /// Check if a velocity selects this layer
+    pub fn contains(&self, velocity: f32) -> bool {
+        (self.min_velocity..=self.max_velocity).contains(&velocity)
+    }
+}
+
+/// Pick the layer for a velocity, falling back to the closest layer when none matches
+pub fn select_velocity_layer(layers: &[VelocityLayer], velocity: f32) -> Option<&VelocityLayer> {
+    layers
+        .iter()
+        .find(|layer| layer.contains(velocity))
+        .or_else(|| {
+            layers.iter().min_by(|a, b| {
+                let distance = |layer: &VelocityLayer| {
+                    (layer.min_velocity - velocity)
+                        .abs()
+                        .min((layer.max_velocity - velocity).abs())
+                };
+                distance(a).total_cmp(&distance(b))
+            })
+        })
+}
+
 /// Sample playback state for tracking individual sample instances
 #[derive(Clone, Debug)]
 pub struct SamplePlayback {
     /// Reference to the audio sample data
     pub sample: AudioSample,
-    /// Start time of playback
-    pub start_time: f32,
+    /// Start time of playback (seconds on the audio clock)
+    pub start_time: f64,
     /// Volume multiplier for this playback instance
     pub volume: f32,
     /// Whether this playback instance is still active
     pub active: bool,
+    /// Whether playback is cycling through the sample's loop region
+    pub looping: bool,
+    /// Key that started this playback, so its release can end the loop
+    pub key_id: Option<String>,
 }
 
 impl SamplePlayback {
     /// Create a new sample playback instance
-    pub fn new(sample: AudioSample, start_time: f32, volume: f32) -> Self {
+    pub fn new(sample: AudioSample, start_time: f64, volume: f32) -> Self {
         Self {
             sample,
             start_time,
             volume,
             active: true,
+            looping: false,
+            key_id: None,
+        }
+    }
+
+    /// Create a playback that loops until released (the whole sample if it has no loop points)
+    pub fn looped(sample: AudioSample, start_time: f64, volume: f32) -> Self {
+        let sample = if sample.loop_points.is_some() {
+            sample
+        } else {
+            sample.with_loop(None, None)
+        };
+        let looping = sample.loop_points.is_some();
+
+        Self {
+            looping,
+            ..Self::new(sample, start_time, volume)
+        }
+    }
+
+    /// Current frame position, wrapped into the loop region while looping
+    ///
+    /// Worked out in f64 so a loop held for minutes still lands on the right frame.
+    fn frame_position(&self, elapsed: f64, target_sample_rate: f32) -> f32 {
+        let sample_rate = f64::from(self.sample.sample_rate);
+        let position = elapsed * sample_rate * sample_rate / f64::from(target_sample_rate);
+
+        match self.sample.loop_points {
+            Some(points) if self.looping && position >= points.end as f64 => {
+                let loop_length = (points.end - points.start) as f64;
+                (points.start as f64 + (position - points.start as f64) % loop_length) as f32
+            }
+            _ => position as f32,
         }
     }
 
     /// Get the current sample value for this playback instance
-    pub fn get_current_sample(&self, current_time: f32, target_sample_rate: f32) -> f32 {
+    pub fn get_current_sample(&self, current_time: f64, target_sample_rate: f32) -> f32 {
         if !self.active {
             return 0.0;
         }
@@ -187,23 +440,43 @@ impl SamplePlayback {
             return 0.0;
         }
 
-        if self
-            .sample
-            .is_finished_at_sample_rate(elapsed, target_sample_rate)
+        if !self.looping
+            && self
+                .sample
+                .is_finished_at_sample_rate(elapsed as f32, target_sample_rate)
         {
             return 0.0;
         }
 
-        self.sample.get_sample_at_time(elapsed, target_sample_rate) * self.volume
+        let position = self.frame_position(elapsed, target_sample_rate);
+        self.sample.get_sample_at_frame(position) * self.volume
     }
 
     /// Check if this playback instance is finished
-    pub fn is_finished(&self, current_time: f32, target_sample_rate: f32) -> bool {
+    pub fn is_finished(&self, current_time: f64, target_sample_rate: f32) -> bool {
+        if self.looping {
+            return false;
+        }
+
         let elapsed = current_time - self.start_time;
         elapsed >= 0.0
             && self
                 .sample
-                .is_finished_at_sample_rate(elapsed, target_sample_rate)
+                .is_finished_at_sample_rate(elapsed as f32, target_sample_rate)
+    }
+
+    /// Leave the loop and let the rest of the sample play out from the current position
+    pub fn release(&mut self, current_time: f64, target_sample_rate: f32) {
+        if !self.looping {
+            return;
+        }
+
+        let elapsed = (current_time - self.start_time).max(0.0);
+        let position = self.frame_position(elapsed, target_sample_rate);
+        self.looping = false;
+        // Rebase the start time so linear playback resumes where the loop left off
+        self.start_time =
+            current_time - f64::from(self.sample.time_for_frame(position, target_sample_rate));
     }
 
     /// Stop this playback instance
@@ -212,6 +485,37 @@ impl SamplePlayback {
     }
 }
 
+/// Read the first sustain loop from a WAV file's `smpl` chunk
+fn read_wav_loop_points(data: &[u8]) -> Option<LoopPoints> {
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes = data.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+
+    // Skip the RIFF/WAVE header and walk the chunk list
+    let mut offset = 12;
+    while offset + 8 <= data.len() {
+        let id = &data[offset..offset + 4];
+        let size = read_u32(offset + 4)? as usize;
+        let body = offset + 8;
+
+        if id == b"smpl" {
+            let loop_count = read_u32(body + 28)?;
+            if loop_count == 0 {
+                return None;
+            }
+            // First loop record follows the 36-byte header; end is inclusive in the file
+            let start = read_u32(body + 36 + 8)? as usize;
+            let end = read_u32(body + 36 + 12)? as usize + 1;
+            return Some(LoopPoints { start, end });
+        }
+
+        offset = body + size + (size & 1);
+    }
+
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,6 +526,7 @@ mod tests {
             samples: vec![0.0, 0.5, 1.0, 0.5, 0.0, -0.5, -1.0, -0.5],
             sample_rate: 44100,
             channels: 1,
+            loop_points: None,
         };
 
         assert_eq!(sample.channels, 1);
@@ -235,6 +540,7 @@ mod tests {
             samples: vec![1.0, 0.5, 0.0, -0.5, -1.0],
             sample_rate: 44100,
             channels: 1,
+            loop_points: None,
         };
 
         let mut playback = SamplePlayback::new(sample, 0.0, 0.8);
@@ -258,6 +564,7 @@ mod tests {
             samples: vec![0.0, 1.0],
             sample_rate: 2, // 2 samples per second for easy testing
             channels: 1,
+            loop_points: None,
         };
 
         // At 0.5 seconds, should be halfway between samples
@@ -265,6 +572,204 @@ mod tests {
         assert!((mid_sample - 0.5).abs() < 0.001);
     }
 
+    #[test]
+    fn test_looped_playback_and_release() {
+        let sample = AudioSample {
+            samples: vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5],
+            sample_rate: 1,
+            channels: 1,
+            loop_points: Some(LoopPoints { start: 2, end: 4 }),
+        };
+        let mut playback = SamplePlayback::looped(sample, 0.0, 1.0);
+
+        // Frames 0, 1, then cycles 2, 3, 2, 3 ...
+        let values: Vec<f32> = (0..8)
+            .map(|t| playback.get_current_sample(f64::from(t), 1.0))
+            .collect();
+        assert_eq!(values, vec![0.0, 0.1, 0.2, 0.3, 0.2, 0.3, 0.2, 0.3]);
+        assert!(!playback.is_finished(100.0, 1.0));
+
+        // Releasing on frame 2 continues through the tail instead of jumping
+        playback.release(8.0, 1.0);
+        assert!(!playback.looping);
+        assert!((playback.get_current_sample(8.0, 1.0) - 0.2).abs() < 1e-6);
+        assert!((playback.get_current_sample(11.0, 1.0) - 0.5).abs() < 1e-6);
+        assert!(playback.is_finished(12.0, 1.0));
+    }
+
+    #[test]
+    fn test_with_loop_defaults() {
+        let sample = AudioSample {
+            samples: vec![0.0; 8],
+            sample_rate: 44100,
+            channels: 2,
+            loop_points: None,
+        };
+
+        let whole = sample.clone().with_loop(None, None);
+        assert_eq!(whole.loop_points, Some(LoopPoints { start: 0, end: 4 }));
+
+        let partial = sample.clone().with_loop(Some(1), Some(3));
+        assert_eq!(partial.loop_points, Some(LoopPoints { start: 1, end: 3 }));
+
+        // Empty regions are discarded
+        assert_eq!(sample.with_loop(Some(3), Some(3)).loop_points, None);
+    }
+
+    #[test]
+    fn test_resampled() {
+        let sample = AudioSample {
+            samples: vec![0.0, 1.0, 0.0, -1.0],
+            sample_rate: 4,
+            channels: 1,
+            loop_points: Some(LoopPoints { start: 1, end: 3 }),
+        };
+
+        let doubled = sample.resampled(8);
+        assert_eq!(doubled.sample_rate, 8);
+        assert_eq!(doubled.samples.len(), 8);
+        assert_eq!(&doubled.samples[..4], &[0.0, 0.5, 1.0, 0.5]);
+        assert_eq!(doubled.loop_points, Some(LoopPoints { start: 2, end: 6 }));
+        // Same length in seconds at the new rate
+        assert_eq!(doubled.duration(), sample.duration());
+
+        let stereo = AudioSample {
+            samples: vec![0.0, 1.0, 1.0, 0.0],
+            sample_rate: 2,
+            channels: 2,
+            loop_points: None,
+        };
+        assert_eq!(
+            stereo.resampled(4).samples,
+            vec![0.0, 1.0, 0.5, 0.5, 1.0, 0.0, 1.0, 0.0]
+        );
+        assert_eq!(stereo.resampled(2).samples, stereo.samples);
+    }
+
+    #[test]
+    fn test_wav_smpl_chunk_loop_points() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut buffer = Cursor::new(Vec::new());
+        {
+            let mut writer = hound::WavWriter::new(&mut buffer, spec).unwrap();
+            for _ in 0..100 {
+                writer.write_sample(0i16).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+
+        // Append a smpl chunk with one loop covering frames 10..=59
+        let mut smpl = vec![0u8; 36 + 24];
+        smpl[28..32].copy_from_slice(&1u32.to_le_bytes());
+        smpl[44..48].copy_from_slice(&10u32.to_le_bytes());
+        smpl[48..52].copy_from_slice(&59u32.to_le_bytes());
+        let mut data = buffer.into_inner();
+        data.extend_from_slice(b"smpl");
+        data.extend_from_slice(&(smpl.len() as u32).to_le_bytes());
+        data.extend_from_slice(&smpl);
+        let riff_size = (data.len() - 8) as u32;
+        data[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+        let sample = AudioSample::load_from_bytes(&data).unwrap();
+        assert_eq!(sample.loop_points, Some(LoopPoints { start: 10, end: 60 }));
+    }
+
+    #[test]
+    fn test_velocity_layer_selection() {
+        let layer = |min, max, value| VelocityLayer {
+            min_velocity: min,
+            max_velocity: max,
+            sample: AudioSample {
+                samples: vec![value],
+                sample_rate: 44100,
+                channels: 1,
+                loop_points: None,
+            },
+        };
+        let layers = vec![layer(0.0, 0.5, 0.1), layer(0.5, 1.0, 0.9)];
+
+        let soft = select_velocity_layer(&layers, 0.3).unwrap();
+        assert_eq!(soft.sample.samples[0], 0.1);
+        let hard = select_velocity_layer(&layers, 1.0).unwrap();
+        assert_eq!(hard.sample.samples[0], 0.9);
+
+        // Gaps fall back to the nearest layer
+        let sparse = vec![layer(0.0, 0.2, 0.1), layer(0.8, 1.0, 0.9)];
+        let near_top = select_velocity_layer(&sparse, 0.7).unwrap();
+        assert_eq!(near_top.sample.samples[0], 0.9);
+
+        assert!(select_velocity_layer(&[], 0.5).is_none());
+    }
+
+    #[test]
+    fn test_load_from_bytes() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut buffer = Cursor::new(Vec::new());
+        {
+            let mut writer = hound::WavWriter::new(&mut buffer, spec).unwrap();
+            for value in [0i16, 16384, -16384, 32767] {
+                writer.write_sample(value).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+
+        let sample = AudioSample::load_from_bytes(buffer.get_ref()).unwrap();
+        assert_eq!(sample.sample_rate, 8000);
+        assert_eq!(sample.channels, 1);
+        assert_eq!(sample.samples.len(), 4);
+        assert!((sample.samples[1] - 0.5).abs() < 1e-4);
+        assert!((sample.samples[2] + 0.5).abs() < 1e-4);
+
+        assert!(AudioSample::load_from_bytes(b"not a wav file").is_err());
+    }
+
+    #[test]
+    fn test_compressed_decoder_pipeline() {
+        // symphonia also understands WAV, which lets us exercise the decode path
+        // without shipping compressed fixtures
+        let path = std::env::temp_dir().join("codebeats_symphonia_test.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 22050,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..100 {
+            writer.write_sample((i * 100) as i16).unwrap();
+            writer.write_sample(-(i * 100) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let sample = AudioSample::load_compressed_file(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(sample.sample_rate, 22050);
+        assert_eq!(sample.channels, 2);
+        assert_eq!(sample.samples.len(), 200);
+        assert!((sample.samples[2] - 100.0 / 32768.0).abs() < 1e-4);
+        assert!((sample.samples[3] + 100.0 / 32768.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_unsupported_file_is_error() {
+        let path = std::env::temp_dir().join("codebeats_not_audio.ogg");
+        std::fs::write(&path, b"definitely not audio").unwrap();
+        let result = AudioSample::load_from_file(&path);
+        std::fs::remove_file(&path).ok();
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_inspect_fart_wav() {
         if let Err(e) = inspect_wav_file("effects/fart-quick-short.wav") {
@@ -0,0 +1,199 @@
+//! Network audio stream module
+//!
+//! This module serves the rendered output as an endless 16-bit PCM WAV stream
+//! over HTTP, so a remote machine or browser tab can listen along (e.g.
+//! `ffplay http://host:47804/` or an `<audio>` element). The audio callback
+//! copies each buffer into a recycled one and hands it over without blocking;
+//! the network thread encodes it. If the network falls behind, buffers are
+//! dropped rather than stalling playback.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Default address of the audio stream
+pub const DEFAULT_STREAM_ADDRESS: &str = "127.0.0.1:47804";
+
+/// Buffers queued between the audio callback and the network thread
+const QUEUED_BUFFERS: usize = 64;
+
+/// How long a write may block before a slow listener is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How long a new listener may take to send its request
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest request accepted from a listener, in bytes
+const MAX_REQUEST_SIZE: u64 = 8192;
+
+/// HTTP server streaming the audio output to every connected listener
+pub struct AudioStreamServer {
+    address: String,
+    buffers: SyncSender<Vec<f32>>,
+    /// Buffers the network thread is done with, reused so the audio
+    /// callback only allocates until each has grown to the device's buffer size
+    spare_buffers: Receiver<Vec<f32>>,
+}
+
+impl AudioStreamServer {
+    /// Start listening on the address for audio with the output's format
+    pub fn start(
+        address: &str,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind(address)?;
+        let listeners: Arc<Mutex<Vec<TcpStream>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let header: Arc<[u8]> = wav_stream_header(sample_rate, channels).into();
+        let listeners_clone = listeners.clone();
+        thread::spawn(move || {
+            // Each handshake runs on its own thread so a stalled client
+            // doesn't hold up the next ones
+            for stream in listener.incoming().flatten() {
+                let header = header.clone();
+                let listeners = listeners_clone.clone();
+                thread::spawn(move || {
+                    if let Ok(stream) = accept_listener(stream, &header)
+                        && let Ok(mut listeners) = listeners.lock()
+                    {
+                        listeners.push(stream);
+                    }
+                });
+            }
+        });
+
+        let (buffers, receiver) = mpsc::sync_channel(QUEUED_BUFFERS);
+        let (spare_sender, spare_buffers) = mpsc::channel();
+        for _ in 0..QUEUED_BUFFERS {
+            let _ = spare_sender.send(Vec::new());
+        }
+        thread::spawn(move || broadcast(receiver, spare_sender, listeners));
+
+        Ok(Self {
+            address: address.to_string(),
+            buffers,
+            spare_buffers,
+        })
+    }
+
+    /// Address this server listens on
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Queue interleaved output samples for the listeners without blocking
+    ///
+    /// The samples are dropped when every spare buffer is still queued.
+    pub fn push(&self, samples: &[f32]) {
+        let Ok(mut buffer) = self.spare_buffers.try_recv() else {
+            return;
+        };
+        buffer.clear();
+        buffer.extend_from_slice(samples);
+        let _ = self.buffers.try_send(buffer);
+    }
+}
+
+/// Encode queued buffers and write them to every listener, dropping those
+/// that fail, then hand each buffer back for reuse
+fn broadcast(
+    buffers: Receiver<Vec<f32>>,
+    spare_buffers: Sender<Vec<f32>>,
+    listeners: Arc<Mutex<Vec<TcpStream>>>,
+) {
+    let mut bytes = Vec::new();
+    for buffer in buffers {
+        encode_pcm16(&buffer, &mut bytes);
+        let _ = spare_buffers.send(buffer);
+        if let Ok(mut listeners) = listeners.lock() {
+            listeners.retain_mut(|listener| listener.write_all(&bytes).is_ok());
+        }
+    }
+}
+
+/// Read the HTTP request and answer with the stream headers
+fn accept_listener(
+    mut stream: TcpStream,
+    wav_header: &[u8],
+) -> Result<TcpStream, Box<dyn std::error::Error>> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST_SIZE));
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+
+    stream.write_all(
+        b"HTTP/1.1 200 OK\r\nContent-Type: audio/wav\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+    )?;
+    stream.write_all(wav_header)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    Ok(stream)
+}
+
+/// WAV header for 16-bit PCM with the data size left open for streaming
+fn wav_stream_header(sample_rate: u32, channels: u16) -> Vec<u8> {
+    let block_align = channels * 2;
+    let byte_rate = sample_rate * block_align as u32;
+
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&u32::MAX.to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&byte_rate.to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&(u32::MAX - 36).to_le_bytes());
+    header
+}
+
+/// Convert float samples to little-endian 16-bit PCM, replacing `out`'s contents
+fn encode_pcm16(samples: &[f32], out: &mut Vec<u8>) {
+    out.clear();
+    out.extend(
+        samples
+            .iter()
+            .flat_map(|sample| ((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes()),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wav_stream_header() {
+        let header = wav_stream_header(44100, 2);
+        assert_eq!(header.len(), 44);
+        assert_eq!(&header[..4], b"RIFF");
+        assert_eq!(&header[8..16], b"WAVEfmt ");
+        assert_eq!(u16::from_le_bytes([header[22], header[23]]), 2);
+        assert_eq!(
+            u32::from_le_bytes([header[24], header[25], header[26], header[27]]),
+            44100
+        );
+        assert_eq!(
+            u32::from_le_bytes([header[28], header[29], header[30], header[31]]),
+            44100 * 4
+        );
+        assert_eq!(&header[36..40], b"data");
+    }
+
+    #[test]
+    fn test_encode_pcm16() {
+        let mut out = vec![1, 2, 3];
+        encode_pcm16(&[0.0, 1.0, -1.0, 2.0], &mut out);
+        assert_eq!(out, vec![0, 0, 0xFF, 0x7F, 0x01, 0x80, 0xFF, 0x7F]);
+    }
+}
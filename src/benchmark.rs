@@ -0,0 +1,120 @@
+//! Synthesis benchmark module
+//!
+//! This module measures how long the synth takes to fill an audio buffer with
+//! a number of notes held down, for each waveform, and compares it with the
+//! real-time budget (the buffer's duration). `codebeats benchmark` prints the
+//! results so performance regressions are easy to spot.
+
+use crate::audio_engine::AudioState;
+use crate::waveforms::{Waveform, get_all_waveforms};
+use std::time::{Duration, Instant};
+
+/// Settings for a benchmark run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchmarkOptions {
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    /// Frames per buffer
+    pub buffer_size: usize,
+    /// Notes held down at the same time
+    pub voices: usize,
+    /// Buffers measured per waveform
+    pub buffers: usize,
+}
+
+impl Default for BenchmarkOptions {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44100,
+            buffer_size: 512,
+            voices: 16,
+            buffers: 500,
+        }
+    }
+}
+
+/// Buffer timings for one waveform
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkResult {
+    pub waveform: Waveform,
+    /// Average time to fill a buffer
+    pub mean: Duration,
+    /// Slowest buffer
+    pub worst: Duration,
+    /// Real-time duration of a buffer
+    pub budget: Duration,
+}
+
+impl BenchmarkResult {
+    /// How many times faster than real time an average buffer is synthesized
+    pub fn headroom(&self) -> f64 {
+        self.budget.as_secs_f64() / self.mean.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Check whether even the slowest buffer finished within the budget
+    pub fn is_realtime(&self) -> bool {
+        self.worst <= self.budget
+    }
+}
+
+/// Benchmark one waveform with the given number of held notes
+pub fn run(waveform: Waveform, options: &BenchmarkOptions) -> BenchmarkResult {
+    let mut state = AudioState::new(options.sample_rate as f32, waveform, 0.5, 1200.0);
+    for voice in 0..options.voices {
+        // Spread the voices over four octaves from A2
+        let frequency = 110.0 * 2.0_f32.powf((voice % 48) as f32 / 12.0);
+        state.start_note_with_id(&format!("voice-{}", voice), frequency, 0.5);
+    }
+
+    let mut total = Duration::ZERO;
+    let mut worst = Duration::ZERO;
+    let mut buffer = vec![0.0; options.buffer_size];
+    for _ in 0..options.buffers {
+        let start = Instant::now();
+        state.fill_block(&mut buffer);
+        let elapsed = start.elapsed();
+        std::hint::black_box(&buffer);
+
+        total += elapsed;
+        worst = worst.max(elapsed);
+    }
+
+    BenchmarkResult {
+        waveform,
+        mean: total / options.buffers.max(1) as u32,
+        worst,
+        budget: Duration::from_secs_f64(options.buffer_size as f64 / options.sample_rate as f64),
+    }
+}
+
+/// Benchmark every waveform
+pub fn run_all(options: &BenchmarkOptions) -> Vec<BenchmarkResult> {
+    get_all_waveforms()
+        .into_iter()
+        .filter_map(|(name, _)| name.parse().ok())
+        .map(|waveform| run(waveform, options))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_all() {
+        let options = BenchmarkOptions {
+            sample_rate: 48000,
+            buffer_size: 480,
+            voices: 4,
+            buffers: 3,
+        };
+
+        let results = run_all(&options);
+        assert_eq!(results.len(), get_all_waveforms().len());
+        for result in &results {
+            assert_eq!(result.budget, Duration::from_millis(10));
+            assert!(result.mean <= result.worst);
+            assert!(result.headroom() > 0.0);
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Call and screen-share detection module
+//!
+//! This module detects calls and screen sharing, so the engine can pause itself
+//! during meetings when asked to. A call is a known call app running while the
+//! microphone is in use; screen sharing is a process that only exists while
+//! sharing. Detection runs on a background thread since the checks scan processes:
+//! - macOS: whether the default input device is running (CoreAudio)
+//! - Linux: whether an ALSA capture stream is running
+//! - Other platforms: no detection
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// How often calls and screen sharing are polled
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Process names of call apps, which count as in a call while the microphone is in use
+const CALL_PROCESSES: &[&str] = &[
+    "zoom",
+    "zoom.us",
+    "MSTeams",
+    "teams-for-linux",
+    "skypeforlinux",
+    "Webex",
+    "webex",
+];
+
+/// Process names that only run while the screen is being shared
+const SCREEN_SHARE_PROCESSES: &[&str] = &[
+    "CptHost", // Zoom screen sharing
+];
+
+/// Background poller tracking calls and screen sharing
+pub struct CaptureMonitor {
+    capturing: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Default for CaptureMonitor {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+impl CaptureMonitor {
+    /// Start polling for calls and screen sharing
+    pub fn start() -> Self {
+        let capturing = Arc::new(AtomicBool::new(false));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let capturing_clone = capturing.clone();
+        let stop_clone = stop.clone();
+        thread::spawn(move || {
+            while !stop_clone.load(Ordering::Relaxed) {
+                let active = call_in_progress() || screen_share_running();
+                capturing_clone.store(active, Ordering::Relaxed);
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+        Self { capturing, stop }
+    }
+
+    /// Whether a call or screen share was in progress at the last poll
+    pub fn is_capturing(&self) -> bool {
+        self.capturing.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for CaptureMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Check whether a known call app is running and the microphone is in use
+pub fn call_in_progress() -> bool {
+    process_running(is_call_process) && microphone_in_use()
+}
+
+/// Check whether a known screen sharing process is running
+pub fn screen_share_running() -> bool {
+    process_running(is_screen_share_process)
+}
+
+/// Check whether the default input device is running in any process
+#[cfg(target_os = "macos")]
+pub fn microphone_in_use() -> bool {
+    use std::ffi::c_void;
+
+    #[repr(C)]
+    struct AudioObjectPropertyAddress {
+        selector: u32,
+        scope: u32,
+        element: u32,
+    }
+
+    #[link(name = "CoreAudio", kind = "framework")]
+    unsafe extern "C" {
+        fn AudioObjectGetPropertyData(
+            object_id: u32,
+            address: *const AudioObjectPropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            data_size: *mut u32,
+            data: *mut c_void,
+        ) -> i32;
+    }
+
+    const SYSTEM_OBJECT: u32 = 1;
+    let global_scope = u32::from_be_bytes(*b"glob");
+
+    // Read a u32 property, returning None on error
+    let read_u32 = |object_id: u32, selector: &[u8; 4]| -> Option<u32> {
+        let address = AudioObjectPropertyAddress {
+            selector: u32::from_be_bytes(*selector),
+            scope: global_scope,
+            element: 0,
+        };
+        let mut value: u32 = 0;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        // SAFETY: the address and output buffer are valid for the duration of the call
+        let status = unsafe {
+            AudioObjectGetPropertyData(
+                object_id,
+                &address,
+                0,
+                std::ptr::null(),
+                &mut size,
+                &mut value as *mut u32 as *mut c_void,
+            )
+        };
+        (status == 0).then_some(value)
+    };
+
+    // kAudioHardwarePropertyDefaultInputDevice, then kAudioDevicePropertyDeviceIsRunningSomewhere
+    read_u32(SYSTEM_OBJECT, b"dIn ")
+        .filter(|device| *device != 0)
+        .and_then(|device| read_u32(device, b"gone"))
+        .is_some_and(|running| running != 0)
+}
+
+/// Check whether any ALSA capture stream is running
+#[cfg(target_os = "linux")]
+pub fn microphone_in_use() -> bool {
+    let Ok(cards) = std::fs::read_dir("/proc/asound") else {
+        return false;
+    };
+
+    // Capture PCMs are named pcm<N>c, each with sub<N>/status files
+    cards
+        .flatten()
+        .filter_map(|card| std::fs::read_dir(card.path()).ok())
+        .flatten()
+        .flatten()
+        .filter(|pcm| {
+            let name = pcm.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("pcm") && name.ends_with('c')
+        })
+        .filter_map(|pcm| std::fs::read_dir(pcm.path()).ok())
+        .flatten()
+        .flatten()
+        .any(|sub| {
+            std::fs::read_to_string(sub.path().join("status"))
+                .is_ok_and(|status| is_running_status(&status))
+        })
+}
+
+/// Check whether the microphone is in use
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub fn microphone_in_use() -> bool {
+    false
+}
+
+/// Check whether a running process's name satisfies the predicate
+#[cfg(target_os = "macos")]
+fn process_running(predicate: fn(&str) -> bool) -> bool {
+    std::process::Command::new("ps")
+        .args(["-axo", "comm="])
+        .output()
+        .is_ok_and(|output| {
+            String::from_utf8_lossy(&output.stdout).lines().any(|path| {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                predicate(name)
+            })
+        })
+}
+
+/// Check whether a running process's name satisfies the predicate
+#[cfg(target_os = "linux")]
+fn process_running(predicate: fn(&str) -> bool) -> bool {
+    crate::privacy::any_process_running(predicate)
+}
+
+/// Check whether a running process's name satisfies the predicate
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn process_running(_predicate: fn(&str) -> bool) -> bool {
+    false
+}
+
+/// Check whether a process name belongs to a call app
+fn is_call_process(process_name: &str) -> bool {
+    CALL_PROCESSES.contains(&process_name)
+}
+
+/// Check whether a process name belongs to a screen sharing helper
+fn is_screen_share_process(process_name: &str) -> bool {
+    SCREEN_SHARE_PROCESSES.contains(&process_name)
+}
+
+/// Check whether an ALSA substream status file reports a running stream
+#[cfg(any(target_os = "linux", test))]
+fn is_running_status(status: &str) -> bool {
+    status.lines().any(|line| line.trim() == "state: RUNNING")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_names() {
+        assert!(is_call_process("zoom"));
+        assert!(is_call_process("zoom.us"));
+        assert!(!is_call_process("zoomer"));
+        assert!(is_screen_share_process("CptHost"));
+        assert!(!is_screen_share_process("obs"));
+        assert!(!is_screen_share_process("bash"));
+    }
+
+    #[test]
+    fn test_alsa_status_parsing() {
+        assert!(is_running_status(
+            "state: RUNNING\nowner_pid   : 1234\ntrigger_time: 1.0\n"
+        ));
+        assert!(!is_running_status("closed\n"));
+        assert!(!is_running_status("state: PREPARED\n"));
+    }
+}
@@ -0,0 +1,587 @@
+//! Config validation module
+//!
+//! This module checks keyboard configs beyond what deserializing them catches:
+//! keys that can never be pressed, notes that don't parse, volumes or pans out of
+//! range, unknown waveforms, unknown layer keys and notes shared by several keys. Problems are
+//! reported with the line and column they were found at, and the expected
+//! structure can be exported as a JSON Schema for editor autocompletion.
+
+use crate::keyboard_config::{ConfigFormat, KeyMapping, KeyboardConfig};
+use crate::keyboard_mapping::{is_known_key_name, note_to_midi};
+use crate::keycode::Keycode;
+use crate::waveforms::{Waveform, get_all_waveforms};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+/// How serious a validation problem is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The config fails to load or part of it won't play
+    Error,
+    /// The config loads, but probably not as intended
+    Warning,
+}
+
+/// A problem found in a config file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    /// 1-based line, if the problem could be located
+    pub line: Option<usize>,
+    /// 1-based column, if the problem could be located
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn new(severity: Severity, location: Option<(usize, usize)>, message: String) -> Self {
+        Self {
+            severity,
+            line: location.map(|(line, _)| line),
+            column: location.map(|(_, column)| column),
+            message,
+        }
+    }
+
+    /// Check whether this issue is an error rather than a warning
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "{}:{}: ", line, self.column.unwrap_or(1))?;
+        }
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}", severity, self.message)
+    }
+}
+
+/// Validate a config's source text, returning every problem found
+/// (an empty list means the config is valid)
+pub fn validate(content: &str, format: ConfigFormat) -> Vec<ValidationIssue> {
+    match parse(content, format) {
+        Ok(config) => check_config(&config, content),
+        Err(issue) => vec![issue],
+    }
+}
+
+/// Parse a config, reporting the syntax or structure error with its location
+fn parse(content: &str, format: ConfigFormat) -> Result<KeyboardConfig, ValidationIssue> {
+    let error =
+        |location, message: String| ValidationIssue::new(Severity::Error, location, message);
+    match format {
+        ConfigFormat::Json => json5::from_str(content).map_err(|e| match e {
+            json5::Error::Message { msg, location } => {
+                error(location.map(|l| (l.line, l.column)), msg)
+            }
+        }),
+        ConfigFormat::Toml => toml::from_str(content).map_err(|e| {
+            let location = e.span().map(|span| offset_to_location(content, span.start));
+            error(location, e.message().to_string())
+        }),
+    }
+}
+
+/// Check a parsed config, locating each problem in its source text
+fn check_config(config: &KeyboardConfig, content: &str) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let mut report = |severity, path: &[&str], message: String| {
+        issues.push(ValidationIssue::new(
+            severity,
+            locate(content, path),
+            message,
+        ));
+    };
+
+    if let Some(ref waveform) = config.waveform
+        && waveform.parse::<Waveform>().is_err()
+    {
+        report(
+            Severity::Error,
+            &["waveform"],
+            format!("Unknown waveform '{}'", waveform),
+        );
+    }
+    if let Some(ref scale) = config.scale
+        && note_to_midi(&scale.root).is_none()
+    {
+        report(
+            Severity::Error,
+            &["scale", "root"],
+            format!("Scale root '{}' is not a note", scale.root),
+        );
+    }
+
+    let keys_by_note = check_mappings(&config.mappings, &["mappings"], &mut report);
+
+    // Sorted so the report follows a stable order
+    let mut layers: Vec<_> = config.layers.iter().collect();
+    layers.sort_by_key(|(name, _)| *name);
+    for (name, layer) in layers {
+        let name = name.as_str();
+        if Keycode::from_str(&layer.key).is_err() {
+            report(
+                Severity::Error,
+                &["layers", name, "key"],
+                format!("Layer '{}' has unknown layer key '{}'", name, layer.key),
+            );
+        }
+        check_mappings(&layer.mappings, &["layers", name, "mappings"], &mut report);
+    }
+
+    for keys in keys_by_note.values().filter(|keys| keys.len() > 1) {
+        let note = &config.mappings[keys[0]].note;
+        report(
+            Severity::Warning,
+            &["mappings", keys[1], "note"],
+            format!("Keys {} all play {}", keys.join(", "), note),
+        );
+    }
+
+    issues
+}
+
+/// Check each mapping of a table found at `path`, returning the keys that
+/// play each note (as a MIDI number)
+fn check_mappings<'a>(
+    mappings: &'a HashMap<String, KeyMapping>,
+    path: &[&str],
+    report: &mut impl FnMut(Severity, &[&str], String),
+) -> BTreeMap<i32, Vec<&'a str>> {
+    // Sorted so the report follows a stable order
+    let mut mappings: Vec<_> = mappings.iter().collect();
+    mappings.sort_by_key(|(key, _)| *key);
+    let mut keys_by_note: BTreeMap<i32, Vec<&str>> = BTreeMap::new();
+    for (key, mapping) in mappings {
+        let key = key.as_str();
+        let at = |field: Option<&'static str>| -> Vec<&str> {
+            path.iter().copied().chain([key]).chain(field).collect()
+        };
+        if !is_known_key_name(key) {
+            report(
+                Severity::Warning,
+                &at(None),
+                format!("Unknown key '{}' will never play", key),
+            );
+        }
+        if !mapping.has_samples() && mapping.note.is_empty() {
+            report(
+                Severity::Error,
+                &at(None),
+                format!("Key '{}' has neither a note nor a sample", key),
+            );
+        } else if !mapping.has_samples() {
+            match note_to_midi(&mapping.note) {
+                Some(midi_note) => keys_by_note.entry(midi_note).or_default().push(key),
+                None => report(
+                    Severity::Error,
+                    &at(Some("note")),
+                    format!("Key '{}' has invalid note '{}'", key, mapping.note),
+                ),
+            }
+        }
+        if !(0.0..=1.0).contains(&mapping.volume) {
+            report(
+                Severity::Error,
+                &at(Some("volume")),
+                format!(
+                    "Key '{}' has volume {} (valid range: 0.0-1.0)",
+                    key, mapping.volume
+                ),
+            );
+        }
+        if let Some(pan) = mapping.pan
+            && !(-1.0..=1.0).contains(&pan)
+        {
+            report(
+                Severity::Error,
+                &at(Some("pan")),
+                format!("Key '{}' has pan {} (valid range: -1.0-1.0)", key, pan),
+            );
+        }
+    }
+    keys_by_note
+}
+
+/// Find where a path of keys (e.g. ["mappings", "A", "note"]) is written,
+/// as a 1-based line and column
+///
+/// Each key is searched for after the previous one, which works for the
+/// nesting styles of JSON and TOML alike. If only part of the path is
+/// found, the location of the deepest key found is returned.
+fn locate(content: &str, path: &[&str]) -> Option<(usize, usize)> {
+    let mut offset = None;
+    for key in path {
+        match find_key(content, offset.unwrap_or(0), key) {
+            Some(found) => offset = Some(found),
+            None => break,
+        }
+    }
+    offset.map(|offset| offset_to_location(content, offset))
+}
+
+/// Find a key written as an object key at or after `from`: the whole word,
+/// optionally quoted, followed by `:` (JSON), `=` (TOML) or `]`/`.`
+/// (TOML table headers)
+fn find_key(content: &str, from: usize, key: &str) -> Option<usize> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut start = from;
+    while let Some(found) = content[start..].find(key) {
+        let at = start + found;
+        let end = at + key.len();
+        let before = content[..at].chars().next_back();
+        let after = &content[end..];
+        let separator = after.trim_start_matches(['"', '\'']).trim_start();
+        if before.is_none_or(|c| !is_word_char(c))
+            && after.chars().next().is_none_or(|c| !is_word_char(c))
+            && separator.starts_with([':', '=', ']', '.'])
+        {
+            return Some(at);
+        }
+        start = end;
+    }
+    None
+}
+
+/// Convert a byte offset into a 1-based line and column
+fn offset_to_location(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// JSON Schema describing keyboard configs, for editor autocompletion and checks
+pub fn schema() -> serde_json::Value {
+    let waveforms: Vec<_> = get_all_waveforms()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    let waveform = serde_json::json!({
+        "anyOf": [
+            { "enum": waveforms },
+            {
+                "type": "string",
+                "pattern": "^harmonic:[0-9]+(\\.[0-9]+)?$",
+                "description": "Harmonic waveform with an overtone rolloff from 0.0 to 4.0"
+            }
+        ]
+    });
+    let note = serde_json::json!({
+        "type": "string",
+        "pattern": "^[A-Ga-g](#|b|B)?[0-9]$",
+        "description": "Musical note, e.g. \"C4\", \"F#5\" or \"Bb3\""
+    });
+    let volume = serde_json::json!({
+        "type": "number",
+        "minimum": 0.0,
+        "maximum": 1.0
+    });
+    let hertz = serde_json::json!({ "type": "number", "minimum": 1.0, "description": "Hz" });
+    let filter_envelope = serde_json::json!({
+        "type": "object",
+        "properties": {
+            "start": hertz,
+            "peak": hertz,
+            "sustain": hertz,
+            "attack": { "type": "number", "minimum": 0.0 },
+            "decay": { "type": "number", "minimum": 0.0 },
+            "resonance": volume
+        },
+        "additionalProperties": false
+    });
+    let action = serde_json::json!({
+        "type": "object",
+        "required": ["type"],
+        "properties": {
+            "type": {
+                "enum": [
+                    "play_sample",
+                    "set_waveform",
+                    "load_config",
+                    "toggle_mute",
+                    "adjust_volume",
+                    "cycle_waveform",
+                    "toggle_pause"
+                ]
+            },
+            "sample": { "type": "string" },
+            "volume": volume,
+            "waveform": waveform,
+            "config": { "type": "string" },
+            "delta": { "type": "number" }
+        }
+    });
+
+    let mapping = serde_json::json!({
+        "type": "object",
+        "required": ["volume"],
+        "properties": {
+            "note": note,
+            "volume": volume,
+            "description": { "type": ["string", "null"] },
+            "waveform": waveform,
+            "adsr": {
+                "type": "object",
+                "properties": {
+                    "attack": { "type": "number", "minimum": 0.0 },
+                    "decay": { "type": "number", "minimum": 0.0 },
+                    "sustain": volume,
+                    "release": { "type": "number", "minimum": 0.0 }
+                },
+                "additionalProperties": false
+            },
+            "pan": { "type": "number", "minimum": -1.0, "maximum": 1.0 },
+            "filter_envelope": filter_envelope,
+            "sample": { "type": "string" },
+            "layers": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["sample"],
+                    "properties": {
+                        "sample": { "type": "string" },
+                        "min_velocity": volume,
+                        "max_velocity": volume
+                    }
+                }
+            },
+            "looped": { "type": "boolean" },
+            "loop_start": { "type": "integer", "minimum": 0 },
+            "loop_end": { "type": "integer", "minimum": 0 },
+            "scale": {
+                "type": "string",
+                "description": "Scale whose notes the keys of a range take in order, e.g. \"C major pentatonic\""
+            }
+        }
+    });
+
+    serde_json::json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "CodeBeats keyboard configuration",
+        "type": "object",
+        "required": ["version", "description", "mappings"],
+        "properties": {
+            "version": { "type": "string" },
+            "description": { "type": "string" },
+            "waveform": waveform,
+            "mappings": {
+                "type": "object",
+                "description": "Key names (e.g. \"A\", \"Space\", \"LeftParen\") or key ranges (e.g. \"A-Z\") mapped to sounds",
+                "additionalProperties": mapping
+            },
+            "sequences": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["name", "keys"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "keys": {
+                            "oneOf": [
+                                { "type": "string" },
+                                { "type": "array", "items": { "type": "string" } }
+                            ]
+                        },
+                        "sample": { "type": "string" },
+                        "volume": volume,
+                        "action": action
+                    }
+                }
+            },
+            "chords": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["name", "keys", "action"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "keys": { "type": "string", "description": "Keys joined with \"+\", e.g. \"Ctrl+Alt+M\"" },
+                        "action": action
+                    }
+                }
+            },
+            "samples": {
+                "type": "object",
+                "additionalProperties": { "type": "string" }
+            },
+            "transpose_semitones": { "type": "integer" },
+            "octave_shift": { "type": "integer" },
+            "scale": {
+                "type": "object",
+                "properties": {
+                    "mode": {
+                        "enum": [
+                            "major",
+                            "minor",
+                            "pentatonic",
+                            "minor_pentatonic",
+                            "blues",
+                            "dorian",
+                            "chromatic"
+                        ]
+                    },
+                    "root": note,
+                    "octaves": { "type": "integer", "minimum": 1 },
+                    "volume": volume
+                }
+            },
+            "auto_assign": { "type": "boolean" },
+            "layers": {
+                "type": "object",
+                "description": "Alternate mappings tables by name, switched on by their layer key",
+                "additionalProperties": {
+                    "type": "object",
+                    "required": ["key"],
+                    "properties": {
+                        "key": { "type": "string", "description": "Key that switches the layer on, e.g. \"CapsLock\"" },
+                        "toggle": { "type": "boolean" },
+                        "mappings": {
+                            "type": "object",
+                            "additionalProperties": mapping
+                        }
+                    }
+                }
+            },
+            "vibrato": {
+                "type": "object",
+                "required": ["depth"],
+                "properties": {
+                    "rate": { "type": "number", "minimum": 0.0, "description": "Hz" },
+                    "depth": { "type": "number", "minimum": 0.0, "description": "Cents" }
+                }
+            },
+            "lfo": {
+                "type": "object",
+                "required": ["rate", "depth", "target"],
+                "properties": {
+                    "rate": { "type": "number", "minimum": 0.0, "description": "Hz" },
+                    "depth": {
+                        "type": "number",
+                        "minimum": 0.0,
+                        "description": "Semitones (pitch), 0.0-1.0 (volume) or octaves (filter)"
+                    },
+                    "target": { "enum": ["pitch", "volume", "filter"] }
+                }
+            },
+            "filter_envelope": filter_envelope,
+            "mod_matrix": {
+                "type": "object",
+                "properties": {
+                    "lfo1_rate": { "type": "number", "minimum": 0.0, "description": "Hz" },
+                    "lfo2_rate": { "type": "number", "minimum": 0.0, "description": "Hz" },
+                    "routes": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["source", "target", "amount"],
+                            "properties": {
+                                "source": { "enum": ["lfo1", "lfo2", "envelope", "wpm", "key_position"] },
+                                "target": { "enum": ["pitch", "amp", "filter", "pan"] },
+                                "amount": { "type": "number" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded_configs;
+
+    #[test]
+    fn test_validate_reports_locations() {
+        let content = r#"{
+  "version": "1.0",
+  "description": "Test",
+  "mappings": {
+    "A": { "note": "C4", "volume": 0.3 },
+    "B": { "note": "H4", "volume": 0.3 },
+    "Bogus": { "note": "D4", "volume": 1.5 },
+    "C": { "note": "C4", "volume": 0.3 },
+    "D": { "volume": 0.3 }
+  }
+}"#;
+        let issues = validate(content, ConfigFormat::Json);
+        let messages: Vec<_> = issues.iter().map(|issue| issue.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "6:13: error: Key 'B' has invalid note 'H4'",
+                "7:6: warning: Unknown key 'Bogus' will never play",
+                "7:31: error: Key 'Bogus' has volume 1.5 (valid range: 0.0-1.0)",
+                "9:6: error: Key 'D' has neither a note nor a sample",
+                "8:13: warning: Keys A, C all play C4",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_toml_locations() {
+        let content = "version = \"1.0\"\ndescription = \"Test\"\n\n[mappings.A]\nnote = \"X9\"\nvolume = 0.3\n";
+        let issues = validate(content, ConfigFormat::Toml);
+        assert_eq!(issues.len(), 1);
+        assert_eq!((issues[0].line, issues[0].column), (Some(5), Some(1)));
+    }
+
+    #[test]
+    fn test_validate_layers() {
+        let content = "version = \"1.0\"\ndescription = \"Test\"\n[mappings.A]\nnote = \"C4\"\nvolume = 0.3\n\n[layers.drums]\nkey = \"Caps\"\n[layers.drums.mappings.A]\nnote = \"C2\"\nvolume = 2.0\n";
+        let messages: Vec<_> = validate(content, ConfigFormat::Toml)
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "8:1: error: Layer 'drums' has unknown layer key 'Caps'",
+                "11:1: error: Key 'A' has volume 2 (valid range: 0.0-1.0)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_syntax_error() {
+        // Bare words are valid JSON5 keys but not values
+        let issues = validate(
+            "{\n  \"version\": \"1.0\",\n  \"description\": oops\n}",
+            ConfigFormat::Json,
+        );
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert!(issues[0].is_error());
+        assert_eq!(issues[0].line, Some(3), "{:?}", issues);
+    }
+
+    #[test]
+    fn test_embedded_configs_have_no_errors() {
+        for name in embedded_configs::get_config_names() {
+            let source = embedded_configs::get_config_source(name).unwrap();
+            let errors: Vec<_> = validate(source, ConfigFormat::Json)
+                .into_iter()
+                .filter(ValidationIssue::is_error)
+                .collect();
+            assert!(errors.is_empty(), "{}: {:?}", name, errors);
+        }
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema = schema();
+        assert_eq!(schema["type"], "object");
+        assert!(
+            schema["properties"]["mappings"]["additionalProperties"]["properties"]["note"]
+                .is_object()
+        );
+    }
+}
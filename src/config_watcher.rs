@@ -0,0 +1,133 @@
+//! Config hot-reload module
+//!
+//! This module watches a keyboard config file for changes, so a custom mapping
+//! can be edited while CodeBeats is running. The file's directory is watched
+//! rather than the file itself, since many editors save by writing a new file
+//! and renaming it over the old one.
+
+use crate::audio_samples::AudioSample;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::f32::consts::TAU;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Quiet period after the last change before the file is reloaded
+/// (editors often write a file in several steps)
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Sample rate of the reload chime
+const CHIME_SAMPLE_RATE: u32 = 44100;
+
+/// Notes of the reload chime in Hz, played one after the other (E6, B6)
+const CHIME_NOTES: [f32; 2] = [1318.51, 1975.53];
+
+/// Seconds each chime note rings for
+const CHIME_NOTE_LENGTH: f32 = 0.12;
+
+/// Watches a config file and reports when it has changed
+pub struct ConfigWatcher {
+    path: PathBuf,
+    changes: mpsc::Receiver<()>,
+    /// Time of the latest change not yet reported
+    pending_since: Option<Instant>,
+    _watcher: RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+    /// Start watching a config file
+    pub fn start<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref().to_path_buf();
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| format!("'{}' is not a file", path.display()))?
+            .to_os_string();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let (sender, changes) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            let Ok(event) = result else {
+                return;
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            if event
+                .paths
+                .iter()
+                .any(|changed| changed.file_name() == Some(file_name.as_os_str()))
+            {
+                let _ = sender.send(());
+            }
+        })?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            path,
+            changes,
+            pending_since: None,
+            _watcher: watcher,
+        })
+    }
+
+    /// Path of the watched config file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Check whether the file changed and has since been left alone for a moment
+    pub fn poll(&mut self) -> bool {
+        while self.changes.try_recv().is_ok() {
+            self.pending_since = Some(Instant::now());
+        }
+
+        match self.pending_since {
+            Some(since) if since.elapsed() >= DEBOUNCE => {
+                self.pending_since = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Short rising two-note chime confirming a config reload
+pub fn reload_chime() -> AudioSample {
+    let note_frames = (CHIME_NOTE_LENGTH * CHIME_SAMPLE_RATE as f32) as usize;
+    let samples = CHIME_NOTES
+        .iter()
+        .flat_map(|&frequency| {
+            (0..note_frames).map(move |frame| {
+                let t = frame as f32 / CHIME_SAMPLE_RATE as f32;
+                // Quick fade-in to avoid a click, then an exponential ring-out
+                let envelope = (t / 0.005).min(1.0) * (-t * 30.0).exp();
+                (TAU * frequency * t).sin() * envelope
+            })
+        })
+        .collect();
+
+    AudioSample {
+        samples,
+        sample_rate: CHIME_SAMPLE_RATE,
+        channels: 1,
+        loop_points: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_chime() {
+        let chime = reload_chime();
+        assert!((chime.duration() - CHIME_NOTE_LENGTH * 2.0).abs() < 0.01);
+        assert!(chime.samples.iter().all(|sample| sample.abs() <= 1.0));
+        // Both notes fade out before the next starts
+        let note_frames = chime.samples.len() / 2;
+        assert!(chime.samples[note_frames - 1].abs() < 0.05);
+    }
+}
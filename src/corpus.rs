@@ -0,0 +1,146 @@
+//! Corpus analysis module
+//!
+//! This module builds a personal keyboard config from a codebase: it counts
+//! how often each key is typed to write the files, then gives the most
+//! consonant notes of a scale to the most frequent keys, the way the
+//! hand-built programming configs do.
+
+use crate::keyboard_config::{KeyMapping, KeyboardConfig};
+use crate::keyboard_mapping::virtual_keycode_for_char;
+use crate::scales::ScaleConfig;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Directories skipped while walking a codebase (build output, dependencies, VCS data)
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules", "build", "dist", "vendor"];
+
+/// Files larger than this are skipped (likely generated or data files)
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Key press counts gathered from a codebase
+#[derive(Debug, Clone, Default)]
+pub struct CorpusAnalysis {
+    /// Times each key (by mapping name, e.g. "A", "LeftParen") is typed
+    pub key_counts: HashMap<String, u64>,
+    /// Text files that were read
+    pub files: usize,
+}
+
+impl CorpusAnalysis {
+    /// Count the keys typed for every text file under a directory (or a single file)
+    ///
+    /// Hidden entries such as `.git` and common build and dependency
+    /// directories are skipped, as are binary and very large files.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut analysis = Self::default();
+        analysis.add_path(path.as_ref())?;
+        Ok(analysis)
+    }
+
+    fn add_path(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if path.is_dir() {
+            let mut entries: Vec<_> = fs::read_dir(path)?.flatten().map(|e| e.path()).collect();
+            entries.sort();
+            for entry in entries {
+                let skipped = entry
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_none_or(|name| {
+                        name.starts_with('.') || (entry.is_dir() && SKIPPED_DIRS.contains(&name))
+                    });
+                if !skipped {
+                    self.add_path(&entry)?;
+                }
+            }
+        } else if fs::metadata(path)?.len() <= MAX_FILE_SIZE {
+            // Files that aren't UTF-8 text are not source code
+            if let Ok(text) = fs::read_to_string(path) {
+                self.add_text(&text);
+                self.files += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Count the keys typed for a piece of text
+    pub fn add_text(&mut self, text: &str) {
+        for key in text.chars().filter_map(virtual_keycode_for_char) {
+            *self.key_counts.entry(key.to_string()).or_default() += 1;
+        }
+    }
+
+    /// Keys ordered from most to least frequent (ties broken by name)
+    pub fn keys_by_frequency(&self) -> Vec<(&str, u64)> {
+        let mut keys: Vec<_> = self
+            .key_counts
+            .iter()
+            .map(|(key, count)| (key.as_str(), *count))
+            .collect();
+        keys.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        keys
+    }
+
+    /// Build a config mapping every counted key to a note of the scale
+    ///
+    /// The most frequent keys get the most consonant notes and play at the
+    /// scale's volume; rarer keys play a little quieter.
+    pub fn to_config(&self, scale: &ScaleConfig, description: &str) -> KeyboardConfig {
+        let notes = scale.notes_by_consonance();
+        let keys = self.keys_by_frequency();
+
+        let mut mappings = HashMap::new();
+        if !notes.is_empty() {
+            for (rank, (key, count)) in keys.iter().enumerate() {
+                let tier = rank * 3 / keys.len();
+                mappings.insert(
+                    key.to_string(),
+                    KeyMapping {
+                        note: notes[rank % notes.len()].clone(),
+                        volume: scale.volume * (1.0 - 0.15 * tier as f32),
+                        description: Some(format!("Typed {} times", count)),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
+        KeyboardConfig {
+            version: "2.0".to_string(),
+            description: description.to_string(),
+            waveform: None,
+            mappings,
+            ..KeyboardConfig::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_text() {
+        let mut analysis = CorpusAnalysis::default();
+        analysis.add_text("let a = (b);\nA");
+        assert_eq!(analysis.key_counts["A"], 2);
+        assert_eq!(analysis.key_counts["Space"], 3);
+        assert_eq!(analysis.key_counts["LeftParen"], 1);
+        assert_eq!(analysis.keys_by_frequency()[0], ("Space", 3));
+    }
+
+    #[test]
+    fn test_to_config() {
+        let mut analysis = CorpusAnalysis::default();
+        analysis.add_text("eeeee ttt a");
+        let scale: ScaleConfig = "D dorian".parse().unwrap();
+
+        let config = analysis.to_config(&scale, "Test");
+        assert_eq!(config.mappings.len(), 4);
+        // Most frequent key gets the root, the next the octave, then the fifth
+        assert_eq!(config.mappings["E"].note, "D4");
+        assert_eq!(config.mappings["T"].note, "D5");
+        assert_eq!(config.mappings["Space"].note, "A4");
+        assert!(config.mappings["A"].volume < config.mappings["E"].volume);
+    }
+}
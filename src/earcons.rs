@@ -0,0 +1,372 @@
+//! Accessibility earcons module
+//!
+//! This module defines the sounds of accessibility mode (`--accessibility`),
+//! aimed at typists who rely on hearing rather than sight. Every key belongs
+//! to a category (letters, numbers, whitespace, punctuation, modifiers,
+//! navigation, deletion, function keys), and each category has its own
+//! timbre and register so the kind of key is clear from its sound alone.
+//! Numbers rise with their value and navigation keys with their direction
+//! (Down below Up, Left below Right). Dedicated cues mark Caps Lock turning
+//! on or off and errors (a failed action, config switch or reload).
+//!
+//! An `earcons.json` file changes any part of the profile; categories it
+//! leaves out keep their built-in sound (see README for the schema).
+
+use crate::keyboard_config::{AdsrConfig, KeyMapping, KeyboardConfig};
+use crate::notifications::Notification;
+use crate::waveforms::Waveform;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// A kind of key, sounding alike within the kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyCategory {
+    Letter,
+    Number,
+    Whitespace,
+    Punctuation,
+    Modifier,
+    Navigation,
+    Deletion,
+    Function,
+}
+
+/// Keys of each category, in the order they take the category's notes
+const CATEGORY_KEYS: &[(KeyCategory, &[&str])] = &[
+    (
+        KeyCategory::Letter,
+        &[
+            "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q",
+            "R", "S", "T", "U", "V", "W", "X", "Y", "Z",
+        ],
+    ),
+    (
+        KeyCategory::Number,
+        &[
+            "Key0", "Key1", "Key2", "Key3", "Key4", "Key5", "Key6", "Key7", "Key8", "Key9",
+            "Numpad0", "Numpad1", "Numpad2", "Numpad3", "Numpad4", "Numpad5", "Numpad6", "Numpad7",
+            "Numpad8", "Numpad9",
+        ],
+    ),
+    (
+        KeyCategory::Whitespace,
+        &["Space", "Enter", "Tab", "NumpadEnter"],
+    ),
+    (
+        KeyCategory::Punctuation,
+        &[
+            "Dot",
+            "Comma",
+            "Semicolon",
+            "Apostrophe",
+            "Slash",
+            "Minus",
+            "Equal",
+            "LeftBracket",
+            "RightBracket",
+            "BackSlash",
+            "Grave",
+            "NumpadSubtract",
+            "NumpadAdd",
+            "NumpadDivide",
+            "NumpadMultiply",
+            "NumpadEquals",
+            "NumpadDecimal",
+            "Exclamation",
+            "At",
+            "Hash",
+            "Dollar",
+            "Percent",
+            "Caret",
+            "Ampersand",
+            "Asterisk",
+            "LeftParen",
+            "RightParen",
+            "Underscore",
+            "Plus",
+            "LeftBrace",
+            "RightBrace",
+            "Pipe",
+            "Colon",
+            "DoubleQuote",
+            "LessThan",
+            "GreaterThan",
+            "Question",
+            "Tilde",
+        ],
+    ),
+    (
+        KeyCategory::Modifier,
+        &[
+            "LShift", "RShift", "LControl", "RControl", "LAlt", "RAlt", "LOption", "ROption",
+            "LMeta", "RMeta", "Command",
+        ],
+    ),
+    (
+        KeyCategory::Navigation,
+        &[
+            "Down", "Left", "Right", "Up", "PageDown", "End", "Home", "PageUp",
+        ],
+    ),
+    (KeyCategory::Deletion, &["Backspace", "Delete"]),
+    (
+        KeyCategory::Function,
+        &[
+            "Escape", "Insert", "F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9", "F10", "F11",
+            "F12",
+        ],
+    ),
+];
+
+/// The category of a key by name (Caps Lock has none: it plays a cue instead)
+pub fn key_category(name: &str) -> Option<KeyCategory> {
+    CATEGORY_KEYS
+        .iter()
+        .find(|(_, keys)| keys.contains(&name))
+        .map(|(category, _)| *category)
+}
+
+/// How the keys of a category sound
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CategorySound {
+    /// Waveform name (e.g. "electronic", "bass", "harmonic:2.0")
+    pub waveform: String,
+    /// Notes the category's keys take in order, wrapping around
+    pub notes: Vec<String>,
+    /// Volume (0.0 to 1.0)
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+    /// Optional envelope, e.g. a short decay for clicks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adsr: Option<AdsrConfig>,
+}
+
+fn default_volume() -> f32 {
+    0.4
+}
+
+impl CategorySound {
+    fn new(waveform: &str, notes: &[&str], volume: f32, release: Option<f32>) -> Self {
+        Self {
+            waveform: waveform.to_string(),
+            notes: notes.iter().map(|note| note.to_string()).collect(),
+            volume,
+            adsr: release.map(|release| AdsrConfig {
+                release: Some(release),
+                ..AdsrConfig::default()
+            }),
+        }
+    }
+}
+
+/// Sounds of accessibility mode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EarconProfile {
+    /// Sound of each key category
+    pub categories: HashMap<KeyCategory, CategorySound>,
+    /// Cue when Caps Lock turns on
+    pub caps_lock_on: Notification,
+    /// Cue when Caps Lock turns off
+    pub caps_lock_off: Notification,
+    /// Cue when an action, config switch or reload fails
+    pub error: Notification,
+}
+
+impl Default for EarconProfile {
+    fn default() -> Self {
+        use KeyCategory::*;
+        let categories = HashMap::from([
+            (
+                Letter,
+                CategorySound::new(
+                    "electronic",
+                    &[
+                        "C4", "D4", "E4", "G4", "A4", "C5", "D5", "E5", "G5", "A5", "C6",
+                    ],
+                    0.35,
+                    None,
+                ),
+            ),
+            (
+                Number,
+                CategorySound::new(
+                    "triangle",
+                    &["C6", "D6", "E6", "F6", "G6", "A6", "B6", "C7", "D7", "E7"],
+                    0.3,
+                    Some(0.1),
+                ),
+            ),
+            (
+                Whitespace,
+                CategorySound::new("natural", &["C3", "G3", "E3", "G3"], 0.45, None),
+            ),
+            (
+                Punctuation,
+                CategorySound::new("square", &["F4", "A4", "C5", "F5"], 0.2, Some(0.05)),
+            ),
+            (
+                Modifier,
+                CategorySound::new("bass", &["C2", "C2", "G2", "G2", "E2", "E2"], 0.4, None),
+            ),
+            (
+                Navigation,
+                CategorySound::new(
+                    "harmonic:2.0",
+                    &["E3", "G3", "A3", "C4", "C3", "D3", "D4", "E4"],
+                    0.4,
+                    Some(0.08),
+                ),
+            ),
+            (
+                Deletion,
+                CategorySound::new("saw", &["A2", "D3"], 0.3, Some(0.05)),
+            ),
+            (
+                Function,
+                CategorySound::new(
+                    "cyberpunk",
+                    &["C5", "D5", "E5", "F#5", "G#5", "A#5"],
+                    0.3,
+                    None,
+                ),
+            ),
+        ]);
+        Self {
+            categories,
+            caps_lock_on: Notification::melody(&["C5", "G5"], 0.08),
+            caps_lock_off: Notification::melody(&["G5", "C5"], 0.08),
+            error: Notification::melody(&["C3+F#3", "-", "C3+F#3"], 0.12),
+        }
+    }
+}
+
+impl EarconProfile {
+    /// Check every waveform and note
+    pub fn validate(&self) -> Result<(), String> {
+        for (category, sound) in &self.categories {
+            sound
+                .waveform
+                .parse::<Waveform>()
+                .map_err(|e| format!("{:?}: {}", category, e))?;
+            if sound.notes.is_empty() {
+                return Err(format!("{:?}: no notes", category));
+            }
+            Notification::melody(&[&sound.notes.join("+")], 0.1)
+                .steps()
+                .map_err(|e| format!("{:?}: {}", category, e))?;
+        }
+        for (name, cue) in [
+            ("caps_lock_on", &self.caps_lock_on),
+            ("caps_lock_off", &self.caps_lock_off),
+            ("error", &self.error),
+        ] {
+            cue.steps().map_err(|e| format!("{}: {}", name, e))?;
+        }
+        Ok(())
+    }
+
+    /// A keyboard config playing every categorized key with its category's sound
+    pub fn keyboard_config(&self) -> KeyboardConfig {
+        let mut mappings = HashMap::new();
+        for (category, keys) in CATEGORY_KEYS {
+            let Some(sound) = self.categories.get(category) else {
+                continue;
+            };
+            for (index, key) in keys.iter().enumerate() {
+                mappings.insert(
+                    key.to_string(),
+                    KeyMapping {
+                        note: sound.notes[index % sound.notes.len()].clone(),
+                        volume: sound.volume,
+                        description: Some(format!("{:?}", category)),
+                        waveform: Some(sound.waveform.clone()),
+                        adsr: sound.adsr.clone(),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+        KeyboardConfig {
+            description: "Accessibility earcons".to_string(),
+            mappings,
+            ..KeyboardConfig::default()
+        }
+    }
+}
+
+/// Load an earcon profile from a JSON file, on top of the built-in one
+pub fn load_earcons<P: AsRef<Path>>(path: P) -> Result<EarconProfile, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let mut profile: EarconProfile = serde_json::from_str(&content)?;
+    let loaded = std::mem::replace(&mut profile.categories, EarconProfile::default().categories);
+    profile.categories.extend(loaded);
+    profile.validate()?;
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard_mapping::{is_known_key_name, note_to_midi};
+
+    #[test]
+    fn test_key_category() {
+        assert_eq!(key_category("Q"), Some(KeyCategory::Letter));
+        assert_eq!(key_category("Numpad7"), Some(KeyCategory::Number));
+        assert_eq!(key_category("Question"), Some(KeyCategory::Punctuation));
+        assert_eq!(key_category("PageUp"), Some(KeyCategory::Navigation));
+        assert_eq!(key_category("CapsLock"), None);
+        for (_, keys) in CATEGORY_KEYS {
+            assert!(keys.iter().all(|key| is_known_key_name(key)));
+        }
+    }
+
+    #[test]
+    fn test_keyboard_config() {
+        let profile = EarconProfile::default();
+        assert!(profile.validate().is_ok());
+        let config = profile.keyboard_config();
+        let midi = |key: &str| note_to_midi(&config.mappings[key].note).unwrap();
+
+        assert_eq!(
+            config.mappings["Backspace"].waveform.as_deref(),
+            Some("saw")
+        );
+        assert!(midi("Key9") > midi("Key1"));
+        assert!(midi("Up") > midi("Down"));
+        assert!(midi("Right") > midi("Left"));
+        assert!(midi("LShift") < midi("A"));
+        assert!(!config.mappings.contains_key("CapsLock"));
+    }
+
+    #[test]
+    fn test_load_earcons() {
+        let path =
+            std::env::temp_dir().join(format!("codebeats-earcons-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{ "categories": { "deletion": { "waveform": "bass", "notes": ["C2"] } },
+                 "error": { "notes": ["B2+F3"] } }"#,
+        )
+        .unwrap();
+        let profile = load_earcons(&path).unwrap();
+
+        assert_eq!(profile.categories[&KeyCategory::Deletion].waveform, "bass");
+        assert_eq!(profile.categories[&KeyCategory::Deletion].volume, 0.4);
+        assert!(profile.categories.contains_key(&KeyCategory::Letter));
+        assert_eq!(profile.error.notes, vec!["B2+F3"]);
+        assert_eq!(profile.caps_lock_on, EarconProfile::default().caps_lock_on);
+
+        fs::write(
+            &path,
+            r#"{ "categories": { "letter": { "waveform": "kazoo", "notes": ["C4"] } } }"#,
+        )
+        .unwrap();
+        assert!(load_earcons(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -6,6 +6,8 @@
 use crate::keyboard_config::KeyboardConfig;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 // Embed all configuration files at compile time
 static CONFIGS: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
@@ -74,11 +76,15 @@ pub fn get_config_names() -> Vec<&'static str> {
     names
 }
 
+/// Get the JSON source of an embedded configuration
+pub fn get_config_source(name: &str) -> Option<&'static str> {
+    CONFIGS.get(name).copied()
+}
+
 /// Load a keyboard configuration by name
 pub fn load_config(name: &str) -> Result<KeyboardConfig, Box<dyn std::error::Error>> {
-    let json_content = CONFIGS
-        .get(name)
-        .ok_or_else(|| format!("Configuration '{}' not found", name))?;
+    let json_content =
+        get_config_source(name).ok_or_else(|| format!("Configuration '{}' not found", name))?;
 
     let config: KeyboardConfig = serde_json::from_str(json_content)?;
     Ok(config)
@@ -103,12 +109,110 @@ pub fn get_config_description(name: &str) -> Option<String> {
     }
 }
 
+/// Write every embedded configuration to `<dir>/<name>.json`, creating the directory if needed
+/// Existing files are kept unless `overwrite` is set; returns the paths that were written
+pub fn export_configs(
+    dir: &Path,
+    overwrite: bool,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    fs::create_dir_all(dir)?;
+
+    let mut written = Vec::new();
+    for name in get_config_names() {
+        let path = dir.join(format!("{}.json", name));
+        if path.exists() && !overwrite {
+            continue;
+        }
+        fs::write(&path, CONFIGS[name])?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Find the embedded configuration for an editor language identifier or file extension
+pub fn config_for_language(language: &str) -> Option<&'static str> {
+    let language = language.trim_start_matches('.').to_lowercase();
+    let name = match language.as_str() {
+        "c" | "h" => "c",
+        "cpp" | "c++" | "cc" | "cxx" | "hpp" | "hh" => "cpp",
+        "csharp" | "cs" => "csharp",
+        "clojure" | "clj" | "cljs" | "cljc" | "edn" => "clojure",
+        "emacs-lisp" | "elisp" | "el" => "emacs-lisp",
+        "go" => "go",
+        "haskell" | "hs" => "haskell",
+        "java" => "java",
+        "javascript" | "javascriptreact" | "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "kotlin" | "kt" | "kts" => "kotlin",
+        "php" => "php",
+        "python" | "py" | "pyi" => "python",
+        "ruby" | "rb" => "ruby",
+        "rust" | "rs" => "rust",
+        "scheme" | "scm" | "ss" | "rkt" => "scheme",
+        "swift" => "swift",
+        "typescript" | "typescriptreact" | "ts" | "tsx" => "typescript",
+        "markdown" | "md" | "plaintext" | "text" | "txt" => "english",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Find the embedded configuration for a file path, based on its extension
+pub fn config_for_file(path: &str) -> Option<&'static str> {
+    let extension = std::path::Path::new(path).extension()?.to_str()?;
+    config_for_language(extension)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::audio_samples::AudioSample;
 
     #[test]
+    fn test_config_for_language() {
+        assert_eq!(config_for_language("rust"), Some("rust"));
+        assert_eq!(config_for_language("typescriptreact"), Some("typescript"));
+        assert_eq!(config_for_language(".py"), Some("python"));
+        assert_eq!(config_for_language("cobol"), None);
+
+        assert_eq!(config_for_file("src/main.rs"), Some("rust"));
+        assert_eq!(config_for_file("README.md"), Some("english"));
+        assert_eq!(config_for_file("Makefile"), None);
+
+        // Every mapped name is an embedded config
+        for language in [
+            "c", "cpp", "cs", "clj", "el", "go", "hs", "java", "js", "kt",
+        ] {
+            assert!(config_exists(config_for_language(language).unwrap()));
+        }
+        for language in ["php", "py", "rb", "rs", "scm", "swift", "ts", "md"] {
+            assert!(config_exists(config_for_language(language).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_export_configs() {
+        let dir = std::env::temp_dir().join(format!("codebeats-export-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let written = export_configs(&dir, false).unwrap();
+        assert_eq!(written.len(), get_config_names().len());
+        let rust = KeyboardConfig::load_from_file(dir.join("rust.json")).unwrap();
+        assert_eq!(rust.description, load_config("rust").unwrap().description);
+
+        // Edited files survive a second export unless overwriting
+        fs::write(dir.join("rust.json"), "{}").unwrap();
+        assert!(export_configs(&dir, false).unwrap().is_empty());
+        assert_eq!(fs::read_to_string(dir.join("rust.json")).unwrap(), "{}");
+        assert_eq!(
+            export_configs(&dir, true).unwrap().len(),
+            get_config_names().len()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[allow(clippy::len_zero)]
     fn test_embedded_fart_audio_data() {
         let fart_data = get_fart_audio_data();
 
@@ -0,0 +1,286 @@
+//! Event stream module
+//!
+//! This module broadcasts what the engine plays as JSON events over a local
+//! WebSocket, so external visualizers (OBS overlays, web dashboards) can react
+//! in real time. Each event is sent as one text frame, e.g.
+//! `{"type":"note_on","key":"A","note":"A4","frequency":440.0,"volume":0.7}`.
+//! The server is send-only: messages from clients are ignored.
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Default address of the event stream
+pub const DEFAULT_EVENTS_ADDRESS: &str = "127.0.0.1:47802";
+
+/// GUID appended to the client key in the WebSocket handshake (RFC 6455)
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// How long a write may block before a slow client is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Something the engine played
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EngineEvent {
+    /// A key started a note or sample
+    NoteOn {
+        key: String,
+        /// Note name, or None for samples
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+        /// Frequency in Hz, or None for samples
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        frequency: Option<f32>,
+        volume: f32,
+    },
+    /// A key was released
+    NoteOff { key: String },
+    /// An Easter egg sequence was typed
+    EasterEgg { name: String },
+}
+
+/// WebSocket server broadcasting engine events to every connected client
+pub struct EventServer {
+    address: String,
+    events: Sender<EngineEvent>,
+}
+
+impl EventServer {
+    /// Start listening on the address (e.g. "127.0.0.1:47802")
+    pub fn start(address: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind(address)?;
+        let clients: Arc<Mutex<Vec<TcpStream>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let clients_clone = clients.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let clients = clients_clone.clone();
+                thread::spawn(move || {
+                    if let Ok(stream) = accept_websocket(stream)
+                        && let Ok(mut clients) = clients.lock()
+                    {
+                        clients.push(stream);
+                    }
+                });
+            }
+        });
+
+        // Serialize and send on a separate thread so the engine never blocks
+        let (events, receiver) = mpsc::channel::<EngineEvent>();
+        thread::spawn(move || {
+            for event in receiver {
+                let Ok(json) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                let frame = text_frame(&json);
+                if let Ok(mut clients) = clients.lock() {
+                    clients.retain_mut(|client| client.write_all(&frame).is_ok());
+                }
+            }
+        });
+
+        Ok(Self {
+            address: address.to_string(),
+            events,
+        })
+    }
+
+    /// Address this server listens on
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Queue an event for every connected client
+    pub fn send(&self, event: EngineEvent) {
+        let _ = self.events.send(event);
+    }
+}
+
+/// Perform the server side of the WebSocket opening handshake
+fn accept_websocket(stream: TcpStream) -> Result<TcpStream, Box<dyn std::error::Error>> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut key = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("sec-websocket-key")
+        {
+            key = Some(value.trim().to_string());
+        }
+    }
+
+    let mut stream = stream;
+    let Some(key) = key else {
+        let body = "expected a WebSocket upgrade\n";
+        write!(
+            stream,
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )?;
+        return Err("missing Sec-WebSocket-Key".into());
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(&key)
+    )?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    Ok(stream)
+}
+
+/// Compute the Sec-WebSocket-Accept value for a client key
+fn accept_key(key: &str) -> String {
+    base64_encode(&sha1(format!("{}{}", key, WEBSOCKET_GUID).as_bytes()))
+}
+
+/// Build an unmasked WebSocket text frame
+fn text_frame(text: &str) -> Vec<u8> {
+    let payload = text.as_bytes();
+    let mut frame = vec![0x81]; // FIN + text opcode
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// SHA-1 digest, needed only for the handshake
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (chunk, state) in digest.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&state.to_be_bytes());
+    }
+    digest
+}
+
+/// Standard base64 encoding with padding
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_key() {
+        // Example from RFC 6455, section 1.3
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64_encode(b"ab"), "YWI=");
+        assert_eq!(base64_encode(b"a"), "YQ==");
+    }
+
+    #[test]
+    fn test_text_frame() {
+        assert_eq!(text_frame("hi"), vec![0x81, 2, b'h', b'i']);
+
+        let long = "x".repeat(300);
+        let frame = text_frame(&long);
+        assert_eq!(&frame[..4], &[0x81, 126, 0x01, 0x2C]);
+        assert_eq!(frame.len(), 304);
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let event = EngineEvent::NoteOn {
+            key: "A".to_string(),
+            note: None,
+            frequency: None,
+            volume: 0.5,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"type":"note_on","key":"A","volume":0.5}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&EngineEvent::EasterEgg {
+                name: "oppokokoppo".to_string()
+            })
+            .unwrap(),
+            r#"{"type":"easter_egg","name":"oppokokoppo"}"#
+        );
+    }
+}
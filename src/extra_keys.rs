@@ -0,0 +1,140 @@
+//! Extra key module
+//!
+//! `device_query` reports letters, digits, navigation keys and the numpad, but
+//! not PrintScreen, the lock keys or media keys. This module polls those keys
+//! directly so they can be mapped like any other key:
+//! - Linux: the X11 keymap (`XQueryKeymap`)
+//! - Windows: `GetAsyncKeyState`
+//! - Other platforms: no detection (macOS delivers media keys as system
+//!   events that can't be polled)
+//!
+//! Polling needs the `native` feature; the key names are always available.
+
+/// Mapping names of the extra keys, with their X11 keycodes and Windows virtual-key codes
+#[cfg_attr(
+    not(all(feature = "native", any(target_os = "linux", target_os = "windows"))),
+    allow(dead_code)
+)]
+const EXTRA_KEYS: &[(&str, u8, i32)] = &[
+    ("PrintScreen", 107, 0x2C),
+    ("ScrollLock", 78, 0x91),
+    ("Pause", 127, 0x13),
+    ("NumLock", 77, 0x90),
+    ("VolumeMute", 121, 0xAD),
+    ("VolumeDown", 122, 0xAE),
+    ("VolumeUp", 123, 0xAF),
+    ("MediaNext", 171, 0xB0),
+    ("MediaPlayPause", 172, 0xB3),
+    ("MediaPrevious", 173, 0xB1),
+    ("MediaStop", 174, 0xB2),
+];
+
+/// Names of the keys reported by `ExtraKeyMonitor`
+pub const EXTRA_KEY_NAMES: &[&str] = &[
+    "PrintScreen",
+    "ScrollLock",
+    "Pause",
+    "NumLock",
+    "VolumeMute",
+    "VolumeDown",
+    "VolumeUp",
+    "MediaNext",
+    "MediaPlayPause",
+    "MediaPrevious",
+    "MediaStop",
+];
+
+/// Polls the keys that `device_query` doesn't report
+#[cfg(feature = "native")]
+pub struct ExtraKeyMonitor {
+    #[cfg(target_os = "linux")]
+    display: *mut std::ffi::c_void,
+}
+
+#[cfg(all(feature = "native", target_os = "linux"))]
+#[link(name = "X11")]
+unsafe extern "C" {
+    fn XOpenDisplay(name: *const std::ffi::c_char) -> *mut std::ffi::c_void;
+    fn XCloseDisplay(display: *mut std::ffi::c_void) -> i32;
+    fn XQueryKeymap(display: *mut std::ffi::c_void, keys: *mut std::ffi::c_char) -> i32;
+}
+
+#[cfg(feature = "native")]
+impl Default for ExtraKeyMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "native")]
+impl ExtraKeyMonitor {
+    pub fn new() -> Self {
+        Self {
+            // SAFETY: a null name opens the display given by $DISPLAY; failure returns null
+            #[cfg(target_os = "linux")]
+            display: unsafe { XOpenDisplay(std::ptr::null()) },
+        }
+    }
+
+    /// Names of the extra keys currently held down
+    #[cfg(target_os = "linux")]
+    pub fn held_keys(&mut self) -> Vec<&'static str> {
+        if self.display.is_null() {
+            return Vec::new();
+        }
+
+        let mut keymap = [0 as std::ffi::c_char; 32];
+        // SAFETY: the display is open and the keymap buffer holds the 32 bytes X11 writes
+        unsafe { XQueryKeymap(self.display, keymap.as_mut_ptr()) };
+        EXTRA_KEYS
+            .iter()
+            .filter(|(_, x11_key, _)| {
+                keymap[usize::from(*x11_key / 8)] as u8 & (1 << (x11_key % 8)) != 0
+            })
+            .map(|(name, _, _)| *name)
+            .collect()
+    }
+
+    /// Names of the extra keys currently held down
+    #[cfg(target_os = "windows")]
+    pub fn held_keys(&mut self) -> Vec<&'static str> {
+        #[link(name = "user32")]
+        unsafe extern "system" {
+            fn GetAsyncKeyState(key: i32) -> i16;
+        }
+
+        EXTRA_KEYS
+            .iter()
+            // SAFETY: only reads the state of a virtual key
+            .filter(|(_, _, virtual_key)| unsafe { GetAsyncKeyState(*virtual_key) } as u16 & 0x8000 != 0)
+            .map(|(name, _, _)| *name)
+            .collect()
+    }
+
+    /// Names of the extra keys currently held down
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    pub fn held_keys(&mut self) -> Vec<&'static str> {
+        Vec::new()
+    }
+}
+
+#[cfg(all(feature = "native", target_os = "linux"))]
+impl Drop for ExtraKeyMonitor {
+    fn drop(&mut self) {
+        if !self.display.is_null() {
+            // SAFETY: the display was opened by XOpenDisplay and is closed only once
+            unsafe { XCloseDisplay(self.display) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extra_key_names() {
+        let names: Vec<&str> = EXTRA_KEYS.iter().map(|(name, _, _)| *name).collect();
+        assert_eq!(names, EXTRA_KEY_NAMES);
+    }
+}
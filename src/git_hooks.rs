@@ -0,0 +1,125 @@
+//! Git hook module
+//!
+//! This module installs Git hooks that play notification sounds on a running
+//! daemon, so commits and pushes get a musical stinger:
+//! - `pre-commit`: the "pre-commit" sound as the commit starts
+//! - `post-commit`: the "commit" sound once it is made
+//! - `pre-push`: the "push" sound as the push starts (Git has no hook after a push)
+//!
+//! The hooks never fail or delay Git: the sound is requested in the
+//! background and errors (such as no daemon running) are ignored. Existing
+//! hooks not written by CodeBeats are left alone unless forced.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Hooks installed, with the notification each plays
+pub const HOOKS: &[(&str, &str)] = &[
+    ("pre-commit", "pre-commit"),
+    ("post-commit", "commit"),
+    ("pre-push", "push"),
+];
+
+/// Line marking a hook as written by CodeBeats
+const MARKER: &str = "# Installed by `codebeats git-hook install`";
+
+/// Hooks directory of the repository containing `dir` (honors `core.hooksPath`)
+pub fn hooks_dir(dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Could not run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Not a Git repository: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(dir.join(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// Shell script of a hook playing a notification through the `codebeats` executable
+pub fn hook_script(codebeats: &Path, notification: &str) -> String {
+    format!(
+        "#!/bin/sh\n{}\n\"{}\" notify {} >/dev/null 2>&1 &\nexit 0\n",
+        MARKER,
+        codebeats.display(),
+        notification
+    )
+}
+
+/// Write the hooks into a hooks directory, returning a line per hook on what was done
+///
+/// Hooks that already exist and weren't written by CodeBeats are skipped unless `force`.
+pub fn install(
+    hooks_dir: &Path,
+    codebeats: &Path,
+    force: bool,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    fs::create_dir_all(hooks_dir)?;
+    let mut report = Vec::new();
+    for (hook, notification) in HOOKS {
+        let path = hooks_dir.join(hook);
+        if !force && fs::read_to_string(&path).is_ok_and(|script| !script.contains(MARKER)) {
+            report.push(format!(
+                "Skipped {} (an existing hook; use --force to replace it)",
+                hook
+            ));
+            continue;
+        }
+        fs::write(&path, hook_script(codebeats, notification))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        }
+        report.push(format!("Installed {} (plays '{}')", hook, notification));
+    }
+    Ok(report)
+}
+
+/// Remove the hooks written by CodeBeats, returning the names of those removed
+pub fn uninstall(hooks_dir: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut removed = Vec::new();
+    for (hook, _) in HOOKS {
+        let path = hooks_dir.join(hook);
+        if fs::read_to_string(&path).is_ok_and(|script| script.contains(MARKER)) {
+            fs::remove_file(&path)?;
+            removed.push(hook.to_string());
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_and_uninstall() {
+        let dir = std::env::temp_dir().join(format!("codebeats-hooks-{}", std::process::id()));
+        let codebeats = Path::new("/usr/local/bin/codebeats");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("pre-push"), "#!/bin/sh\nmake lint\n").unwrap();
+
+        let report = install(&dir, codebeats, false).unwrap();
+        assert_eq!(report.len(), 3);
+        assert!(report[2].starts_with("Skipped pre-push"));
+        let script = fs::read_to_string(dir.join("post-commit")).unwrap();
+        assert!(script.contains("\"/usr/local/bin/codebeats\" notify commit"));
+        assert_eq!(
+            fs::read_to_string(dir.join("pre-push")).unwrap(),
+            "#!/bin/sh\nmake lint\n"
+        );
+
+        // Reinstalling replaces CodeBeats' own hooks without forcing
+        assert!(install(&dir, codebeats, false).unwrap()[0].starts_with("Installed"));
+
+        assert_eq!(uninstall(&dir).unwrap(), vec!["pre-commit", "post-commit"]);
+        assert!(dir.join("pre-push").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
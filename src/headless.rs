@@ -0,0 +1,155 @@
+//! Headless module
+//!
+//! This module plays key events through a configuration without a keyboard
+//! or audio device: the caller presses and releases keys and pulls the
+//! samples. It needs none of the `native` dependencies, so servers and CI
+//! can render and test the sound (`--no-default-features`), and the offline
+//! renderer and the web build play keys through it.
+
+use crate::CodeBeatsConfig;
+use crate::audio_engine::AudioState;
+use crate::keyboard_config::KeyboardConfig;
+use crate::keyboard_mapping::{
+    VirtualKeycode, get_frequency_and_volume_with_config_virtual, get_mapping_for_virtual,
+    note_overrides_for_virtual,
+};
+use std::collections::HashMap;
+
+/// The synthesis engine driven by key events
+pub struct HeadlessEngine {
+    state: AudioState,
+    keyboard_config: KeyboardConfig,
+    /// Held keys, and whether each plays a sample (true) or a note (false)
+    held: HashMap<String, bool>,
+}
+
+impl HeadlessEngine {
+    /// Set up the synthesis from a config's waveform, volume, filter, humanization,
+    /// SoundFont and fart sample; call `set_keyboard_config` to choose the keys
+    pub fn new(
+        config: &CodeBeatsConfig,
+        sample_rate: u32,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut state = AudioState::new(
+            sample_rate as f32,
+            config.waveform,
+            config.volume,
+            config.filter_cutoff,
+        );
+        state.set_velocity_enabled(config.velocity);
+        state.set_humanize(config.humanize);
+        if let Some(ref selection) = config.soundfont {
+            state.set_soundfont_preset(Some(selection.load_preset()?));
+        }
+        if let Some(ref path) = config.fart_sample_path {
+            let sample = state
+                .load_sample(path)
+                .map_err(|e| format!("Could not load fart sample '{}': {}", path, e))?;
+            state.set_fart_sample(sample);
+        }
+        Ok(Self {
+            state,
+            keyboard_config: KeyboardConfig::default(),
+            held: HashMap::new(),
+        })
+    }
+
+    /// Play keys through a keyboard config, loading its samples and modulation
+    ///
+    /// Returns a message for each sample that could not be loaded.
+    pub fn set_keyboard_config(&mut self, keyboard_config: KeyboardConfig) -> Vec<String> {
+        self.state.release_all();
+        self.held.clear();
+        self.state.clear_key_samples();
+        let warnings = self.state.load_config_samples(&keyboard_config);
+        self.state.set_modulation(
+            keyboard_config.vibrato,
+            keyboard_config.lfo,
+            keyboard_config.mod_matrix.clone(),
+        );
+        self.state
+            .set_filter_envelope(keyboard_config.filter_envelope.clone());
+        self.keyboard_config = keyboard_config;
+        warnings
+    }
+
+    /// Press a key: its samples if it has any, otherwise its note
+    ///
+    /// Returns false if the key is unmapped or already held.
+    pub fn key_down(&mut self, key: &VirtualKeycode) -> bool {
+        let key_id = key.to_string();
+        if self.held.contains_key(&key_id) {
+            return false;
+        }
+
+        let mapping = get_mapping_for_virtual(key, &self.keyboard_config);
+        let sampled = mapping
+            .filter(|mapping| mapping.has_samples())
+            .and_then(|mapping| self.state.start_sample_with_id(&key_id, mapping.volume))
+            .is_some();
+        if sampled {
+            self.held.insert(key_id, true);
+            return true;
+        }
+        let Some((frequency, volume, _)) =
+            get_frequency_and_volume_with_config_virtual(key, &self.keyboard_config)
+        else {
+            return false;
+        };
+        let mut overrides = note_overrides_for_virtual(key, &self.keyboard_config, false);
+        self.state.prepare_key_press(&mut overrides);
+        self.state
+            .start_note_with_overrides(&key_id, frequency, volume, &overrides);
+        self.held.insert(key_id, false);
+        true
+    }
+
+    /// Release a key pressed with `key_down`
+    pub fn key_up(&mut self, key: &VirtualKeycode) {
+        let key_id = key.to_string();
+        match self.held.remove(&key_id) {
+            Some(true) => self.state.release_sample_with_id(&key_id),
+            Some(false) => self.state.stop_note_with_id(&key_id),
+            None => {}
+        }
+    }
+
+    /// Render the next block of mono samples
+    pub fn process(&mut self, block: &mut [f32]) {
+        self.state.fill_block(block);
+    }
+
+    /// The underlying engine, for settings such as waveform, volume and filter
+    pub fn state_mut(&mut self) -> &mut AudioState {
+        &mut self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keycode::Keycode;
+
+    #[test]
+    fn test_key_events() {
+        let mut engine = HeadlessEngine::new(&CodeBeatsConfig::default(), 44100).unwrap();
+        assert!(
+            engine
+                .set_keyboard_config(KeyboardConfig::default())
+                .is_empty()
+        );
+        let key = VirtualKeycode::Physical(Keycode::A);
+        let mut block = [0.0; 512];
+
+        assert!(engine.key_down(&key));
+        assert!(!engine.key_down(&key));
+        engine.process(&mut block);
+        assert!(block.iter().any(|sample| sample.abs() > 0.001));
+
+        engine.key_up(&key);
+        for _ in 0..200 {
+            engine.process(&mut block);
+        }
+        assert!(block.iter().all(|sample| sample.abs() < 1e-6));
+    }
+}
@@ -0,0 +1,211 @@
+//! Heatmap module
+//!
+//! This module renders session statistics as a standalone HTML page: a
+//! keyboard colored by how often each key was pressed, and a histogram of
+//! the most-played notes. `codebeats heatmap` writes it for the most recent
+//! session.
+
+use crate::stats::StatsSnapshot;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Keyboard rows as (mapping name, label, width in key units)
+const ROWS: &[&[(&str, &str, f32)]] = &[
+    &[
+        ("Grave", "`", 1.0),
+        ("Key1", "1", 1.0),
+        ("Key2", "2", 1.0),
+        ("Key3", "3", 1.0),
+        ("Key4", "4", 1.0),
+        ("Key5", "5", 1.0),
+        ("Key6", "6", 1.0),
+        ("Key7", "7", 1.0),
+        ("Key8", "8", 1.0),
+        ("Key9", "9", 1.0),
+        ("Key0", "0", 1.0),
+        ("Minus", "-", 1.0),
+        ("Equal", "=", 1.0),
+        ("Backspace", "⌫", 2.0),
+    ],
+    &[
+        ("Tab", "Tab", 1.5),
+        ("Q", "Q", 1.0),
+        ("W", "W", 1.0),
+        ("E", "E", 1.0),
+        ("R", "R", 1.0),
+        ("T", "T", 1.0),
+        ("Y", "Y", 1.0),
+        ("U", "U", 1.0),
+        ("I", "I", 1.0),
+        ("O", "O", 1.0),
+        ("P", "P", 1.0),
+        ("LeftBracket", "[", 1.0),
+        ("RightBracket", "]", 1.0),
+        ("BackSlash", "\\", 1.5),
+    ],
+    &[
+        ("CapsLock", "Caps", 1.75),
+        ("A", "A", 1.0),
+        ("S", "S", 1.0),
+        ("D", "D", 1.0),
+        ("F", "F", 1.0),
+        ("G", "G", 1.0),
+        ("H", "H", 1.0),
+        ("J", "J", 1.0),
+        ("K", "K", 1.0),
+        ("L", "L", 1.0),
+        ("Semicolon", ";", 1.0),
+        ("Apostrophe", "'", 1.0),
+        ("Enter", "Enter", 2.25),
+    ],
+    &[
+        ("LShift", "Shift", 2.25),
+        ("Z", "Z", 1.0),
+        ("X", "X", 1.0),
+        ("C", "C", 1.0),
+        ("V", "V", 1.0),
+        ("B", "B", 1.0),
+        ("N", "N", 1.0),
+        ("M", "M", 1.0),
+        ("Comma", ",", 1.0),
+        ("Dot", ".", 1.0),
+        ("Slash", "/", 1.0),
+        ("RShift", "Shift", 2.75),
+    ],
+    &[
+        ("LControl", "Ctrl", 1.25),
+        ("LMeta", "Meta", 1.25),
+        ("LAlt", "Alt", 1.25),
+        ("Space", "Space", 6.25),
+        ("RAlt", "Alt", 1.25),
+        ("RMeta", "Meta", 1.25),
+        ("RControl", "Ctrl", 1.5),
+    ],
+];
+
+/// Width of one key unit in pixels
+const KEY_UNIT: f32 = 44.0;
+
+/// Background color for a count, from pale yellow (never pressed) to deep red (most pressed)
+fn heat_color(count: u64, max: u64) -> String {
+    if count == 0 || max == 0 {
+        return "#f4f1e8".to_string();
+    }
+    let heat = count as f32 / max as f32;
+    format!(
+        "hsl({:.0}, 90%, {:.0}%)",
+        50.0 - 50.0 * heat,
+        85.0 - 40.0 * heat
+    )
+}
+
+/// Escape text for HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render a keyboard heatmap and note histogram as a complete HTML page
+pub fn render_html(stats: &StatsSnapshot, title: &str) -> String {
+    let counts: HashMap<&str, u64> = stats
+        .key_counts
+        .iter()
+        .map(|(key, count)| (key.as_str(), *count))
+        .collect();
+    let max_key = stats.key_counts.first().map_or(0, |(_, count)| *count);
+    let max_note = stats.top_notes.first().map_or(0, |(_, count)| *count);
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>\n\
+         body {{ font-family: sans-serif; margin: 2em; color: #333; }}\n\
+         .row {{ display: flex; gap: 4px; margin-bottom: 4px; }}\n\
+         .key {{ height: {unit}px; border-radius: 6px; display: flex; flex-direction: column; \
+         align-items: center; justify-content: center; font-size: 13px; }}\n\
+         .key small {{ font-size: 10px; opacity: 0.7; }}\n\
+         .bar {{ display: flex; align-items: center; gap: 8px; margin: 3px 0; }}\n\
+         .bar span {{ width: 3em; text-align: right; }}\n\
+         .bar div {{ height: 18px; background: #d9534f; border-radius: 3px; }}\n\
+         </style>\n</head>\n<body>\n<h1>{title}</h1>\n\
+         <p>{keys} keystrokes, {notes} notes, {minutes:.0} min</p>\n<h2>Keys</h2>\n",
+        title = escape(title),
+        unit = KEY_UNIT,
+        keys = stats.total_keys,
+        notes = stats.total_notes,
+        minutes = stats.session_seconds / 60.0,
+    );
+
+    for row in ROWS {
+        html.push_str("<div class=\"row\">");
+        for (name, label, width) in *row {
+            let count = counts.get(name).copied().unwrap_or(0);
+            let _ = write!(
+                html,
+                "<div class=\"key\" style=\"width: {:.0}px; background: {}\" title=\"{}: {}\">\
+                 {}<small>{}</small></div>",
+                width * KEY_UNIT + (width - 1.0) * 4.0,
+                heat_color(count, max_key),
+                name,
+                count,
+                escape(label),
+                count
+            );
+        }
+        html.push_str("</div>\n");
+    }
+
+    html.push_str("<h2>Notes</h2>\n");
+    if stats.top_notes.is_empty() {
+        html.push_str("<p>No notes played.</p>\n");
+    }
+    for (note, count) in &stats.top_notes {
+        let _ = writeln!(
+            html,
+            "<div class=\"bar\"><span>{}</span><div style=\"width: {:.0}px\"></div>{}</div>",
+            escape(note),
+            400.0 * *count as f32 / max_note.max(1) as f32,
+            count
+        );
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_html() {
+        let stats = StatsSnapshot {
+            session_seconds: 120.0,
+            total_keys: 6,
+            total_notes: 4,
+            easter_eggs: 0,
+            keys_per_minute: 0,
+            notes_per_minute: 0,
+            top_notes: vec![("C4".to_string(), 3), ("E4".to_string(), 1)],
+            note_counts: vec![("C4".to_string(), 3), ("E4".to_string(), 1)],
+            key_counts: vec![("A".to_string(), 4), ("Space".to_string(), 2)],
+        };
+        let html = render_html(&stats, "Session <1>");
+
+        assert!(html.contains("<title>Session &lt;1&gt;</title>"));
+        assert!(html.contains("title=\"A: 4\""));
+        assert!(html.contains("title=\"Space: 2\""));
+        assert!(html.contains("title=\"Q: 0\""));
+        assert!(html.contains("<span>C4</span><div style=\"width: 400px\">"));
+        assert!(html.contains("<span>E4</span><div style=\"width: 133px\">"));
+    }
+
+    #[test]
+    fn test_heat_color() {
+        assert_eq!(heat_color(0, 10), "#f4f1e8");
+        assert_eq!(heat_color(10, 10), "hsl(0, 90%, 45%)");
+        assert_eq!(heat_color(5, 10), "hsl(25, 90%, 65%)");
+    }
+}
@@ -0,0 +1,340 @@
+//! HTTP control API module
+//!
+//! This module (enabled by the `http-api` feature) embeds a tiny HTTP/1.1
+//! server so tools like stream decks, Raycast scripts, and home automation can
+//! control CodeBeats with plain HTTP requests. Requests are translated into
+//! IPC messages and handled by the engine like `codebeats ctl` commands:
+//! - `GET /status` (or `GET` on any endpoint): current settings
+//! - `POST /config`: switch config (body: embedded name or JSON file path)
+//! - `POST /volume`: set the master volume (body: 0.0-1.0)
+//! - `POST /waveform`: switch waveform (body: waveform name)
+//! - `POST /notify`: play a notification sound (body: its name, e.g. build-success)
+//!
+//! Only requests addressed to a loopback host (and, from browsers, sent by a
+//! loopback page) are served, so web pages can't drive the API through
+//! cross-site requests or DNS rebinding.
+
+use crate::ipc::{IpcMessage, IpcRequest};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// Largest request body accepted, in bytes
+const MAX_BODY_SIZE: usize = 4096;
+
+/// Largest request line and headers accepted, in bytes
+const MAX_HEADER_SIZE: usize = 8192;
+
+/// How long a connection may take to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Host names that reach this machine only
+const LOOPBACK_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "[::1]"];
+
+/// A parsed request
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    host: Option<String>,
+    origin: Option<String>,
+    body: String,
+}
+
+/// HTTP listener accepting control requests on a background thread
+pub struct HttpServer {
+    address: String,
+    requests: Receiver<IpcRequest>,
+}
+
+impl HttpServer {
+    /// Start listening on the address (e.g. "127.0.0.1:47801")
+    pub fn start(address: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind(address)?;
+        let (sender, requests) = mpsc::channel();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                thread::spawn(move || serve_connection(stream, sender));
+            }
+        });
+
+        Ok(Self {
+            address: address.to_string(),
+            requests,
+        })
+    }
+
+    /// Address this server listens on
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Take the next pending request without blocking
+    pub fn try_next(&self) -> Option<IpcRequest> {
+        self.requests.try_recv().ok()
+    }
+}
+
+/// Handle a single request on a connection, then close it
+fn serve_connection(stream: TcpStream, requests: Sender<IpcRequest>) {
+    let Ok(writer) = stream.try_clone() else {
+        return;
+    };
+    if stream.set_read_timeout(Some(READ_TIMEOUT)).is_err() {
+        return;
+    }
+    let limit = (MAX_HEADER_SIZE + MAX_BODY_SIZE) as u64;
+    handle_request(BufReader::new(stream.take(limit)), writer, &requests);
+}
+
+/// Parse one request, forward it to the engine, and write the response
+fn handle_request<R: BufRead, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    requests: &Sender<IpcRequest>,
+) {
+    let (status, body) = match read_request(&mut reader) {
+        Ok(request) => match check_source(&request)
+            .and_then(|()| route(&request.method, &request.path, &request.body))
+        {
+            Ok(message) => forward(message, requests),
+            Err(response) => response,
+        },
+        Err(e) => (400, format!("error: {}", e)),
+    };
+
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let body = format!("{}\n", body);
+    let _ = write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+}
+
+/// Send a message to the engine and turn its reply into a status and body
+fn forward(message: IpcMessage, requests: &Sender<IpcRequest>) -> (u16, String) {
+    let (request, reply) = IpcRequest::new(message);
+    if requests.send(request).is_err() {
+        return (500, "error: engine stopped".to_string());
+    }
+
+    match reply.recv() {
+        Ok(reply) => match reply.strip_prefix("error: ") {
+            Some(error) => (400, format!("error: {}", error)),
+            None => (
+                200,
+                reply.strip_prefix("ok: ").unwrap_or(&reply).to_string(),
+            ),
+        },
+        Err(_) => (500, "error: engine stopped".to_string()),
+    }
+}
+
+/// Read the request line, headers, and body
+fn read_request<R: BufRead>(reader: &mut R) -> Result<Request, Box<dyn std::error::Error>> {
+    let mut request_line = String::new();
+    let mut header_size = reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err("malformed request line".into());
+    };
+
+    let mut content_length = 0;
+    let mut host = None;
+    let mut origin = None;
+    loop {
+        let mut header = String::new();
+        let read = reader.read_line(&mut header)?;
+        header_size += read;
+        if header_size > MAX_HEADER_SIZE {
+            return Err("request headers too large".into());
+        }
+        if read == 0 || header.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => content_length = value.parse()?,
+            "host" => host = Some(value.to_string()),
+            "origin" => origin = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    if content_length > MAX_BODY_SIZE {
+        return Err("request body too large".into());
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method: method.to_uppercase(),
+        path: path.to_string(),
+        host,
+        origin,
+        body: String::from_utf8(body)?,
+    })
+}
+
+/// Reject requests for a host other than this machine (DNS rebinding) and
+/// requests a browser sends from another site's page
+fn check_source(request: &Request) -> Result<(), (u16, String)> {
+    let host = request.host.as_deref().unwrap_or_default();
+    if !is_loopback(host) {
+        return Err((403, format!("error: host '{}' not allowed", host)));
+    }
+    if let Some(ref origin) = request.origin {
+        let origin_host = origin
+            .strip_prefix("http://")
+            .or_else(|| origin.strip_prefix("https://"));
+        if !origin_host.is_some_and(is_loopback) {
+            return Err((403, format!("error: origin '{}' not allowed", origin)));
+        }
+    }
+    Ok(())
+}
+
+/// Whether a `host[:port]` names the loopback interface
+fn is_loopback(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        // An IPv6 address without a port ends in its closing bracket
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    LOOPBACK_HOSTS
+        .iter()
+        .any(|loopback| name.eq_ignore_ascii_case(loopback))
+}
+
+/// Map a method, path, and body to an IPC message, or an error response
+fn route(method: &str, path: &str, body: &str) -> Result<IpcMessage, (u16, String)> {
+    let path = path.split('?').next().unwrap_or(path).trim_end_matches('/');
+    let known = ["/status", "/config", "/volume", "/waveform", "/notify"];
+    if !known.contains(&path) {
+        return Err((404, format!("error: unknown endpoint '{}'", path)));
+    }
+
+    match method {
+        "GET" => return Ok(IpcMessage::Status),
+        "POST" | "PUT" if path != "/status" => {}
+        _ => return Err((405, format!("error: {} not allowed on {}", method, path))),
+    }
+
+    // Accept plain text bodies, with or without JSON string quotes
+    let value = body.trim().trim_matches('"').to_string();
+    if value.is_empty() {
+        return Err((400, "error: missing request body".to_string()));
+    }
+
+    Ok(match path {
+        "/config" => IpcMessage::LoadConfig { config: value },
+        "/volume" => IpcMessage::SetVolume {
+            volume: value
+                .parse()
+                .map_err(|_| (400, format!("error: invalid volume '{}'", value)))?,
+        },
+        "/notify" => IpcMessage::Notify { name: value },
+        _ => IpcMessage::SetWaveform { waveform: value },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routes() {
+        assert_eq!(route("GET", "/status", ""), Ok(IpcMessage::Status));
+        assert_eq!(route("GET", "/volume/", ""), Ok(IpcMessage::Status));
+        assert_eq!(
+            route("POST", "/volume", "0.5\n"),
+            Ok(IpcMessage::SetVolume { volume: 0.5 })
+        );
+        assert_eq!(
+            route("PUT", "/waveform", "\"bass\""),
+            Ok(IpcMessage::SetWaveform {
+                waveform: "bass".to_string()
+            })
+        );
+        assert_eq!(
+            route("POST", "/notify", "build-fail"),
+            Ok(IpcMessage::Notify {
+                name: "build-fail".to_string()
+            })
+        );
+        assert_eq!(route("POST", "/volume", "loud").unwrap_err().0, 400);
+        assert_eq!(route("POST", "/status", "x").unwrap_err().0, 405);
+        assert_eq!(route("GET", "/missing", "").unwrap_err().0, 404);
+    }
+
+    #[test]
+    fn test_handle_request() {
+        let (sender, receiver) = mpsc::channel::<IpcRequest>();
+        let engine = thread::spawn(move || {
+            let request = receiver.recv().unwrap();
+            assert_eq!(
+                request.message,
+                IpcMessage::LoadConfig {
+                    config: "rust".to_string()
+                }
+            );
+            request.reply("ok: config=rust");
+        });
+
+        let input = b"POST /config HTTP/1.1\r\nHost: localhost\r\nContent-Length: 4\r\n\r\nrust";
+        let mut output = Vec::new();
+        handle_request(&input[..], &mut output, &sender);
+        engine.join().unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(output.ends_with("\r\n\r\nconfig=rust\n"));
+    }
+
+    #[test]
+    fn test_rejects_foreign_requests() {
+        let respond = |input: &[u8]| {
+            let (sender, _receiver) = mpsc::channel::<IpcRequest>();
+            let mut output = Vec::new();
+            handle_request(input, &mut output, &sender);
+            String::from_utf8(output).unwrap()
+        };
+        let forbidden = "HTTP/1.1 403 Forbidden\r\n";
+        assert!(
+            respond(b"GET /status HTTP/1.1\r\nHost: evil.example:47801\r\n\r\n")
+                .starts_with(forbidden)
+        );
+        assert!(respond(b"GET /status HTTP/1.1\r\n\r\n").starts_with(forbidden));
+        assert!(
+            respond(b"POST /volume HTTP/1.1\r\nHost: 127.0.0.1:47801\r\nOrigin: https://evil.example\r\nContent-Length: 1\r\n\r\n0")
+                .starts_with(forbidden)
+        );
+        let huge = format!(
+            "GET /status HTTP/1.1\r\nX-Padding: {}\r\n\r\n",
+            "a".repeat(MAX_HEADER_SIZE)
+        );
+        assert!(respond(huge.as_bytes()).starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+        assert!(is_loopback("localhost:47801"));
+        assert!(is_loopback("[::1]"));
+        assert!(is_loopback("[::1]:47801"));
+        assert!(!is_loopback("127.0.0.1.evil.example"));
+    }
+}
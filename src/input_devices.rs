@@ -0,0 +1,295 @@
+//! Input device module
+//!
+//! `device_query` merges every keyboard into one key state, so this module
+//! reads the input devices themselves to tell which keyboard a key came from.
+//! That lets a macro pad play a drum kit while the main keyboard plays melody:
+//! - Linux: the evdev devices (`/dev/input/event*`, readable by the `input`
+//!   group), each read on a background thread
+//! - Other platforms: not supported
+
+use crate::keyboard_config::KeyboardConfig;
+use crate::keycode::Keycode;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// A keyboard config used for the keys of one input device
+#[derive(Debug, Clone)]
+pub struct DeviceConfig {
+    /// Device name, or part of it (case-insensitive), e.g. "Macro Pad"
+    pub device: String,
+    /// Config played by the device's keys
+    pub keyboard_config: KeyboardConfig,
+}
+
+/// An input device that reports keys
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputDevice {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Input devices that report keys (keyboards, macro pads, ...)
+#[cfg(target_os = "linux")]
+pub fn list_devices() -> Vec<InputDevice> {
+    let Ok(entries) = std::fs::read_dir("/sys/class/input") else {
+        return Vec::new();
+    };
+
+    let mut devices: Vec<InputDevice> = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("event"))
+        .filter_map(|entry| {
+            let device = entry.path().join("device");
+            let keys = std::fs::read_to_string(device.join("capabilities/key")).ok()?;
+            if keys.trim().chars().all(|c| c == '0' || c.is_whitespace()) {
+                return None;
+            }
+            Some(InputDevice {
+                name: std::fs::read_to_string(device.join("name"))
+                    .ok()?
+                    .trim()
+                    .to_string(),
+                path: PathBuf::from("/dev/input").join(entry.file_name()),
+            })
+        })
+        .collect();
+    devices.sort_by(|a, b| a.path.cmp(&b.path));
+    devices
+}
+
+/// Input devices that report keys (keyboards, macro pads, ...)
+#[cfg(not(target_os = "linux"))]
+pub fn list_devices() -> Vec<InputDevice> {
+    Vec::new()
+}
+
+/// Tracks which device holds each key, for the devices with their own config
+pub struct DeviceKeyMonitor {
+    /// Held keys and the index of the device config whose device holds them
+    held: Arc<Mutex<HashMap<Keycode, usize>>>,
+}
+
+impl DeviceKeyMonitor {
+    /// Start reading the devices matching each pattern (device name or part of it)
+    ///
+    /// Returns the monitor and a warning for each pattern no readable device matched.
+    pub fn start(patterns: &[String]) -> (Self, Vec<String>) {
+        let held = Arc::new(Mutex::new(HashMap::new()));
+        let devices = list_devices();
+        let mut warnings = Vec::new();
+
+        for (index, pattern) in patterns.iter().enumerate() {
+            let pattern_lower = pattern.to_lowercase();
+            let matching: Vec<_> = devices
+                .iter()
+                .filter(|device| device.name.to_lowercase().contains(&pattern_lower))
+                .collect();
+            if matching.is_empty() {
+                warnings.push(format!("No input device matching '{}'", pattern));
+            }
+            for device in matching {
+                if let Err(e) = read_device(device, index, held.clone()) {
+                    warnings.push(format!(
+                        "Could not read input device '{}' ({}): {}",
+                        device.name,
+                        device.path.display(),
+                        e
+                    ));
+                }
+            }
+        }
+
+        (Self { held }, warnings)
+    }
+
+    /// Index of the device config whose device holds a key, if any
+    pub fn device_for(&self, key: Keycode) -> Option<usize> {
+        self.held.lock().ok()?.get(&key).copied()
+    }
+}
+
+/// Read a device's key events on a background thread until it goes away
+#[cfg(target_os = "linux")]
+fn read_device(
+    device: &InputDevice,
+    index: usize,
+    held: Arc<Mutex<HashMap<Keycode, usize>>>,
+) -> std::io::Result<()> {
+    use std::io::Read;
+
+    /// `struct input_event`: a timeval (two C longs), then type, code and value
+    const EVENT_SIZE: usize = 2 * std::mem::size_of::<std::ffi::c_long>() + 8;
+    const EV_KEY: u16 = 1;
+
+    let mut file = std::fs::File::open(&device.path)?;
+    std::thread::spawn(move || {
+        let mut event = [0u8; EVENT_SIZE];
+        while file.read_exact(&mut event).is_ok() {
+            let at = EVENT_SIZE - 8;
+            let kind = u16::from_ne_bytes([event[at], event[at + 1]]);
+            let code = u16::from_ne_bytes([event[at + 2], event[at + 3]]);
+            let value =
+                i32::from_ne_bytes([event[at + 4], event[at + 5], event[at + 6], event[at + 7]]);
+            if kind != EV_KEY {
+                continue;
+            }
+            let Some(key) = evdev_keycode(code) else {
+                continue;
+            };
+            let Ok(mut held) = held.lock() else {
+                break;
+            };
+            // 1 is a press, 2 a repeat and 0 a release
+            if value == 0 {
+                held.remove(&key);
+            } else {
+                held.insert(key, index);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_device(
+    _device: &InputDevice,
+    _index: usize,
+    _held: Arc<Mutex<HashMap<Keycode, usize>>>,
+) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "per-device input is only supported on Linux",
+    ))
+}
+
+/// Keycode for a Linux evdev key code (see linux/input-event-codes.h)
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+fn evdev_keycode(code: u16) -> Option<Keycode> {
+    let key = match code {
+        1 => Keycode::Escape,
+        2 => Keycode::Key1,
+        3 => Keycode::Key2,
+        4 => Keycode::Key3,
+        5 => Keycode::Key4,
+        6 => Keycode::Key5,
+        7 => Keycode::Key6,
+        8 => Keycode::Key7,
+        9 => Keycode::Key8,
+        10 => Keycode::Key9,
+        11 => Keycode::Key0,
+        12 => Keycode::Minus,
+        13 => Keycode::Equal,
+        14 => Keycode::Backspace,
+        15 => Keycode::Tab,
+        16 => Keycode::Q,
+        17 => Keycode::W,
+        18 => Keycode::E,
+        19 => Keycode::R,
+        20 => Keycode::T,
+        21 => Keycode::Y,
+        22 => Keycode::U,
+        23 => Keycode::I,
+        24 => Keycode::O,
+        25 => Keycode::P,
+        26 => Keycode::LeftBracket,
+        27 => Keycode::RightBracket,
+        28 => Keycode::Enter,
+        29 => Keycode::LControl,
+        30 => Keycode::A,
+        31 => Keycode::S,
+        32 => Keycode::D,
+        33 => Keycode::F,
+        34 => Keycode::G,
+        35 => Keycode::H,
+        36 => Keycode::J,
+        37 => Keycode::K,
+        38 => Keycode::L,
+        39 => Keycode::Semicolon,
+        40 => Keycode::Apostrophe,
+        41 => Keycode::Grave,
+        42 => Keycode::LShift,
+        43 => Keycode::BackSlash,
+        44 => Keycode::Z,
+        45 => Keycode::X,
+        46 => Keycode::C,
+        47 => Keycode::V,
+        48 => Keycode::B,
+        49 => Keycode::N,
+        50 => Keycode::M,
+        51 => Keycode::Comma,
+        52 => Keycode::Dot,
+        53 => Keycode::Slash,
+        54 => Keycode::RShift,
+        55 => Keycode::NumpadMultiply,
+        56 => Keycode::LAlt,
+        57 => Keycode::Space,
+        58 => Keycode::CapsLock,
+        59 => Keycode::F1,
+        60 => Keycode::F2,
+        61 => Keycode::F3,
+        62 => Keycode::F4,
+        63 => Keycode::F5,
+        64 => Keycode::F6,
+        65 => Keycode::F7,
+        66 => Keycode::F8,
+        67 => Keycode::F9,
+        68 => Keycode::F10,
+        71 => Keycode::Numpad7,
+        72 => Keycode::Numpad8,
+        73 => Keycode::Numpad9,
+        74 => Keycode::NumpadSubtract,
+        75 => Keycode::Numpad4,
+        76 => Keycode::Numpad5,
+        77 => Keycode::Numpad6,
+        78 => Keycode::NumpadAdd,
+        79 => Keycode::Numpad1,
+        80 => Keycode::Numpad2,
+        81 => Keycode::Numpad3,
+        82 => Keycode::Numpad0,
+        83 => Keycode::NumpadDecimal,
+        87 => Keycode::F11,
+        88 => Keycode::F12,
+        96 => Keycode::NumpadEnter,
+        97 => Keycode::RControl,
+        98 => Keycode::NumpadDivide,
+        100 => Keycode::RAlt,
+        102 => Keycode::Home,
+        103 => Keycode::Up,
+        104 => Keycode::PageUp,
+        105 => Keycode::Left,
+        106 => Keycode::Right,
+        107 => Keycode::End,
+        108 => Keycode::Down,
+        109 => Keycode::PageDown,
+        110 => Keycode::Insert,
+        111 => Keycode::Delete,
+        117 => Keycode::NumpadEquals,
+        125 => Keycode::LMeta,
+        126 => Keycode::RMeta,
+        183 => Keycode::F13,
+        184 => Keycode::F14,
+        185 => Keycode::F15,
+        186 => Keycode::F16,
+        187 => Keycode::F17,
+        188 => Keycode::F18,
+        189 => Keycode::F19,
+        190 => Keycode::F20,
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evdev_keycode() {
+        assert_eq!(evdev_keycode(30), Some(Keycode::A));
+        assert_eq!(evdev_keycode(82), Some(Keycode::Numpad0));
+        assert_eq!(evdev_keycode(57), Some(Keycode::Space));
+        // Mouse buttons are key events too, but not keys
+        assert_eq!(evdev_keycode(0x110), None);
+    }
+}
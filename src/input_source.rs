@@ -0,0 +1,395 @@
+//! Input source module
+//!
+//! An `InputSource` reports the keys held down and lets the main loop wait
+//! for the next key event:
+//! - Polling (all platforms): `device_query` reads the whole key state on
+//!   every tick of the main loop
+//! - Windows: a low-level keyboard hook (`WH_KEYBOARD_LL`) that the system
+//!   calls on each key event, so a press wakes the main loop right away and
+//!   the loop no longer scans every virtual key on each tick
+
+use device_query::{DeviceQuery, DeviceState, Keycode};
+use std::time::Duration;
+
+/// Source of the keys held down
+pub trait InputSource {
+    /// Keys currently held down
+    fn held_keys(&mut self) -> Vec<Keycode>;
+
+    /// Wait until the next key event or the timeout, whichever comes first
+    fn wait(&mut self, timeout: Duration) {
+        std::thread::sleep(timeout);
+    }
+
+    /// Short name of the backend, for logging
+    fn name(&self) -> &'static str;
+}
+
+/// Reads the key state with `device_query` on every call
+pub struct PollingInput {
+    device_state: DeviceState,
+}
+
+impl Default for PollingInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PollingInput {
+    pub fn new() -> Self {
+        Self {
+            device_state: DeviceState::new(),
+        }
+    }
+}
+
+impl InputSource for PollingInput {
+    fn held_keys(&mut self) -> Vec<Keycode> {
+        self.device_state.get_keys()
+    }
+
+    fn name(&self) -> &'static str {
+        "polling"
+    }
+}
+
+/// The event-driven source for this platform, falling back to polling when
+/// it can't be installed (or `polling` is set)
+///
+/// Returns the source and a warning if the event-driven one couldn't be installed.
+#[cfg(target_os = "windows")]
+pub fn platform_source(polling: bool) -> (Box<dyn InputSource>, Option<String>) {
+    if polling {
+        return (Box::new(PollingInput::new()), None);
+    }
+    match hook::KeyboardHook::install() {
+        Ok(hook) => (Box::new(hook), None),
+        Err(e) => (
+            Box::new(PollingInput::new()),
+            Some(format!(
+                "Could not install keyboard hook, polling instead: {}",
+                e
+            )),
+        ),
+    }
+}
+
+/// The event-driven source for this platform; there is none here, so this polls
+#[cfg(not(target_os = "windows"))]
+pub fn platform_source(_polling: bool) -> (Box<dyn InputSource>, Option<String>) {
+    (Box::new(PollingInput::new()), None)
+}
+
+#[cfg(target_os = "windows")]
+mod hook {
+    use super::{InputSource, virtual_key_keycode};
+    use device_query::Keycode;
+    use std::ffi::c_void;
+    use std::sync::{Condvar, Mutex, mpsc};
+    use std::time::Duration;
+
+    const WH_KEYBOARD_LL: i32 = 13;
+    const WM_KEYDOWN: usize = 0x0100;
+    const WM_KEYUP: usize = 0x0101;
+    const WM_SYSKEYDOWN: usize = 0x0104;
+    const WM_SYSKEYUP: usize = 0x0105;
+    const WM_QUIT: u32 = 0x0012;
+    /// `KBDLLHOOKSTRUCT.flags` bit set for extended keys (e.g. the numpad Enter)
+    const LLKHF_EXTENDED: u32 = 0x01;
+
+    // Fields are laid out for the system; only some are read
+    #[allow(dead_code)]
+    #[repr(C)]
+    struct KbdLlHookStruct {
+        vk_code: u32,
+        scan_code: u32,
+        flags: u32,
+        time: u32,
+        extra_info: usize,
+    }
+
+    #[allow(dead_code)]
+    #[repr(C)]
+    struct Msg {
+        hwnd: *mut c_void,
+        message: u32,
+        wparam: usize,
+        lparam: isize,
+        time: u32,
+        pt: [i32; 2],
+    }
+
+    type HookProc = unsafe extern "system" fn(i32, usize, isize) -> isize;
+
+    #[link(name = "user32")]
+    unsafe extern "system" {
+        fn SetWindowsHookExW(
+            id: i32,
+            hook: HookProc,
+            module: *mut c_void,
+            thread: u32,
+        ) -> *mut c_void;
+        fn UnhookWindowsHookEx(hook: *mut c_void) -> i32;
+        fn CallNextHookEx(hook: *mut c_void, code: i32, wparam: usize, lparam: isize) -> isize;
+        fn GetMessageW(msg: *mut Msg, hwnd: *mut c_void, min: u32, max: u32) -> i32;
+        fn PostThreadMessageW(thread: u32, message: u32, wparam: usize, lparam: isize) -> i32;
+    }
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetModuleHandleW(name: *const u16) -> *mut c_void;
+        fn GetCurrentThreadId() -> u32;
+        fn GetLastError() -> u32;
+    }
+
+    /// Keys held down and a count of key events, shared with the hook procedure
+    struct KeyState {
+        held: Vec<Keycode>,
+        events: u64,
+    }
+
+    static KEY_STATE: Mutex<KeyState> = Mutex::new(KeyState {
+        held: Vec::new(),
+        events: 0,
+    });
+    static KEY_EVENT: Condvar = Condvar::new();
+
+    /// Records key events from a hook thread running a message loop
+    pub struct KeyboardHook {
+        thread_id: u32,
+        /// Key events seen by the last `held_keys` call
+        seen_events: u64,
+    }
+
+    impl KeyboardHook {
+        /// Install the hook on its own thread
+        pub fn install() -> Result<Self, String> {
+            let (sender, receiver) = mpsc::channel();
+            std::thread::spawn(move || {
+                // SAFETY: the hook procedure lives in this module, which is
+                // part of the executable GetModuleHandleW(null) returns
+                let hook = unsafe {
+                    SetWindowsHookExW(
+                        WH_KEYBOARD_LL,
+                        hook_proc,
+                        GetModuleHandleW(std::ptr::null()),
+                        0,
+                    )
+                };
+                if hook.is_null() {
+                    // SAFETY: only reads this thread's last error code
+                    let error = unsafe { GetLastError() };
+                    let _ = sender.send(Err(format!("SetWindowsHookExW failed (error {})", error)));
+                    return;
+                }
+                // SAFETY: returns this thread's ID
+                let _ = sender.send(Ok(unsafe { GetCurrentThreadId() }));
+
+                // Low-level hooks are called through this thread's message loop
+                let mut msg = std::mem::MaybeUninit::<Msg>::uninit();
+                // SAFETY: GetMessageW fills msg; the loop ends on WM_QUIT (0) or an error (-1)
+                while unsafe { GetMessageW(msg.as_mut_ptr(), std::ptr::null_mut(), 0, 0) } > 0 {}
+
+                // SAFETY: the hook was installed above and is removed only once
+                unsafe { UnhookWindowsHookEx(hook) };
+            });
+
+            let thread_id = receiver
+                .recv()
+                .map_err(|_| "keyboard hook thread exited".to_string())??;
+            Ok(Self {
+                thread_id,
+                seen_events: 0,
+            })
+        }
+    }
+
+    impl InputSource for KeyboardHook {
+        fn held_keys(&mut self) -> Vec<Keycode> {
+            let state = KEY_STATE.lock().unwrap();
+            self.seen_events = state.events;
+            state.held.clone()
+        }
+
+        fn wait(&mut self, timeout: Duration) {
+            let state = KEY_STATE.lock().unwrap();
+            let _ = KEY_EVENT
+                .wait_timeout_while(state, timeout, |state| state.events == self.seen_events)
+                .unwrap();
+        }
+
+        fn name(&self) -> &'static str {
+            "keyboard hook"
+        }
+    }
+
+    impl Drop for KeyboardHook {
+        fn drop(&mut self) {
+            // SAFETY: posts WM_QUIT to the hook thread, ending its message loop
+            unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, 0, 0) };
+            let mut state = KEY_STATE.lock().unwrap();
+            state.held.clear();
+        }
+    }
+
+    /// Called by the system for each key event; passes every event on unchanged
+    unsafe extern "system" fn hook_proc(code: i32, wparam: usize, lparam: isize) -> isize {
+        if code >= 0 {
+            // SAFETY: for WH_KEYBOARD_LL, lparam points to a KBDLLHOOKSTRUCT
+            let event = unsafe { &*(lparam as *const KbdLlHookStruct) };
+            let extended = event.flags & LLKHF_EXTENDED != 0;
+            if let Some(key) = virtual_key_keycode(event.vk_code, extended)
+                && let Ok(mut state) = KEY_STATE.lock()
+            {
+                match wparam {
+                    WM_KEYDOWN | WM_SYSKEYDOWN if !state.held.contains(&key) => {
+                        state.held.push(key);
+                        state.events += 1;
+                    }
+                    WM_KEYUP | WM_SYSKEYUP => {
+                        state.held.retain(|held| *held != key);
+                        state.events += 1;
+                    }
+                    _ => {}
+                }
+                KEY_EVENT.notify_all();
+            }
+        }
+        // SAFETY: the hook handle argument is ignored by the system
+        unsafe { CallNextHookEx(std::ptr::null_mut(), code, wparam, lparam) }
+    }
+}
+
+/// Keycode for a Windows virtual-key code, as reported by the keyboard hook
+///
+/// `extended` tells the numpad Enter apart from the main one.
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+fn virtual_key_keycode(virtual_key: u32, extended: bool) -> Option<Keycode> {
+    let key = match virtual_key {
+        0x08 => Keycode::Backspace,
+        0x09 => Keycode::Tab,
+        0x0D if extended => Keycode::NumpadEnter,
+        0x0D => Keycode::Enter,
+        0x14 => Keycode::CapsLock,
+        0x1B => Keycode::Escape,
+        0x20 => Keycode::Space,
+        0x21 => Keycode::PageUp,
+        0x22 => Keycode::PageDown,
+        0x23 => Keycode::End,
+        0x24 => Keycode::Home,
+        0x25 => Keycode::Left,
+        0x26 => Keycode::Up,
+        0x27 => Keycode::Right,
+        0x28 => Keycode::Down,
+        0x2D => Keycode::Insert,
+        0x2E => Keycode::Delete,
+        0x30 => Keycode::Key0,
+        0x31 => Keycode::Key1,
+        0x32 => Keycode::Key2,
+        0x33 => Keycode::Key3,
+        0x34 => Keycode::Key4,
+        0x35 => Keycode::Key5,
+        0x36 => Keycode::Key6,
+        0x37 => Keycode::Key7,
+        0x38 => Keycode::Key8,
+        0x39 => Keycode::Key9,
+        0x41 => Keycode::A,
+        0x42 => Keycode::B,
+        0x43 => Keycode::C,
+        0x44 => Keycode::D,
+        0x45 => Keycode::E,
+        0x46 => Keycode::F,
+        0x47 => Keycode::G,
+        0x48 => Keycode::H,
+        0x49 => Keycode::I,
+        0x4A => Keycode::J,
+        0x4B => Keycode::K,
+        0x4C => Keycode::L,
+        0x4D => Keycode::M,
+        0x4E => Keycode::N,
+        0x4F => Keycode::O,
+        0x50 => Keycode::P,
+        0x51 => Keycode::Q,
+        0x52 => Keycode::R,
+        0x53 => Keycode::S,
+        0x54 => Keycode::T,
+        0x55 => Keycode::U,
+        0x56 => Keycode::V,
+        0x57 => Keycode::W,
+        0x58 => Keycode::X,
+        0x59 => Keycode::Y,
+        0x5A => Keycode::Z,
+        0x5B => Keycode::LMeta,
+        0x5C => Keycode::RMeta,
+        0x60 => Keycode::Numpad0,
+        0x61 => Keycode::Numpad1,
+        0x62 => Keycode::Numpad2,
+        0x63 => Keycode::Numpad3,
+        0x64 => Keycode::Numpad4,
+        0x65 => Keycode::Numpad5,
+        0x66 => Keycode::Numpad6,
+        0x67 => Keycode::Numpad7,
+        0x68 => Keycode::Numpad8,
+        0x69 => Keycode::Numpad9,
+        0x6A => Keycode::NumpadMultiply,
+        0x6B => Keycode::NumpadAdd,
+        0x6D => Keycode::NumpadSubtract,
+        0x6E => Keycode::NumpadDecimal,
+        0x6F => Keycode::NumpadDivide,
+        0x70 => Keycode::F1,
+        0x71 => Keycode::F2,
+        0x72 => Keycode::F3,
+        0x73 => Keycode::F4,
+        0x74 => Keycode::F5,
+        0x75 => Keycode::F6,
+        0x76 => Keycode::F7,
+        0x77 => Keycode::F8,
+        0x78 => Keycode::F9,
+        0x79 => Keycode::F10,
+        0x7A => Keycode::F11,
+        0x7B => Keycode::F12,
+        0x7C => Keycode::F13,
+        0x7D => Keycode::F14,
+        0x7E => Keycode::F15,
+        0x7F => Keycode::F16,
+        0x80 => Keycode::F17,
+        0x81 => Keycode::F18,
+        0x82 => Keycode::F19,
+        0x83 => Keycode::F20,
+        0xA0 => Keycode::LShift,
+        0xA1 => Keycode::RShift,
+        0xA2 => Keycode::LControl,
+        0xA3 => Keycode::RControl,
+        0xA4 => Keycode::LAlt,
+        0xA5 => Keycode::RAlt,
+        0xBA => Keycode::Semicolon,
+        0xBB => Keycode::Equal,
+        0xBC => Keycode::Comma,
+        0xBD => Keycode::Minus,
+        0xBE => Keycode::Dot,
+        0xBF => Keycode::Slash,
+        0xC0 => Keycode::Grave,
+        0xDB => Keycode::LeftBracket,
+        0xDC => Keycode::BackSlash,
+        0xDD => Keycode::RightBracket,
+        0xDE => Keycode::Apostrophe,
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_key_keycode() {
+        assert_eq!(virtual_key_keycode(0x41, false), Some(Keycode::A));
+        assert_eq!(virtual_key_keycode(0x0D, false), Some(Keycode::Enter));
+        assert_eq!(virtual_key_keycode(0x0D, true), Some(Keycode::NumpadEnter));
+        assert_eq!(virtual_key_keycode(0xA3, true), Some(Keycode::RControl));
+        // Media keys are reported by the extra key monitor instead
+        assert_eq!(virtual_key_keycode(0xB3, false), None);
+    }
+}
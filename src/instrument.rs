@@ -0,0 +1,236 @@
+//! Instrument module
+//!
+//! This module plays the CodeBeats synthesis from MIDI notes instead of the
+//! keyboard, without an audio device, so a host that supplies its own audio
+//! buffers and note events (such as the CLAP/VST3 plugin in `plugin/`) can
+//! use the same waveforms and configs as the standalone app.
+//!
+//! A MIDI note plays the config key mapped to that pitch, with the key's
+//! volume, waveform, envelope and samples; notes no key is mapped to play
+//! the current waveform.
+
+use crate::audio_engine::{AudioState, NoteOverrides};
+use crate::keyboard_config::{FilterEnvelope, KeyboardConfig};
+use crate::keyboard_mapping::{midi_to_frequency, note_to_midi};
+use crate::modulation::{Lfo, ModMatrix, Vibrato};
+use crate::waveforms::Waveform;
+use std::collections::HashMap;
+
+/// The config key a MIDI note plays
+#[derive(Debug, Clone)]
+struct NoteKey {
+    key_id: String,
+    volume: f32,
+    overrides: NoteOverrides,
+    sampled: bool,
+}
+
+/// A config's keys, samples and modulation, loaded ahead of time so that
+/// switching to it does no file I/O (see `Instrument::apply_config`)
+pub struct PreparedConfig {
+    keys: HashMap<u8, NoteKey>,
+    /// Engine the samples were loaded into, at the instrument's sample rate
+    samples: AudioState,
+    vibrato: Option<Vibrato>,
+    lfo: Option<Lfo>,
+    mod_matrix: ModMatrix,
+    filter_envelope: Option<FilterEnvelope>,
+    /// A message for each sample that could not be loaded
+    pub warnings: Vec<String>,
+}
+
+/// The synthesis engine driven by MIDI notes
+pub struct Instrument {
+    state: AudioState,
+    keys: HashMap<u8, NoteKey>,
+}
+
+impl Instrument {
+    pub fn new(sample_rate: f32, waveform: Waveform, volume: f32, filter_cutoff: f32) -> Self {
+        Self {
+            state: AudioState::new(sample_rate, waveform, volume, filter_cutoff),
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Play notes through a keyboard config's keys, loading its samples and modulation
+    ///
+    /// Returns a message for each sample that could not be loaded.
+    pub fn set_config(&mut self, config: &KeyboardConfig) -> Vec<String> {
+        let mut prepared = Self::prepare_config(config, self.state.sample_rate());
+        let warnings = std::mem::take(&mut prepared.warnings);
+        self.apply_config(prepared);
+        warnings
+    }
+
+    /// Load a config's keys and samples for an instrument running at
+    /// `sample_rate`, without touching it, e.g. away from the audio thread
+    ///
+    /// When several keys play the same pitch, base-layer keys win over layered
+    /// ones, then the first by name.
+    pub fn prepare_config(config: &KeyboardConfig, sample_rate: f32) -> PreparedConfig {
+        let mut keys = HashMap::new();
+        let mut mappings: Vec<_> = config.all_mappings().collect();
+        mappings.sort_by(|(a, _), (b, _)| (a.contains(':'), a).cmp(&(b.contains(':'), b)));
+        for (key_id, mapping) in mappings {
+            let Some(note) = note_to_midi(&mapping.note).and_then(|n| u8::try_from(n).ok()) else {
+                continue;
+            };
+            keys.entry(note).or_insert_with(|| NoteKey {
+                key_id,
+                volume: mapping.volume,
+                overrides: mapping.note_overrides(),
+                sampled: mapping.has_samples(),
+            });
+        }
+        let mut samples = AudioState::new(sample_rate, Waveform::Electronic, 1.0, 1200.0);
+        let warnings = samples.load_config_samples(config);
+
+        PreparedConfig {
+            keys,
+            samples,
+            vibrato: config.vibrato,
+            lfo: config.lfo,
+            mod_matrix: config.mod_matrix.clone(),
+            filter_envelope: config.filter_envelope.clone(),
+            warnings,
+        }
+    }
+
+    /// Switch to a prepared config; nothing is loaded, so this is safe on the
+    /// audio thread
+    pub fn apply_config(&mut self, prepared: PreparedConfig) {
+        let PreparedConfig {
+            keys,
+            mut samples,
+            vibrato,
+            lfo,
+            mod_matrix,
+            filter_envelope,
+            ..
+        } = prepared;
+        self.state.release_all();
+        self.keys = keys;
+        self.state.swap_samples(&mut samples);
+        self.state.set_modulation(vibrato, lfo, mod_matrix);
+        self.state.set_filter_envelope(filter_envelope);
+    }
+
+    /// Forget the config's keys and samples, so every note plays the current waveform
+    pub fn clear_config(&mut self) {
+        self.state.release_all();
+        self.keys.clear();
+        self.state.clear_key_samples();
+        self.state.clear_named_samples();
+        self.state.set_modulation(None, None, ModMatrix::default());
+        self.state.set_filter_envelope(None);
+    }
+
+    /// Start a MIDI note (velocity from 0.0 to 1.0)
+    pub fn note_on(&mut self, note: u8, velocity: f32) {
+        let frequency = midi_to_frequency(i32::from(note));
+        match self.keys.get(&note) {
+            Some(key) if key.sampled => {
+                self.state
+                    .start_sample_with_id(&key.key_id, key.volume * velocity);
+            }
+            Some(key) => {
+                self.state.start_note_with_overrides(
+                    &key.key_id,
+                    frequency,
+                    key.volume * velocity,
+                    &key.overrides,
+                );
+            }
+            None => {
+                self.state
+                    .start_note_with_id(&midi_key_id(note), frequency, velocity);
+            }
+        }
+    }
+
+    /// Release a MIDI note
+    pub fn note_off(&mut self, note: u8) {
+        match self.keys.get(&note) {
+            Some(key) if key.sampled => self.state.release_sample_with_id(&key.key_id),
+            Some(key) => self.state.stop_note_with_id(&key.key_id),
+            None => self.state.stop_note_with_id(&midi_key_id(note)),
+        }
+    }
+
+    /// Release every sounding note
+    pub fn all_notes_off(&mut self) {
+        self.state.release_all();
+    }
+
+    /// Render the next block of mono samples
+    pub fn process(&mut self, block: &mut [f32]) {
+        self.state.fill_block(block);
+    }
+
+    /// The underlying engine, for settings such as waveform, volume and filter
+    pub fn state_mut(&mut self) -> &mut AudioState {
+        &mut self.state
+    }
+}
+
+/// Key ID of a note no config key plays
+fn midi_key_id(note: u8) -> String {
+    format!("midi:{}", note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_silent(block: &[f32]) -> bool {
+        block.iter().all(|sample| sample.abs() < 1e-6)
+    }
+
+    #[test]
+    fn test_note_on_and_off() {
+        let mut instrument = Instrument::new(44100.0, Waveform::Electronic, 0.8, 1200.0);
+        let mut block = [0.0; 512];
+
+        instrument.process(&mut block);
+        assert!(is_silent(&block));
+
+        instrument.note_on(69, 1.0);
+        instrument.process(&mut block);
+        assert!(!is_silent(&block));
+        assert_eq!(instrument.state.active_voice_count(), 1);
+
+        instrument.note_off(69);
+        for _ in 0..200 {
+            instrument.process(&mut block);
+        }
+        assert_eq!(instrument.state.active_voice_count(), 0);
+    }
+
+    #[test]
+    fn test_config_keys() {
+        let mut config = KeyboardConfig::default();
+        config.mappings.clear();
+        let mapping = config.mappings.entry("A".to_string()).or_default();
+        mapping.note = "A4".to_string();
+        mapping.volume = 0.5;
+        mapping.waveform = Some("bass".to_string());
+
+        let mut instrument = Instrument::new(44100.0, Waveform::Electronic, 0.8, 1200.0);
+        assert!(instrument.set_config(&config).is_empty());
+
+        // A4 plays the A key with its own waveform; other notes use the current one
+        instrument.note_on(69, 1.0);
+        instrument.note_on(60, 1.0);
+        let key = instrument.state.note("A").unwrap();
+        assert_eq!(key.waveform, Waveform::Bass);
+        assert!(instrument.state.note("midi:60").is_some());
+
+        // A config prepared ahead of time plays the same once applied
+        let prepared = Instrument::prepare_config(&config, 44100.0);
+        let mut instrument = Instrument::new(44100.0, Waveform::Electronic, 0.8, 1200.0);
+        instrument.apply_config(prepared);
+        instrument.note_on(69, 1.0);
+        assert_eq!(instrument.state.note("A").unwrap().waveform, Waveform::Bass);
+    }
+}
@@ -0,0 +1,311 @@
+//! Local IPC module
+//!
+//! This module runs a small local endpoint (a Unix socket, or a localhost TCP
+//! port where Unix sockets are unavailable) that accepts newline-delimited JSON
+//! messages, such as editor plugins reporting the language being edited or
+//! `codebeats ctl` controlling a running daemon. Each message gets a one-line
+//! reply, starting with "error:" on failure.
+
+use crate::actions::Action;
+use crate::notifications::Notification;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// A message sent to the engine over IPC
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpcMessage {
+    /// The editor switched to a file; either field selects the config
+    EditorContext {
+        /// Editor language identifier (e.g. "rust", "python")
+        #[serde(default)]
+        language: Option<String>,
+        /// Path of the edited file, used for its extension
+        #[serde(default)]
+        file: Option<String>,
+    },
+    /// Set the master volume (0.0-1.0)
+    SetVolume { volume: f32 },
+    /// Switch to a waveform by name
+    SetWaveform { waveform: String },
+    /// Set the output low-pass cutoff in Hz
+    SetFilterCutoff { cutoff: f32 },
+    /// Transpose new notes by a number of semitones
+    SetTranspose { semitones: i32 },
+    /// Mute or unmute the output
+    SetMuted { muted: bool },
+    /// Pause or resume the engine
+    SetPaused { paused: bool },
+    /// Switch to an embedded config or JSON file
+    LoadConfig { config: String },
+    /// Run an action, as bound to hotkeys
+    Action { action: Action },
+    /// Play a notification sound by name (e.g. "build-success")
+    Notify { name: String },
+    /// Play a melody given in full, `delay` seconds from now (e.g. `codebeats watch` motifs)
+    PlayMelody {
+        melody: Notification,
+        #[serde(default)]
+        delay: f32,
+    },
+    /// Report the engine's current settings
+    Status,
+}
+
+/// A received message along with the channel for its reply
+pub struct IpcRequest {
+    pub message: IpcMessage,
+    reply: Sender<String>,
+}
+
+impl IpcRequest {
+    /// Create a request, returning the receiver for its reply
+    pub(crate) fn new(message: IpcMessage) -> (Self, Receiver<String>) {
+        let (reply, reply_receiver) = mpsc::channel();
+        (Self { message, reply }, reply_receiver)
+    }
+
+    /// Send the one-line reply back to the client
+    pub fn reply(&self, reply: impl Into<String>) {
+        let _ = self.reply.send(reply.into());
+    }
+}
+
+/// Default address of the HTTP control API (`http-api` feature)
+pub const DEFAULT_HTTP_ADDRESS: &str = "127.0.0.1:47801";
+
+/// Default IPC endpoint for this platform
+pub fn default_endpoint() -> String {
+    #[cfg(unix)]
+    {
+        let dir = std::env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| "/tmp".to_string());
+        let user = std::env::var("USER").unwrap_or_else(|_| "user".to_string());
+        format!("{}/codebeats-{}.sock", dir, user)
+    }
+    #[cfg(not(unix))]
+    {
+        "127.0.0.1:47800".to_string()
+    }
+}
+
+/// Listener accepting IPC connections on a background thread
+pub struct IpcServer {
+    endpoint: String,
+    requests: Receiver<IpcRequest>,
+}
+
+impl IpcServer {
+    /// Start listening on the endpoint, replacing a stale Unix socket but
+    /// failing if another instance is still listening on it
+    pub fn start(endpoint: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let (sender, requests) = mpsc::channel();
+
+        #[cfg(unix)]
+        {
+            // Only a socket nobody answers on is stale and safe to replace
+            if std::os::unix::net::UnixStream::connect(endpoint).is_ok() {
+                return Err(
+                    format!("another instance is already listening on {}", endpoint).into(),
+                );
+            }
+            let _ = std::fs::remove_file(endpoint);
+            let listener = std::os::unix::net::UnixListener::bind(endpoint)?;
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let reader = stream.try_clone();
+                    let sender = sender.clone();
+                    if let Ok(reader) = reader {
+                        thread::spawn(move || serve_connection(reader, stream, sender));
+                    }
+                }
+            });
+        }
+        #[cfg(not(unix))]
+        {
+            let listener = std::net::TcpListener::bind(endpoint)?;
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let reader = stream.try_clone();
+                    let sender = sender.clone();
+                    if let Ok(reader) = reader {
+                        thread::spawn(move || serve_connection(reader, stream, sender));
+                    }
+                }
+            });
+        }
+
+        Ok(Self {
+            endpoint: endpoint.to_string(),
+            requests,
+        })
+    }
+
+    /// Endpoint this server listens on
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Take the next pending request without blocking
+    pub fn try_next(&self) -> Option<IpcRequest> {
+        self.requests.try_recv().ok()
+    }
+}
+
+/// Send a message to a running instance and return its reply
+pub fn send(endpoint: &str, message: &IpcMessage) -> Result<String, Box<dyn std::error::Error>> {
+    #[cfg(unix)]
+    let stream = std::os::unix::net::UnixStream::connect(endpoint)?;
+    #[cfg(not(unix))]
+    let stream = std::net::TcpStream::connect(endpoint)?;
+
+    let mut writer = stream.try_clone()?;
+    writeln!(writer, "{}", serde_json::to_string(message)?)?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    if reply.is_empty() {
+        return Err("connection closed without a reply".into());
+    }
+    Ok(reply.trim_end().to_string())
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        let _ = std::fs::remove_file(&self.endpoint);
+    }
+}
+
+/// Read messages from one connection, forwarding them and writing back replies
+fn serve_connection<R: std::io::Read, W: Write>(
+    reader: R,
+    mut writer: W,
+    requests: Sender<IpcRequest>,
+) {
+    for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let reply = match serde_json::from_str::<IpcMessage>(&line) {
+            Ok(message) => {
+                let (request, reply_receiver) = IpcRequest::new(message);
+                if requests.send(request).is_err() {
+                    return;
+                }
+                reply_receiver
+                    .recv()
+                    .unwrap_or_else(|_| "error: engine stopped".to_string())
+            }
+            Err(e) => format!("error: {}", e),
+        };
+
+        if writeln!(writer, "{}", reply).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_deserialization() {
+        let message: IpcMessage =
+            serde_json::from_str(r#"{ "type": "editor_context", "file": "src/main.rs" }"#).unwrap();
+        assert_eq!(
+            message,
+            IpcMessage::EditorContext {
+                language: None,
+                file: Some("src/main.rs".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_control_messages() {
+        let message: IpcMessage =
+            serde_json::from_str(r#"{ "type": "set_volume", "volume": 0.5 }"#).unwrap();
+        assert_eq!(message, IpcMessage::SetVolume { volume: 0.5 });
+
+        let message: IpcMessage = serde_json::from_str(
+            r#"{ "type": "action", "action": { "type": "adjust_volume", "delta": 0.1 } }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            message,
+            IpcMessage::Action {
+                action: Action::AdjustVolume { .. }
+            }
+        ));
+
+        let status = serde_json::to_string(&IpcMessage::Status).unwrap();
+        assert_eq!(status, r#"{"type":"status"}"#);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send_round_trip() {
+        let endpoint = std::env::temp_dir()
+            .join(format!("codebeats-test-{}.sock", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let server = IpcServer::start(&endpoint).unwrap();
+
+        let client = {
+            let endpoint = endpoint.clone();
+            thread::spawn(move || send(&endpoint, &IpcMessage::Status).unwrap())
+        };
+        let request = loop {
+            if let Some(request) = server.try_next() {
+                break request;
+            }
+            thread::sleep(std::time::Duration::from_millis(5));
+        };
+        assert_eq!(request.message, IpcMessage::Status);
+        request.reply("ok: running");
+
+        assert_eq!(client.join().unwrap(), "ok: running");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_start_keeps_live_socket() {
+        let endpoint = std::env::temp_dir()
+            .join(format!("codebeats-test-live-{}.sock", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let server = IpcServer::start(&endpoint).unwrap();
+        assert!(IpcServer::start(&endpoint).is_err());
+        drop(server);
+
+        // A leftover socket file with nobody listening is replaced
+        drop(std::os::unix::net::UnixListener::bind(&endpoint).unwrap());
+        assert!(IpcServer::start(&endpoint).is_ok());
+    }
+
+    #[test]
+    fn test_serve_connection_replies() {
+        let (sender, receiver) = mpsc::channel::<IpcRequest>();
+        let engine = thread::spawn(move || {
+            let request = receiver.recv().unwrap();
+            request.reply("ok");
+        });
+
+        let input = b"{\"type\":\"editor_context\",\"language\":\"rust\"}\nnot json\n";
+        let mut output = Vec::new();
+        serve_connection(&input[..], &mut output, sender);
+        engine.join().unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "ok");
+        assert!(lines[1].starts_with("error:"));
+    }
+}
@@ -0,0 +1,161 @@
+//! Networked jam mode module (experimental)
+//!
+//! This module lets several CodeBeats instances on a LAN play together: each
+//! instance multicasts its note events over UDP and renders the notes it
+//! receives from the others alongside its own. Packets are small JSON objects
+//! tagged with a random per-instance peer ID so an instance ignores its own
+//! events when multicast loopback delivers them back. Packets are not
+//! authenticated, so received notes are checked before they are played.
+
+use crate::events::EngineEvent;
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default multicast group and port for jam sessions
+pub const DEFAULT_JAM_GROUP: &str = "239.255.77.77:47803";
+
+/// Multicast TTL; 1 keeps packets on the local network
+const MULTICAST_TTL: u32 = 1;
+
+/// Audible frequency range remote notes are clamped to (Hz)
+const MIN_FREQUENCY: f32 = 20.0;
+const MAX_FREQUENCY: f32 = 20_000.0;
+
+/// A note event sent between jam peers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JamPacket {
+    /// Random ID of the sending instance
+    pub peer: u64,
+    pub event: EngineEvent,
+}
+
+/// Membership in a jam session's multicast group
+pub struct JamSession {
+    peer: u64,
+    group: SocketAddrV4,
+    socket: UdpSocket,
+    packets: Receiver<JamPacket>,
+}
+
+impl JamSession {
+    /// Join the multicast group (e.g. "239.255.77.77:47803")
+    pub fn join(group: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let group: SocketAddrV4 = group.parse()?;
+        if !group.ip().is_multicast() {
+            return Err(format!("{} is not a multicast address", group.ip()).into());
+        }
+
+        let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, group.port()))?;
+        socket.join_multicast_v4(group.ip(), &Ipv4Addr::UNSPECIFIED)?;
+        socket.set_multicast_loop_v4(true)?;
+        socket.set_multicast_ttl_v4(MULTICAST_TTL)?;
+
+        let peer = new_peer_id();
+        let (sender, packets) = mpsc::channel();
+        let receiver = socket.try_clone()?;
+        thread::spawn(move || {
+            let mut buffer = [0u8; 2048];
+            while let Ok(len) = receiver.recv(&mut buffer) {
+                let Ok(packet) = serde_json::from_slice::<JamPacket>(&buffer[..len]) else {
+                    continue;
+                };
+                if packet.peer != peer && sender.send(packet).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Self {
+            peer,
+            group,
+            socket,
+            packets,
+        })
+    }
+
+    /// This instance's peer ID
+    pub fn peer(&self) -> u64 {
+        self.peer
+    }
+
+    /// Share a local note event with the other players
+    pub fn send(&self, event: &EngineEvent) {
+        let packet = JamPacket {
+            peer: self.peer,
+            event: event.clone(),
+        };
+        if let Ok(json) = serde_json::to_vec(&packet) {
+            let _ = self.socket.send_to(&json, self.group);
+        }
+    }
+
+    /// Take the next event received from another player without blocking
+    pub fn try_next(&self) -> Option<JamPacket> {
+        self.packets.try_recv().ok()
+    }
+}
+
+/// Key ID under which a remote player's note sounds locally
+pub fn remote_key_id(peer: u64, key: &str) -> String {
+    format!("jam-{:x}-{}", peer, key)
+}
+
+/// Frequency and volume of a remote note, clamped to the audible range and
+/// 0.0-1.0, or None if either is not a finite number
+pub fn playable_note(frequency: f32, volume: f32) -> Option<(f32, f32)> {
+    if !frequency.is_finite() || !volume.is_finite() {
+        return None;
+    }
+    Some((
+        frequency.clamp(MIN_FREQUENCY, MAX_FREQUENCY),
+        volume.clamp(0.0, 1.0),
+    ))
+}
+
+/// Pick a peer ID from the clock and process ID
+fn new_peer_id() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_nanos() as u64)
+        .unwrap_or_default();
+    nanos ^ ((std::process::id() as u64) << 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packet_round_trip() {
+        let packet = JamPacket {
+            peer: 42,
+            event: EngineEvent::NoteOn {
+                key: "A".to_string(),
+                note: Some("A4".to_string()),
+                frequency: Some(440.0),
+                volume: 0.5,
+            },
+        };
+        let json = serde_json::to_string(&packet).unwrap();
+        assert_eq!(serde_json::from_str::<JamPacket>(&json).unwrap(), packet);
+        assert_eq!(remote_key_id(42, "A"), "jam-2a-A");
+    }
+
+    #[test]
+    fn test_playable_note() {
+        assert_eq!(playable_note(440.0, 0.5), Some((440.0, 0.5)));
+        assert_eq!(playable_note(1e9, 1e6), Some((MAX_FREQUENCY, 1.0)));
+        assert_eq!(playable_note(-5.0, -1.0), Some((MIN_FREQUENCY, 0.0)));
+        assert_eq!(playable_note(f32::NAN, 0.5), None);
+        assert_eq!(playable_note(440.0, f32::INFINITY), None);
+    }
+
+    #[test]
+    fn test_rejects_unicast_group() {
+        assert!(JamSession::join("192.168.1.10:47803").is_err());
+        assert!(JamSession::join("not an address").is_err());
+    }
+}
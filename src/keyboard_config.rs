@@ -3,22 +3,227 @@
 //! This module handles loading and managing customizable keyboard mappings
 //! from configuration files, allowing users to define their own key-to-sound mappings.
 
+use crate::actions::Action;
+use crate::audio_engine::{ADSRParams, NoteOverrides};
+use crate::keycode::Keycode;
+use crate::modulation::{Lfo, ModMatrix, Vibrato};
+use crate::scales::ScaleConfig;
 use crate::waveforms::Waveform;
-use device_query::Keycode;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
 /// A single key mapping configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct KeyMapping {
     /// Musical note (e.g., "C4", "F#5", "Bb3")
+    #[serde(default)]
     pub note: String,
     /// Volume level (0.0 to 1.0)
     pub volume: f32,
     /// Optional description for the key
     pub description: Option<String>,
+    /// Optional waveform for this key instead of the config's (e.g. "bass" for Space)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub waveform: Option<String>,
+    /// Optional envelope for this key; stages left out keep the waveform's values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adsr: Option<AdsrConfig>,
+    /// Optional stereo position from -1.0 (left) to 1.0 (right)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pan: Option<f32>,
+    /// Optional resonant filter sweep for this key, instead of the config's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter_envelope: Option<FilterEnvelope>,
+    /// Optional sound file (WAV/OGG/FLAC/MP3) played as a one-shot instead of the note
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<String>,
+    /// Optional velocity layers, each with its own sample (takes precedence over `sample`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<SampleLayer>,
+    /// Loop the sample while the key is held (the whole sample unless loop points are set)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub looped: bool,
+    /// Loop start in frames, overriding the file's loop points
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loop_start: Option<usize>,
+    /// Loop end in frames (exclusive), overriding the file's loop points
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loop_end: Option<usize>,
+    /// Scale (e.g. "C major pentatonic") whose notes the keys of a range such
+    /// as "A-Z" take in order, instead of `note`; used only while loading
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<String>,
+}
+
+impl KeyMapping {
+    /// Check if this key plays samples instead of a synthesized note
+    pub fn has_samples(&self) -> bool {
+        self.sample.is_some() || !self.layers.is_empty()
+    }
+
+    /// Settings this key uses instead of the engine-wide ones (unknown waveforms are ignored)
+    pub fn note_overrides(&self) -> NoteOverrides {
+        NoteOverrides {
+            waveform: self.waveform.as_ref().and_then(|w| w.parse().ok()),
+            adsr: self.adsr.clone(),
+            pan: self.pan,
+            filter_envelope: self.filter_envelope.clone(),
+            ..NoteOverrides::default()
+        }
+    }
+
+    /// Check if this key's samples should sustain in a loop
+    pub fn is_looped(&self) -> bool {
+        self.looped || self.loop_start.is_some() || self.loop_end.is_some()
+    }
+}
+
+/// Envelope stages set by a key mapping, in seconds (sustain is a level from 0.0 to 1.0)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AdsrConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attack: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decay: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sustain: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release: Option<f32>,
+}
+
+impl AdsrConfig {
+    /// Replace the given stages of an envelope, keeping the others
+    pub fn apply(&self, base: ADSRParams) -> ADSRParams {
+        ADSRParams {
+            attack_time: self.attack.map_or(base.attack_time, |t| t.max(0.0)),
+            decay_time: self.decay.map_or(base.decay_time, |t| t.max(0.0)),
+            sustain_level: self
+                .sustain
+                .map_or(base.sustain_level, |l| l.clamp(0.0, 1.0)),
+            release_time: self.release.map_or(base.release_time, |t| t.max(0.0)),
+        }
+    }
+}
+
+/// Cutoff sweep of a note's own resonant low-pass: from `start` up to `peak`
+/// over `attack` seconds, then down to `sustain` over `decay` seconds, where
+/// it stays (cutoffs in Hz)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterEnvelope {
+    pub start: f32,
+    pub peak: f32,
+    pub sustain: f32,
+    pub attack: f32,
+    pub decay: f32,
+    /// Emphasis around the cutoff, from 0.0 (none) to 1.0 (ringing)
+    pub resonance: f32,
+}
+
+impl Default for FilterEnvelope {
+    fn default() -> Self {
+        Self {
+            start: 200.0,
+            peak: 6000.0,
+            sustain: 1200.0,
+            attack: 0.05,
+            decay: 0.3,
+            resonance: 0.5,
+        }
+    }
+}
+
+impl FilterEnvelope {
+    /// Cutoff `time` seconds into a note, moving evenly in pitch
+    pub fn cutoff(&self, time: f32) -> f32 {
+        let sweep = |from: f32, to: f32, progress: f32| {
+            let (from, to) = (from.max(1.0), to.max(1.0));
+            from * (to / from).powf(progress)
+        };
+        if time < self.attack {
+            sweep(self.start, self.peak, time / self.attack)
+        } else if time < self.attack + self.decay {
+            sweep(self.peak, self.sustain, (time - self.attack) / self.decay)
+        } else {
+            self.sustain
+        }
+    }
+}
+
+/// A velocity layer of a sample-based key mapping
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampleLayer {
+    /// Sound file played for this layer
+    pub sample: String,
+    /// Lowest velocity (0.0 to 1.0) that selects this layer
+    #[serde(default)]
+    pub min_velocity: f32,
+    /// Highest velocity (0.0 to 1.0) that selects this layer
+    #[serde(default = "default_max_velocity")]
+    pub max_velocity: f32,
+}
+
+fn default_max_velocity() -> f32 {
+    1.0
+}
+
+/// An input sequence that triggers a sound when typed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceConfig {
+    /// Name of the sequence, shown when it triggers
+    pub name: String,
+    /// Letters to type, in order, or a list of key names (e.g. ["Up", "Up", "Down"])
+    pub keys: SequenceKeys,
+    /// Optional sound file to play (defaults to the fart sample)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<String>,
+    /// Playback volume (0.0 to 1.0)
+    #[serde(default = "default_sequence_volume")]
+    pub volume: f32,
+    /// Optional action to run (the fart sample only plays if neither this nor `sample` is set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<Action>,
+}
+
+/// Keys held together that run an action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChordConfig {
+    /// Name of the chord, shown when it triggers
+    pub name: String,
+    /// Keys joined with "+", e.g. "Ctrl+Alt+M" ("Ctrl", "Alt", "Shift", "Meta" match either side)
+    pub keys: String,
+    /// Action to run when the chord is pressed
+    pub action: Action,
+}
+
+/// An alternate mappings table switched on by a layer key (as in QMK firmware)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MappingLayer {
+    /// Key that switches to the layer, e.g. "CapsLock" (it plays no note itself)
+    pub key: String,
+    /// Press the layer key once to switch the layer on and again to switch it
+    /// off, instead of holding it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub toggle: bool,
+    /// Mappings used while the layer is on; keys not listed play their usual mapping
+    #[serde(default, deserialize_with = "deserialize_mappings")]
+    pub mappings: HashMap<String, KeyMapping>,
+}
+
+/// Keys making up a sequence
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SequenceKeys {
+    /// A word of letters; spaces and other non-letter keys typed in between are ignored
+    Letters(String),
+    /// Exact key names (as in `mappings`), including arrows, digits and symbols
+    Keys(Vec<String>),
+}
+
+fn default_sequence_volume() -> f32 {
+    0.7
 }
 
 /// Complete keyboard configuration
@@ -31,8 +236,136 @@ pub struct KeyboardConfig {
     /// Default waveform for this configuration
     #[serde(default)]
     pub waveform: Option<String>,
-    /// Key mappings - map from key name to sound configuration
+    /// Key mappings - map from key name (or key range, e.g. "A-Z") to sound configuration
+    #[serde(deserialize_with = "deserialize_mappings")]
     pub mappings: HashMap<String, KeyMapping>,
+    /// Extra Easter egg sequences, detected alongside the built-in one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sequences: Vec<SequenceConfig>,
+    /// Key chords mapped to actions, detected independently of sequences
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chords: Vec<ChordConfig>,
+    /// Named sound files that actions can play (shares names with sequence samples)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub samples: HashMap<String, String>,
+    /// Semitones every mapped note is shifted by
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub transpose_semitones: i32,
+    /// Octaves every mapped note is shifted by (on top of `transpose_semitones`)
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub octave_shift: i32,
+    /// Scale that unmapped keys take their notes from when `auto_assign` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<ScaleConfig>,
+    /// Give keys missing from `mappings` notes from `scale` instead of leaving them silent
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub auto_assign: bool,
+    /// Alternate mappings tables by layer name, switched on by their layer key
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub layers: HashMap<String, MappingLayer>,
+    /// Pitch wobble of every synthesized note, replacing the waveform's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vibrato: Option<Vibrato>,
+    /// Low-frequency oscillator moving the pitch, volume or filter of the whole output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lfo: Option<Lfo>,
+    /// Modulation sources routed to the pitch, amplitude, filter and pan of every note
+    #[serde(default, skip_serializing_if = "ModMatrix::is_empty")]
+    pub mod_matrix: ModMatrix,
+    /// Resonant filter sweep given to every synthesized note (keys can set their own)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter_envelope: Option<FilterEnvelope>,
+}
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+/// Deserialize a mappings table, expanding key ranges and rejecting unknown waveforms
+fn deserialize_mappings<'de, D>(deserializer: D) -> Result<HashMap<String, KeyMapping>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mappings = HashMap::<String, KeyMapping>::deserialize(deserializer)?;
+    for (key, mapping) in &mappings {
+        if let Some(ref waveform) = mapping.waveform
+            && waveform.parse::<Waveform>().is_err()
+        {
+            return Err(serde::de::Error::custom(format!(
+                "Key '{}' has unknown waveform '{}'",
+                key, waveform
+            )));
+        }
+    }
+    expand_key_ranges(mappings).map_err(serde::de::Error::custom)
+}
+
+/// Expand range entries such as "A-Z", "Key0-Key9" (or "0-9"), "F1-F12" and
+/// "Numpad0-Numpad9" into one mapping per key
+///
+/// A range with a `scale` gives its keys the scale's notes in order; otherwise
+/// every key shares the range's mapping. Keys listed on their own keep their
+/// own mapping.
+pub fn expand_key_ranges(
+    mappings: HashMap<String, KeyMapping>,
+) -> Result<HashMap<String, KeyMapping>, String> {
+    let (mut ranges, single): (Vec<_>, Vec<_>) = mappings
+        .into_iter()
+        .partition(|(name, mapping)| name.contains('-') || mapping.scale.is_some());
+    let mut expanded: HashMap<_, _> = single.into_iter().collect();
+    // Sorted so overlapping ranges resolve the same way every time
+    ranges.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (name, mapping) in ranges {
+        let keys = if name.contains('-') {
+            key_range(&name).ok_or_else(|| format!("Invalid key range '{}'", name))?
+        } else {
+            vec![name.clone()]
+        };
+        let scale = match mapping.scale {
+            Some(ref scale) => Some(
+                scale
+                    .parse::<ScaleConfig>()
+                    .map_err(|e| format!("Key range '{}': {}", name, e))?,
+            ),
+            None => None,
+        };
+
+        for (degree, key) in keys.into_iter().enumerate() {
+            let mut key_mapping = mapping.clone();
+            key_mapping.scale = None;
+            if let Some(ref scale) = scale {
+                key_mapping.note = scale.note_for_degree(degree).unwrap_or_default();
+            }
+            expanded.entry(key).or_insert(key_mapping);
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Key names covered by a range such as "A-Z" or "F1-F12", in order
+fn key_range(range: &str) -> Option<Vec<String>> {
+    let sequences: [Vec<String>; 4] = [
+        ('A'..='Z').map(String::from).collect(),
+        (0..=9).map(|digit| format!("Key{}", digit)).collect(),
+        (1..=20).map(|number| format!("F{}", number)).collect(),
+        (0..=9).map(|digit| format!("Numpad{}", digit)).collect(),
+    ];
+    // Single letters may be written in either case, single digits without "Key"
+    let normalize = |key: &str| match key.trim() {
+        key if key.len() == 1 && key.chars().all(|c| c.is_ascii_digit()) => format!("Key{}", key),
+        key if key.len() == 1 => key.to_uppercase(),
+        key => key.to_string(),
+    };
+
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (normalize(start), normalize(end));
+    sequences.into_iter().find_map(|keys| {
+        let from = keys.iter().position(|key| *key == start)?;
+        let to = keys.iter().position(|key| *key == end)?;
+        (from <= to).then(|| keys[from..=to].to_vec())
+    })
 }
 
 impl Default for KeyboardConfig {
@@ -53,6 +386,7 @@ impl KeyboardConfig {
                 note: "E4".to_string(),
                 volume: 0.3,
                 description: Some("Very common letter".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -61,6 +395,7 @@ impl KeyboardConfig {
                 note: "G4".to_string(),
                 volume: 0.3,
                 description: Some("Very common letter".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -69,6 +404,7 @@ impl KeyboardConfig {
                 note: "C4".to_string(),
                 volume: 0.3,
                 description: Some("Most common letter".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -77,6 +413,7 @@ impl KeyboardConfig {
                 note: "D4".to_string(),
                 volume: 0.3,
                 description: Some("Very common letter".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -85,6 +422,7 @@ impl KeyboardConfig {
                 note: "A4".to_string(),
                 volume: 0.3,
                 description: Some("Very common letter".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -93,6 +431,7 @@ impl KeyboardConfig {
                 note: "E5".to_string(),
                 volume: 0.3,
                 description: Some("Very common letter".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -101,6 +440,7 @@ impl KeyboardConfig {
                 note: "G5".to_string(),
                 volume: 0.3,
                 description: Some("Very common letter".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -109,6 +449,7 @@ impl KeyboardConfig {
                 note: "C5".to_string(),
                 volume: 0.3,
                 description: Some("Very common letter".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -117,6 +458,7 @@ impl KeyboardConfig {
                 note: "D5".to_string(),
                 volume: 0.3,
                 description: Some("Very common letter".to_string()),
+                ..Default::default()
             },
         );
 
@@ -127,6 +469,7 @@ impl KeyboardConfig {
                 note: "F4".to_string(),
                 volume: 0.25,
                 description: Some("Common letter".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -135,6 +478,7 @@ impl KeyboardConfig {
                 note: "A3".to_string(),
                 volume: 0.25,
                 description: Some("Common letter".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -143,6 +487,7 @@ impl KeyboardConfig {
                 note: "F5".to_string(),
                 volume: 0.25,
                 description: Some("Common letter".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -151,6 +496,7 @@ impl KeyboardConfig {
                 note: "B4".to_string(),
                 volume: 0.25,
                 description: Some("Common letter".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -159,6 +505,7 @@ impl KeyboardConfig {
                 note: "B3".to_string(),
                 volume: 0.25,
                 description: Some("Common letter".to_string()),
+                ..Default::default()
             },
         );
 
@@ -169,6 +516,7 @@ impl KeyboardConfig {
                 note: "C3".to_string(),
                 volume: 0.2,
                 description: None,
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -177,6 +525,7 @@ impl KeyboardConfig {
                 note: "D3".to_string(),
                 volume: 0.2,
                 description: None,
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -185,6 +534,7 @@ impl KeyboardConfig {
                 note: "E3".to_string(),
                 volume: 0.2,
                 description: None,
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -193,6 +543,7 @@ impl KeyboardConfig {
                 note: "G3".to_string(),
                 volume: 0.2,
                 description: None,
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -201,6 +552,7 @@ impl KeyboardConfig {
                 note: "A5".to_string(),
                 volume: 0.2,
                 description: None,
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -209,6 +561,7 @@ impl KeyboardConfig {
                 note: "F3".to_string(),
                 volume: 0.2,
                 description: None,
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -217,6 +570,7 @@ impl KeyboardConfig {
                 note: "B5".to_string(),
                 volume: 0.2,
                 description: None,
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -225,6 +579,7 @@ impl KeyboardConfig {
                 note: "C6".to_string(),
                 volume: 0.2,
                 description: None,
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -233,6 +588,7 @@ impl KeyboardConfig {
                 note: "D6".to_string(),
                 volume: 0.2,
                 description: None,
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -241,6 +597,7 @@ impl KeyboardConfig {
                 note: "E6".to_string(),
                 volume: 0.2,
                 description: None,
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -249,6 +606,7 @@ impl KeyboardConfig {
                 note: "F6".to_string(),
                 volume: 0.2,
                 description: None,
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -257,6 +615,7 @@ impl KeyboardConfig {
                 note: "G6".to_string(),
                 volume: 0.2,
                 description: None,
+                ..Default::default()
             },
         );
 
@@ -267,6 +626,7 @@ impl KeyboardConfig {
                 note: "C4".to_string(),
                 volume: 0.25,
                 description: Some("Number key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -275,6 +635,7 @@ impl KeyboardConfig {
                 note: "E4".to_string(),
                 volume: 0.25,
                 description: Some("Number key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -283,6 +644,7 @@ impl KeyboardConfig {
                 note: "G4".to_string(),
                 volume: 0.25,
                 description: Some("Number key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -291,6 +653,7 @@ impl KeyboardConfig {
                 note: "A4".to_string(),
                 volume: 0.25,
                 description: Some("Number key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -299,6 +662,7 @@ impl KeyboardConfig {
                 note: "D4".to_string(),
                 volume: 0.25,
                 description: Some("Number key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -307,6 +671,7 @@ impl KeyboardConfig {
                 note: "F4".to_string(),
                 volume: 0.25,
                 description: Some("Number key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -315,6 +680,7 @@ impl KeyboardConfig {
                 note: "C5".to_string(),
                 volume: 0.25,
                 description: Some("Number key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -323,6 +689,7 @@ impl KeyboardConfig {
                 note: "E5".to_string(),
                 volume: 0.25,
                 description: Some("Number key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -331,6 +698,7 @@ impl KeyboardConfig {
                 note: "G5".to_string(),
                 volume: 0.25,
                 description: Some("Number key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -339,6 +707,7 @@ impl KeyboardConfig {
                 note: "A5".to_string(),
                 volume: 0.25,
                 description: Some("Number key".to_string()),
+                ..Default::default()
             },
         );
 
@@ -349,6 +718,7 @@ impl KeyboardConfig {
                 note: "C4".to_string(),
                 volume: 0.2,
                 description: Some("Programming symbol".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -357,6 +727,7 @@ impl KeyboardConfig {
                 note: "E4".to_string(),
                 volume: 0.2,
                 description: Some("Programming symbol".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -365,6 +736,7 @@ impl KeyboardConfig {
                 note: "G4".to_string(),
                 volume: 0.2,
                 description: Some("Programming symbol".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -373,6 +745,7 @@ impl KeyboardConfig {
                 note: "A4".to_string(),
                 volume: 0.2,
                 description: Some("Programming symbol".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -381,6 +754,7 @@ impl KeyboardConfig {
                 note: "D4".to_string(),
                 volume: 0.2,
                 description: Some("Programming symbol".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -389,6 +763,7 @@ impl KeyboardConfig {
                 note: "F4".to_string(),
                 volume: 0.2,
                 description: Some("Programming symbol".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -397,6 +772,7 @@ impl KeyboardConfig {
                 note: "B4".to_string(),
                 volume: 0.2,
                 description: Some("Programming symbol".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -405,6 +781,7 @@ impl KeyboardConfig {
                 note: "C5".to_string(),
                 volume: 0.2,
                 description: Some("Programming symbol".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -413,6 +790,7 @@ impl KeyboardConfig {
                 note: "D5".to_string(),
                 volume: 0.2,
                 description: Some("Programming symbol".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -421,6 +799,7 @@ impl KeyboardConfig {
                 note: "E5".to_string(),
                 volume: 0.2,
                 description: Some("Programming symbol".to_string()),
+                ..Default::default()
             },
         );
 
@@ -431,6 +810,7 @@ impl KeyboardConfig {
                 note: "C3".to_string(),
                 volume: 0.1,
                 description: Some("Common key - quiet".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -439,6 +819,7 @@ impl KeyboardConfig {
                 note: "G2".to_string(),
                 volume: 0.1,
                 description: Some("Common key - quiet".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -447,6 +828,7 @@ impl KeyboardConfig {
                 note: "C3".to_string(),
                 volume: 0.1,
                 description: Some("Common key - quiet".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -455,6 +837,7 @@ impl KeyboardConfig {
                 note: "F2".to_string(),
                 volume: 0.1,
                 description: Some("Common key - quiet".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -463,6 +846,7 @@ impl KeyboardConfig {
                 note: "A2".to_string(),
                 volume: 0.1,
                 description: Some("Common key - quiet".to_string()),
+                ..Default::default()
             },
         );
 
@@ -473,6 +857,7 @@ impl KeyboardConfig {
                 note: "C2".to_string(),
                 volume: 0.05,
                 description: Some("Modifier - very quiet".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -481,6 +866,7 @@ impl KeyboardConfig {
                 note: "E2".to_string(),
                 volume: 0.05,
                 description: Some("Modifier - very quiet".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -489,6 +875,7 @@ impl KeyboardConfig {
                 note: "G2".to_string(),
                 volume: 0.05,
                 description: Some("Modifier - very quiet".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -497,6 +884,7 @@ impl KeyboardConfig {
                 note: "A2".to_string(),
                 volume: 0.05,
                 description: Some("Modifier - very quiet".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -505,6 +893,7 @@ impl KeyboardConfig {
                 note: "D2".to_string(),
                 volume: 0.05,
                 description: Some("Modifier - very quiet".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -513,6 +902,7 @@ impl KeyboardConfig {
                 note: "F2".to_string(),
                 volume: 0.05,
                 description: Some("Modifier - very quiet".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -521,6 +911,7 @@ impl KeyboardConfig {
                 note: "B1".to_string(),
                 volume: 0.05,
                 description: Some("Modifier - very quiet".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -529,6 +920,7 @@ impl KeyboardConfig {
                 note: "C2".to_string(),
                 volume: 0.05,
                 description: Some("Modifier - very quiet".to_string()),
+                ..Default::default()
             },
         );
 
@@ -539,6 +931,7 @@ impl KeyboardConfig {
                 note: "E3".to_string(),
                 volume: 0.15,
                 description: Some("Navigation key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -547,6 +940,7 @@ impl KeyboardConfig {
                 note: "D3".to_string(),
                 volume: 0.15,
                 description: Some("Navigation key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -555,6 +949,7 @@ impl KeyboardConfig {
                 note: "C3".to_string(),
                 volume: 0.15,
                 description: Some("Navigation key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -563,6 +958,7 @@ impl KeyboardConfig {
                 note: "G3".to_string(),
                 volume: 0.15,
                 description: Some("Navigation key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -571,6 +967,7 @@ impl KeyboardConfig {
                 note: "C3".to_string(),
                 volume: 0.15,
                 description: Some("Navigation key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -579,6 +976,7 @@ impl KeyboardConfig {
                 note: "G3".to_string(),
                 volume: 0.15,
                 description: Some("Navigation key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -587,6 +985,7 @@ impl KeyboardConfig {
                 note: "E3".to_string(),
                 volume: 0.15,
                 description: Some("Navigation key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -595,6 +994,7 @@ impl KeyboardConfig {
                 note: "A3".to_string(),
                 volume: 0.15,
                 description: Some("Navigation key".to_string()),
+                ..Default::default()
             },
         );
 
@@ -605,6 +1005,7 @@ impl KeyboardConfig {
                 note: "C6".to_string(),
                 volume: 0.2,
                 description: Some("Function key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -613,6 +1014,7 @@ impl KeyboardConfig {
                 note: "D6".to_string(),
                 volume: 0.2,
                 description: Some("Function key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -621,6 +1023,7 @@ impl KeyboardConfig {
                 note: "E6".to_string(),
                 volume: 0.2,
                 description: Some("Function key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -629,6 +1032,7 @@ impl KeyboardConfig {
                 note: "F6".to_string(),
                 volume: 0.2,
                 description: Some("Function key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -637,6 +1041,7 @@ impl KeyboardConfig {
                 note: "G6".to_string(),
                 volume: 0.2,
                 description: Some("Function key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -645,6 +1050,7 @@ impl KeyboardConfig {
                 note: "A6".to_string(),
                 volume: 0.2,
                 description: Some("Function key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -653,6 +1059,7 @@ impl KeyboardConfig {
                 note: "B6".to_string(),
                 volume: 0.2,
                 description: Some("Function key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -661,6 +1068,7 @@ impl KeyboardConfig {
                 note: "C7".to_string(),
                 volume: 0.2,
                 description: Some("Function key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -669,6 +1077,7 @@ impl KeyboardConfig {
                 note: "D7".to_string(),
                 volume: 0.2,
                 description: Some("Function key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -677,6 +1086,7 @@ impl KeyboardConfig {
                 note: "E7".to_string(),
                 volume: 0.2,
                 description: Some("Function key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -685,6 +1095,7 @@ impl KeyboardConfig {
                 note: "F7".to_string(),
                 volume: 0.2,
                 description: Some("Function key".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -693,6 +1104,7 @@ impl KeyboardConfig {
                 note: "G7".to_string(),
                 volume: 0.2,
                 description: Some("Function key".to_string()),
+                ..Default::default()
             },
         );
 
@@ -702,21 +1114,140 @@ impl KeyboardConfig {
                 .to_string(),
             waveform: None, // Use system default
             mappings,
+            sequences: Vec::new(),
+            chords: Vec::new(),
+            samples: HashMap::new(),
+            transpose_semitones: 0,
+            octave_shift: 0,
+            scale: None,
+            auto_assign: false,
+            layers: HashMap::new(),
+            vibrato: None,
+            lfo: None,
+            mod_matrix: ModMatrix::default(),
+            filter_envelope: None,
         }
     }
 
+    /// Note and volume for a key missing from `mappings`, taken from the scale if `auto_assign` is set
+    pub fn auto_assigned_note(&self, key_name: &str) -> Option<(String, f32)> {
+        if !self.auto_assign || self.mappings.contains_key(key_name) {
+            return None;
+        }
+        let scale = self.scale.as_ref()?;
+        let note = scale.note_for_key(key_name, |key| self.mappings.contains_key(key))?;
+        Some((note, scale.volume))
+    }
+
+    /// Every mapping with the key ID it plays under: the key name for `mappings`,
+    /// and "layer:key" for the mappings of a layer
+    pub fn all_mappings(&self) -> impl Iterator<Item = (String, &KeyMapping)> {
+        let layered = self.layers.iter().flat_map(|(layer, mapping_layer)| {
+            mapping_layer
+                .mappings
+                .iter()
+                .map(move |(key, mapping)| (format!("{}:{}", layer, key), mapping))
+        });
+        self.mappings
+            .iter()
+            .map(|(key, mapping)| (key.clone(), mapping))
+            .chain(layered)
+    }
+
+    /// Total shift in semitones applied to every mapped note
+    pub fn note_shift(&self) -> i32 {
+        self.transpose_semitones + 12 * self.octave_shift
+    }
+
     /// Get the waveform for this configuration, falling back to default if none specified
     pub fn get_waveform(&self) -> Option<Waveform> {
         self.waveform.as_ref().and_then(|w| w.parse().ok())
     }
 
-    /// Load configuration from a JSON file
+    /// Parse a configuration from JSON, allowing comments and trailing commas (JSON5)
+    pub fn from_json(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(json5::from_str(content)?)
+    }
+
+    /// Parse a configuration in the given format
+    pub fn parse(content: &str, format: ConfigFormat) -> Result<Self, Box<dyn std::error::Error>> {
+        match format {
+            ConfigFormat::Json => Self::from_json(content),
+            ConfigFormat::Toml => Ok(toml::from_str(content)?),
+        }
+    }
+
+    /// Load configuration from a file, in the format given by its extension (JSON by default)
+    /// Relative sample paths are taken from the file's directory
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
-        let content = fs::read_to_string(path)?;
-        let config: KeyboardConfig = serde_json::from_str(&content)?;
+        let content = fs::read_to_string(&path)?;
+        let mut config = Self::parse(&content, ConfigFormat::from_path(path.as_ref()))?;
+        if let Some(dir) = path.as_ref().parent() {
+            config.resolve_sample_paths(dir);
+        }
         Ok(config)
     }
 
+    /// Make relative sample paths relative to `dir` (the config file's
+    /// directory) rather than the working directory
+    pub fn resolve_sample_paths(&mut self, dir: &Path) {
+        let resolve = |path: &mut String| {
+            if Path::new(path.as_str()).is_relative() {
+                *path = dir.join(path.as_str()).to_string_lossy().into_owned();
+            }
+        };
+        self.samples.values_mut().for_each(resolve);
+        self.sequences
+            .iter_mut()
+            .filter_map(|sequence| sequence.sample.as_mut())
+            .for_each(resolve);
+        let layered = self
+            .layers
+            .values_mut()
+            .flat_map(|layer| layer.mappings.values_mut());
+        for mapping in self.mappings.values_mut().chain(layered) {
+            mapping.sample.iter_mut().for_each(resolve);
+            for layer in &mut mapping.layers {
+                resolve(&mut layer.sample);
+            }
+        }
+    }
+
+    /// Deep-merge an override config onto a base config, both given as JSON (or JSON5)
+    /// Objects (such as `mappings` and each mapping) merge key by key, other values
+    /// (including arrays) are replaced, and `null` removes a key, e.g. to drop a mapping
+    /// Returns the merged JSON after checking that it is a valid configuration
+    pub fn merge_json(
+        base: &str,
+        overlay: &str,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        Self::merge_overlay(base, json5::from_str(overlay)?)
+    }
+
+    /// Deep-merge an already parsed override onto a base config given as JSON,
+    /// the same way as `merge_json`
+    pub fn merge_overlay(
+        base: &str,
+        overlay: serde_json::Value,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let mut merged: serde_json::Value = json5::from_str(base)?;
+        merge_value(&mut merged, overlay);
+        serde_json::from_value::<KeyboardConfig>(merged.clone())?;
+        Ok(merged)
+    }
+
+    /// Parse config contents in the given format without checking that they
+    /// form a complete configuration (e.g. for an override)
+    pub fn parse_value(
+        content: &str,
+        format: ConfigFormat,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        match format {
+            ConfigFormat::Json => Ok(json5::from_str(content)?),
+            ConfigFormat::Toml => Ok(toml::from_str(content)?),
+        }
+    }
+
     /// Save configuration to a JSON file
     #[allow(dead_code)]
     pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
@@ -737,6 +1268,7 @@ impl KeyboardConfig {
                 note: "C4".to_string(),
                 volume: 0.3,
                 description: Some("White key - C4".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -745,6 +1277,7 @@ impl KeyboardConfig {
                 note: "D4".to_string(),
                 volume: 0.3,
                 description: Some("White key - D4".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -753,6 +1286,7 @@ impl KeyboardConfig {
                 note: "E4".to_string(),
                 volume: 0.3,
                 description: Some("White key - E4".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -761,6 +1295,7 @@ impl KeyboardConfig {
                 note: "F4".to_string(),
                 volume: 0.3,
                 description: Some("White key - F4".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -769,6 +1304,7 @@ impl KeyboardConfig {
                 note: "G4".to_string(),
                 volume: 0.3,
                 description: Some("White key - G4".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -777,6 +1313,7 @@ impl KeyboardConfig {
                 note: "A4".to_string(),
                 volume: 0.3,
                 description: Some("White key - A4".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -785,6 +1322,7 @@ impl KeyboardConfig {
                 note: "B4".to_string(),
                 volume: 0.3,
                 description: Some("White key - B4".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -793,6 +1331,7 @@ impl KeyboardConfig {
                 note: "C5".to_string(),
                 volume: 0.3,
                 description: Some("White key - C5".to_string()),
+                ..Default::default()
             },
         );
 
@@ -803,6 +1342,7 @@ impl KeyboardConfig {
                 note: "C#4".to_string(),
                 volume: 0.25,
                 description: Some("Black key - C#4".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -811,6 +1351,7 @@ impl KeyboardConfig {
                 note: "D#4".to_string(),
                 volume: 0.25,
                 description: Some("Black key - D#4".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -819,6 +1360,7 @@ impl KeyboardConfig {
                 note: "F#4".to_string(),
                 volume: 0.25,
                 description: Some("Black key - F#4".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -827,6 +1369,7 @@ impl KeyboardConfig {
                 note: "G#4".to_string(),
                 volume: 0.25,
                 description: Some("Black key - G#4".to_string()),
+                ..Default::default()
             },
         );
         mappings.insert(
@@ -835,6 +1378,7 @@ impl KeyboardConfig {
                 note: "A#4".to_string(),
                 volume: 0.25,
                 description: Some("Black key - A#4".to_string()),
+                ..Default::default()
             },
         );
 
@@ -843,6 +1387,18 @@ impl KeyboardConfig {
             description: "Standard piano layout keyboard mapping".to_string(),
             waveform: None, // Use system default
             mappings,
+            sequences: Vec::new(),
+            chords: Vec::new(),
+            samples: HashMap::new(),
+            transpose_semitones: 0,
+            octave_shift: 0,
+            scale: None,
+            auto_assign: false,
+            layers: HashMap::new(),
+            vibrato: None,
+            lfo: None,
+            mod_matrix: ModMatrix::default(),
+            filter_envelope: None,
         }
     }
 }
@@ -853,6 +1409,45 @@ pub fn keycode_to_string(keycode: Keycode) -> String {
     format!("{:?}", keycode)
 }
 
+/// File formats a keyboard config can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// JSON, with comments and trailing commas allowed
+    Json,
+    /// TOML, e.g. `[mappings.A]` tables
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Detect the format from a file extension, falling back to JSON
+    pub fn from_path(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Json,
+        }
+    }
+}
+
+/// Recursively merge `overlay` into `base` (JSON merge patch semantics)
+fn merge_value(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                if value.is_null() {
+                    base.remove(&key);
+                } else {
+                    merge_value(base.entry(key).or_insert(serde_json::Value::Null), value);
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -864,6 +1459,96 @@ mod tests {
         assert!(!config.mappings.is_empty());
     }
 
+    #[test]
+    fn test_merge_json() {
+        let base = r#"{
+            "version": "1.0",
+            "description": "Base",
+            "mappings": {
+                "A": {"note": "C4", "volume": 0.3},
+                "B": {"note": "D4", "volume": 0.3},
+                "C": {"note": "E4", "volume": 0.3}
+            }
+        }"#;
+        let overlay = r#"{
+            "description": "Personal",
+            "mappings": {
+                "A": {"note": "G4"},
+                "B": null,
+                "Z": {"note": "A5", "volume": 0.5}
+            }
+        }"#;
+
+        let merged = KeyboardConfig::merge_json(base, overlay).unwrap();
+        let config: KeyboardConfig = serde_json::from_value(merged).unwrap();
+        assert_eq!(config.description, "Personal");
+        assert_eq!(config.mappings.len(), 3);
+        assert_eq!(config.mappings["A"].note, "G4");
+        assert_eq!(config.mappings["A"].volume, 0.3);
+        assert!(!config.mappings.contains_key("B"));
+        assert_eq!(config.mappings["C"].note, "E4");
+        assert_eq!(config.mappings["Z"].volume, 0.5);
+
+        // A merge that leaves the config incomplete is rejected
+        assert!(
+            KeyboardConfig::merge_json(base, r#"{"mappings": {"Q": {"note": "C4"}}}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn test_key_ranges() {
+        let config = KeyboardConfig::from_json(
+            r#"{
+                "version": "2.0",
+                "description": "Ranges",
+                "mappings": {
+                    "A-Z": { "scale": "C major pentatonic", "volume": 0.3 },
+                    "E": { "note": "B2", "volume": 0.5 },
+                    "0-9": { "note": "G3", "volume": 0.2 }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.mappings.len(), 36);
+        assert_eq!(config.mappings["A"].note, "C4");
+        assert_eq!(config.mappings["B"].note, "D4");
+        assert_eq!(config.mappings["F"].note, "C5");
+        assert!(config.mappings["A"].scale.is_none());
+        // Keys listed on their own win over the range
+        assert_eq!(config.mappings["E"].note, "B2");
+        assert_eq!(config.mappings["Key7"].note, "G3");
+
+        assert_eq!(
+            key_range("F1-F3").unwrap(),
+            ["F1", "F2", "F3"].map(String::from)
+        );
+        assert!(key_range("Z-A").is_none());
+        assert!(key_range("A-F3").is_none());
+        assert!(KeyboardConfig::from_json(
+            r#"{ "version": "2.0", "description": "Bad", "mappings": { "A-F3": { "note": "C4", "volume": 0.3 } } }"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_merge_toml_overlay() {
+        let base = r#"{
+            "version": "1.0",
+            "description": "Base",
+            "mappings": { "A": {"note": "C4", "volume": 0.3} }
+        }"#;
+        let overlay =
+            KeyboardConfig::parse_value("[mappings.A]\nnote = \"E4\"\n", ConfigFormat::Toml)
+                .unwrap();
+
+        let merged = KeyboardConfig::merge_overlay(base, overlay).unwrap();
+        let config: KeyboardConfig = serde_json::from_value(merged).unwrap();
+        assert_eq!(config.description, "Base");
+        assert_eq!(config.mappings["A"].note, "E4");
+        assert_eq!(config.mappings["A"].volume, 0.3);
+    }
+
     #[test]
     fn test_config_serialization() {
         let config = KeyboardConfig::default();
@@ -874,6 +1559,227 @@ mod tests {
         assert_eq!(config.version, deserialized.version);
     }
 
+    #[test]
+    fn test_config_with_comments() {
+        let json = r#"{
+            // Personal tweaks
+            "version": "1.0",
+            "description": "Annotated",
+            "octave_shift": -1,
+            "mappings": {
+                /* home row */
+                "A": { "note": "C4", "volume": 0.3, }, // trailing comma
+            },
+        }"#;
+        let config = KeyboardConfig::from_json(json).unwrap();
+        assert_eq!(config.mappings["A"].note, "C4");
+        assert_eq!(config.octave_shift, -1);
+
+        // Merging keeps integers as integers
+        let merged = KeyboardConfig::merge_json(json, "{ octave_shift: 2, // up\n }").unwrap();
+        assert_eq!(merged["octave_shift"], serde_json::json!(2));
+
+        // Saving writes plain JSON
+        let saved = serde_json::to_string_pretty(&config).unwrap();
+        assert!(!saved.contains("//"));
+        serde_json::from_str::<serde_json::Value>(&saved).unwrap();
+    }
+
+    #[test]
+    fn test_toml_config() {
+        let toml = r#"
+            version = "1.0"
+            description = "TOML config"
+            waveform = "natural"
+
+            [mappings.A]
+            note = "C4"
+            volume = 0.3
+
+            [mappings.Space]
+            note = "C2"
+            volume = 0.5
+            waveform = "bass"
+
+            [[sequences]]
+            name = "hello"
+            keys = "hello"
+        "#;
+        let config = KeyboardConfig::parse(toml, ConfigFormat::Toml).unwrap();
+        assert_eq!(config.mappings["A"].note, "C4");
+        assert_eq!(config.mappings["Space"].waveform.as_deref(), Some("bass"));
+        assert_eq!(config.sequences[0].name, "hello");
+        assert!(
+            KeyboardConfig::parse(
+                "version = \"1.0\"\ndescription = \"Bad\"\n[mappings.A]\nnote = \"C4\"\nvolume = 0.3\nwaveform = \"kazoo\"\n",
+                ConfigFormat::Toml
+            )
+            .is_err()
+        );
+
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("my.TOML")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("rust.json")),
+            ConfigFormat::Json
+        );
+    }
+
+    #[test]
+    fn test_sample_mapping_deserialization() {
+        let json = r#"{
+            "version": "1.0",
+            "description": "Drum kit",
+            "mappings": {
+                "Space": { "sample": "drums/kick.wav", "volume": 0.8, "description": "Kick" },
+                "A": { "note": "C4", "volume": 0.3, "description": null }
+            }
+        }"#;
+        let config: KeyboardConfig = serde_json::from_str(json).unwrap();
+
+        let kick = &config.mappings["Space"];
+        assert_eq!(kick.sample.as_deref(), Some("drums/kick.wav"));
+        assert!(kick.note.is_empty());
+        assert_eq!(config.mappings["A"].sample, None);
+
+        // Mappings without a sample don't serialize the field
+        let json = serde_json::to_string(&config.mappings["A"]).unwrap();
+        assert!(!json.contains("sample"));
+
+        // Sample paths are relative to the config file
+        let mut config = config;
+        config.resolve_sample_paths(Path::new("/configs"));
+        assert_eq!(
+            config.mappings["Space"].sample.as_deref(),
+            Some(
+                Path::new("/configs")
+                    .join("drums/kick.wav")
+                    .to_str()
+                    .unwrap()
+            )
+        );
+        config.resolve_sample_paths(Path::new("/elsewhere"));
+        assert_eq!(
+            config.mappings["Space"].sample.as_deref(),
+            Some(
+                Path::new("/configs")
+                    .join("drums/kick.wav")
+                    .to_str()
+                    .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_velocity_layer_deserialization() {
+        let json = r#"{
+            "volume": 0.7,
+            "description": "Snare",
+            "layers": [
+                { "sample": "snare_soft.wav", "max_velocity": 0.6 },
+                { "sample": "snare_hard.wav", "min_velocity": 0.6 }
+            ]
+        }"#;
+        let mapping: KeyMapping = serde_json::from_str(json).unwrap();
+
+        assert!(mapping.has_samples());
+        assert_eq!(mapping.layers.len(), 2);
+        assert_eq!(mapping.layers[0].min_velocity, 0.0);
+        assert_eq!(mapping.layers[0].max_velocity, 0.6);
+        assert_eq!(mapping.layers[1].max_velocity, 1.0);
+    }
+
+    #[test]
+    fn test_sequence_deserialization() {
+        let json = r#"{
+            "version": "1.0",
+            "description": "Sequences",
+            "mappings": {},
+            "sequences": [
+                { "name": "hello", "keys": "hello", "sample": "hello.wav" },
+                { "name": "bye", "keys": "bye", "volume": 0.3 }
+            ]
+        }"#;
+        let config: KeyboardConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.sequences.len(), 2);
+        assert_eq!(config.sequences[0].sample.as_deref(), Some("hello.wav"));
+        assert_eq!(config.sequences[0].volume, 0.7);
+        assert_eq!(config.sequences[1].sample, None);
+        assert_eq!(config.sequences[1].volume, 0.3);
+
+        assert_eq!(config.sequences[0].action, None);
+        assert_eq!(
+            config.sequences[0].keys,
+            SequenceKeys::Letters("hello".to_string())
+        );
+
+        // Configs without sequences don't serialize the field
+        let json = serde_json::to_string(&KeyboardConfig::default()).unwrap();
+        assert!(!json.contains("sequences"));
+    }
+
+    #[test]
+    fn test_sequence_action_deserialization() {
+        let json = r#"{
+            "version": "1.0",
+            "description": "Actions",
+            "mappings": {},
+            "samples": { "chime": "sounds/chime.wav" },
+            "sequences": [
+                { "name": "mute", "keys": "mute", "action": { "type": "toggle_mute" } },
+                {
+                    "name": "chime",
+                    "keys": "ding",
+                    "action": { "type": "play_sample", "sample": "chime", "volume": 0.5 }
+                }
+            ]
+        }"#;
+        let config: KeyboardConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.samples["chime"], "sounds/chime.wav");
+        assert_eq!(config.sequences[0].action, Some(Action::ToggleMute));
+        assert_eq!(
+            config.sequences[1].action,
+            Some(Action::PlaySample {
+                sample: "chime".to_string(),
+                volume: 0.5
+            })
+        );
+    }
+
+    #[test]
+    fn test_key_list_sequence_deserialization() {
+        let json = r#"{
+            "name": "konami",
+            "keys": ["Up", "Up", "Down", "Down", "Left", "Right", "Left", "Right", "B", "A"]
+        }"#;
+        let sequence: SequenceConfig = serde_json::from_str(json).unwrap();
+        match sequence.keys {
+            SequenceKeys::Keys(keys) => {
+                assert_eq!(keys.len(), 10);
+                assert_eq!(keys[0], "Up");
+            }
+            SequenceKeys::Letters(_) => panic!("expected a key list"),
+        }
+    }
+
+    #[test]
+    fn test_chord_deserialization() {
+        let json = r#"{
+            "version": "1.0",
+            "description": "Chords",
+            "mappings": {},
+            "chords": [
+                { "name": "mute", "keys": "Ctrl+Alt+M", "action": { "type": "toggle_mute" } }
+            ]
+        }"#;
+        let config: KeyboardConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.chords.len(), 1);
+        assert_eq!(config.chords[0].keys, "Ctrl+Alt+M");
+        assert_eq!(config.chords[0].action, Action::ToggleMute);
+    }
+
     #[test]
     fn test_keycode_conversion() {
         let key_str = keycode_to_string(Keycode::A);
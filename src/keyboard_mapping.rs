@@ -5,14 +5,24 @@
 //! - Note frequency calculation using standard tuning
 //! - Programming-optimized key assignments for pleasant coding experience
 //! - Rate limiting to prevent high-pitched sounds from rapid key presses
+//! - Detecting key chords (several keys held together, e.g. Ctrl+Alt+M)
 
-use crate::keyboard_config::KeyboardConfig;
-use device_query::Keycode;
+use crate::audio_engine::NoteOverrides;
+use crate::extra_keys::EXTRA_KEY_NAMES;
+use crate::keyboard_config::{KeyMapping, KeyboardConfig};
+use crate::keycode::Keycode;
+use crate::layouts::{INTERNATIONAL_KEY_NAMES, KeyboardLayout, key_column_pan};
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::str::FromStr;
 
 /// Calculate frequency from musical note string (e.g., "C4", "F#5", "Bb3")
 pub fn get_frequency_from_note(note: &str) -> Option<f32> {
+    note_to_midi(note).map(midi_to_frequency)
+}
+
+/// Calculate the MIDI note number of a musical note string (C4 = 60)
+pub fn note_to_midi(note: &str) -> Option<i32> {
     let note = note.to_uppercase();
 
     // Parse note and octave
@@ -25,29 +35,47 @@ pub fn get_frequency_from_note(note: &str) -> Option<f32> {
         return None;
     };
 
-    // Note to semitone mapping (C = 0)
-    let semitone = match note_name {
-        "C" => 0,
-        "C#" | "DB" => 1,
-        "D" => 2,
-        "D#" | "EB" => 3,
-        "E" => 4,
-        "F" => 5,
-        "F#" | "GB" => 6,
-        "G" => 7,
-        "G#" | "AB" => 8,
-        "A" => 9,
-        "A#" | "BB" => 10,
-        "B" => 11,
+    // Note to semitone mapping (C = 0), then one accidental or a double one;
+    // enharmonic spellings such as E# or Cb cross into the neighbouring note or octave
+    let mut chars = note_name.chars();
+    let semitone = match chars.next()? {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    let accidental = match chars.as_str() {
+        "" => 0,
+        "#" => 1,
+        "##" => 2,
+        "B" => -1,
+        "BB" => -2,
         _ => return None,
     };
 
-    // Calculate frequency using A4=440Hz tuning
+    Some(octave * 12 + semitone + accidental + 12) // MIDI note number (C4 = 60)
+}
+
+/// Calculate frequency from a MIDI note number using A4=440Hz tuning
+pub fn midi_to_frequency(midi_note: i32) -> f32 {
     // Formula: f = 440 * 2^((n-69)/12) where n is MIDI note number
-    let midi_note = octave * 12 + semitone + 12; // MIDI note number (C4 = 60)
-    let frequency = 440.0 * 2.0_f32.powf((midi_note as f32 - 69.0) / 12.0);
+    440.0 * 2.0_f32.powf((midi_note as f32 - 69.0) / 12.0)
+}
 
-    Some(frequency)
+/// Name a MIDI note number, using sharps (e.g. 61 → "C#4")
+pub fn midi_to_note(midi_note: i32) -> String {
+    const NAMES: [&str; 12] = [
+        "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+    ];
+    format!(
+        "{}{}",
+        NAMES[midi_note.rem_euclid(12) as usize],
+        midi_note.div_euclid(12) - 1
+    )
 }
 
 /// Keyboard state tracker for handling shifted characters
@@ -56,6 +84,96 @@ pub struct KeyboardStateTracker {
     pressed_keys: HashSet<Keycode>,
     /// Track which virtual keycode was used when each physical key was pressed
     pressed_virtual_keys: HashMap<Keycode, VirtualKeycode>,
+    /// Chords to detect while keys are held
+    chords: Vec<Chord>,
+    /// Names of chords currently held (so each fires once per hold)
+    active_chords: HashSet<String>,
+    /// Layout used to translate key positions before mapping lookup
+    layout: KeyboardLayout,
+    /// Modifier combinations with their own mapping (e.g. "Ctrl+S")
+    combos: Vec<Chord>,
+    /// Mapping layers and the keys that switch them on
+    layers: Vec<LayerKey>,
+    /// Names of the layers currently on, most recently switched on last
+    active_layers: Vec<String>,
+}
+
+/// A mapping layer as seen by the tracker: its layer key and the keys it maps
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerKey {
+    pub name: String,
+    pub key: Keycode,
+    /// The layer key switches the layer on and off instead of being held
+    pub toggle: bool,
+    /// Names of the keys the layer maps (other keys fall through to the base mappings)
+    pub mapped: HashSet<String>,
+}
+
+/// A set of keys that triggers when all are held together (e.g. Ctrl+Alt+M)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chord {
+    pub name: String,
+    /// Each entry lists interchangeable keys (e.g. left or right Control)
+    pub keys: Vec<Vec<Keycode>>,
+}
+
+impl Chord {
+    /// Parse a chord from a "+"-separated combo such as "Ctrl+Alt+M"
+    pub fn parse(name: &str, combo: &str) -> Result<Self, String> {
+        let keys = combo
+            .split('+')
+            .map(|key| parse_chord_key(key.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if keys.len() < 2 {
+            return Err(format!("Chord '{}' needs at least two keys", name));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            keys,
+        })
+    }
+
+    /// Check whether every key of the chord is held
+    pub fn is_held(&self, pressed_keys: &HashSet<Keycode>) -> bool {
+        self.keys
+            .iter()
+            .all(|options| options.iter().any(|key| pressed_keys.contains(key)))
+    }
+
+    /// Check whether a key is part of this chord
+    pub fn contains(&self, keycode: Keycode) -> bool {
+        self.keys.iter().any(|options| options.contains(&keycode))
+    }
+}
+
+/// Parse one key of a chord, accepting side-less modifier names like "Ctrl"
+fn parse_chord_key(name: &str) -> Result<Vec<Keycode>, String> {
+    let keys = match name.to_lowercase().as_str() {
+        "ctrl" | "control" => vec![Keycode::LControl, Keycode::RControl],
+        "shift" => vec![Keycode::LShift, Keycode::RShift],
+        "alt" => vec![Keycode::LAlt, Keycode::RAlt],
+        "option" => vec![Keycode::LOption, Keycode::ROption],
+        "meta" | "cmd" | "super" => vec![Keycode::LMeta, Keycode::RMeta, Keycode::Command],
+        _ => {
+            // Single letters may be written in either case
+            let name = if name.len() == 1 {
+                name.to_uppercase()
+            } else {
+                name.to_string()
+            };
+            vec![Keycode::from_str(&name).map_err(|_| format!("Unknown key '{}'", name))?]
+        }
+    };
+
+    Ok(keys)
+}
+
+impl Default for KeyboardStateTracker {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl KeyboardStateTracker {
@@ -64,9 +182,61 @@ impl KeyboardStateTracker {
             shift_pressed: false,
             pressed_keys: HashSet::new(),
             pressed_virtual_keys: HashMap::new(),
+            chords: Vec::new(),
+            active_chords: HashSet::new(),
+            layout: KeyboardLayout::default(),
+            combos: Vec::new(),
+            layers: Vec::new(),
+            active_layers: Vec::new(),
         }
     }
 
+    /// Replace the mapping layers, switching all of them off
+    pub fn set_layers(&mut self, layers: Vec<LayerKey>) {
+        self.layers = layers;
+        self.active_layers.clear();
+    }
+
+    /// Name of the layer whose mappings take precedence, if any is on
+    pub fn active_layer(&self) -> Option<&str> {
+        self.active_layers.last().map(String::as_str)
+    }
+
+    /// Replace the modifier combinations that are looked up before plain keys
+    pub fn set_combos(&mut self, combos: Vec<Chord>) {
+        self.combos = combos;
+    }
+
+    /// Set the keyboard layout that key positions are translated with
+    pub fn set_layout(&mut self, layout: KeyboardLayout) {
+        self.layout = layout;
+    }
+
+    /// Replace the chords to detect
+    pub fn set_chords(&mut self, chords: Vec<Chord>) {
+        self.chords = chords;
+        self.active_chords.clear();
+    }
+
+    /// Return the chords that became fully held since the last check
+    ///
+    /// Call after `update`; a chord fires again only once one of its keys is released.
+    pub fn check_chords(&mut self) -> Vec<Chord> {
+        let mut triggered = Vec::new();
+
+        for chord in &self.chords {
+            if chord.is_held(&self.pressed_keys) {
+                if self.active_chords.insert(chord.name.clone()) {
+                    triggered.push(chord.clone());
+                }
+            } else {
+                self.active_chords.remove(&chord.name);
+            }
+        }
+
+        triggered
+    }
+
     /// Update keyboard state based on pressed and released keys
     pub fn update(&mut self, pressed_keys: &[Keycode], released_keys: &[Keycode]) {
         // First, add all newly pressed keys
@@ -74,6 +244,24 @@ impl KeyboardStateTracker {
             self.pressed_keys.insert(*key);
         }
 
+        // Layer keys switch their layer on while held, or on and off when toggled
+        for layer in &self.layers {
+            let key = &layer.key;
+            let pressed = pressed_keys
+                .iter()
+                .any(|pressed| self.layout.translate(*pressed) == *key);
+            let released = released_keys
+                .iter()
+                .any(|released| self.layout.translate(*released) == *key);
+            let active = self.active_layers.contains(&layer.name);
+            if pressed && !(layer.toggle && active) {
+                self.active_layers.retain(|name| *name != layer.name);
+                self.active_layers.push(layer.name.clone());
+            } else if (pressed && layer.toggle) || (released && !layer.toggle) {
+                self.active_layers.retain(|name| *name != layer.name);
+            }
+        }
+
         // Then, remove all released keys from pressed_keys only
         // We DON'T remove from pressed_virtual_keys here because we need them
         // for the release event handling that happens after this update
@@ -98,12 +286,38 @@ impl KeyboardStateTracker {
     ) -> Option<VirtualKeycode> {
         // Check if shift is currently pressed (including keys pressed in this frame)
         let shift_currently_pressed = self.shift_pressed;
+        // Look up the key the user's layout puts at this position
+        let key = self.layout.translate(physical_key);
+
+        // Layer keys only switch layers
+        if self.layers.iter().any(|layer| layer.key == key) {
+            return None;
+        }
 
-        let virtual_key = if !shift_currently_pressed {
-            VirtualKeycode::Physical(physical_key)
+        // A mapped combination completed by this key wins over the key itself,
+        // the one with the most keys if several are held
+        let combo = self
+            .combos
+            .iter()
+            .filter(|combo| {
+                combo
+                    .keys
+                    .last()
+                    .is_some_and(|options| options.contains(&key))
+                    && combo.is_held(&self.pressed_keys)
+            })
+            .max_by_key(|combo| combo.keys.len());
+
+        let virtual_key = if let Some(combo) = combo {
+            VirtualKeycode::Combo(combo.name.clone())
+        } else if let Some(name) = self.layout.international_key(physical_key) {
+            // International keys keep their name with or without shift
+            VirtualKeycode::Extra(name)
+        } else if !shift_currently_pressed {
+            VirtualKeycode::Physical(key)
         } else {
             // Map shifted characters
-            match physical_key {
+            match key {
                 Keycode::Key1 => VirtualKeycode::Shifted("Exclamation"),
                 Keycode::Key2 => VirtualKeycode::Shifted("At"),
                 Keycode::Key3 => VirtualKeycode::Shifted("Hash"),
@@ -126,12 +340,24 @@ impl KeyboardStateTracker {
                 Keycode::Slash => VirtualKeycode::Shifted("Question"),
                 Keycode::Grave => VirtualKeycode::Shifted("Tilde"),
                 // Don't shift modifier keys themselves
-                Keycode::LShift | Keycode::RShift => VirtualKeycode::Physical(physical_key),
+                Keycode::LShift | Keycode::RShift => VirtualKeycode::Physical(key),
                 // Regular letters get capitalized when shift is pressed, but we treat them the same
-                _ => VirtualKeycode::Physical(physical_key),
+                _ => VirtualKeycode::Physical(key),
             }
         };
 
+        // The most recently switched-on layer that maps the key plays it
+        let name = virtual_key.to_string();
+        let layer = self.active_layers.iter().rev().find(|active| {
+            self.layers
+                .iter()
+                .any(|layer| layer.name == **active && layer.mapped.contains(&name))
+        });
+        let virtual_key = match layer {
+            Some(layer) => VirtualKeycode::Layered(layer.clone(), Box::new(virtual_key)),
+            None => virtual_key,
+        };
+
         // Remember which virtual keycode was used for this physical key
         self.pressed_virtual_keys
             .insert(physical_key, virtual_key.clone());
@@ -150,22 +376,200 @@ impl KeyboardStateTracker {
     }
 }
 
-/// Virtual keycode that can represent both physical keys and shifted characters
+/// Virtual keycode that can represent physical keys, shifted characters and
+/// keys outside `Keycode` (media and lock keys, see `extra_keys`, and the
+/// international keys of a layout, see `layouts`)
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum VirtualKeycode {
     Physical(Keycode),
     Shifted(&'static str),
+    Extra(&'static str),
+    /// A modifier combination with its own mapping, by mapping name (e.g. "Ctrl+S")
+    Combo(String),
+    /// A key played from a mapping layer (by layer name), shown as "layer:key"
+    Layered(String, Box<VirtualKeycode>),
 }
 
-impl VirtualKeycode {
-    pub fn to_string(&self) -> String {
+impl std::fmt::Display for VirtualKeycode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            VirtualKeycode::Physical(keycode) => format!("{:?}", keycode),
-            VirtualKeycode::Shifted(name) => name.to_string(),
+            VirtualKeycode::Physical(keycode) => write!(f, "{:?}", keycode),
+            VirtualKeycode::Shifted(name) | VirtualKeycode::Extra(name) => write!(f, "{}", name),
+            VirtualKeycode::Combo(name) => write!(f, "{}", name),
+            VirtualKeycode::Layered(layer, key) => write!(f, "{}:{}", layer, key),
         }
     }
 }
 
+/// Get the virtual keycode typed for a character on a US layout, if any
+/// (letters such as ü or ñ give the international key of that name)
+pub fn virtual_keycode_for_char(c: char) -> Option<VirtualKeycode> {
+    let shifted = match c {
+        '!' => Some("Exclamation"),
+        '@' => Some("At"),
+        '#' => Some("Hash"),
+        '$' => Some("Dollar"),
+        '%' => Some("Percent"),
+        '^' => Some("Caret"),
+        '&' => Some("Ampersand"),
+        '*' => Some("Asterisk"),
+        '(' => Some("LeftParen"),
+        ')' => Some("RightParen"),
+        '_' => Some("Underscore"),
+        '+' => Some("Plus"),
+        '{' => Some("LeftBrace"),
+        '}' => Some("RightBrace"),
+        '|' => Some("Pipe"),
+        ':' => Some("Colon"),
+        '"' => Some("DoubleQuote"),
+        '<' => Some("LessThan"),
+        '>' => Some("GreaterThan"),
+        '?' => Some("Question"),
+        '~' => Some("Tilde"),
+        _ => None,
+    };
+    if let Some(name) = shifted {
+        return Some(VirtualKeycode::Shifted(name));
+    }
+
+    let international = match c {
+        'ä' | 'Ä' => Some("Ä"),
+        'ö' | 'Ö' => Some("Ö"),
+        'ü' | 'Ü' => Some("Ü"),
+        'ß' => Some("ß"),
+        'ñ' | 'Ñ' => Some("Ñ"),
+        'ç' | 'Ç' => Some("Ç"),
+        'ù' | 'Ù' => Some("Ù"),
+        '¡' => Some("InvertedExclamation"),
+        _ => None,
+    };
+    if let Some(name) = international {
+        return Some(VirtualKeycode::Extra(name));
+    }
+
+    let keycode = match c {
+        ' ' => Keycode::Space,
+        '\n' => Keycode::Enter,
+        '\t' => Keycode::Tab,
+        '-' => Keycode::Minus,
+        '=' => Keycode::Equal,
+        '[' => Keycode::LeftBracket,
+        ']' => Keycode::RightBracket,
+        '\\' => Keycode::BackSlash,
+        ';' => Keycode::Semicolon,
+        '\'' => Keycode::Apostrophe,
+        ',' => Keycode::Comma,
+        '.' => Keycode::Dot,
+        '/' => Keycode::Slash,
+        '`' => Keycode::Grave,
+        // Letters and digits share their key names (uppercase letters use the same key)
+        c if c.is_ascii_alphabetic() => {
+            Keycode::from_str(&c.to_ascii_uppercase().to_string()).ok()?
+        }
+        c if c.is_ascii_digit() => Keycode::from_str(&format!("Key{}", c)).ok()?,
+        _ => return None,
+    };
+    Some(VirtualKeycode::Physical(keycode))
+}
+
+/// Names of the shifted characters that can be mapped like keys
+pub const SHIFTED_KEY_NAMES: &[&str] = &[
+    "Exclamation",
+    "At",
+    "Hash",
+    "Dollar",
+    "Percent",
+    "Caret",
+    "Ampersand",
+    "Asterisk",
+    "LeftParen",
+    "RightParen",
+    "Underscore",
+    "Plus",
+    "LeftBrace",
+    "RightBrace",
+    "Pipe",
+    "Colon",
+    "DoubleQuote",
+    "LessThan",
+    "GreaterThan",
+    "Question",
+    "Tilde",
+];
+
+/// Check whether a name used in `mappings` can ever be played
+/// (a key, shifted character, extra key, international key or combination)
+pub fn is_known_key_name(name: &str) -> bool {
+    SHIFTED_KEY_NAMES.contains(&name)
+        || EXTRA_KEY_NAMES.contains(&name)
+        || INTERNATIONAL_KEY_NAMES.contains(&name)
+        || Keycode::from_str(name).is_ok()
+        || (name.contains('+') && Chord::parse(name, name).is_ok())
+}
+
+/// Modifier combinations mapped in a config (mapping names such as "Ctrl+S" or "Cmd+Shift+Z")
+pub fn combo_mappings(config: &KeyboardConfig) -> Vec<Chord> {
+    config
+        .mappings
+        .keys()
+        .filter(|name| name.contains('+'))
+        .filter_map(|name| Chord::parse(name, name).ok())
+        .collect()
+}
+
+/// Mapping layers of a config as tracked by `KeyboardStateTracker`
+/// (layers whose key isn't a known key are left out)
+pub fn layer_keys(config: &KeyboardConfig) -> Vec<LayerKey> {
+    config
+        .layers
+        .iter()
+        .filter_map(|(name, layer)| {
+            Some(LayerKey {
+                name: name.clone(),
+                key: Keycode::from_str(&layer.key).ok()?,
+                toggle: layer.toggle,
+                mapped: layer.mappings.keys().cloned().collect(),
+            })
+        })
+        .collect()
+}
+
+/// Get the raw mapping configured for a virtual keycode
+pub fn get_mapping_for_virtual<'a>(
+    virtual_keycode: &VirtualKeycode,
+    config: &'a KeyboardConfig,
+) -> Option<&'a KeyMapping> {
+    match virtual_keycode {
+        VirtualKeycode::Layered(layer, key) => {
+            config.layers.get(layer)?.mappings.get(&key.to_string())
+        }
+        _ => config.mappings.get(&virtual_keycode.to_string()),
+    }
+}
+
+/// Per-key settings for a virtual keycode, including its keyboard column as
+/// `key_position`; with `auto_pan`, keys without a `pan` take it as their pan
+pub fn note_overrides_for_virtual(
+    virtual_keycode: &VirtualKeycode,
+    config: &KeyboardConfig,
+    auto_pan: bool,
+) -> NoteOverrides {
+    let mut overrides = get_mapping_for_virtual(virtual_keycode, config)
+        .map(|mapping| mapping.note_overrides())
+        .unwrap_or_default();
+    let name = match virtual_keycode {
+        VirtualKeycode::Layered(_, key) => key.to_string(),
+        _ => virtual_keycode.to_string(),
+    };
+    // A combination sits where its last key does (e.g. S for "Ctrl+S")
+    let key = name.rsplit('+').next().unwrap_or(&name);
+    overrides.key_position = key_column_pan(key);
+    if auto_pan && overrides.pan.is_none() {
+        overrides.pan = overrides.key_position;
+    }
+    overrides
+}
+
 /// Get frequency and volume for a virtual keycode using the provided keyboard configuration
 /// Returns (frequency, volume, note_name) for a given virtual keycode
 pub fn get_frequency_and_volume_with_config_virtual(
@@ -173,9 +577,22 @@ pub fn get_frequency_and_volume_with_config_virtual(
     config: &KeyboardConfig,
 ) -> Option<(f32, f32, String)> {
     let key_name = virtual_keycode.to_string();
-    let mapping = config.mappings.get(&key_name)?;
-    let frequency = get_frequency_from_note(&mapping.note)?;
-    Some((frequency, mapping.volume, mapping.note.clone()))
+    let (note, volume) = match get_mapping_for_virtual(virtual_keycode, config) {
+        Some(mapping) => (mapping.note.clone(), mapping.volume),
+        None => config.auto_assigned_note(&key_name)?,
+    };
+    let shift = config.note_shift();
+    if shift == 0 {
+        let frequency = get_frequency_from_note(&note)?;
+        return Some((frequency, volume, note));
+    }
+
+    let midi_note = note_to_midi(&note)? + shift;
+    Some((
+        midi_to_frequency(midi_note),
+        volume,
+        midi_to_note(midi_note),
+    ))
 }
 
 /// Get frequency and volume for a keycode using the provided keyboard configuration
@@ -199,7 +616,7 @@ pub fn get_frequency_and_volume(keycode: Keycode) -> Option<(f32, f32, &'static
     // Use a static default config for backward compatibility
     use std::sync::OnceLock;
     static DEFAULT_CONFIG: OnceLock<KeyboardConfig> = OnceLock::new();
-    let config = DEFAULT_CONFIG.get_or_init(|| KeyboardConfig::default());
+    let config = DEFAULT_CONFIG.get_or_init(KeyboardConfig::default);
 
     if let Some((freq, vol, note)) = get_frequency_and_volume_with_config(keycode, config) {
         // We need to return a &'static str, so we'll need to handle the string conversion
@@ -260,6 +677,19 @@ pub fn get_frequency_and_volume(keycode: Keycode) -> Option<(f32, f32, &'static
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_virtual_keycode_for_char() {
+        let name = |c| virtual_keycode_for_char(c).map(|key| key.to_string());
+        assert_eq!(name('a'), Some("A".to_string()));
+        assert_eq!(name('Q'), Some("Q".to_string()));
+        assert_eq!(name('7'), Some("Key7".to_string()));
+        assert_eq!(name(' '), Some("Space".to_string()));
+        assert_eq!(name('\n'), Some("Enter".to_string()));
+        assert_eq!(name('('), Some("LeftParen".to_string()));
+        assert_eq!(name(';'), Some("Semicolon".to_string()));
+        assert_eq!(name('é'), None);
+    }
+
     #[test]
     fn test_frequency_calculation() {
         // Test A4 = 440Hz
@@ -284,6 +714,95 @@ mod tests {
         assert!((cs4_freq - db4_freq).abs() < 0.01);
     }
 
+    #[test]
+    fn test_midi_note_names() {
+        assert_eq!(note_to_midi("C4"), Some(60));
+        assert_eq!(note_to_midi("Bb3"), Some(58));
+        // Enharmonic spellings used by some configs
+        assert_eq!(note_to_midi("E#5"), note_to_midi("F5"));
+        assert_eq!(note_to_midi("Cb4"), note_to_midi("B3"));
+        assert_eq!(note_to_midi("H4"), None);
+        assert_eq!(note_to_midi("F##4"), note_to_midi("G4"));
+        assert_eq!(note_to_midi("Ebb4"), note_to_midi("D4"));
+        assert_eq!(note_to_midi("C#b#4"), None);
+        assert_eq!(note_to_midi("C###4"), None);
+        assert_eq!(midi_to_note(61), "C#4");
+        assert_eq!(midi_to_note(21), "A0");
+        assert!((midi_to_frequency(69) - 440.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_config_note_shift() {
+        let mut config = KeyboardConfig::default();
+        let key = VirtualKeycode::Physical(Keycode::A);
+        let (base_freq, _, _) =
+            get_frequency_and_volume_with_config_virtual(&key, &config).unwrap();
+
+        config.transpose_semitones = 2;
+        config.octave_shift = -1;
+        let (freq, vol, note) =
+            get_frequency_and_volume_with_config_virtual(&key, &config).unwrap();
+        assert_eq!(note, "D3");
+        assert_eq!(vol, 0.3);
+        assert!((freq / base_freq - 2.0_f32.powf(-10.0 / 12.0)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_auto_pan() {
+        let mut config = KeyboardConfig::default();
+        config.mappings.get_mut("P").unwrap().pan = Some(-0.5);
+        let pan =
+            |key: &VirtualKeycode, auto_pan| note_overrides_for_virtual(key, &config, auto_pan).pan;
+
+        let q = VirtualKeycode::Physical(Keycode::Q);
+        assert_eq!(pan(&q, false), None);
+        assert_eq!(pan(&q, true), Some(-1.0));
+        // An explicit pan wins
+        assert_eq!(pan(&VirtualKeycode::Physical(Keycode::P), true), Some(-0.5));
+        assert_eq!(pan(&VirtualKeycode::Physical(Keycode::Space), true), None);
+        let p = note_overrides_for_virtual(&VirtualKeycode::Physical(Keycode::P), &config, false);
+        assert_eq!(p.key_position, Some(1.0));
+        assert_eq!(
+            pan(&VirtualKeycode::Combo("Ctrl+Slash".to_string()), true),
+            Some(1.0)
+        );
+    }
+
+    #[test]
+    fn test_auto_assigned_notes() {
+        let mut config: KeyboardConfig = serde_json::from_str(
+            r#"{
+                "version": "1.0",
+                "description": "Scale only",
+                "mappings": { "Space": { "note": "C2", "volume": 0.5 } },
+                "scale": { "mode": "major", "root": "C4", "volume": 0.4 }
+            }"#,
+        )
+        .unwrap();
+        let e = VirtualKeycode::Physical(Keycode::E);
+        assert!(get_frequency_and_volume_with_config_virtual(&e, &config).is_none());
+
+        config.auto_assign = true;
+        let (_, volume, note) = get_frequency_and_volume_with_config_virtual(&e, &config).unwrap();
+        assert_eq!((note.as_str(), volume), ("C4", 0.4));
+        let t = VirtualKeycode::Physical(Keycode::T);
+        assert_eq!(
+            get_frequency_and_volume_with_config_virtual(&t, &config)
+                .unwrap()
+                .2,
+            "D4"
+        );
+
+        // Mapped keys keep their own notes
+        let space = VirtualKeycode::Physical(Keycode::Space);
+        assert_eq!(
+            get_frequency_and_volume_with_config_virtual(&space, &config)
+                .unwrap()
+                .2,
+            "C2"
+        );
+    }
+
     #[test]
     fn test_keyboard_mapping() {
         // Test common programming keys
@@ -311,4 +830,166 @@ mod tests {
         assert_eq!(get_frequency_from_note("C"), None);
         assert_eq!(get_frequency_from_note(""), None);
     }
+
+    #[test]
+    fn test_chord_parsing() {
+        let chord = Chord::parse("mute", "Ctrl+Alt+m").unwrap();
+        assert_eq!(chord.keys.len(), 3);
+        assert!(chord.contains(Keycode::RControl));
+        assert!(chord.contains(Keycode::M));
+        assert!(!chord.contains(Keycode::N));
+
+        assert!(Chord::parse("single", "M").is_err());
+        assert!(Chord::parse("unknown", "Ctrl+Nope").is_err());
+    }
+
+    #[test]
+    fn test_chord_detection() {
+        let mut tracker = KeyboardStateTracker::new();
+        tracker.set_chords(vec![Chord::parse("mute", "Ctrl+Alt+M").unwrap()]);
+
+        tracker.update(&[Keycode::LControl, Keycode::RAlt], &[]);
+        assert!(tracker.check_chords().is_empty());
+
+        tracker.update(&[Keycode::M], &[]);
+        let triggered = tracker.check_chords();
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(triggered[0].name, "mute");
+
+        // Holding doesn't retrigger, but releasing and pressing again does
+        assert!(tracker.check_chords().is_empty());
+        tracker.update(&[], &[Keycode::M]);
+        assert!(tracker.check_chords().is_empty());
+        tracker.update(&[Keycode::M], &[]);
+        assert_eq!(tracker.check_chords().len(), 1);
+    }
+
+    #[test]
+    fn test_layout_translation() {
+        let mut tracker = KeyboardStateTracker::new();
+        tracker.set_layout(KeyboardLayout::Dvorak);
+
+        // The QWERTY S position types O on Dvorak
+        tracker.update(&[Keycode::S], &[]);
+        assert_eq!(
+            tracker.get_virtual_keycode_for_press(Keycode::S),
+            Some(VirtualKeycode::Physical(Keycode::O))
+        );
+        assert_eq!(
+            tracker.get_virtual_keycode_for_release(Keycode::S),
+            Some(VirtualKeycode::Physical(Keycode::O))
+        );
+
+        // Shifted characters follow the translated key
+        tracker.update(&[Keycode::LShift, Keycode::Q], &[]);
+        assert_eq!(
+            tracker.get_virtual_keycode_for_press(Keycode::Q),
+            Some(VirtualKeycode::Shifted("DoubleQuote"))
+        );
+    }
+
+    #[test]
+    fn test_international_keys() {
+        let mut tracker = KeyboardStateTracker::new();
+        tracker.set_layout(KeyboardLayout::German);
+
+        tracker.update(&[Keycode::LShift, Keycode::LeftBracket], &[]);
+        assert_eq!(
+            tracker.get_virtual_keycode_for_press(Keycode::LeftBracket),
+            Some(VirtualKeycode::Extra("Ü"))
+        );
+        assert!(is_known_key_name("Ü"));
+        assert_eq!(
+            virtual_keycode_for_char('ñ').map(|key| key.to_string()),
+            Some("Ñ".to_string())
+        );
+    }
+
+    #[test]
+    fn test_combo_mappings() {
+        let mut config = KeyboardConfig::default();
+        for name in ["S", "Ctrl+S", "Ctrl+Shift+S"] {
+            config
+                .mappings
+                .insert(name.to_string(), KeyMapping::default());
+        }
+        let mut combos = combo_mappings(&config);
+        combos.sort_by_key(|combo| combo.keys.len());
+        assert_eq!(combos.len(), 2);
+
+        let mut tracker = KeyboardStateTracker::new();
+        tracker.set_combos(combos);
+
+        tracker.update(&[Keycode::S], &[]);
+        assert_eq!(
+            tracker.get_virtual_keycode_for_press(Keycode::S),
+            Some(VirtualKeycode::Physical(Keycode::S))
+        );
+        tracker.update(&[], &[Keycode::S]);
+        tracker.get_virtual_keycode_for_release(Keycode::S);
+
+        tracker.update(&[Keycode::LControl, Keycode::S], &[]);
+        assert_eq!(
+            tracker
+                .get_virtual_keycode_for_press(Keycode::S)
+                .unwrap()
+                .to_string(),
+            "Ctrl+S"
+        );
+        tracker.update(&[], &[Keycode::S]);
+        tracker.get_virtual_keycode_for_release(Keycode::S);
+
+        // The most specific combination wins
+        tracker.update(&[Keycode::RShift, Keycode::S], &[]);
+        assert_eq!(
+            tracker
+                .get_virtual_keycode_for_press(Keycode::S)
+                .unwrap()
+                .to_string(),
+            "Ctrl+Shift+S"
+        );
+        assert!(is_known_key_name("Cmd+Z"));
+        assert!(!is_known_key_name("Ctrl+Bogus"));
+    }
+
+    #[test]
+    fn test_mapping_layers() {
+        let config: KeyboardConfig = serde_json::from_str(
+            r#"{
+                "version": "2.0",
+                "description": "Layers",
+                "mappings": { "A": { "note": "C4", "volume": 0.3 }, "S": { "note": "D4", "volume": 0.3 } },
+                "layers": {
+                    "drums": { "key": "CapsLock", "mappings": { "A": { "note": "C2", "volume": 0.5 } } },
+                    "high": { "key": "F12", "toggle": true, "mappings": { "S": { "note": "D6", "volume": 0.3 } } }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut tracker = KeyboardStateTracker::new();
+        tracker.set_layers(layer_keys(&config));
+
+        // Holding the layer key plays the layer's mapping; other keys fall through
+        tracker.update(&[Keycode::CapsLock, Keycode::A, Keycode::S], &[]);
+        assert_eq!(
+            tracker.get_virtual_keycode_for_press(Keycode::CapsLock),
+            None
+        );
+        let a = tracker.get_virtual_keycode_for_press(Keycode::A).unwrap();
+        assert_eq!(a.to_string(), "drums:A");
+        assert_eq!(get_mapping_for_virtual(&a, &config).unwrap().note, "C2");
+        let s = tracker.get_virtual_keycode_for_press(Keycode::S).unwrap();
+        assert_eq!(s, VirtualKeycode::Physical(Keycode::S));
+
+        tracker.update(&[], &[Keycode::CapsLock, Keycode::A, Keycode::S]);
+        assert_eq!(tracker.active_layer(), None);
+
+        // Toggled layers stay on until the key is pressed again
+        tracker.update(&[Keycode::F12], &[]);
+        tracker.update(&[], &[Keycode::F12]);
+        assert_eq!(tracker.active_layer(), Some("high"));
+        tracker.update(&[Keycode::F12], &[]);
+        assert_eq!(tracker.active_layer(), None);
+    }
 }
@@ -0,0 +1,321 @@
+//! Keycode module
+//!
+//! Keys are named after `device_query::Keycode`, which native builds use
+//! directly. Builds without `device_query` (WebAssembly, headless) get a copy
+//! with the same variants, names and parsing, so configs and mappings work
+//! the same everywhere.
+
+#[cfg(feature = "native")]
+pub use device_query::Keycode;
+
+#[cfg(not(feature = "native"))]
+pub use fallback::Keycode;
+
+#[cfg(not(feature = "native"))]
+mod fallback {
+    use std::fmt;
+    use std::str::FromStr;
+
+    /// A key, named by its position on a US keyboard
+    #[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+    pub enum Keycode {
+        Key0,
+        Key1,
+        Key2,
+        Key3,
+        Key4,
+        Key5,
+        Key6,
+        Key7,
+        Key8,
+        Key9,
+        A,
+        B,
+        C,
+        D,
+        E,
+        F,
+        G,
+        H,
+        I,
+        J,
+        K,
+        L,
+        M,
+        N,
+        O,
+        P,
+        Q,
+        R,
+        S,
+        T,
+        U,
+        V,
+        W,
+        X,
+        Y,
+        Z,
+        F1,
+        F2,
+        F3,
+        F4,
+        F5,
+        F6,
+        F7,
+        F8,
+        F9,
+        F10,
+        F11,
+        F12,
+        F13,
+        F14,
+        F15,
+        F16,
+        F17,
+        F18,
+        F19,
+        F20,
+        Escape,
+        Space,
+        LControl,
+        RControl,
+        LShift,
+        RShift,
+        LAlt,
+        RAlt,
+        Command,
+        LOption,
+        ROption,
+        LMeta,
+        RMeta,
+        Enter,
+        Up,
+        Down,
+        Left,
+        Right,
+        Backspace,
+        CapsLock,
+        Tab,
+        Home,
+        End,
+        PageUp,
+        PageDown,
+        Insert,
+        Delete,
+        Numpad0,
+        Numpad1,
+        Numpad2,
+        Numpad3,
+        Numpad4,
+        Numpad5,
+        Numpad6,
+        Numpad7,
+        Numpad8,
+        Numpad9,
+        NumpadSubtract,
+        NumpadAdd,
+        NumpadDivide,
+        NumpadMultiply,
+        NumpadEquals,
+        NumpadEnter,
+        NumpadDecimal,
+        Grave,
+        Minus,
+        Equal,
+        LeftBracket,
+        RightBracket,
+        BackSlash,
+        Semicolon,
+        Apostrophe,
+        Comma,
+        Dot,
+        Slash,
+    }
+
+    const ALL: &[Keycode] = &[
+        Keycode::Key0,
+        Keycode::Key1,
+        Keycode::Key2,
+        Keycode::Key3,
+        Keycode::Key4,
+        Keycode::Key5,
+        Keycode::Key6,
+        Keycode::Key7,
+        Keycode::Key8,
+        Keycode::Key9,
+        Keycode::A,
+        Keycode::B,
+        Keycode::C,
+        Keycode::D,
+        Keycode::E,
+        Keycode::F,
+        Keycode::G,
+        Keycode::H,
+        Keycode::I,
+        Keycode::J,
+        Keycode::K,
+        Keycode::L,
+        Keycode::M,
+        Keycode::N,
+        Keycode::O,
+        Keycode::P,
+        Keycode::Q,
+        Keycode::R,
+        Keycode::S,
+        Keycode::T,
+        Keycode::U,
+        Keycode::V,
+        Keycode::W,
+        Keycode::X,
+        Keycode::Y,
+        Keycode::Z,
+        Keycode::F1,
+        Keycode::F2,
+        Keycode::F3,
+        Keycode::F4,
+        Keycode::F5,
+        Keycode::F6,
+        Keycode::F7,
+        Keycode::F8,
+        Keycode::F9,
+        Keycode::F10,
+        Keycode::F11,
+        Keycode::F12,
+        Keycode::F13,
+        Keycode::F14,
+        Keycode::F15,
+        Keycode::F16,
+        Keycode::F17,
+        Keycode::F18,
+        Keycode::F19,
+        Keycode::F20,
+        Keycode::Escape,
+        Keycode::Space,
+        Keycode::LControl,
+        Keycode::RControl,
+        Keycode::LShift,
+        Keycode::RShift,
+        Keycode::LAlt,
+        Keycode::RAlt,
+        Keycode::Command,
+        Keycode::LOption,
+        Keycode::ROption,
+        Keycode::LMeta,
+        Keycode::RMeta,
+        Keycode::Enter,
+        Keycode::Up,
+        Keycode::Down,
+        Keycode::Left,
+        Keycode::Right,
+        Keycode::Backspace,
+        Keycode::CapsLock,
+        Keycode::Tab,
+        Keycode::Home,
+        Keycode::End,
+        Keycode::PageUp,
+        Keycode::PageDown,
+        Keycode::Insert,
+        Keycode::Delete,
+        Keycode::Numpad0,
+        Keycode::Numpad1,
+        Keycode::Numpad2,
+        Keycode::Numpad3,
+        Keycode::Numpad4,
+        Keycode::Numpad5,
+        Keycode::Numpad6,
+        Keycode::Numpad7,
+        Keycode::Numpad8,
+        Keycode::Numpad9,
+        Keycode::NumpadSubtract,
+        Keycode::NumpadAdd,
+        Keycode::NumpadDivide,
+        Keycode::NumpadMultiply,
+        Keycode::NumpadEquals,
+        Keycode::NumpadEnter,
+        Keycode::NumpadDecimal,
+        Keycode::Grave,
+        Keycode::Minus,
+        Keycode::Equal,
+        Keycode::LeftBracket,
+        Keycode::RightBracket,
+        Keycode::BackSlash,
+        Keycode::Semicolon,
+        Keycode::Apostrophe,
+        Keycode::Comma,
+        Keycode::Dot,
+        Keycode::Slash,
+    ];
+
+    impl FromStr for Keycode {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            ALL.iter()
+                .find(|key| format!("{:?}", key) == s)
+                .copied()
+                .ok_or_else(|| String::from("failed to parse keycode"))
+        }
+    }
+
+    impl fmt::Display for Keycode {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+}
+
+/// Keycode for a browser `KeyboardEvent.code` (e.g. "KeyA", "Digit1", "ShiftLeft")
+pub fn keycode_for_dom_code(code: &str) -> Option<Keycode> {
+    if let Some(letter) = code.strip_prefix("Key") {
+        return letter.parse().ok();
+    }
+    if let Some(digit) = code.strip_prefix("Digit") {
+        return format!("Key{}", digit).parse().ok();
+    }
+
+    let key = match code {
+        "Backquote" => Keycode::Grave,
+        "Backslash" => Keycode::BackSlash,
+        "BracketLeft" => Keycode::LeftBracket,
+        "BracketRight" => Keycode::RightBracket,
+        "Quote" => Keycode::Apostrophe,
+        "Period" => Keycode::Dot,
+        "ShiftLeft" => Keycode::LShift,
+        "ShiftRight" => Keycode::RShift,
+        "ControlLeft" => Keycode::LControl,
+        "ControlRight" => Keycode::RControl,
+        "AltLeft" => Keycode::LAlt,
+        "AltRight" => Keycode::RAlt,
+        "MetaLeft" => Keycode::LMeta,
+        "MetaRight" => Keycode::RMeta,
+        "ArrowUp" => Keycode::Up,
+        "ArrowDown" => Keycode::Down,
+        "ArrowLeft" => Keycode::Left,
+        "ArrowRight" => Keycode::Right,
+        // The rest share their name: Space, Enter, Tab, F1, Numpad0, Minus, ...
+        _ => return code.parse().ok(),
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keycode_for_dom_code() {
+        assert_eq!(keycode_for_dom_code("KeyA"), Some(Keycode::A));
+        assert_eq!(keycode_for_dom_code("Digit7"), Some(Keycode::Key7));
+        assert_eq!(keycode_for_dom_code("Space"), Some(Keycode::Space));
+        assert_eq!(
+            keycode_for_dom_code("BracketLeft"),
+            Some(Keycode::LeftBracket)
+        );
+        assert_eq!(
+            keycode_for_dom_code("NumpadEnter"),
+            Some(Keycode::NumpadEnter)
+        );
+        assert_eq!(keycode_for_dom_code("ShiftRight"), Some(Keycode::RShift));
+        assert_eq!(keycode_for_dom_code("IntlBackslash"), None);
+    }
+}
@@ -0,0 +1,399 @@
+//! Keyboard layout module
+//!
+//! Key events report where a key sits on the keyboard, named after the US
+//! QWERTY key in that position. This module translates those positions into
+//! the keys of other layouts before mapping lookup, so a Dvorak typist gets
+//! the note mapped to the letter they typed rather than the QWERTY key that
+//! shares its position.
+//!
+//! International layouts also have keys with no US counterpart, such as Ü,
+//! Ñ or dead keys (accents typed before a letter). These are reported by name
+//! (e.g. "Ü", "DeadAcute") so configs can map them like any other key.
+//!
+//! Windows reports keys that already follow the active layout, so the
+//! default `qwerty` (no translation) is right there for the US letters;
+//! international keys still need the layout to be set.
+
+use crate::keycode::Keycode;
+use std::fmt;
+use std::str::FromStr;
+
+/// Physical keyboard layout of the user's keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyboardLayout {
+    /// US QWERTY; keys are used as reported
+    #[default]
+    Qwerty,
+    /// Dvorak Simplified Keyboard
+    Dvorak,
+    /// Colemak
+    Colemak,
+    /// French AZERTY (letters, the keys next to M, Ù and the circumflex dead key)
+    Azerty,
+    /// German QWERTZ
+    German,
+    /// Spanish QWERTY
+    Spanish,
+}
+
+impl KeyboardLayout {
+    /// Key this layout produces at the position of a QWERTY key
+    pub fn translate(&self, key: Keycode) -> Keycode {
+        let table: &[(Keycode, Keycode)] = match self {
+            KeyboardLayout::Qwerty => &[],
+            KeyboardLayout::Dvorak => DVORAK,
+            KeyboardLayout::Colemak => COLEMAK,
+            KeyboardLayout::Azerty => AZERTY,
+            KeyboardLayout::German => GERMAN,
+            KeyboardLayout::Spanish => SPANISH,
+        };
+        table
+            .iter()
+            .find(|(position, _)| *position == key)
+            .map_or(key, |(_, logical)| *logical)
+    }
+
+    /// Name of the international key (one without a US counterpart) this
+    /// layout has at the position of a QWERTY key, if any
+    pub fn international_key(&self, key: Keycode) -> Option<&'static str> {
+        let table: &[(Keycode, &str)] = match self {
+            KeyboardLayout::Azerty => AZERTY_INTERNATIONAL,
+            KeyboardLayout::German => GERMAN_INTERNATIONAL,
+            KeyboardLayout::Spanish => SPANISH_INTERNATIONAL,
+            _ => &[],
+        };
+        table
+            .iter()
+            .find(|(position, _)| *position == key)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl FromStr for KeyboardLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "qwerty" | "us" => Ok(KeyboardLayout::Qwerty),
+            "dvorak" => Ok(KeyboardLayout::Dvorak),
+            "colemak" => Ok(KeyboardLayout::Colemak),
+            "azerty" | "fr" | "french" => Ok(KeyboardLayout::Azerty),
+            "german" | "de" | "qwertz" => Ok(KeyboardLayout::German),
+            "spanish" | "es" => Ok(KeyboardLayout::Spanish),
+            _ => Err(format!("Unknown keyboard layout: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for KeyboardLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            KeyboardLayout::Qwerty => "qwerty",
+            KeyboardLayout::Dvorak => "dvorak",
+            KeyboardLayout::Colemak => "colemak",
+            KeyboardLayout::Azerty => "azerty",
+            KeyboardLayout::German => "german",
+            KeyboardLayout::Spanish => "spanish",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// QWERTY positions and the Dvorak keys found there (keys not listed are unchanged)
+const DVORAK: &[(Keycode, Keycode)] = &[
+    (Keycode::Minus, Keycode::LeftBracket),
+    (Keycode::Equal, Keycode::RightBracket),
+    (Keycode::Q, Keycode::Apostrophe),
+    (Keycode::W, Keycode::Comma),
+    (Keycode::E, Keycode::Dot),
+    (Keycode::R, Keycode::P),
+    (Keycode::T, Keycode::Y),
+    (Keycode::Y, Keycode::F),
+    (Keycode::U, Keycode::G),
+    (Keycode::I, Keycode::C),
+    (Keycode::O, Keycode::R),
+    (Keycode::P, Keycode::L),
+    (Keycode::LeftBracket, Keycode::Slash),
+    (Keycode::RightBracket, Keycode::Equal),
+    (Keycode::S, Keycode::O),
+    (Keycode::D, Keycode::E),
+    (Keycode::F, Keycode::U),
+    (Keycode::G, Keycode::I),
+    (Keycode::H, Keycode::D),
+    (Keycode::J, Keycode::H),
+    (Keycode::K, Keycode::T),
+    (Keycode::L, Keycode::N),
+    (Keycode::Semicolon, Keycode::S),
+    (Keycode::Apostrophe, Keycode::Minus),
+    (Keycode::Z, Keycode::Semicolon),
+    (Keycode::X, Keycode::Q),
+    (Keycode::C, Keycode::J),
+    (Keycode::V, Keycode::K),
+    (Keycode::B, Keycode::X),
+    (Keycode::N, Keycode::B),
+    (Keycode::Comma, Keycode::W),
+    (Keycode::Dot, Keycode::V),
+    (Keycode::Slash, Keycode::Z),
+];
+
+/// QWERTY positions and the Colemak keys found there (keys not listed are unchanged)
+const COLEMAK: &[(Keycode, Keycode)] = &[
+    (Keycode::E, Keycode::F),
+    (Keycode::R, Keycode::P),
+    (Keycode::T, Keycode::G),
+    (Keycode::Y, Keycode::J),
+    (Keycode::U, Keycode::L),
+    (Keycode::I, Keycode::U),
+    (Keycode::O, Keycode::Y),
+    (Keycode::P, Keycode::Semicolon),
+    (Keycode::S, Keycode::R),
+    (Keycode::D, Keycode::S),
+    (Keycode::F, Keycode::T),
+    (Keycode::G, Keycode::D),
+    (Keycode::J, Keycode::N),
+    (Keycode::K, Keycode::E),
+    (Keycode::L, Keycode::I),
+    (Keycode::Semicolon, Keycode::O),
+    (Keycode::N, Keycode::K),
+];
+
+/// QWERTY positions and the AZERTY keys found there (keys not listed are unchanged)
+const AZERTY: &[(Keycode, Keycode)] = &[
+    (Keycode::Q, Keycode::A),
+    (Keycode::A, Keycode::Q),
+    (Keycode::W, Keycode::Z),
+    (Keycode::Z, Keycode::W),
+    (Keycode::Semicolon, Keycode::M),
+    (Keycode::M, Keycode::Comma),
+    (Keycode::Comma, Keycode::Semicolon),
+];
+
+/// QWERTY positions and the German keys found there (keys not listed are unchanged)
+const GERMAN: &[(Keycode, Keycode)] = &[
+    (Keycode::Y, Keycode::Z),
+    (Keycode::Z, Keycode::Y),
+    (Keycode::Slash, Keycode::Minus),
+];
+
+/// QWERTY positions and the Spanish keys found there (keys not listed are unchanged)
+const SPANISH: &[(Keycode, Keycode)] = &[
+    (Keycode::Minus, Keycode::Apostrophe),
+    (Keycode::Slash, Keycode::Minus),
+];
+
+/// Names of the international keys, as used in `mappings`
+///
+/// Keys that produce a US character on another layout (e.g. "+" or "#" on
+/// German) use that character's name instead.
+pub const INTERNATIONAL_KEY_NAMES: &[&str] = &[
+    "Ä",
+    "Ö",
+    "Ü",
+    "ß",
+    "Ñ",
+    "Ç",
+    "Ù",
+    "InvertedExclamation",
+    "DeadAcute",
+    "DeadGrave",
+    "DeadCircumflex",
+];
+
+/// QWERTY positions of the international keys on AZERTY
+const AZERTY_INTERNATIONAL: &[(Keycode, &str)] = &[
+    (Keycode::LeftBracket, "DeadCircumflex"),
+    (Keycode::Apostrophe, "Ù"),
+];
+
+/// QWERTY positions of the international keys on German QWERTZ
+const GERMAN_INTERNATIONAL: &[(Keycode, &str)] = &[
+    (Keycode::Minus, "ß"),
+    (Keycode::Equal, "DeadAcute"),
+    (Keycode::LeftBracket, "Ü"),
+    (Keycode::RightBracket, "Plus"),
+    (Keycode::Semicolon, "Ö"),
+    (Keycode::Apostrophe, "Ä"),
+    (Keycode::BackSlash, "Hash"),
+    (Keycode::Grave, "DeadCircumflex"),
+];
+
+/// QWERTY positions of the international keys on Spanish QWERTY
+const SPANISH_INTERNATIONAL: &[(Keycode, &str)] = &[
+    (Keycode::Equal, "InvertedExclamation"),
+    (Keycode::LeftBracket, "DeadGrave"),
+    (Keycode::RightBracket, "Plus"),
+    (Keycode::Semicolon, "Ñ"),
+    (Keycode::Apostrophe, "DeadAcute"),
+    (Keycode::BackSlash, "Ç"),
+];
+
+/// Rows of a US keyboard as (position of the first key, keys left to right),
+/// with shifted characters in the position of their key
+const KEY_ROWS: &[(f32, &[&str])] = &[
+    (
+        0.0,
+        &[
+            "Grave", "Key1", "Key2", "Key3", "Key4", "Key5", "Key6", "Key7", "Key8", "Key9",
+            "Key0", "Minus", "Equal",
+        ],
+    ),
+    (
+        0.0,
+        &[
+            "Tilde",
+            "Exclamation",
+            "At",
+            "Hash",
+            "Dollar",
+            "Percent",
+            "Caret",
+            "Ampersand",
+            "Asterisk",
+            "LeftParen",
+            "RightParen",
+            "Underscore",
+            "Plus",
+        ],
+    ),
+    (
+        1.5,
+        &[
+            "Q",
+            "W",
+            "E",
+            "R",
+            "T",
+            "Y",
+            "U",
+            "I",
+            "O",
+            "P",
+            "LeftBracket",
+            "RightBracket",
+            "BackSlash",
+        ],
+    ),
+    (11.5, &["LeftBrace", "RightBrace", "Pipe"]),
+    (
+        1.75,
+        &[
+            "A",
+            "S",
+            "D",
+            "F",
+            "G",
+            "H",
+            "J",
+            "K",
+            "L",
+            "Semicolon",
+            "Apostrophe",
+        ],
+    ),
+    (10.75, &["Colon", "DoubleQuote"]),
+    (
+        2.25,
+        &["Z", "X", "C", "V", "B", "N", "M", "Comma", "Dot", "Slash"],
+    ),
+    (9.25, &["LessThan", "GreaterThan", "Question"]),
+];
+
+/// Stereo position (-1.0 left to 1.0 right) of a key by its column on a US
+/// keyboard, running from Q to P; keys further out sit at the edges
+///
+/// Keys off the main block (Space, modifiers, arrows, numpad) have none.
+pub fn key_column_pan(name: &str) -> Option<f32> {
+    let (start, keys) = KEY_ROWS.iter().find(|(_, keys)| keys.contains(&name))?;
+    let column = start + keys.iter().position(|key| *key == name)? as f32;
+    // Q is at 1.5 and P at 10.5
+    Some(((column - 1.5) / 9.0 * 2.0 - 1.0).clamp(-1.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(KeyboardLayout::Qwerty.translate(Keycode::Q), Keycode::Q);
+        assert_eq!(KeyboardLayout::Dvorak.translate(Keycode::S), Keycode::O);
+        assert_eq!(
+            KeyboardLayout::Dvorak.translate(Keycode::Q),
+            Keycode::Apostrophe
+        );
+        assert_eq!(KeyboardLayout::Colemak.translate(Keycode::K), Keycode::E);
+        assert_eq!(KeyboardLayout::Azerty.translate(Keycode::A), Keycode::Q);
+        // Keys outside the table keep their name
+        assert_eq!(
+            KeyboardLayout::Dvorak.translate(Keycode::Space),
+            Keycode::Space
+        );
+    }
+
+    #[test]
+    fn test_layouts_are_permutations() {
+        for table in [DVORAK, COLEMAK, AZERTY] {
+            let mut positions: Vec<_> = table.iter().map(|(position, _)| *position).collect();
+            let mut keys: Vec<_> = table.iter().map(|(_, key)| *key).collect();
+            positions.sort_by_key(|key| format!("{:?}", key));
+            keys.sort_by_key(|key| format!("{:?}", key));
+            assert_eq!(positions, keys);
+        }
+    }
+
+    #[test]
+    fn test_international_keys() {
+        assert_eq!(
+            KeyboardLayout::German.international_key(Keycode::LeftBracket),
+            Some("Ü")
+        );
+        assert_eq!(
+            KeyboardLayout::Spanish.international_key(Keycode::Semicolon),
+            Some("Ñ")
+        );
+        assert_eq!(
+            KeyboardLayout::Qwerty.international_key(Keycode::Semicolon),
+            None
+        );
+        assert_eq!(KeyboardLayout::German.translate(Keycode::Z), Keycode::Y);
+
+        // Every international key is either listed or named after a US character
+        for table in [
+            AZERTY_INTERNATIONAL,
+            GERMAN_INTERNATIONAL,
+            SPANISH_INTERNATIONAL,
+        ] {
+            for (_, name) in table {
+                assert!(
+                    INTERNATIONAL_KEY_NAMES.contains(name)
+                        || crate::keyboard_mapping::SHIFTED_KEY_NAMES.contains(name)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_key_column_pan() {
+        assert_eq!(key_column_pan("Q"), Some(-1.0));
+        assert_eq!(key_column_pan("P"), Some(1.0));
+        assert_eq!(key_column_pan("Key1"), Some(-1.0));
+        assert_eq!(key_column_pan("Slash"), Some(1.0));
+        assert!(key_column_pan("G").unwrap().abs() < 0.1);
+        assert_eq!(key_column_pan("Question"), key_column_pan("Slash"));
+        assert_eq!(key_column_pan("Space"), None);
+        for (_, keys) in KEY_ROWS {
+            assert!(
+                keys.iter()
+                    .all(|key| crate::keyboard_mapping::is_known_key_name(key))
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_layout() {
+        assert_eq!("Dvorak".parse(), Ok(KeyboardLayout::Dvorak));
+        assert_eq!("fr".parse(), Ok(KeyboardLayout::Azerty));
+        assert_eq!("qwertz".parse(), Ok(KeyboardLayout::German));
+        assert!("workman".parse::<KeyboardLayout>().is_err());
+    }
+}
@@ -6,23 +6,80 @@
 //! - Waveform generation
 //! - Real-time audio processing
 
+pub mod actions;
+pub mod active_app;
 pub mod audio_engine;
 pub mod audio_samples;
+pub mod audio_stream;
+pub mod benchmark;
+pub mod capture;
+pub mod config_validation;
+#[cfg(feature = "native")]
+pub mod config_watcher;
+pub mod corpus;
+pub mod earcons;
 pub mod embedded_configs;
+pub mod events;
+pub mod extra_keys;
+pub mod git_hooks;
+pub mod headless;
+pub mod heatmap;
+#[cfg(feature = "http-api")]
+pub mod http_api;
+pub mod input_devices;
+#[cfg(feature = "native")]
+pub mod input_source;
+pub mod instrument;
+pub mod ipc;
+pub mod jam;
 pub mod keyboard_config;
 pub mod keyboard_mapping;
+pub mod keycode;
+pub mod layouts;
+pub mod modulation;
+pub mod notifications;
+pub mod osc;
+pub mod permissions;
+pub mod privacy;
+pub mod profiles;
+pub mod registry;
+pub mod render;
+pub mod rhythm;
+pub mod scales;
 pub mod sequence_detector;
+pub mod soundfont;
+pub mod stats;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod tutor;
+pub mod user_configs;
+pub mod watch;
 pub mod waveforms;
+#[cfg(feature = "web")]
+pub mod web;
 
+#[cfg(feature = "native")]
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use device_query::{DeviceQuery, DeviceState, Keycode};
+#[cfg(feature = "native")]
 use keyboard_mapping::{
-    KeyboardStateTracker, VirtualKeycode, get_frequency_and_volume_with_config_virtual,
+    Chord, KeyboardStateTracker, VirtualKeycode, combo_mappings,
+    get_frequency_and_volume_with_config_virtual, get_mapping_for_virtual, layer_keys,
+    note_overrides_for_virtual,
 };
-use std::sync::{Arc, Mutex};
+#[cfg(feature = "native")]
+use keycode::Keycode;
+#[cfg(feature = "native")]
+use std::collections::HashMap;
+#[cfg(feature = "native")]
+use std::sync::{Arc, Mutex, mpsc};
+#[cfg(feature = "native")]
 use std::thread;
+#[cfg(feature = "native")]
 use std::time::Duration;
 
+/// JACK client name used unless one is configured
+pub const DEFAULT_JACK_CLIENT_NAME: &str = "codebeats";
+
 /// Configuration for the CodeBeats engine
 #[derive(Debug, Clone)]
 pub struct CodeBeatsConfig {
@@ -31,6 +88,66 @@ pub struct CodeBeatsConfig {
     pub volume: f32,
     pub filter_cutoff: f32,
     pub verbose: bool,
+    /// Optional SoundFont preset used instead of the waveform
+    pub soundfont: Option<soundfont::SoundFontSelection>,
+    /// Optional sound file overriding the embedded fart sample
+    pub fart_sample_path: Option<String>,
+    /// Runtime control hotkeys (mute, volume, waveform cycling)
+    pub hotkeys: actions::HotkeyMap,
+    /// Sounds played by name over IPC (`codebeats notify <name>`)
+    pub notifications: notifications::NotificationMap,
+    /// Global shortcut that pauses and resumes the engine (None disables it)
+    pub pause_hotkey: Option<String>,
+    /// Seconds without key presses before the output fades to silence (None disables it)
+    pub idle_timeout: Option<f32>,
+    /// Stay silent while secure input (e.g. a password field) is active
+    pub privacy_mode: bool,
+    /// Keyboard configs to switch to based on the focused application
+    pub profiles: Option<profiles::ProfilesConfig>,
+    /// Applications (names or bundle IDs) in which the engine stays silent
+    pub muted_apps: Vec<String>,
+    /// Pause during calls and screen sharing
+    pub pause_during_calls: bool,
+    /// Local IPC endpoint for editor plugins (None disables it)
+    pub ipc_endpoint: Option<String>,
+    /// Address of the HTTP control API (requires the `http-api` feature)
+    pub http_address: Option<String>,
+    /// Address of the WebSocket note event stream (None disables it)
+    pub events_address: Option<String>,
+    /// UDP address for OSC input (None disables it)
+    pub osc_address: Option<String>,
+    /// Multicast group for networked jam mode (None disables it)
+    pub jam_group: Option<String>,
+    /// Address to stream the audio output from over HTTP (None disables it)
+    pub stream_address: Option<String>,
+    /// Audio host name, e.g. "jack" (case-insensitive; None uses the platform default)
+    pub audio_host: Option<String>,
+    /// JACK client name; the output ports appear as "<name>_out:out_0" and "out_1"
+    pub jack_client_name: String,
+    /// Output device name (case-insensitive substring; None uses the default)
+    pub output_device: Option<String>,
+    /// Audio buffer size in frames (None uses the device default)
+    pub buffer_size: Option<u32>,
+    /// Stream sample rate in Hz (None uses the device default)
+    pub sample_rate: Option<u32>,
+    /// Config (user config name or file path) to watch and reload when its file changes
+    /// (None disables it)
+    pub watch_config: Option<String>,
+    /// Keyboard layout used to translate key positions before mapping lookup
+    pub layout: layouts::KeyboardLayout,
+    /// Configs for the keys of particular input devices (e.g. a macro pad)
+    pub device_configs: Vec<input_devices::DeviceConfig>,
+    /// Poll the keyboard even where an event-driven backend is available
+    pub polling_input: bool,
+    /// Accessibility earcons, whose Caps Lock and error cues play when set
+    /// (the profile's keyboard config is expected as `keyboard_config`)
+    pub earcons: Option<earcons::EarconProfile>,
+    /// Place keys without a `pan` in the stereo field by their keyboard column
+    pub auto_pan: bool,
+    /// Give key presses a velocity from the typing speed
+    pub velocity: bool,
+    /// Random detune and timing jitter of key presses
+    pub humanize: audio_engine::Humanize,
 }
 
 impl Default for CodeBeatsConfig {
@@ -41,40 +158,237 @@ impl Default for CodeBeatsConfig {
             volume: 1.0,
             filter_cutoff: 1200.0,
             verbose: false,
+            soundfont: None,
+            fart_sample_path: None,
+            hotkeys: actions::default_hotkeys(),
+            notifications: notifications::default_notifications(),
+            pause_hotkey: Some(actions::DEFAULT_PAUSE_HOTKEY.to_string()),
+            idle_timeout: None,
+            privacy_mode: true,
+            profiles: None,
+            muted_apps: Vec::new(),
+            pause_during_calls: false,
+            ipc_endpoint: None,
+            http_address: None,
+            events_address: None,
+            osc_address: None,
+            jam_group: None,
+            stream_address: None,
+            audio_host: None,
+            jack_client_name: DEFAULT_JACK_CLIENT_NAME.to_string(),
+            output_device: None,
+            buffer_size: None,
+            sample_rate: None,
+            watch_config: None,
+            layout: layouts::KeyboardLayout::default(),
+            device_configs: Vec::new(),
+            polling_input: false,
+            earcons: None,
+            auto_pan: false,
+            velocity: false,
+            humanize: audio_engine::Humanize::default(),
         }
     }
 }
 
-/// The main CodeBeats engine
+/// Shared logging callback used to forward engine messages
+#[cfg(feature = "native")]
+type LogCallback = Arc<Mutex<dyn FnMut(&str) + Send>>;
+
+/// The main CodeBeats engine (requires the `native` feature)
+#[cfg(feature = "native")]
 pub struct CodeBeatsEngine {
     audio_state: Arc<Mutex<audio_engine::AudioState>>,
-    device_state: DeviceState,
+    /// Keys held down (a keyboard hook where available, else polling)
+    input: Box<dyn input_source::InputSource>,
+    /// Media, lock and PrintScreen keys that `input` doesn't report
+    extra_keys: extra_keys::ExtraKeyMonitor,
+    held_extra_keys: Vec<&'static str>,
+    /// Caps Lock state as tracked from its presses (assumed off at start)
+    caps_lock: bool,
     keyboard_tracker: KeyboardStateTracker,
     sequence_detector: sequence_detector::SequenceDetector,
     config: CodeBeatsConfig,
     is_running: bool,
+    /// Set (e.g. from a Ctrl+C handler) to make run_blocking return
+    shutdown: Arc<std::sync::atomic::AtomicBool>,
+    is_paused: bool,
+    secure_input: Option<privacy::SecureInputMonitor>,
+    secure_input_active: bool,
+    active_app: Option<active_app::ActiveAppMonitor>,
+    last_active_app: Option<String>,
+    in_muted_app: bool,
+    capture_monitor: Option<capture::CaptureMonitor>,
+    capture_active: bool,
+    ipc_server: Option<ipc::IpcServer>,
+    #[cfg(feature = "http-api")]
+    http_server: Option<http_api::HttpServer>,
+    event_server: Option<events::EventServer>,
+    osc_server: Option<osc::OscServer>,
+    jam_session: Option<jam::JamSession>,
+    config_watcher: Option<config_watcher::ConfigWatcher>,
+    /// Tells which device holds a key, for devices with their own config
+    device_monitor: Option<input_devices::DeviceKeyMonitor>,
+    /// Keys pressed on a device with its own config, with that config's index
+    pressed_device_keys: HashMap<Keycode, (usize, VirtualKeycode)>,
+    /// Config selected by the editor's reported language
+    editor_config: Option<String>,
+    /// Config name applied by the current application profile
+    active_profile: Option<String>,
+    /// Keyboard config to return to when no profile applies
+    base_keyboard_config: keyboard_config::KeyboardConfig,
     _stream: Box<dyn std::any::Any + Send>, // Keep the audio stream alive
-    log_callback: Option<Arc<Mutex<dyn FnMut(&str) + Send>>>,
+    log_callback: Option<LogCallback>,
+    output_level: Arc<audio_engine::OutputLevel>,
+    output_history: Arc<audio_engine::OutputHistory>,
+    xruns: Arc<audio_engine::XrunCounter>,
+    stats: Mutex<stats::SessionStats>,
+    /// Front ends listening for note events (e.g. an on-screen keyboard)
+    event_subscribers: Mutex<Vec<mpsc::Sender<events::EngineEvent>>>,
 }
 
+#[cfg(feature = "native")]
 impl CodeBeatsEngine {
     /// Create a new CodeBeats engine with the given configuration
     pub fn new(config: CodeBeatsConfig) -> Result<Self, Box<dyn std::error::Error>> {
-        let audio_state = Self::setup_audio(&config)?;
-        let device_state = DeviceState::new();
-        let keyboard_tracker = KeyboardStateTracker::new();
-        let sequence_detector = sequence_detector::SequenceDetector::new();
+        let output_level = Arc::new(audio_engine::OutputLevel::new());
+        let output_history = Arc::new(audio_engine::OutputHistory::default());
+        let xruns = Arc::new(audio_engine::XrunCounter::new());
+        let audio_state = Self::setup_audio(
+            &config,
+            output_level.clone(),
+            output_history.clone(),
+            xruns.clone(),
+        )?;
+        let (input, input_warning) = input_source::platform_source(config.polling_input);
+        let mut keyboard_tracker = KeyboardStateTracker::new();
+        keyboard_tracker.set_chords(Self::build_chords(&config)?);
+        keyboard_tracker.set_layout(config.layout);
+        keyboard_tracker.set_combos(combo_mappings(&config.keyboard_config));
+        keyboard_tracker.set_layers(layer_keys(&config.keyboard_config));
+        let sequence_detector = Self::build_sequence_detector(&config.keyboard_config)?;
+        let active_app = (config.profiles.is_some() || !config.muted_apps.is_empty())
+            .then(active_app::ActiveAppMonitor::start);
+        let base_keyboard_config = config.keyboard_config.clone();
+        let privacy_monitor = config.privacy_mode.then(privacy::SecureInputMonitor::start);
+        let capture_monitor = config
+            .pause_during_calls
+            .then(capture::CaptureMonitor::start);
+        let ipc_server = match config.ipc_endpoint {
+            Some(ref endpoint) => Some(
+                ipc::IpcServer::start(endpoint)
+                    .map_err(|e| format!("Could not listen on '{}': {}", endpoint, e))?,
+            ),
+            None => None,
+        };
+        #[cfg(feature = "http-api")]
+        let http_server = match config.http_address {
+            Some(ref address) => Some(
+                http_api::HttpServer::start(address)
+                    .map_err(|e| format!("Could not listen on '{}': {}", address, e))?,
+            ),
+            None => None,
+        };
+        #[cfg(not(feature = "http-api"))]
+        if config.http_address.is_some() {
+            return Err("CodeBeats was built without the 'http-api' feature".into());
+        }
+        let osc_server = match config.osc_address {
+            Some(ref address) => Some(
+                osc::OscServer::start(address)
+                    .map_err(|e| format!("Could not listen on '{}': {}", address, e))?,
+            ),
+            None => None,
+        };
+        let jam_session = match config.jam_group {
+            Some(ref group) => Some(
+                jam::JamSession::join(group)
+                    .map_err(|e| format!("Could not join jam group '{}': {}", group, e))?,
+            ),
+            None => None,
+        };
+        let config_watcher = match config.watch_config {
+            Some(ref name) => {
+                let path = user_configs::find_user_config(name).unwrap_or_else(|| name.into());
+                Some(
+                    config_watcher::ConfigWatcher::start(&path)
+                        .map_err(|e| format!("Could not watch '{}': {}", path.display(), e))?,
+                )
+            }
+            None => None,
+        };
+        let (device_monitor, device_warnings) = if config.device_configs.is_empty() {
+            (None, Vec::new())
+        } else {
+            let patterns: Vec<String> = config
+                .device_configs
+                .iter()
+                .map(|device_config| device_config.device.clone())
+                .collect();
+            let (monitor, warnings) = input_devices::DeviceKeyMonitor::start(&patterns);
+            (Some(monitor), warnings)
+        };
+        let event_server = match config.events_address {
+            Some(ref address) => Some(
+                events::EventServer::start(address)
+                    .map_err(|e| format!("Could not listen on '{}': {}", address, e))?,
+            ),
+            None => None,
+        };
 
-        Ok(Self {
+        if let Some(ref path) = config.fart_sample_path {
+            let mut state = audio_state.lock().unwrap();
+            let sample = state
+                .load_sample(path)
+                .map_err(|e| format!("Could not load fart sample '{}': {}", path, e))?;
+            state.set_fart_sample(sample);
+        }
+
+        let engine = Self {
             audio_state,
-            device_state,
+            input,
+            extra_keys: extra_keys::ExtraKeyMonitor::new(),
+            held_extra_keys: Vec::new(),
+            caps_lock: false,
             keyboard_tracker,
             sequence_detector,
             config,
             is_running: false,
+            shutdown: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            is_paused: false,
+            secure_input: privacy_monitor,
+            secure_input_active: false,
+            active_app,
+            last_active_app: None,
+            in_muted_app: false,
+            capture_monitor,
+            capture_active: false,
+            ipc_server,
+            #[cfg(feature = "http-api")]
+            http_server,
+            event_server,
+            osc_server,
+            jam_session,
+            config_watcher,
+            device_monitor,
+            pressed_device_keys: HashMap::new(),
+            editor_config: None,
+            active_profile: None,
+            base_keyboard_config,
             _stream: Box::new(()), // Will be replaced with actual stream
             log_callback: None,
-        })
+            output_level,
+            output_history,
+            xruns,
+            stats: Mutex::new(stats::SessionStats::default()),
+            event_subscribers: Mutex::new(Vec::new()),
+        };
+        engine.load_key_samples();
+        for warning in input_warning.into_iter().chain(device_warnings) {
+            engine.log(&format!("⚠️ {}", warning));
+        }
+
+        Ok(engine)
     }
 
     /// Set a callback function for logging
@@ -85,6 +399,37 @@ impl CodeBeatsEngine {
         self.log_callback = Some(Arc::new(Mutex::new(callback)));
     }
 
+    /// Receive the engine's note events (note on/off, Easter eggs) on a channel,
+    /// e.g. to light up an on-screen keyboard
+    pub fn subscribe_events(&self) -> mpsc::Receiver<events::EngineEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.event_subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Shared output level (RMS and peak of the latest buffer), e.g. for a level meter
+    pub fn output_level(&self) -> Arc<audio_engine::OutputLevel> {
+        self.output_level.clone()
+    }
+
+    /// Shared ring buffer of recent output samples, e.g. for a scope or spectrum display
+    pub fn output_history(&self) -> Arc<audio_engine::OutputHistory> {
+        self.output_history.clone()
+    }
+
+    /// Shared counts of audio dropouts (underruns and overruns) since the engine started
+    pub fn xruns(&self) -> Arc<audio_engine::XrunCounter> {
+        self.xruns.clone()
+    }
+
+    /// Typing and music statistics for the session so far
+    pub fn stats(&self) -> stats::StatsSnapshot {
+        self.stats
+            .lock()
+            .unwrap()
+            .snapshot(std::time::Instant::now())
+    }
+
     /// Log a message either to the callback or to stdout
     fn log(&self, message: &str) {
         if let Some(ref callback) = self.log_callback {
@@ -96,37 +441,114 @@ impl CodeBeatsEngine {
         }
     }
 
+    /// Log a failure, playing the error earcon in accessibility mode
+    fn report_error(&self, message: &str) {
+        self.log(&format!("⚠️ {}", message));
+        if let Some(ref earcons) = self.config.earcons {
+            self.play_earcon(&earcons.error);
+        }
+    }
+
+    /// Play an accessibility cue
+    fn play_earcon(&self, cue: &notifications::Notification) {
+        let mut state = self.audio_state.lock().unwrap();
+        if let Err(e) = cue.schedule("earcon", 0.0, &mut state) {
+            drop(state);
+            self.log(&format!("⚠️ Could not play earcon: {}", e));
+        }
+    }
+
     /// Setup audio system and return audio state
     fn setup_audio(
         config: &CodeBeatsConfig,
+        output_level: Arc<audio_engine::OutputLevel>,
+        output_history: Arc<audio_engine::OutputHistory>,
+        xruns: Arc<audio_engine::XrunCounter>,
     ) -> Result<Arc<Mutex<audio_engine::AudioState>>, Box<dyn std::error::Error>> {
-        let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .ok_or("No output device available")?;
-        let device_config = device.default_output_config()?;
+        let mut host = Self::select_host(config.audio_host.as_deref())?;
+        let device = Self::output_device(&mut host, config)?;
+        let device_config = Self::output_config(&device, config.sample_rate)?;
 
         let sample_rate = device_config.sample_rate().0 as f32;
-        let audio_state = audio_engine::AudioState::new(
+        let audio_stream = match config.stream_address {
+            Some(ref address) => Some(
+                audio_stream::AudioStreamServer::start(
+                    address,
+                    device_config.sample_rate().0,
+                    device_config.channels(),
+                )
+                .map_err(|e| format!("Could not listen on '{}': {}", address, e))?,
+            ),
+            None => None,
+        };
+        let mut audio_state = audio_engine::AudioState::new(
             sample_rate,
-            config.waveform.clone(),
+            config.waveform,
             config.volume,
             config.filter_cutoff,
         );
+        if let Some(ref selection) = config.soundfont {
+            audio_state.set_soundfont_preset(Some(selection.load_preset()?));
+        }
+        audio_state.set_idle_timeout(config.idle_timeout);
+        audio_state.set_velocity_enabled(config.velocity);
+        audio_state.set_humanize(config.humanize);
+        audio_state.set_modulation(
+            config.keyboard_config.vibrato,
+            config.keyboard_config.lfo,
+            config.keyboard_config.mod_matrix.clone(),
+        );
+        audio_state.set_filter_envelope(config.keyboard_config.filter_envelope.clone());
         let audio_state = Arc::new(Mutex::new(audio_state));
         let audio_state_clone = audio_state.clone();
 
-        let stream = device.build_output_stream(
-            &device_config.into(),
-            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                let mut state = audio_state_clone.lock().unwrap();
-                for sample in data.iter_mut() {
-                    *sample = state.generate_sample();
-                }
-            },
-            |err| eprintln!("Audio stream error: {}", err),
-            None,
-        )?;
+        let sample_format = device_config.sample_format();
+        let mut stream_config: cpal::StreamConfig = device_config.into();
+        if let Some(frames) = config.buffer_size {
+            stream_config.buffer_size = cpal::BufferSize::Fixed(frames);
+        }
+
+        let (channels, stream_rate) = (stream_config.channels, stream_config.sample_rate.0);
+        // Start and duration of the last buffer, to spot gaps in playback
+        let mut previous_buffer: Option<(cpal::StreamInstant, Duration)> = None;
+        let render = move |data: &mut [f32], info: &cpal::OutputCallbackInfo| {
+            let render_start = std::time::Instant::now();
+            audio_state_clone
+                .lock()
+                .unwrap()
+                .fill_interleaved(data, usize::from(channels));
+
+            let frames = data.len() / usize::from(channels.max(1));
+            let duration = Duration::from_secs_f64(frames as f64 / f64::from(stream_rate));
+            let playback = info.timestamp().playback;
+            xruns.record(
+                previous_buffer.and_then(|(start, _)| playback.duration_since(&start)),
+                previous_buffer.map_or(duration, |(_, duration)| duration),
+                render_start.elapsed(),
+                duration,
+            );
+            previous_buffer = Some((playback, duration));
+
+            output_level.update(data);
+            output_history.push(data);
+            if let Some(ref audio_stream) = audio_stream {
+                audio_stream.push(data);
+            }
+        };
+
+        // Most devices take f32, but ASIO drivers usually want integer samples
+        let stream = match sample_format {
+            cpal::SampleFormat::F32 => device.build_output_stream(
+                &stream_config,
+                render,
+                |err| eprintln!("Audio stream error: {}", err),
+                None,
+            )?,
+            cpal::SampleFormat::I16 => Self::build_stream::<i16>(&device, &stream_config, render)?,
+            cpal::SampleFormat::I32 => Self::build_stream::<i32>(&device, &stream_config, render)?,
+            cpal::SampleFormat::F64 => Self::build_stream::<f64>(&device, &stream_config, render)?,
+            format => return Err(format!("Unsupported output sample format: {}", format).into()),
+        };
 
         stream.play()?;
 
@@ -136,6 +558,216 @@ impl CodeBeatsEngine {
         Ok(audio_state)
     }
 
+    /// Build an output stream of the device's sample type, rendering in f32 and
+    /// converting when the device wants something else
+    fn build_stream<T>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        mut render: impl FnMut(&mut [f32], &cpal::OutputCallbackInfo) + Send + 'static,
+    ) -> Result<cpal::Stream, cpal::BuildStreamError>
+    where
+        T: cpal::SizedSample + cpal::FromSample<f32> + 'static,
+    {
+        let mut buffer: Vec<f32> = Vec::new();
+        device.build_output_stream(
+            config,
+            move |data: &mut [T], info: &cpal::OutputCallbackInfo| {
+                // Only allocates when the device asks for a bigger buffer than before
+                buffer.resize(data.len(), 0.0);
+                render(&mut buffer, info);
+                for (output, sample) in data.iter_mut().zip(&buffer) {
+                    *output = T::from_sample(*sample);
+                }
+            },
+            |err| eprintln!("Audio stream error: {}", err),
+            None,
+        )
+    }
+
+    /// Audio host with the given name (ignoring case), or the platform default
+    fn select_host(name: Option<&str>) -> Result<cpal::Host, Box<dyn std::error::Error>> {
+        let Some(name) = name else {
+            return Ok(cpal::default_host());
+        };
+        let available = cpal::available_hosts();
+        let id = available
+            .iter()
+            .find(|id| id.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let names: Vec<&str> = available.iter().map(|id| id.name()).collect();
+                format!(
+                    "Audio host '{}' is not available (available: {})",
+                    name,
+                    names.join(", ")
+                )
+            })?;
+        Ok(cpal::host_from_id(*id)?)
+    }
+
+    /// Output device for the config: a named JACK client on the JACK host,
+    /// otherwise the device matching `output_device` or the default one
+    ///
+    /// Without an explicit `audio_host`, the device is also looked up on the
+    /// other hosts the `devices` command shows.
+    fn output_device(
+        host: &mut cpal::Host,
+        config: &CodeBeatsConfig,
+    ) -> Result<cpal::Device, Box<dyn std::error::Error>> {
+        // JACK has no devices to pick; CodeBeats becomes a client with its own ports
+        #[cfg(all(feature = "jack", target_os = "linux"))]
+        if let cpal::platform::HostInner::Jack(jack) = host.as_inner_mut() {
+            return jack
+                .output_device_with_name(&config.jack_client_name)
+                .map(cpal::Device::from)
+                .ok_or_else(|| "Could not connect to the JACK server".into());
+        }
+
+        match config.output_device {
+            Some(ref name) => Self::find_output_device(host, name, config.audio_host.is_none()),
+            None => Ok(host
+                .default_output_device()
+                .ok_or("No output device available")?),
+        }
+    }
+
+    /// Find an output device whose name contains the given text (ignoring case),
+    /// on the given host first, then on the other available hosts if asked to
+    fn find_output_device(
+        host: &cpal::Host,
+        name: &str,
+        other_hosts: bool,
+    ) -> Result<cpal::Device, Box<dyn std::error::Error>> {
+        let name = name.to_lowercase();
+        let find_on = |host: &cpal::Host| {
+            host.output_devices().ok()?.find(|device| {
+                device
+                    .name()
+                    .is_ok_and(|device_name| device_name.to_lowercase().contains(&name))
+            })
+        };
+
+        if let Some(device) = find_on(host) {
+            return Ok(device);
+        }
+        if other_hosts {
+            for host_id in cpal::available_hosts() {
+                if host_id == host.id() {
+                    continue;
+                }
+                if let Some(device) = cpal::host_from_id(host_id).ok().and_then(|h| find_on(&h)) {
+                    return Ok(device);
+                }
+            }
+        }
+        Err(format!("No output device matching '{}'", name).into())
+    }
+
+    /// Stream config for a device: its default, or the closest supported config
+    /// at the requested sample rate
+    fn output_config(
+        device: &cpal::Device,
+        sample_rate: Option<u32>,
+    ) -> Result<cpal::SupportedStreamConfig, Box<dyn std::error::Error>> {
+        let default = device.default_output_config()?;
+        let Some(rate) = sample_rate else {
+            return Ok(default);
+        };
+        if default.sample_rate().0 == rate {
+            return Ok(default);
+        }
+
+        // Prefer the default channel count and sample format at the new rate
+        let mut supported: Vec<_> = device
+            .supported_output_configs()?
+            .filter(|range| (range.min_sample_rate().0..=range.max_sample_rate().0).contains(&rate))
+            .collect();
+        supported.sort_by_key(|range| {
+            (
+                range.channels() != default.channels(),
+                range.sample_format() != default.sample_format(),
+            )
+        });
+        supported
+            .into_iter()
+            .next()
+            .map(|range| range.with_sample_rate(cpal::SampleRate(rate)))
+            .ok_or_else(|| {
+                format!(
+                    "Output device doesn't support a sample rate of {} Hz (default {} Hz)",
+                    rate,
+                    default.sample_rate().0
+                )
+                .into()
+            })
+    }
+
+    /// Build a sequence detector with the built-in and configured sequences
+    fn build_sequence_detector(
+        keyboard_config: &keyboard_config::KeyboardConfig,
+    ) -> Result<sequence_detector::SequenceDetector, Box<dyn std::error::Error>> {
+        let mut detector = sequence_detector::SequenceDetector::new();
+        for sequence in &keyboard_config.sequences {
+            match sequence.keys {
+                keyboard_config::SequenceKeys::Letters(ref letters) => {
+                    detector.add_sequence(&sequence.name, letters)?
+                }
+                keyboard_config::SequenceKeys::Keys(ref names) => {
+                    let keys = names
+                        .iter()
+                        .map(|name| {
+                            name.parse::<Keycode>().map_err(|_| {
+                                format!("Sequence '{}' has unknown key '{}'", sequence.name, name)
+                            })
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    detector.add_key_sequence(&sequence.name, keys)?
+                }
+            }
+        }
+        Ok(detector)
+    }
+
+    /// Parse the hotkeys and the chords declared in the keyboard configuration
+    ///
+    /// Hotkey chords are named after their key combo.
+    fn build_chords(config: &CodeBeatsConfig) -> Result<Vec<Chord>, Box<dyn std::error::Error>> {
+        let hotkeys = config
+            .pause_hotkey
+            .iter()
+            .chain(config.hotkeys.keys())
+            .map(|combo| Chord::parse(combo, combo));
+        let chords = config
+            .keyboard_config
+            .chords
+            .iter()
+            .map(|chord| Chord::parse(&chord.name, &chord.keys));
+
+        Ok(hotkeys.chain(chords).collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Load the one-shot samples referenced by the keyboard configuration
+    /// and the device configs
+    ///
+    /// The files are decoded into a scratch engine without holding the audio
+    /// state's lock, which is only taken to swap the loaded samples in.
+    fn load_key_samples(&self) {
+        let sample_rate = self.audio_state.lock().unwrap().sample_rate();
+        let mut samples = AudioState::new(sample_rate, Waveform::Electronic, 1.0, 1200.0);
+        let mut warnings = samples.load_config_samples(&self.config.keyboard_config);
+        for (index, device_config) in self.config.device_configs.iter().enumerate() {
+            warnings.extend(samples.load_prefixed_samples(
+                &Self::device_key_prefix(index),
+                &device_config.keyboard_config,
+            ));
+        }
+        self.audio_state.lock().unwrap().swap_samples(&mut samples);
+        // The previous samples are freed here, after the lock is released
+        drop(samples);
+        for warning in warnings {
+            self.log(&format!("⚠️ {}", warning));
+        }
+    }
+
     /// Start the CodeBeats engine
     pub fn start(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         if self.is_running {
@@ -152,6 +784,21 @@ impl CodeBeatsEngine {
                 "🔊 Audio settings: volume={:.1}, filter={:.0}Hz",
                 self.config.volume, self.config.filter_cutoff
             ));
+            if let Some(ref selection) = self.config.soundfont {
+                self.log(&format!(
+                    "🎹 SoundFont: {} (preset {})",
+                    selection.path, selection.preset
+                ));
+            }
+            if let Some(ref combo) = self.config.pause_hotkey {
+                self.log(&format!("⌨️ Pause/resume: {}", combo));
+            }
+            let mut hotkeys: Vec<_> = self.config.hotkeys.iter().collect();
+            hotkeys.sort_by(|a, b| a.0.cmp(b.0));
+            for (combo, action) in hotkeys {
+                self.log(&format!("⌨️ Hotkey: {} → {}", combo, action));
+            }
+            self.log(&format!("⌨️ Keyboard input: {}", self.input.name()));
             self.log("💡 Easter egg hint: Try typing 'oppokokoppokosuttenten' for a surprise! 🎉");
         }
 
@@ -163,6 +810,13 @@ impl CodeBeatsEngine {
         self.is_running = false;
         if self.config.verbose {
             self.log("🔇 CodeBeats stopped");
+            let (underruns, overruns) = (self.xruns.underruns(), self.xruns.overruns());
+            if underruns > 0 || overruns > 0 {
+                self.log(&format!(
+                    "⚠️ Audio dropouts: {} underruns, {} overruns",
+                    underruns, overruns
+                ));
+            }
         }
     }
 
@@ -171,6 +825,350 @@ impl CodeBeatsEngine {
         self.is_running
     }
 
+    /// Flag that makes run_blocking return when set, e.g. from a signal handler
+    pub fn shutdown_flag(&self) -> Arc<std::sync::atomic::AtomicBool> {
+        self.shutdown.clone()
+    }
+
+    /// Check whether shutdown was requested through the shutdown flag
+    pub fn shutdown_requested(&self) -> bool {
+        self.shutdown.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Check if the engine is paused
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    /// Check if the output is muted
+    pub fn is_muted(&self) -> bool {
+        self.audio_state.lock().unwrap().is_muted()
+    }
+
+    /// Pause or resume the engine; pausing releases every sounding note
+    pub fn set_paused(&mut self, paused: bool) {
+        if paused == self.is_paused {
+            return;
+        }
+
+        self.is_paused = paused;
+        if paused {
+            self.audio_state.lock().unwrap().release_all();
+            self.log("⏸️ CodeBeats paused");
+        } else {
+            self.log("▶️ CodeBeats resumed");
+        }
+    }
+
+    /// Poll for secure input, silencing sounding notes when it starts
+    /// Returns true while secure input is active
+    fn update_secure_input(&mut self) -> bool {
+        let active = self
+            .secure_input
+            .as_ref()
+            .is_some_and(|monitor| monitor.is_active());
+        if active != self.secure_input_active {
+            self.secure_input_active = active;
+            self.auto_silence_changed(
+                active,
+                if active {
+                    "🔒 Secure input detected - sound suppressed"
+                } else {
+                    "🔓 Secure input ended - sound restored"
+                },
+            );
+        }
+        active
+    }
+
+    /// Poll for calls and screen sharing
+    /// Returns true while the engine should stay paused
+    fn update_capture(&mut self) -> bool {
+        let Some(ref monitor) = self.capture_monitor else {
+            return false;
+        };
+
+        let active = monitor.is_capturing();
+        if active != self.capture_active {
+            self.capture_active = active;
+            self.auto_silence_changed(
+                active,
+                if active {
+                    "📞 Call or screen share in progress - paused"
+                } else {
+                    "📞 Call ended - resumed"
+                },
+            );
+        }
+        active
+    }
+
+    /// Handle pending IPC messages (e.g. from editor plugins)
+    fn handle_ipc_requests(&mut self) {
+        while let Some(request) = self.next_ipc_request() {
+            let reply = self.handle_ipc_message(&request.message);
+            request.reply(reply);
+        }
+    }
+
+    /// Take the next pending request from the IPC socket, OSC input, or HTTP API
+    fn next_ipc_request(&self) -> Option<ipc::IpcRequest> {
+        let request = self
+            .ipc_server
+            .as_ref()
+            .and_then(|server| server.try_next())
+            .or_else(|| {
+                self.osc_server
+                    .as_ref()
+                    .and_then(|server| server.try_next())
+            });
+        #[cfg(feature = "http-api")]
+        let request = request.or_else(|| {
+            self.http_server
+                .as_ref()
+                .and_then(|server| server.try_next())
+        });
+        request
+    }
+
+    /// Apply one IPC message, returning the reply line
+    fn handle_ipc_message(&mut self, message: &ipc::IpcMessage) -> String {
+        let action = match message {
+            ipc::IpcMessage::EditorContext { language, file } => {
+                let name = language
+                    .as_deref()
+                    .and_then(embedded_configs::config_for_language)
+                    .or_else(|| file.as_deref().and_then(embedded_configs::config_for_file));
+                return self.apply_editor_config(name);
+            }
+            ipc::IpcMessage::SetVolume { volume } => {
+                let volume = volume.clamp(0.0, 1.0);
+                self.audio_state.lock().unwrap().set_volume(volume);
+                self.config.volume = volume;
+                return self.status();
+            }
+            ipc::IpcMessage::SetWaveform { waveform } => actions::Action::SetWaveform {
+                waveform: waveform.clone(),
+            },
+            ipc::IpcMessage::SetFilterCutoff { cutoff } => {
+                let mut state = self.audio_state.lock().unwrap();
+                state.set_filter_cutoff(*cutoff);
+                self.config.filter_cutoff = state.get_filter_cutoff();
+                drop(state);
+                return self.status();
+            }
+            ipc::IpcMessage::SetTranspose { semitones } => {
+                self.audio_state.lock().unwrap().set_transpose(*semitones);
+                return self.status();
+            }
+            ipc::IpcMessage::SetMuted { muted } => {
+                self.audio_state.lock().unwrap().set_muted(*muted);
+                return self.status();
+            }
+            ipc::IpcMessage::SetPaused { paused } => {
+                self.set_paused(*paused);
+                return self.status();
+            }
+            ipc::IpcMessage::LoadConfig { config } => actions::Action::LoadConfig {
+                config: config.clone(),
+            },
+            ipc::IpcMessage::Action { action } => action.clone(),
+            ipc::IpcMessage::Notify { name } => {
+                return match self.play_notification(name) {
+                    Ok(()) => format!("ok: played {}", name),
+                    Err(e) => format!("error: {}", e),
+                };
+            }
+            ipc::IpcMessage::PlayMelody { melody, delay } => {
+                return match self.play_melody("melody", melody, *delay) {
+                    Ok(()) => "ok: playing melody".to_string(),
+                    Err(e) => format!("error: {}", e),
+                };
+            }
+            ipc::IpcMessage::Status => return self.status(),
+        };
+
+        match self.run_action(&action) {
+            Ok(()) => self.status(),
+            Err(e) => format!("error: {}", e),
+        }
+    }
+
+    /// One-line summary of the current settings, used as the IPC reply
+    fn status(&self) -> String {
+        format!(
+            "ok: config=\"{}\" waveform={} volume={:.2} muted={} paused={} underruns={} overruns={}",
+            self.config.keyboard_config.description,
+            self.config.waveform,
+            self.config.volume,
+            self.audio_state.lock().unwrap().is_muted(),
+            self.is_paused,
+            self.xruns.underruns(),
+            self.xruns.overruns()
+        )
+    }
+
+    /// Switch to the config for the editor's language, returning the IPC reply
+    fn apply_editor_config(&mut self, name: Option<&str>) -> String {
+        let Some(name) = name else {
+            return "ok: no matching config".to_string();
+        };
+        if self.editor_config.as_deref() == Some(name) {
+            return format!("ok: {}", name);
+        }
+
+        match self.switch_keyboard_config(name) {
+            Ok(()) => {
+                if self.config.verbose {
+                    self.log(&format!("📝 Editor language → {}", name));
+                }
+                self.editor_config = Some(name.to_string());
+                format!("ok: {}", name)
+            }
+            Err(e) => format!("error: {}", e),
+        }
+    }
+
+    /// Release sounding notes when an automatic silence starts and report the change
+    fn auto_silence_changed(&self, silenced: bool, message: &str) {
+        if silenced {
+            self.audio_state.lock().unwrap().release_all();
+        }
+        if self.config.verbose {
+            self.log(message);
+        }
+    }
+
+    /// React to focus changes: apply the mute list and switch keyboard configs
+    fn update_profile(&mut self) {
+        let Some(ref monitor) = self.active_app else {
+            return;
+        };
+
+        let app = monitor.current();
+        if app == self.last_active_app {
+            return;
+        }
+        self.last_active_app = app.clone();
+
+        let Some(app) = app else {
+            return;
+        };
+
+        let muted = self
+            .config
+            .muted_apps
+            .iter()
+            .any(|pattern| profiles::app_matches(&app, pattern));
+        if muted != self.in_muted_app {
+            self.in_muted_app = muted;
+            let status = if muted {
+                "🔇 Muted in"
+            } else {
+                "🔊 Unmuted in"
+            };
+            self.auto_silence_changed(muted, &format!("{} {}", status, app));
+        }
+
+        let Some(ref profiles) = self.config.profiles else {
+            return;
+        };
+        let wanted = profiles.config_for_app(&app).map(str::to_string);
+        if wanted == self.active_profile {
+            return;
+        }
+
+        let result = match wanted {
+            Some(ref name) => self.switch_keyboard_config(name),
+            None => self.apply_keyboard_config(self.base_keyboard_config.clone()),
+        };
+        match result {
+            Ok(()) => {
+                if self.config.verbose {
+                    self.log(&format!(
+                        "🪟 {} → {}",
+                        app,
+                        wanted.as_deref().unwrap_or("startup config")
+                    ));
+                }
+                self.active_profile = wanted;
+            }
+            Err(e) => self.report_error(&format!("Could not switch profile for {}: {}", app, e)),
+        }
+    }
+
+    /// Load a keyboard config (embedded or user config name, or file path) and switch to it
+    pub fn switch_keyboard_config(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.apply_keyboard_config(Self::load_named_keyboard_config(name)?)
+    }
+
+    /// Load a keyboard config by embedded or user config name, or from a file path
+    fn load_named_keyboard_config(
+        name: &str,
+    ) -> Result<keyboard_config::KeyboardConfig, Box<dyn std::error::Error>> {
+        if user_configs::config_exists(name) {
+            user_configs::load_config(name)
+        } else {
+            keyboard_config::KeyboardConfig::load_from_file(name)
+        }
+    }
+
+    /// Switch to another keyboard config, keeping the other settings
+    fn apply_keyboard_config(
+        &mut self,
+        keyboard_config: keyboard_config::KeyboardConfig,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let new_config = CodeBeatsConfig {
+            keyboard_config,
+            ..self.config.clone()
+        };
+        self.update_config(new_config)
+    }
+
+    /// Reload the watched config file after it changes, keeping the current
+    /// mapping if the new contents don't load
+    fn handle_config_changes(&mut self) {
+        let Some(ref mut watcher) = self.config_watcher else {
+            return;
+        };
+        if !watcher.poll() {
+            return;
+        }
+
+        let path = watcher.path().to_path_buf();
+        let Some(name) = self.config.watch_config.clone() else {
+            return;
+        };
+        let keyboard_config = match Self::load_named_keyboard_config(&name) {
+            Ok(keyboard_config) => keyboard_config,
+            Err(e) => {
+                self.report_error(&format!("Could not reload {}: {}", path.display(), e));
+                return;
+            }
+        };
+
+        // An application profile keeps its config; the reload applies once it ends
+        self.base_keyboard_config = keyboard_config.clone();
+        if self.active_profile.is_none()
+            && let Err(e) = self.apply_keyboard_config(keyboard_config)
+        {
+            self.report_error(&format!("Could not reload {}: {}", path.display(), e));
+            return;
+        }
+
+        self.log(&format!("🔄 Reloaded {}", path.display()));
+        let mut state = self.audio_state.lock().unwrap();
+        let playback =
+            audio_samples::SamplePlayback::new(config_watcher::reload_chime(), state.now(), 0.3);
+        state.add_sample_playback(playback);
+    }
+
+    /// Check whether a triggered chord pauses or resumes the engine
+    fn is_pause_chord(&self, name: &str) -> bool {
+        self.config.pause_hotkey.as_deref() == Some(name)
+            || self.config.hotkeys.get(name) == Some(&actions::Action::TogglePause)
+    }
+
     /// Process one iteration of keyboard input
     /// Returns true if the engine should continue running
     pub fn process_input(&mut self) -> bool {
@@ -178,7 +1176,11 @@ impl CodeBeatsEngine {
             return false;
         }
 
-        let current_keys: Vec<Keycode> = self.device_state.get_keys();
+        self.handle_ipc_requests();
+        self.handle_jam_packets();
+        self.handle_config_changes();
+
+        let current_keys: Vec<Keycode> = self.input.held_keys();
 
         // Get the previous keys for comparison
         let prev_keys = self.keyboard_tracker.get_current_keys();
@@ -200,11 +1202,95 @@ impl CodeBeatsEngine {
         // Update keyboard state tracker
         self.keyboard_tracker.update(&pressed_keys, &released_keys);
 
+        let extra_keys = self.extra_keys.held_keys();
+        let pressed_extra_keys: Vec<&'static str> = extra_keys
+            .iter()
+            .filter(|key| !self.held_extra_keys.contains(key))
+            .copied()
+            .collect();
+        let released_extra_keys: Vec<&'static str> = self
+            .held_extra_keys
+            .iter()
+            .filter(|key| !extra_keys.contains(key))
+            .copied()
+            .collect();
+        self.held_extra_keys = extra_keys;
+
+        // Stay silent while typing into password fields
+        if self.update_secure_input() {
+            return true;
+        }
+
+        self.update_profile();
+
+        // Stay silent in applications on the mute list
+        if self.in_muted_app {
+            return true;
+        }
+
+        // Pause during calls and screen sharing
+        if self.update_capture() {
+            return true;
+        }
+
+        // Any key press counts as activity for the idle fade
+        if !pressed_keys.is_empty() || !pressed_extra_keys.is_empty() {
+            self.audio_state.lock().unwrap().register_activity();
+        }
+
+        // Chords run their action instead of playing the key that completed them
+        let triggered_chords = self.keyboard_tracker.check_chords();
+
+        // While paused, only the pause shortcut is honoured
+        if self.is_paused {
+            for chord in &triggered_chords {
+                if self.is_pause_chord(&chord.name) {
+                    self.trigger_chord(&chord.name);
+                }
+            }
+            return true;
+        }
+
+        for chord in &triggered_chords {
+            self.trigger_chord(&chord.name);
+        }
+
         // Handle pressed keys
         for key in pressed_keys {
-            // Check for Easter egg sequence
-            if self.sequence_detector.process_input(key) {
-                self.trigger_easter_egg();
+            // Keys of devices with their own config play only that config
+            if let Some(index) = self
+                .device_monitor
+                .as_ref()
+                .and_then(|monitor| monitor.device_for(key))
+            {
+                self.handle_device_key_press(index, key);
+                continue;
+            }
+            if triggered_chords.iter().any(|chord| chord.contains(key)) {
+                continue;
+            }
+            self.stats
+                .lock()
+                .unwrap()
+                .record_key(&key.to_string(), std::time::Instant::now());
+
+            // Check for Easter egg sequences
+            if let Some(name) = self.sequence_detector.detect(key) {
+                self.trigger_sequence(&name);
+            }
+
+            // In accessibility mode Caps Lock announces its new state instead of a note
+            if key == Keycode::CapsLock
+                && let Some(ref earcons) = self.config.earcons
+            {
+                self.caps_lock = !self.caps_lock;
+                let cue = if self.caps_lock {
+                    &earcons.caps_lock_on
+                } else {
+                    &earcons.caps_lock_off
+                };
+                self.play_earcon(cue);
+                continue;
             }
 
             if let Some(virtual_key) = self.keyboard_tracker.get_virtual_keycode_for_press(key) {
@@ -212,25 +1298,131 @@ impl CodeBeatsEngine {
             }
         }
 
+        for name in pressed_extra_keys {
+            self.stats
+                .lock()
+                .unwrap()
+                .record_key(name, std::time::Instant::now());
+            self.handle_key_press(&VirtualKeycode::Extra(name));
+        }
+
         // Handle released keys
         for key in released_keys {
+            if let Some((index, virtual_key)) = self.pressed_device_keys.remove(&key) {
+                self.handle_device_key_release(index, &virtual_key);
+                continue;
+            }
             if let Some(virtual_key) = self.keyboard_tracker.get_virtual_keycode_for_release(key) {
                 self.handle_key_release(&virtual_key);
             }
         }
+        for name in released_extra_keys {
+            self.handle_key_release(&VirtualKeycode::Extra(name));
+        }
 
         true
     }
 
+    /// Prefix of the key IDs played by a device config, keeping its voices
+    /// and samples apart from the main config's
+    fn device_key_prefix(index: usize) -> String {
+        format!("device{}", index + 1)
+    }
+
+    /// Play a key pressed on a device with its own config
+    fn handle_device_key_press(&mut self, index: usize, key: Keycode) {
+        let virtual_key = VirtualKeycode::Physical(key);
+        if let Some(device_config) = self.config.device_configs.get(index) {
+            self.play_key(
+                &virtual_key,
+                &device_config.keyboard_config,
+                format!("{}:{}", Self::device_key_prefix(index), virtual_key),
+            );
+        }
+        self.pressed_device_keys.insert(key, (index, virtual_key));
+    }
+
+    /// Stop a key released on a device with its own config
+    fn handle_device_key_release(&self, index: usize, virtual_key: &VirtualKeycode) {
+        if let Some(device_config) = self.config.device_configs.get(index) {
+            self.stop_key(
+                virtual_key,
+                &device_config.keyboard_config,
+                format!("{}:{}", Self::device_key_prefix(index), virtual_key),
+            );
+        }
+    }
+
     /// Handle a key press event
     fn handle_key_press(&self, virtual_key: &VirtualKeycode) {
-        let key_id = virtual_key.to_string();
+        self.play_key(
+            virtual_key,
+            &self.config.keyboard_config,
+            virtual_key.to_string(),
+        );
+    }
+
+    /// Handle a key release event
+    fn handle_key_release(&self, virtual_key: &VirtualKeycode) {
+        self.stop_key(
+            virtual_key,
+            &self.config.keyboard_config,
+            virtual_key.to_string(),
+        );
+    }
+
+    /// Start the sound a keyboard config maps to a key, under the given key ID
+    fn play_key(
+        &self,
+        virtual_key: &VirtualKeycode,
+        keyboard_config: &keyboard_config::KeyboardConfig,
+        key_id: String,
+    ) {
+        // Keys with a one-shot sample (e.g. drum kits) play it instead of a note
+        if let Some(mapping) = get_mapping_for_virtual(virtual_key, keyboard_config)
+            && mapping.has_samples()
+        {
+            let mut state = self.audio_state.lock().unwrap();
+            if let Some(actual_volume) = state.start_sample_with_id(&key_id, mapping.volume) {
+                self.emit(events::EngineEvent::NoteOn {
+                    key: key_id.clone(),
+                    note: None,
+                    frequency: None,
+                    volume: actual_volume,
+                });
+                if self.config.verbose {
+                    self.log(&format!(
+                        "🥁 Key: {} → sample ({:.2})",
+                        key_id, actual_volume
+                    ));
+                }
+                return;
+            }
+        }
 
         if let Some((frequency, volume, note)) =
-            get_frequency_and_volume_with_config_virtual(virtual_key, &self.config.keyboard_config)
+            get_frequency_and_volume_with_config_virtual(virtual_key, keyboard_config)
         {
+            let mut overrides =
+                note_overrides_for_virtual(virtual_key, keyboard_config, self.config.auto_pan);
             let mut state = self.audio_state.lock().unwrap();
-            let actual_volume = state.start_note_with_id(&key_id, frequency, volume);
+            state.prepare_key_press(&mut overrides);
+            let actual_volume =
+                state.start_note_with_overrides(&key_id, frequency, volume, &overrides);
+            drop(state);
+            // Nothing sounded, e.g. a SoundFont key no region covers
+            if actual_volume == 0.0 {
+                if self.config.verbose {
+                    self.log(&format!("⚪ Key: {} → {} (not played)", key_id, note));
+                }
+                return;
+            }
+            self.emit(events::EngineEvent::NoteOn {
+                key: key_id.clone(),
+                note: Some(note.clone()),
+                frequency: Some(frequency),
+                volume: actual_volume,
+            });
 
             if self.config.verbose {
                 self.log(&format!(
@@ -243,14 +1435,30 @@ impl CodeBeatsEngine {
         }
     }
 
-    /// Handle a key release event
-    fn handle_key_release(&self, virtual_key: &VirtualKeycode) {
+    /// Stop the sound a keyboard config maps to a key, under the given key ID
+    fn stop_key(
+        &self,
+        virtual_key: &VirtualKeycode,
+        keyboard_config: &keyboard_config::KeyboardConfig,
+        key_id: String,
+    ) {
+        if let Some(mapping) = get_mapping_for_virtual(virtual_key, keyboard_config)
+            && mapping.has_samples()
+        {
+            let mut state = self.audio_state.lock().unwrap();
+            state.release_sample_with_id(&key_id);
+            self.emit(events::EngineEvent::NoteOff { key: key_id });
+            return;
+        }
+
         if let Some((_, _, note)) =
-            get_frequency_and_volume_with_config_virtual(virtual_key, &self.config.keyboard_config)
+            get_frequency_and_volume_with_config_virtual(virtual_key, keyboard_config)
         {
             let mut state = self.audio_state.lock().unwrap();
-            let key_id = virtual_key.to_string();
             state.stop_note_with_id(&key_id);
+            self.emit(events::EngineEvent::NoteOff {
+                key: key_id.clone(),
+            });
 
             if self.config.verbose {
                 self.log(&format!("🔇 Key: {} → {} (released)", key_id, note));
@@ -258,19 +1466,264 @@ impl CodeBeatsEngine {
         }
     }
 
+    /// Count a local event in the stats and send it to subscribers, jam peers, and visualizers
+    fn emit(&self, event: events::EngineEvent) {
+        match event {
+            events::EngineEvent::NoteOn {
+                ref key, ref note, ..
+            } => {
+                let note = note.as_deref().unwrap_or(key);
+                self.stats
+                    .lock()
+                    .unwrap()
+                    .record_note(note, std::time::Instant::now());
+            }
+            events::EngineEvent::EasterEgg { .. } => self.stats.lock().unwrap().record_easter_egg(),
+            events::EngineEvent::NoteOff { .. } => {}
+        }
+        self.event_subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        if let Some(ref session) = self.jam_session {
+            session.send(&event);
+        }
+        if let Some(ref server) = self.event_server {
+            server.send(event);
+        }
+    }
+
+    /// Play the notes received from other jam players
+    fn handle_jam_packets(&mut self) {
+        let silenced =
+            self.is_paused || self.secure_input_active || self.in_muted_app || self.capture_active;
+
+        while let Some(packet) = self
+            .jam_session
+            .as_ref()
+            .and_then(|session| session.try_next())
+        {
+            match packet.event {
+                events::EngineEvent::NoteOn {
+                    ref key,
+                    frequency: Some(frequency),
+                    volume,
+                    ..
+                } if !silenced => {
+                    let Some((frequency, volume)) = jam::playable_note(frequency, volume) else {
+                        continue;
+                    };
+                    let key_id = jam::remote_key_id(packet.peer, key);
+                    self.audio_state
+                        .lock()
+                        .unwrap()
+                        .start_note_with_id(&key_id, frequency, volume);
+                }
+                events::EngineEvent::NoteOff { ref key } => {
+                    let key_id = jam::remote_key_id(packet.peer, key);
+                    self.audio_state.lock().unwrap().stop_note_with_id(&key_id);
+                }
+                events::EngineEvent::EasterEgg { ref name } if !silenced => {
+                    if self.config.verbose {
+                        self.log(&format!("🎸 Jam peer {:x} triggered {}", packet.peer, name));
+                    }
+                    self.play_fart_sample(0.7);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Trigger the sound for a detected sequence
+    fn trigger_sequence(&mut self, name: &str) {
+        self.emit(events::EngineEvent::EasterEgg {
+            name: name.to_string(),
+        });
+
+        let Some(sequence) = self
+            .config
+            .keyboard_config
+            .sequences
+            .iter()
+            .find(|sequence| sequence.name == name)
+            .cloned()
+        else {
+            if name == sequence_detector::DEFAULT_SEQUENCE_NAME {
+                self.trigger_easter_egg();
+            }
+            return;
+        };
+
+        if self.config.verbose {
+            self.log(&format!("🎉 Sequence triggered: {}", sequence.name));
+        }
+
+        // Sequences with an action only make a sound if they name a sample
+        let played = self
+            .audio_state
+            .lock()
+            .unwrap()
+            .start_named_sample(&sequence.name, sequence.volume);
+        if !played && sequence.action.is_none() {
+            self.play_fart_sample(sequence.volume);
+        }
+
+        if let Some(ref action) = sequence.action
+            && let Err(e) = self.run_action(action)
+        {
+            self.report_error(&format!("Could not {}: {}", action, e));
+        }
+    }
+
+    /// Run the action mapped to a hotkey or chord
+    fn trigger_chord(&mut self, name: &str) {
+        if self.config.pause_hotkey.as_deref() == Some(name) {
+            self.set_paused(!self.is_paused);
+            return;
+        }
+
+        if let Some(action) = self.config.hotkeys.get(name).cloned() {
+            if let Err(e) = self.run_action(&action) {
+                self.report_error(&format!("Could not {}: {}", action, e));
+            }
+            return;
+        }
+
+        let Some(chord) = self
+            .config
+            .keyboard_config
+            .chords
+            .iter()
+            .find(|chord| chord.name == name)
+            .cloned()
+        else {
+            return;
+        };
+
+        if self.config.verbose {
+            self.log(&format!(
+                "🎹 Chord triggered: {} ({})",
+                chord.name, chord.keys
+            ));
+        }
+
+        if let Err(e) = self.run_action(&chord.action) {
+            self.report_error(&format!("Could not {}: {}", chord.action, e));
+        }
+    }
+
+    /// Play a notification sound by name
+    pub fn play_notification(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let notification = self
+            .config
+            .notifications
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Unknown notification '{}'", name))?;
+        self.play_melody(name, &notification, 0.0)
+    }
+
+    /// Play a melody `delay` seconds from now; its sample, if any, plays right away
+    pub fn play_melody(
+        &mut self,
+        id: &str,
+        melody: &notifications::Notification,
+        delay: f32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        melody.schedule(id, delay, &mut self.audio_state.lock().unwrap())?;
+        if let Some(ref sample) = melody.sample {
+            self.run_action(&actions::Action::PlaySample {
+                sample: sample.clone(),
+                volume: melody.volume,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Run an engine action (e.g. from a sequence trigger)
+    pub fn run_action(
+        &mut self,
+        action: &actions::Action,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match action {
+            actions::Action::PlaySample { sample, volume } => {
+                let played = self
+                    .audio_state
+                    .lock()
+                    .unwrap()
+                    .start_named_sample(sample, *volume);
+                if !played {
+                    if sample != actions::FART_SAMPLE_NAME {
+                        return Err(format!("Sample '{}' not found", sample).into());
+                    }
+                    self.play_fart_sample(*volume);
+                }
+            }
+            actions::Action::SetWaveform { waveform } => {
+                let waveform: waveforms::Waveform = waveform.parse()?;
+                self.audio_state.lock().unwrap().set_waveform(waveform);
+                self.config.waveform = waveform;
+            }
+            actions::Action::LoadConfig { config } => self.switch_keyboard_config(config)?,
+            actions::Action::ToggleMute => {
+                let mut state = self.audio_state.lock().unwrap();
+                let muted = !state.is_muted();
+                state.set_muted(muted);
+            }
+            actions::Action::AdjustVolume { delta } => {
+                let volume = (self.config.volume + delta).clamp(0.0, 1.0);
+                self.audio_state.lock().unwrap().set_volume(volume);
+                self.config.volume = volume;
+            }
+            actions::Action::CycleWaveform => {
+                let waveform = self.config.waveform.next();
+                self.audio_state.lock().unwrap().set_waveform(waveform);
+                self.config.waveform = waveform;
+            }
+            actions::Action::TogglePause => self.set_paused(!self.is_paused),
+        }
+
+        if self.config.verbose {
+            self.log(&format!("⚙️ Action: {}", action));
+        }
+        Ok(())
+    }
+
+    /// Type text through the current config in real time, e.g. for demos
+    /// Each key sounds for `key_duration`, followed by a gap of half that
+    pub fn play_text(&mut self, text: &str, key_duration: Duration) {
+        for c in text.chars() {
+            if self.shutdown_requested() {
+                break;
+            }
+
+            match keyboard_mapping::virtual_keycode_for_char(c) {
+                Some(key) => {
+                    self.handle_key_press(&key);
+                    thread::sleep(key_duration);
+                    self.handle_key_release(&key);
+                }
+                None => thread::sleep(key_duration),
+            }
+            thread::sleep(key_duration / 2);
+        }
+    }
+
     /// Trigger the Easter egg fart sound
     fn trigger_easter_egg(&self) {
         if self.config.verbose {
             self.log("🎉 Easter egg triggered: おっぽこ　こっぽこ　すってんてん! 💨");
         }
 
+        self.play_fart_sample(0.7); // Easter egg volume
+    }
+
+    /// Play the fart sample at the given volume
+    fn play_fart_sample(&self, volume: f32) {
         let mut state = self.audio_state.lock().unwrap();
         if let Some(fart_sample) = state.get_fart_sample() {
-            let playback = audio_samples::SamplePlayback::new(
-                fart_sample.clone(),
-                state.get_global_time(),
-                0.7, // Easter egg volume
-            );
+            let playback =
+                audio_samples::SamplePlayback::new(fart_sample.clone(), state.now(), volume);
             state.add_sample_playback(playback);
         } else if self.config.verbose {
             self.log("⚠️ Fart sample not available for Easter egg");
@@ -285,10 +1738,10 @@ impl CodeBeatsEngine {
 
         // Main loop
         loop {
-            if !self.process_input() {
+            if self.shutdown_requested() || !self.process_input() {
                 break;
             }
-            thread::sleep(Duration::from_millis(10));
+            self.input.wait(Duration::from_millis(10));
         }
 
         self.stop();
@@ -296,17 +1749,78 @@ impl CodeBeatsEngine {
     }
 
     /// Update the engine configuration
+    ///
+    /// Sound settings (waveform and its envelope, volume, filter, SoundFont, samples)
+    /// and key handling apply right away; the output device, buffer size and network
+    /// endpoints keep the values the engine was created with.
     pub fn update_config(
         &mut self,
         new_config: CodeBeatsConfig,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        // Update audio state with new settings
+        let sequence_detector = Self::build_sequence_detector(&new_config.keyboard_config)?;
+        let chords = Self::build_chords(&new_config)?;
+        // Load anything that can fail before changing the running state
+        let soundfont_preset = match new_config.soundfont {
+            Some(ref selection) if new_config.soundfont != self.config.soundfont => {
+                Some(Some(selection.load_preset()?))
+            }
+            None if self.config.soundfont.is_some() => Some(None),
+            _ => None,
+        };
+        let fart_sample = match new_config.fart_sample_path {
+            Some(ref path) if new_config.fart_sample_path != self.config.fart_sample_path => Some(
+                audio_samples::AudioSample::load_from_file(path)
+                    .map_err(|e| format!("Could not load fart sample '{}': {}", path, e))?,
+            ),
+            None if self.config.fart_sample_path.is_some() => {
+                audio_samples::AudioSample::load_from_bytes(embedded_configs::get_fart_audio_data())
+                    .ok()
+            }
+            _ => None,
+        };
+
+        // Update audio state with new settings; volume and filter changes ramp
+        // so sounding notes don't click
         if let Ok(mut state) = self.audio_state.lock() {
-            state.set_waveform(new_config.waveform.clone());
+            state.set_waveform(new_config.waveform);
             state.set_volume(new_config.volume);
+            // The filter stays bypassed until a cutoff other than the initial one is asked for
+            if new_config.filter_cutoff != self.config.filter_cutoff {
+                state.set_filter_cutoff(new_config.filter_cutoff);
+            }
+            state.set_idle_timeout(new_config.idle_timeout);
+            state.set_velocity_enabled(new_config.velocity);
+            state.set_humanize(new_config.humanize);
+            state.set_modulation(
+                new_config.keyboard_config.vibrato,
+                new_config.keyboard_config.lfo,
+                new_config.keyboard_config.mod_matrix.clone(),
+            );
+            state.set_filter_envelope(new_config.keyboard_config.filter_envelope.clone());
+            if let Some(preset) = soundfont_preset {
+                state.set_soundfont_preset(preset);
+            }
+            if let Some(sample) = fart_sample {
+                let sample_rate = state.sample_rate() as u32;
+                state.set_fart_sample(sample.resampled(sample_rate));
+            }
+        }
+
+        if new_config.privacy_mode != self.secure_input.is_some() {
+            self.secure_input = new_config
+                .privacy_mode
+                .then(privacy::SecureInputMonitor::start);
         }
 
+        self.sequence_detector = sequence_detector;
+        self.keyboard_tracker.set_chords(chords);
+        self.keyboard_tracker.set_layout(new_config.layout);
+        self.keyboard_tracker
+            .set_combos(combo_mappings(&new_config.keyboard_config));
+        self.keyboard_tracker
+            .set_layers(layer_keys(&new_config.keyboard_config));
         self.config = new_config;
+        self.load_key_samples();
         Ok(())
     }
 
@@ -319,60 +1833,17 @@ impl CodeBeatsEngine {
     pub fn load_keyboard_config(
         path: &str,
     ) -> Result<keyboard_config::KeyboardConfig, Box<dyn std::error::Error>> {
-        keyboard_config::KeyboardConfig::load_from_file(path).map_err(|e| e.into())
+        keyboard_config::KeyboardConfig::load_from_file(path)
     }
 
-    /// Get available language configurations
+    /// Get available language configurations (embedded and user configs)
     pub fn discover_language_configs() -> Vec<String> {
-        let mut configs = Vec::new();
-
-        let mut possible_dirs = vec!["language_configs".to_string()];
-
-        // For macOS app bundles, add executable-relative paths
-        if cfg!(target_os = "macos") {
-            if let Ok(exe_path) = std::env::current_exe() {
-                if let Some(exe_dir) = exe_path.parent() {
-                    // Contents/MacOS/ -> Contents/Resources/language_configs
-                    if let Some(contents_dir) = exe_dir.parent() {
-                        let resources_config =
-                            contents_dir.join("Resources").join("language_configs");
-                        if let Some(path_str) = resources_config.to_str() {
-                            possible_dirs.insert(0, path_str.to_string());
-                        }
-                    }
-                }
-            }
-        }
-
-        // Add more fallback paths
-        possible_dirs.push("Contents/Resources/language_configs".to_string());
-        possible_dirs.push("../Resources/language_configs".to_string());
-
-        for dir in possible_dirs {
-            if let Ok(entries) = std::fs::read_dir(&dir) {
-                for entry in entries.flatten() {
-                    if let Some(path) = entry.path().to_str() {
-                        if path.ends_with(".json") {
-                            if let Some(name) = entry.path().file_stem() {
-                                if let Some(name_str) = name.to_str() {
-                                    if !configs.contains(&name_str.to_string()) {
-                                        configs.push(name_str.to_string());
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                break; // Use first directory that exists
-            }
-        }
-
-        configs.sort();
-        configs
+        user_configs::all_config_names()
     }
 }
 
 // Re-export commonly used types for external use
+pub use actions::Action;
 pub use audio_engine::AudioState;
 pub use keyboard_config::KeyboardConfig;
 pub use waveforms::Waveform;
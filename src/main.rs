@@ -19,7 +19,37 @@
 //! along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use clap::{Parser, Subcommand};
-use codebeats::{CodeBeatsConfig, CodeBeatsEngine, Waveform, embedded_configs};
+use codebeats::audio_engine::{self, Humanize};
+use codebeats::benchmark::{self, BenchmarkOptions};
+use codebeats::corpus::CorpusAnalysis;
+use codebeats::earcons;
+use codebeats::git_hooks;
+use codebeats::heatmap;
+use codebeats::input_devices::{self, DeviceConfig};
+use codebeats::ipc::{self, IpcMessage};
+use codebeats::keyboard_config::ConfigFormat;
+use codebeats::layouts::KeyboardLayout;
+use codebeats::notifications;
+use codebeats::permissions;
+use codebeats::registry::{self, RegistryIndex};
+use codebeats::render::{self, RenderOptions};
+#[cfg(feature = "tui")]
+use codebeats::rhythm;
+use codebeats::scales::ScaleConfig;
+use codebeats::soundfont::{PresetId, SoundFont, SoundFontSelection};
+use codebeats::stats::{self, StatsSnapshot};
+#[cfg(feature = "tui")]
+use codebeats::tutor;
+use codebeats::watch;
+use codebeats::{
+    CodeBeatsConfig, CodeBeatsEngine, Waveform, actions, config_validation, embedded_configs,
+    user_configs,
+};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+/// Text typed by the `demo` subcommand
+const DEMO_TEXT: &str = "fn main() {\n    println!(\"Hello, CodeBeats!\");\n}\n";
 
 #[derive(Parser)]
 #[command(
@@ -31,47 +61,464 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    /// Language configuration to use
-    #[arg(short, long, default_value = "general")]
+    /// Language configuration to use (embedded or user config name, or JSON/TOML config file)
+    #[arg(short, long, env = "CODEBEATS_CONFIG", default_value = "general")]
     language: String,
 
+    /// Reload the config file when it changes (a config file or user config)
+    #[arg(long, env = "CODEBEATS_WATCH")]
+    watch: bool,
+
     /// Waveform type to use
-    #[arg(short, long)]
+    #[arg(short, long, env = "CODEBEATS_WAVEFORM")]
     waveform: Option<String>,
 
     /// Master volume (0.0-1.0)
-    #[arg(short = 'v', long = "volume", default_value = "1.0")]
+    #[arg(
+        short = 'v',
+        long = "volume",
+        env = "CODEBEATS_VOLUME",
+        default_value = "1.0"
+    )]
     volume: f32,
 
+    /// Place each key in the stereo field by its keyboard column (Q left, P right),
+    /// unless its mapping sets a `pan`
+    #[arg(long, env = "CODEBEATS_AUTO_PAN")]
+    auto_pan: bool,
+
+    /// Play fast typing louder and brighter than slow, deliberate presses
+    #[arg(long, env = "CODEBEATS_VELOCITY")]
+    velocity: bool,
+
+    /// Detune each key press by a random amount up to this many cents either way (0-50)
+    #[arg(
+        long,
+        env = "CODEBEATS_HUMANIZE_DETUNE",
+        value_name = "CENTS",
+        default_value_t = 0.0
+    )]
+    humanize_detune: f32,
+
+    /// Delay each key press by a random amount up to this many milliseconds (0-50)
+    #[arg(
+        long,
+        env = "CODEBEATS_HUMANIZE_TIMING",
+        value_name = "MS",
+        default_value_t = 0.0
+    )]
+    humanize_timing: f32,
+
+    /// SoundFont (.sf2) file to use as the sound source instead of a waveform
+    #[arg(long, env = "CODEBEATS_SOUNDFONT")]
+    soundfont: Option<String>,
+
+    /// SoundFont preset as bank:program (or just program)
+    #[arg(long, env = "CODEBEATS_PRESET", default_value = "0:0")]
+    preset: PresetId,
+
+    /// Sound file to use instead of the built-in fart sample
+    #[arg(long, env = "CODEBEATS_FART_SAMPLE")]
+    fart_sample: Option<String>,
+
+    /// JSON file mapping key combos to actions, replacing the default hotkeys
+    #[arg(long, env = "CODEBEATS_HOTKEYS")]
+    hotkeys: Option<String>,
+
+    /// JSON file of notification sounds, added to the built-in ones
+    #[arg(long, env = "CODEBEATS_NOTIFICATIONS")]
+    notifications: Option<String>,
+
+    /// Accessibility mode: key categories get distinct sounds, with cues for Caps Lock and errors
+    #[arg(long, env = "CODEBEATS_ACCESSIBILITY")]
+    accessibility: bool,
+
+    /// JSON file changing the accessibility earcons (see `codebeats earcons`)
+    #[arg(long, env = "CODEBEATS_EARCONS")]
+    earcons: Option<String>,
+
+    /// Global shortcut that pauses and resumes CodeBeats ("none" to disable)
+    #[arg(long, env = "CODEBEATS_PAUSE_HOTKEY", default_value = actions::DEFAULT_PAUSE_HOTKEY)]
+    pause_hotkey: String,
+
+    /// Fade to silence after this many seconds without typing
+    #[arg(long, env = "CODEBEATS_IDLE_TIMEOUT", value_name = "SECONDS")]
+    idle_timeout: Option<f32>,
+
+    /// Keep playing while secure input (e.g. a password field) is active
+    #[arg(long, env = "CODEBEATS_NO_PRIVACY")]
+    no_privacy: bool,
+
+    /// JSON file mapping focused applications to keyboard configs
+    #[arg(long, env = "CODEBEATS_PROFILES")]
+    profiles: Option<String>,
+
+    /// Application name or bundle ID in which to stay silent (repeatable)
+    #[arg(
+        long = "mute-app",
+        env = "CODEBEATS_MUTE_APPS",
+        value_name = "APP",
+        value_delimiter = ','
+    )]
+    muted_apps: Vec<String>,
+
+    /// Play a different config for the keys of one input device, e.g.
+    /// "Macro Pad=drums" (repeatable; Linux only, see `input-devices`)
+    #[arg(
+        long = "device-config",
+        env = "CODEBEATS_DEVICE_CONFIGS",
+        value_name = "DEVICE=CONFIG",
+        value_delimiter = ','
+    )]
+    device_configs: Vec<String>,
+
+    /// Pause during calls (a call app using the microphone) and Zoom screen sharing
+    #[arg(long, env = "CODEBEATS_PAUSE_ON_CALLS")]
+    pause_on_calls: bool,
+
+    /// Listen for editor plugins reporting the edited file's language
+    #[arg(long, env = "CODEBEATS_EDITOR_IPC")]
+    editor_ipc: bool,
+
+    /// IPC endpoint (Unix socket path, or host:port on Windows)
+    #[arg(long, env = "CODEBEATS_IPC_ENDPOINT", default_value_t = codebeats::ipc::default_endpoint())]
+    ipc_endpoint: String,
+
+    /// Serve the HTTP control API (built with the `http-api` feature)
+    #[arg(long, env = "CODEBEATS_HTTP_API", value_name = "ADDR", num_args = 0..=1, default_missing_value = ipc::DEFAULT_HTTP_ADDRESS)]
+    http_api: Option<String>,
+
+    /// Stream note events as JSON over a local WebSocket
+    #[arg(long, env = "CODEBEATS_EVENTS", value_name = "ADDR", num_args = 0..=1, default_missing_value = codebeats::events::DEFAULT_EVENTS_ADDRESS)]
+    events: Option<String>,
+
+    /// Accept OSC control messages over UDP
+    #[arg(long, env = "CODEBEATS_OSC", value_name = "ADDR", num_args = 0..=1, default_missing_value = codebeats::osc::DEFAULT_OSC_ADDRESS)]
+    osc: Option<String>,
+
+    /// Jam with other instances on the LAN via UDP multicast (experimental)
+    #[arg(long, env = "CODEBEATS_JAM", value_name = "GROUP", num_args = 0..=1, default_missing_value = codebeats::jam::DEFAULT_JAM_GROUP)]
+    jam: Option<String>,
+
+    /// Stream the audio output as WAV over HTTP for remote listeners
+    #[arg(long, env = "CODEBEATS_STREAM", value_name = "ADDR", num_args = 0..=1, default_missing_value = codebeats::audio_stream::DEFAULT_STREAM_ADDRESS)]
+    stream: Option<String>,
+
+    /// Audio host to play through (jack with the jack feature on Linux, asio with the asio
+    /// feature on Windows; defaults to the platform's default host)
+    #[arg(long, env = "CODEBEATS_HOST", value_name = "NAME")]
+    host: Option<String>,
+
+    /// JACK client name; the output ports appear as <NAME>_out:out_0 and out_1
+    #[arg(long, env = "CODEBEATS_JACK_NAME", value_name = "NAME", default_value = codebeats::DEFAULT_JACK_CLIENT_NAME)]
+    jack_name: String,
+
+    /// Output device to play on (name or part of it; defaults to the system default).
+    /// Without --host, every host listed by `devices` is searched
+    #[arg(long, env = "CODEBEATS_DEVICE", value_name = "NAME")]
+    device: Option<String>,
+
+    /// Audio buffer size in frames (smaller means lower latency)
+    #[arg(long, env = "CODEBEATS_BUFFER_SIZE", value_name = "FRAMES")]
+    buffer_size: Option<u32>,
+
+    /// Stream sample rate in Hz (defaults to the output device's rate); loaded samples
+    /// are resampled to match
+    #[arg(long, env = "CODEBEATS_SAMPLE_RATE", value_name = "HZ")]
+    sample_rate: Option<u32>,
+
+    /// Keyboard layout you type on (qwerty, dvorak, colemak, azerty, german, spanish), so
+    /// mappings follow the keys typed rather than the QWERTY key positions
+    #[arg(long, env = "CODEBEATS_LAYOUT", default_value = "qwerty")]
+    layout: KeyboardLayout,
+
+    /// Poll the keyboard instead of using the Windows low-level keyboard hook
+    #[arg(long, env = "CODEBEATS_POLLING_INPUT")]
+    polling_input: bool,
+
     /// Enable verbose logging
-    #[arg(long)]
+    #[arg(long, env = "CODEBEATS_VERBOSE")]
     verbose: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// List available language configurations
-    ListConfigs,
+    ListConfigs {
+        /// Print the list as JSON (name and description) for other tools
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write the embedded language configurations to a directory for customization
+    ExportConfigs {
+        /// Directory to write the JSON files to
+        dir: String,
+        /// Overwrite files that already exist
+        #[arg(long)]
+        force: bool,
+    },
+    /// Merge a personal override config onto a base config
+    MergeConfig {
+        /// Base config (embedded name or JSON config file)
+        base: String,
+        /// JSON file with the overrides; `null` removes a key
+        overlay: String,
+        /// File to write the merged config to (prints it if omitted)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Check a config for mistakes, reporting each with its line and column
+    ValidateConfig {
+        /// Config to check (embedded name or JSON/TOML config file)
+        config: String,
+    },
+    /// Print a JSON Schema of the config format for editor autocompletion
+    Schema,
+    /// Print the built-in accessibility earcons as JSON, a starting point for earcons.json
+    Earcons,
+    /// Build a config from how often keys are typed in a codebase
+    AnalyzeCorpus {
+        /// Directory (or file) to analyze
+        path: String,
+        /// Scale to take notes from, as root and mode (e.g. "D dorian")
+        #[arg(long, default_value = "C pentatonic")]
+        scale: ScaleConfig,
+        /// File to write the config to (prints it if omitted)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Browse the community config registry
+    Search {
+        /// Text to look for in names, descriptions and tags (lists everything if omitted)
+        query: Option<String>,
+        /// Registry index URL (or local path)
+        #[arg(long, env = "CODEBEATS_REGISTRY", default_value = registry::DEFAULT_REGISTRY_URL)]
+        registry: String,
+    },
+    /// Install a config from the community registry into the user config directory
+    Install {
+        /// Name of the config in the registry
+        name: String,
+        /// Replace an installed config of the same name
+        #[arg(long)]
+        force: bool,
+        /// Registry index URL (or local path)
+        #[arg(long, env = "CODEBEATS_REGISTRY", default_value = registry::DEFAULT_REGISTRY_URL)]
+        registry: String,
+    },
     /// List available waveforms
     ListWaveforms,
+    /// List audio hosts and output devices
+    Devices,
+    /// List keyboards and other key input devices (for --device-config)
+    InputDevices,
+    /// Show statistics from past sessions
+    Stats,
+    /// Summarize activity per day over a recent period
+    Report {
+        /// How far back to report, e.g. 12h, 7d or 2w
+        #[arg(long, default_value = "7d")]
+        since: String,
+    },
+    /// Write a keyboard heatmap and note histogram of the most recent session as HTML
+    Heatmap {
+        /// HTML file to write
+        #[arg(short, long, default_value = "heatmap.html")]
+        output: String,
+    },
+    /// Play a short scripted melody through the selected waveform and config
+    Demo,
+    /// Render typed text to a WAV file without an audio device
+    Render {
+        /// Text file to type through the selected config
+        #[arg(long)]
+        text: String,
+        /// WAV file to write
+        #[arg(long)]
+        out: String,
+        /// Output sample rate in Hz
+        #[arg(long, default_value_t = render::DEFAULT_SAMPLE_RATE)]
+        sample_rate: u32,
+        /// Seconds each key is held down
+        #[arg(long, default_value_t = 0.14)]
+        key_duration: f32,
+    },
+    /// List the presets in a SoundFont file
+    ListPresets {
+        /// SoundFont (.sf2) file to inspect
+        file: String,
+    },
+    /// Measure synthesis time per buffer against the real-time budget
+    Benchmark {
+        /// Notes held down at the same time
+        #[arg(long, default_value_t = 16)]
+        voices: usize,
+        /// Frames per buffer
+        #[arg(long, default_value_t = 512)]
+        buffer_size: usize,
+        /// Buffers measured per waveform
+        #[arg(long, default_value_t = 500)]
+        buffers: usize,
+        /// Sample rate in Hz
+        #[arg(long, default_value_t = 44100)]
+        sample_rate: u32,
+    },
     /// Show version information
     Version,
+    /// Run headless, accepting `ctl` commands on the IPC endpoint
+    Daemon,
+    /// Run with a terminal display of notes, levels, and hotkeys
+    #[cfg(feature = "tui")]
+    Tui,
+    /// Play a rhythm game: type along with a target pattern and get scored on timing
+    #[cfg(feature = "tui")]
+    Rhythm {
+        /// Pattern: quarters, eighths, offbeats, clave, swing, gallop
+        #[arg(long, default_value = "quarters")]
+        pattern: String,
+        /// Tempo in beats per minute
+        #[arg(long, default_value_t = 90.0)]
+        bpm: f32,
+        /// Bars to play after the count-in
+        #[arg(long, default_value_t = 8)]
+        bars: u32,
+        /// Keep clicking on every beat after the count-in
+        #[arg(long)]
+        metronome: bool,
+    },
+    /// Practice typing a text, hearing a consonant tone per correct key and a dissonant one per mistake
+    #[cfg(feature = "tui")]
+    Practice {
+        /// File with the text to type (a short code snippet by default)
+        file: Option<String>,
+    },
+    /// Control a running daemon
+    Ctl {
+        #[command(subcommand)]
+        command: CtlCommand,
+    },
+    /// Install or remove Git hooks that play notification sounds on commit and push
+    GitHook {
+        #[command(subcommand)]
+        command: GitHookCommand,
+    },
+    /// Play a notification sound on a running daemon (e.g. build-success, test-fail)
+    Notify {
+        /// Notification name, built in or from notifications.json
+        name: String,
+    },
+    /// Run a build command, playing its warnings and errors on a running daemon
+    Watch {
+        /// Command and its arguments (e.g. `codebeats watch -- cargo check`)
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum GitHookCommand {
+    /// Add pre-commit, post-commit and pre-push hooks to the current repository
+    Install {
+        /// Replace existing hooks that CodeBeats didn't write
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove the hooks CodeBeats installed
+    Uninstall,
 }
 
-fn list_configs() {
+#[derive(Subcommand)]
+enum CtlCommand {
+    /// Set the master volume (0.0-1.0)
+    SetVolume { volume: f32 },
+    /// Switch to another waveform
+    SetWaveform { waveform: String },
+    /// Switch to an embedded config or JSON config file
+    LoadConfig { config: String },
+    /// Mute the output
+    Mute,
+    /// Unmute the output
+    Unmute,
+    /// Toggle mute
+    ToggleMute,
+    /// Pause the engine
+    Pause,
+    /// Resume the engine
+    Resume,
+    /// Show the current settings
+    Status,
+}
+
+impl CtlCommand {
+    fn to_message(&self) -> IpcMessage {
+        match self {
+            CtlCommand::SetVolume { volume } => IpcMessage::SetVolume { volume: *volume },
+            CtlCommand::SetWaveform { waveform } => IpcMessage::SetWaveform {
+                waveform: waveform.clone(),
+            },
+            CtlCommand::LoadConfig { config } => IpcMessage::LoadConfig {
+                config: config.clone(),
+            },
+            CtlCommand::Mute => IpcMessage::SetMuted { muted: true },
+            CtlCommand::Unmute => IpcMessage::SetMuted { muted: false },
+            CtlCommand::ToggleMute => IpcMessage::Action {
+                action: actions::Action::ToggleMute,
+            },
+            CtlCommand::Pause => IpcMessage::SetPaused { paused: true },
+            CtlCommand::Resume => IpcMessage::SetPaused { paused: false },
+            CtlCommand::Status => IpcMessage::Status,
+        }
+    }
+}
+
+fn list_configs(json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let user_configs = user_configs::user_config_names();
+
+    if json {
+        let configs: Vec<_> = user_configs::all_config_names()
+            .into_iter()
+            .map(|name| {
+                serde_json::json!({
+                    "name": name,
+                    "description": config_description(&name),
+                    "user": user_configs.contains(&name),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&configs)?);
+        return Ok(());
+    }
+
     println!("Available language configurations:");
     println!();
 
-    let configs = embedded_configs::get_config_names();
-    for name in configs {
-        if let Some(description) = embedded_configs::get_config_description(name) {
-            println!("  {:<15} - {}", name, description);
+    for name in user_configs::all_config_names() {
+        let marker = if user_configs.contains(&name) {
+            "*"
+        } else {
+            " "
+        };
+        if let Some(description) = config_description(&name) {
+            println!("{} {:<15} - {}", marker, name, description);
         } else {
-            println!("  {}", name);
+            println!("{} {}", marker, name);
         }
     }
     println!();
+    if !user_configs.is_empty()
+        && let Some(dir) = user_configs::configs_dir()
+    {
+        println!("* = user config in {}", dir.display());
+    }
     println!("Usage: codebeats --language <config_name>");
+    Ok(())
+}
+
+/// Description of an embedded or user config
+fn config_description(name: &str) -> Option<String> {
+    user_configs::load_config(name)
+        .ok()
+        .map(|config| config.description)
 }
 
 fn list_waveforms() {
@@ -87,6 +534,10 @@ fn list_waveforms() {
         ("triangle", "Smooth triangular wave"),
         ("fart", "Realistic fart sound synthesis"),
         ("bass", "Deep bass with rich low frequencies"),
+        (
+            "harmonic",
+            "Overtone series (harmonic:<rolloff> sets how fast overtones fade)",
+        ),
     ];
 
     for (name, description) in &waveforms {
@@ -96,6 +547,569 @@ fn list_waveforms() {
     println!("Usage: codebeats --waveform <waveform_name>");
 }
 
+fn list_devices() -> Result<(), Box<dyn std::error::Error>> {
+    use cpal::traits::{DeviceTrait, HostTrait};
+
+    for host_id in cpal::available_hosts() {
+        let host = match cpal::host_from_id(host_id) {
+            Ok(host) => host,
+            Err(e) => {
+                println!("{} output devices:", host_id.name());
+                println!();
+                println!("  (host unavailable: {})", e);
+                println!();
+                continue;
+            }
+        };
+        let default_name = host
+            .default_output_device()
+            .and_then(|device| device.name().ok());
+
+        println!("{} output devices:", host_id.name());
+        println!();
+        let devices = match host.output_devices() {
+            Ok(devices) => devices.collect::<Vec<_>>(),
+            Err(e) => {
+                println!("  (could not enumerate devices: {})", e);
+                println!();
+                continue;
+            }
+        };
+        if devices.is_empty() {
+            println!("  (none)");
+        }
+        for device in devices {
+            let name = device.name().unwrap_or_else(|_| "<unknown>".to_string());
+            let marker = if Some(&name) == default_name.as_ref() {
+                "*"
+            } else {
+                " "
+            };
+            let format = match device.default_output_config() {
+                Ok(config) => format!("{} Hz, {} ch", config.sample_rate().0, config.channels()),
+                Err(e) => format!("unavailable: {}", e),
+            };
+            println!("{} {:<40} - {}", marker, name, format);
+        }
+        println!();
+    }
+
+    println!("* = default device");
+    println!("Usage: codebeats [--host <host>] --device <name>");
+    Ok(())
+}
+
+fn list_input_devices() {
+    let devices = input_devices::list_devices();
+    if devices.is_empty() {
+        println!("No input devices found (per-device configs are supported on Linux only).");
+        return;
+    }
+
+    println!("Input devices:");
+    println!();
+    for device in devices {
+        println!("  {:<40} - {}", device.name, device.path.display());
+    }
+    println!();
+    println!("Usage: codebeats --device-config \"<name>=<config>\"");
+    println!("Reading devices needs access to /dev/input (e.g. membership of the 'input' group).");
+}
+
+/// Parse a `--device-config` value ("DEVICE=CONFIG") and load its config
+fn load_device_config(spec: &str) -> Result<DeviceConfig, Box<dyn std::error::Error>> {
+    let (device, name) = spec
+        .rsplit_once('=')
+        .ok_or_else(|| format!("Invalid device config '{}' (expected DEVICE=CONFIG)", spec))?;
+    let keyboard_config = if user_configs::config_exists(name) {
+        user_configs::load_config(name)
+    } else {
+        CodeBeatsEngine::load_keyboard_config(name)
+    }
+    .map_err(|e| format!("Could not load config '{}' for '{}': {}", name, device, e))?;
+
+    Ok(DeviceConfig {
+        device: device.trim().to_string(),
+        keyboard_config,
+    })
+}
+
+fn print_stats(stats: &StatsSnapshot) {
+    let minutes = stats.session_seconds / 60.0;
+    println!("  Time played:      {:.0} min", minutes);
+    println!("  Keystrokes:       {}", stats.total_keys);
+    println!("  Notes played:     {}", stats.total_notes);
+    if let Some((note, count)) = stats.top_notes.first() {
+        println!("  Most common note: {} ({} times)", note, count);
+    }
+    println!("  Easter eggs:      {}", stats.easter_eggs);
+}
+
+fn show_stats() -> Result<(), Box<dyn std::error::Error>> {
+    let path = stats::history_path().ok_or("Could not determine the data directory")?;
+    let history = stats::StatsHistory::load_from_file(&path)?;
+    if history.sessions.is_empty() {
+        println!("No sessions recorded yet ({})", path.display());
+        return Ok(());
+    }
+
+    println!("All {} sessions:", history.sessions.len());
+    print_stats(&history.totals());
+    if let Some(last) = history.sessions.last() {
+        println!();
+        println!("Last session:");
+        print_stats(&last.stats);
+    }
+    Ok(())
+}
+
+fn show_report(since: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let period = stats::parse_period(since)?;
+    let path = stats::history_path().ok_or("Could not determine the data directory")?;
+    let history = stats::StatsHistory::load_from_file(&path)?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let days = history.daily_summaries(now.saturating_sub(period.as_secs()));
+    if days.is_empty() {
+        println!("No sessions in the last {} ({})", since, path.display());
+        return Ok(());
+    }
+
+    println!("Activity in the last {} (UTC days):", since);
+    println!(
+        "  {:<10}  {:>8}  {:>10}  {:>7}  {:<16}  {:<12}",
+        "Date", "Sessions", "Keystrokes", "Minutes", "Config", "Waveform"
+    );
+    for day in &days {
+        println!(
+            "  {:<10}  {:>8}  {:>10}  {:>7.0}  {:<16}  {:<12}",
+            stats::format_day(day.day),
+            day.sessions,
+            day.keystrokes,
+            day.minutes,
+            day.top_config.as_deref().unwrap_or("-"),
+            day.top_waveform.as_deref().unwrap_or("-")
+        );
+    }
+    println!(
+        "  {:<10}  {:>8}  {:>10}  {:>7.0}",
+        "Total",
+        days.iter().map(|day| day.sessions).sum::<usize>(),
+        days.iter().map(|day| day.keystrokes).sum::<u64>(),
+        days.iter().map(|day| day.minutes).sum::<f32>()
+    );
+    Ok(())
+}
+
+fn write_heatmap(output: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path = stats::history_path().ok_or("Could not determine the data directory")?;
+    let history = stats::StatsHistory::load_from_file(&path)?;
+    let last = history.sessions.last().ok_or_else(|| {
+        format!(
+            "No sessions recorded yet ({}); sessions are saved when CodeBeats exits",
+            path.display()
+        )
+    })?;
+
+    std::fs::write(
+        output,
+        heatmap::render_html(&last.stats, "CodeBeats session heatmap"),
+    )
+    .map_err(|e| format!("Could not write '{}': {}", output, e))?;
+    println!("✓ Wrote the heatmap of the last session to {}", output);
+    Ok(())
+}
+
+/// Print the session summary and append the session to the history file
+fn finish_session(stats: StatsSnapshot, config: &str, waveform: Waveform) {
+    println!();
+    println!("🎵 Session summary:");
+    print_stats(&stats);
+
+    if stats.total_keys == 0 {
+        return;
+    }
+    let Some(path) = stats::history_path() else {
+        return;
+    };
+    let result = stats::StatsHistory::load_from_file(&path).and_then(|mut history| {
+        history.sessions.push(stats::SessionRecord {
+            config: Some(config.to_string()),
+            waveform: Some(waveform.to_string()),
+            ..stats::SessionRecord::ended_now(stats)
+        });
+        history.save_to_file(&path)
+    });
+    if let Err(e) = result {
+        eprintln!("⚠️  Could not save stats to {}: {}", path.display(), e);
+    }
+}
+
+fn export_configs(dir: &str, force: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let written = embedded_configs::export_configs(std::path::Path::new(dir), force)
+        .map_err(|e| format!("Could not export configs to '{}': {}", dir, e))?;
+
+    let skipped = embedded_configs::get_config_names().len() - written.len();
+    println!("✓ Wrote {} configurations to {}", written.len(), dir);
+    if skipped > 0 {
+        println!(
+            "  Kept {} existing files (use --force to overwrite them)",
+            skipped
+        );
+    }
+    println!(
+        "Usage: codebeats --language {}/<name>.json",
+        dir.trim_end_matches('/')
+    );
+    Ok(())
+}
+
+fn merge_config(
+    base: &str,
+    overlay: &str,
+    output: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let base_json = if user_configs::config_exists(base) {
+        user_configs::config_source(base)?
+    } else {
+        std::fs::read_to_string(base).map_err(|e| format!("Could not read '{}': {}", base, e))?
+    };
+    let overlay_json = std::fs::read_to_string(overlay)
+        .map_err(|e| format!("Could not read '{}': {}", overlay, e))?;
+
+    let merged = codebeats::KeyboardConfig::merge_json(&base_json, &overlay_json)
+        .map_err(|e| format!("Could not merge '{}' onto '{}': {}", overlay, base, e))?;
+    let json = serde_json::to_string_pretty(&merged)?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, json + "\n")?;
+            println!("✓ Merged '{}' onto '{}' into {}", overlay, base, path);
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+fn validate_config(config: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let (label, content, format) = if embedded_configs::config_exists(config) {
+        // User overrides are checked merged onto the embedded config
+        (
+            config.to_string(),
+            user_configs::config_source(config)?,
+            ConfigFormat::Json,
+        )
+    } else {
+        let path = user_configs::find_user_config(config).unwrap_or_else(|| config.into());
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Could not read '{}': {}", path.display(), e))?;
+        (
+            path.display().to_string(),
+            content,
+            ConfigFormat::from_path(&path),
+        )
+    };
+
+    let issues = config_validation::validate(&content, format);
+    for issue in &issues {
+        println!("{}:{}", label, issue);
+    }
+
+    let errors = issues.iter().filter(|issue| issue.is_error()).count();
+    let warnings = issues.len() - errors;
+    if errors > 0 {
+        return Err(format!(
+            "'{}' has {} errors and {} warnings",
+            config, errors, warnings
+        )
+        .into());
+    }
+    println!("✓ '{}' is valid ({} warnings)", config, warnings);
+    Ok(())
+}
+
+fn analyze_corpus(
+    path: &str,
+    scale: &ScaleConfig,
+    output: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let analysis = CorpusAnalysis::from_path(path)
+        .map_err(|e| format!("Could not analyze '{}': {}", path, e))?;
+    if analysis.key_counts.is_empty() {
+        return Err(format!("No text files found in '{}'", path).into());
+    }
+
+    let description = format!("Generated from {} ({} {:?})", path, scale.root, scale.mode);
+    let json = serde_json::to_string_pretty(&analysis.to_config(scale, &description))?;
+
+    match output {
+        Some(output) => {
+            std::fs::write(output, json + "\n")?;
+            println!(
+                "✓ Mapped {} keys from {} files into {}",
+                analysis.key_counts.len(),
+                analysis.files,
+                output
+            );
+            println!("Usage: codebeats --language {}", output);
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+fn search_registry(query: Option<&str>, url: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let index = RegistryIndex::fetch(url)?;
+    let entries = index.search(query.unwrap_or(""));
+    if entries.is_empty() {
+        println!("No configs found in the registry");
+        return Ok(());
+    }
+
+    println!("Community configurations:");
+    println!();
+    for entry in entries {
+        match entry.author {
+            Some(ref author) => println!(
+                "  {:<15} - {} (by {})",
+                entry.name, entry.description, author
+            ),
+            None => println!("  {:<15} - {}", entry.name, entry.description),
+        }
+    }
+    println!();
+    println!("Usage: codebeats install <config_name>");
+    Ok(())
+}
+
+fn install_config(name: &str, force: bool, url: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let index = RegistryIndex::fetch(url)?;
+    let entry = index.find(name).ok_or_else(|| {
+        format!(
+            "'{}' is not in the registry. Use 'codebeats search' to browse it.",
+            name
+        )
+    })?;
+    let dir = user_configs::configs_dir().ok_or("Could not determine the config directory")?;
+
+    let path = index.install(entry, &dir, force)?;
+    println!("✓ Installed '{}' to {}", name, path.display());
+    println!("Usage: codebeats --language {}", name);
+    Ok(())
+}
+
+fn run_benchmark(options: &BenchmarkOptions) {
+    println!(
+        "Synthesis benchmark: {} voices, {} frames at {} Hz, {} buffers per waveform",
+        options.voices, options.buffer_size, options.sample_rate, options.buffers
+    );
+    println!();
+    println!(
+        "  {:<12} {:>10} {:>10} {:>10} {:>10}",
+        "waveform", "mean µs", "worst µs", "budget µs", "headroom"
+    );
+
+    for result in benchmark::run_all(options) {
+        println!(
+            "  {:<12} {:>10.1} {:>10.1} {:>10.1} {:>9.1}x{}",
+            result.waveform.to_string(),
+            result.mean.as_secs_f64() * 1e6,
+            result.worst.as_secs_f64() * 1e6,
+            result.budget.as_secs_f64() * 1e6,
+            result.headroom(),
+            if result.is_realtime() {
+                ""
+            } else {
+                "  ⚠️ over budget"
+            }
+        );
+    }
+    println!();
+}
+
+fn list_presets(file: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let soundfont = SoundFont::load_from_file(file)?;
+
+    println!("Presets in {}:", file);
+    println!();
+    for preset in soundfont.presets() {
+        println!("  {:<8} - {}", preset.id.to_string(), preset.name);
+    }
+    println!();
+    println!(
+        "Usage: codebeats --soundfont {} --preset <bank:program>",
+        file
+    );
+    Ok(())
+}
+
+fn run_ctl(endpoint: &str, message: &IpcMessage) -> Result<(), Box<dyn std::error::Error>> {
+    let reply = ipc::send(endpoint, message).map_err(|e| {
+        format!(
+            "Could not reach CodeBeats at '{}' ({}). Is 'codebeats daemon' running?",
+            endpoint, e
+        )
+    })?;
+
+    match reply.strip_prefix("error: ") {
+        Some(error) => Err(error.into()),
+        None => {
+            println!("{}", reply.strip_prefix("ok: ").unwrap_or(&reply));
+            Ok(())
+        }
+    }
+}
+
+/// Run a command, echoing its output and playing a motif per diagnostic
+///
+/// Motifs play one after another rather than piling up; ones that would
+/// start more than `MAX_MOTIF_BACKLOG` from now are skipped.
+fn run_watch(endpoint: &str, command: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    const MAX_MOTIF_BACKLOG: Duration = Duration::from_secs(3);
+
+    let mut child = std::process::Command::new(&command[0])
+        .args(&command[1..])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Could not run '{}': {}", command[0], e))?;
+
+    // Read both streams on their own threads so neither blocks the other
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let stdout = child.stdout.take().expect("piped stdout");
+    let stderr = child.stderr.take().expect("piped stderr");
+    for (stream, is_stderr) in [
+        (Box::new(stdout) as Box<dyn std::io::Read + Send>, false),
+        (Box::new(stderr), true),
+    ] {
+        let sender = sender.clone();
+        std::thread::spawn(move || {
+            use std::io::BufRead;
+            for line in std::io::BufReader::new(stream)
+                .lines()
+                .map_while(Result::ok)
+            {
+                if sender.send((is_stderr, line)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(sender);
+
+    let mut warnings = 0;
+    let mut errors = 0;
+    let mut free_at = std::time::Instant::now();
+    let mut reachable = true;
+    let mut play = |melody: notifications::Notification| {
+        let now = std::time::Instant::now();
+        let delay = free_at.saturating_duration_since(now);
+        if !reachable || delay > MAX_MOTIF_BACKLOG {
+            return;
+        }
+        free_at = now + delay + Duration::from_secs_f32(watch::melody_length(&melody));
+        let message = IpcMessage::PlayMelody {
+            melody,
+            delay: delay.as_secs_f32(),
+        };
+        if let Err(e) = ipc::send(endpoint, &message) {
+            eprintln!(
+                "codebeats: could not reach CodeBeats at '{}' ({}); is 'codebeats daemon' running?",
+                endpoint, e
+            );
+            reachable = false;
+        }
+    };
+
+    for (is_stderr, line) in receiver {
+        if is_stderr {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+        match watch::parse_diagnostic(&line) {
+            Some(watch::DiagnosticKind::Warning) => {
+                warnings += 1;
+                play(watch::diagnostic_motif(
+                    watch::DiagnosticKind::Warning,
+                    warnings,
+                ));
+            }
+            Some(watch::DiagnosticKind::Error) => {
+                errors += 1;
+                play(watch::diagnostic_motif(
+                    watch::DiagnosticKind::Error,
+                    errors,
+                ));
+            }
+            None => {}
+        }
+    }
+
+    let status = child.wait()?;
+    if status.success() {
+        play(watch::resolution_chord());
+    }
+    eprintln!("codebeats: {} error(s), {} warning(s)", errors, warnings);
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "tui")]
+fn print_rhythm_summary(game: &rhythm::RhythmGame, pattern: &rhythm::RhythmPattern, bpm: f32) {
+    use rhythm::Judgement;
+    println!("🥁 Rhythm: {} at {:.0} bpm", pattern.name, bpm);
+    println!(
+        "   Score {} ({:.0}% accuracy), best combo {}",
+        game.score(),
+        game.accuracy(),
+        game.max_combo()
+    );
+    println!(
+        "   Perfect {} • Good {} • Ok {} • Miss {} • off-beat presses {}",
+        game.count(Judgement::Perfect),
+        game.count(Judgement::Good),
+        game.count(Judgement::Ok),
+        game.count(Judgement::Miss),
+        game.stray_presses()
+    );
+    if let Some(error) = game.mean_error() {
+        let direction = if error >= 0.0 { "late" } else { "early" };
+        println!("   On average {:.0} ms {}", error.abs() * 1000.0, direction);
+    }
+}
+
+fn run_git_hook(command: &GitHookCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let hooks_dir = git_hooks::hooks_dir(&std::env::current_dir()?)?;
+    match command {
+        GitHookCommand::Install { force } => {
+            let codebeats = std::env::current_exe()?;
+            for line in git_hooks::install(&hooks_dir, &codebeats, *force)? {
+                println!("{}", line);
+            }
+            println!("The hooks play sounds on a running 'codebeats daemon' (or 'codebeats tui')");
+        }
+        GitHookCommand::Uninstall => {
+            let removed = git_hooks::uninstall(&hooks_dir)?;
+            if removed.is_empty() {
+                println!("No CodeBeats hooks installed in {}", hooks_dir.display());
+            }
+            for hook in removed {
+                println!("Removed {}", hook);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Path of a settings file in the user config directory, if it exists
+fn user_setting(name: &str) -> Option<String> {
+    user_configs::settings_file(name).map(|path| path.display().to_string())
+}
+
 fn show_version() {
     println!("CodeBeats v{}", env!("CARGO_PKG_VERSION"));
     println!("Programming Music Simulator");
@@ -107,32 +1121,175 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Handle subcommands
     match &cli.command {
-        Some(Commands::ListConfigs) => {
-            list_configs();
+        Some(Commands::ListConfigs { json }) => {
+            return list_configs(*json);
+        }
+        Some(Commands::ExportConfigs { dir, force }) => {
+            return export_configs(dir, *force);
+        }
+        Some(Commands::MergeConfig {
+            base,
+            overlay,
+            output,
+        }) => {
+            return merge_config(base, overlay, output.as_deref());
+        }
+        Some(Commands::ValidateConfig { config }) => {
+            return validate_config(config);
+        }
+        Some(Commands::Schema) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&config_validation::schema())?
+            );
             return Ok(());
         }
+        Some(Commands::Earcons) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&earcons::EarconProfile::default())?
+            );
+            return Ok(());
+        }
+        Some(Commands::AnalyzeCorpus {
+            path,
+            scale,
+            output,
+        }) => {
+            return analyze_corpus(path, scale, output.as_deref());
+        }
+        Some(Commands::Search { query, registry }) => {
+            return search_registry(query.as_deref(), registry);
+        }
+        Some(Commands::Install {
+            name,
+            force,
+            registry,
+        }) => {
+            return install_config(name, *force, registry);
+        }
         Some(Commands::ListWaveforms) => {
             list_waveforms();
             return Ok(());
         }
+        Some(Commands::Devices) => {
+            return list_devices();
+        }
+        Some(Commands::InputDevices) => {
+            list_input_devices();
+            return Ok(());
+        }
+        Some(Commands::Stats) => {
+            return show_stats();
+        }
+        Some(Commands::Report { since }) => {
+            return show_report(since);
+        }
+        Some(Commands::Heatmap { output }) => {
+            return write_heatmap(output);
+        }
+        Some(Commands::ListPresets { file }) => {
+            return list_presets(file);
+        }
+        Some(Commands::Benchmark {
+            voices,
+            buffer_size,
+            buffers,
+            sample_rate,
+        }) => {
+            run_benchmark(&BenchmarkOptions {
+                sample_rate: *sample_rate,
+                buffer_size: *buffer_size,
+                voices: *voices,
+                buffers: *buffers,
+            });
+            return Ok(());
+        }
         Some(Commands::Version) => {
             show_version();
             return Ok(());
         }
-        None => {
+        Some(Commands::Ctl { command }) => {
+            return run_ctl(&cli.ipc_endpoint, &command.to_message());
+        }
+        Some(Commands::GitHook { command }) => {
+            return run_git_hook(command);
+        }
+        Some(Commands::Notify { name }) => {
+            return run_ctl(
+                &cli.ipc_endpoint,
+                &IpcMessage::Notify { name: name.clone() },
+            );
+        }
+        Some(Commands::Watch { command }) => {
+            return run_watch(&cli.ipc_endpoint, command);
+        }
+        #[cfg(feature = "tui")]
+        Some(Commands::Tui) => {
+            // Continue to main program with the terminal UI
+        }
+        #[cfg(feature = "tui")]
+        Some(Commands::Rhythm { pattern, .. }) => {
+            if rhythm::find_pattern(pattern).is_none() {
+                let names: Vec<&str> = rhythm::PATTERNS.iter().map(|p| p.name).collect();
+                return Err(format!(
+                    "Unknown rhythm pattern '{}' (available: {})",
+                    pattern,
+                    names.join(", ")
+                )
+                .into());
+            }
+            // Continue to main program with the rhythm game
+        }
+        #[cfg(feature = "tui")]
+        Some(Commands::Practice { .. }) => {
+            // Continue to main program with the typing tutor
+        }
+        Some(Commands::Daemon) | Some(Commands::Demo) | Some(Commands::Render { .. }) | None => {
             // Continue to main program
         }
     }
 
+    let daemon = matches!(cli.command, Some(Commands::Daemon));
+
     // Validate and clamp volume
     let volume = cli.volume.clamp(0.0, 1.0);
     if cli.volume != volume && cli.verbose {
         println!("⚠️  Volume clamped to {:.1} (valid range: 0.0-1.0)", volume);
     }
 
+    // Validate and clamp humanize amounts
+    let max_timing_ms = audio_engine::MAX_HUMANIZE_TIMING * 1000.0;
+    let humanize_detune = cli
+        .humanize_detune
+        .clamp(0.0, audio_engine::MAX_HUMANIZE_DETUNE);
+    if cli.humanize_detune != humanize_detune && cli.verbose {
+        println!(
+            "⚠️  Humanize detune clamped to {:.0} cents (valid range: 0-{:.0})",
+            humanize_detune,
+            audio_engine::MAX_HUMANIZE_DETUNE
+        );
+    }
+    let humanize_timing = cli.humanize_timing.clamp(0.0, max_timing_ms);
+    if cli.humanize_timing != humanize_timing && cli.verbose {
+        println!(
+            "⚠️  Humanize timing clamped to {:.0} ms (valid range: 0-{:.0})",
+            humanize_timing, max_timing_ms
+        );
+    }
+
     // Load keyboard configuration
-    let keyboard_config = if embedded_configs::config_exists(&cli.language) {
-        match embedded_configs::load_config(&cli.language) {
+    let mut watch_config = None;
+    let keyboard_config = if user_configs::config_exists(&cli.language) {
+        if cli.watch && user_configs::find_user_config(&cli.language).is_some() {
+            watch_config = Some(cli.language.clone());
+        } else if cli.watch {
+            eprintln!(
+                "⚠️  --watch has no effect on the embedded '{}' configuration; pass a config file or add a user config to watch",
+                cli.language
+            );
+        }
+        match user_configs::load_config(&cli.language) {
             Ok(config) => {
                 if cli.verbose {
                     println!(
@@ -148,12 +1305,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 embedded_configs::get_default_config()?
             }
         }
+    } else if std::path::Path::new(&cli.language).is_file() {
+        if cli.watch {
+            watch_config = Some(cli.language.clone());
+        }
+        CodeBeatsEngine::load_keyboard_config(&cli.language)
+            .map_err(|e| format!("Could not load config '{}': {}", cli.language, e))?
     } else {
         eprintln!("✗ Configuration '{}' not found.", cli.language);
         eprintln!("Use 'codebeats list-configs' to see available configurations.");
         std::process::exit(1);
     };
 
+    let device_configs = cli
+        .device_configs
+        .iter()
+        .map(|spec| load_device_config(spec))
+        .collect::<Result<Vec<_>, _>>()?;
+
     // Parse waveform with priority: CLI arg > config file > default
     let waveform = if let Some(cli_waveform) = &cli.waveform {
         // User explicitly specified waveform via CLI
@@ -183,6 +1352,58 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         );
     }
 
+    // Settings files in the user config directory apply unless given on the command line
+    let profiles_path = cli
+        .profiles
+        .clone()
+        .or_else(|| user_setting("profiles.json"));
+    let hotkeys_path = cli.hotkeys.clone().or_else(|| user_setting("hotkeys.json"));
+    let notifications_path = cli
+        .notifications
+        .clone()
+        .or_else(|| user_setting("notifications.json"));
+
+    let profiles = match profiles_path {
+        Some(ref path) => Some(
+            codebeats::profiles::ProfilesConfig::load_from_file(path)
+                .map_err(|e| format!("Could not load profiles '{}': {}", path, e))?,
+        ),
+        None => None,
+    };
+
+    // Mute list from the command line plus the profiles file
+    let mut muted_apps = cli.muted_apps.clone();
+    if let Some(ref profiles) = profiles {
+        muted_apps.extend(profiles.muted_apps.iter().cloned());
+    }
+
+    let hotkeys = match hotkeys_path {
+        Some(ref path) => actions::load_hotkeys(path)
+            .map_err(|e| format!("Could not load hotkeys '{}': {}", path, e))?,
+        None => actions::default_hotkeys(),
+    };
+
+    let notifications = match notifications_path {
+        Some(ref path) => notifications::load_notifications(path)
+            .map_err(|e| format!("Could not load notifications '{}': {}", path, e))?,
+        None => notifications::default_notifications(),
+    };
+
+    // Accessibility mode plays its category sounds instead of the language config
+    let earcons = if cli.accessibility {
+        let earcons_path = cli.earcons.clone().or_else(|| user_setting("earcons.json"));
+        Some(match earcons_path {
+            Some(ref path) => earcons::load_earcons(path)
+                .map_err(|e| format!("Could not load earcons '{}': {}", path, e))?,
+            None => earcons::EarconProfile::default(),
+        })
+    } else {
+        None
+    };
+    let keyboard_config = earcons
+        .as_ref()
+        .map_or(keyboard_config, |profile| profile.keyboard_config());
+
     // Create configuration
     let config = CodeBeatsConfig {
         waveform,
@@ -190,16 +1411,152 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         volume,
         filter_cutoff: 1200.0,
         verbose: cli.verbose,
+        soundfont: cli.soundfont.map(|path| SoundFontSelection {
+            path,
+            preset: cli.preset,
+        }),
+        fart_sample_path: cli.fart_sample,
+        hotkeys,
+        notifications,
+        idle_timeout: cli.idle_timeout,
+        privacy_mode: !cli.no_privacy,
+        profiles,
+        muted_apps,
+        pause_during_calls: cli.pause_on_calls,
+        http_address: cli.http_api,
+        events_address: cli.events,
+        osc_address: cli.osc,
+        jam_group: cli.jam,
+        stream_address: cli.stream,
+        audio_host: cli.host,
+        jack_client_name: cli.jack_name,
+        output_device: cli.device,
+        buffer_size: cli.buffer_size,
+        sample_rate: cli.sample_rate,
+        watch_config,
+        layout: cli.layout,
+        device_configs,
+        polling_input: cli.polling_input,
+        earcons,
+        auto_pan: cli.auto_pan,
+        velocity: cli.velocity,
+        humanize: Humanize {
+            detune_cents: humanize_detune,
+            timing_jitter: humanize_timing / 1000.0,
+        },
+        ipc_endpoint: (daemon || cli.editor_ipc).then(|| cli.ipc_endpoint.clone()),
+        pause_hotkey: Some(cli.pause_hotkey).filter(|combo| !combo.eq_ignore_ascii_case("none")),
     };
 
+    if let Some(Commands::Render {
+        ref text,
+        ref out,
+        sample_rate,
+        key_duration,
+    }) = cli.command
+    {
+        let text = std::fs::read_to_string(text)
+            .map_err(|e| format!("Could not read '{}': {}", text, e))?;
+        let options = RenderOptions {
+            sample_rate,
+            key_duration,
+            gap: key_duration / 2.0,
+            ..RenderOptions::default()
+        };
+        let samples = render::render_text(&config, &text, &options)?;
+        render::write_wav(out, &samples, sample_rate)?;
+        println!(
+            "✓ Rendered {} characters ({:.1}s) to {}",
+            text.chars().count(),
+            samples.len() as f32 / sample_rate as f32,
+            out
+        );
+        return Ok(());
+    }
+
     // Create and run the engine
     let mut engine = CodeBeatsEngine::new(config)?;
 
-    if !cli.verbose {
+    // Stop cleanly on Ctrl+C so the session summary can be printed
+    let shutdown = engine.shutdown_flag();
+    ctrlc::set_handler(move || shutdown.store(true, Ordering::Relaxed))?;
+
+    if matches!(cli.command, Some(Commands::Demo)) {
+        println!("🎵 Playing demo...");
+        engine.start()?;
+        engine.play_text(DEMO_TEXT, Duration::from_millis(140));
+        // Let the last notes ring out
+        std::thread::sleep(Duration::from_millis(800));
+        engine.stop();
+        return Ok(());
+    }
+
+    // Without these, macOS reports no keys rather than an error. The daemon
+    // keeps serving ctl, notify and IPC clients without key capture.
+    let missing_permissions = permissions::missing_permissions();
+    if !missing_permissions.is_empty() {
+        if daemon {
+            eprintln!("⚠️  {}", permissions::guidance(&missing_permissions));
+            eprintln!("⚠️  Running without key capture; ctl, notify and IPC clients still work");
+        } else {
+            eprintln!("✗ {}", permissions::guidance(&missing_permissions));
+            permissions::show_dialog(&missing_permissions);
+            std::process::exit(1);
+        }
+    }
+
+    #[cfg(feature = "tui")]
+    if matches!(cli.command, Some(Commands::Tui)) {
+        let stats = codebeats::tui::run(engine)?;
+        finish_session(stats, &cli.language, waveform);
+        return Ok(());
+    }
+
+    #[cfg(feature = "tui")]
+    if let Some(Commands::Rhythm {
+        ref pattern,
+        bpm,
+        bars,
+        metronome,
+    }) = cli.command
+    {
+        let pattern = rhythm::find_pattern(pattern).expect("pattern checked above");
+        let game = codebeats::tui::run_rhythm(engine, pattern, bpm, bars, metronome)?;
+        print_rhythm_summary(&game, pattern, bpm);
+        return Ok(());
+    }
+
+    #[cfg(feature = "tui")]
+    if let Some(Commands::Practice { ref file }) = cli.command {
+        let text = match file {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| format!("Could not read '{}': {}", path, e))?,
+            None => tutor::DEFAULT_TEXT.to_string(),
+        };
+        let tutor = codebeats::tui::run_practice(engine, &text)?;
+        let status = if tutor.is_finished() {
+            "finished"
+        } else {
+            "stopped"
+        };
+        println!(
+            "⌨️  Practice {}: {:.0} wpm, {:.0}% accuracy, {} mistakes",
+            status,
+            tutor.wpm(),
+            tutor.accuracy(),
+            tutor.mistakes()
+        );
+        return Ok(());
+    }
+
+    if daemon {
+        println!("🎵 CodeBeats daemon listening on {}", cli.ipc_endpoint);
+    } else if !cli.verbose {
         println!("🎵 CodeBeats started - Press Ctrl+C to exit");
     }
 
     engine.run_blocking()?;
+    finish_session(engine.stats(), &cli.language, waveform);
 
     Ok(())
 }
@@ -0,0 +1,256 @@
+//! Modulation module
+//!
+//! This module holds the slow movements applied on top of the waveforms:
+//! a per-note vibrato wobbling each note's pitch from the moment it starts,
+//! and a global LFO (low-frequency oscillator) sweeping the pitch, volume or
+//! filter cutoff of the whole output. Both are set from the keyboard config
+//! (`"vibrato"` and `"lfo"`); without a `"vibrato"` entry, each waveform keeps
+//! its own default (only the natural piano has one).
+//!
+//! The config's `"mod_matrix"` routes more sources to more targets: two
+//! free-running LFOs, each note's envelope, the typing speed and the key's
+//! position on the keyboard can each move a note's pitch, amplitude, filter
+//! cutoff or pan. Routes are evaluated once per audio block for every note.
+
+use crate::waveforms::Waveform;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Typing speed at which the WPM source reaches 1.0
+const MAX_MOD_WPM: f32 = 120.0;
+
+/// Vibrato of the natural piano when the config sets none
+const NATURAL_VIBRATO: Vibrato = Vibrato {
+    rate: 4.5,
+    depth: 8.0,
+};
+
+/// Pitch wobble of each note, timed from the note's start
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Vibrato {
+    /// Wobbles per second (Hz)
+    #[serde(default = "default_vibrato_rate")]
+    pub rate: f32,
+    /// Largest pitch offset in either direction (cents)
+    pub depth: f32,
+}
+
+fn default_vibrato_rate() -> f32 {
+    5.0
+}
+
+impl Vibrato {
+    /// Vibrato a waveform plays with when the config sets none
+    pub fn for_waveform(waveform: Waveform) -> Option<Self> {
+        match waveform {
+            Waveform::Natural => Some(NATURAL_VIBRATO),
+            _ => None,
+        }
+    }
+
+    /// Frequency multiplier at a position in the wobble's cycle (0.0 to 1.0)
+    ///
+    /// Each note keeps its own position, advanced by `rate / sample_rate` per
+    /// sample, so long notes don't lose precision.
+    #[inline]
+    pub fn ratio(&self, phase: f32) -> f32 {
+        (self.depth / 1200.0 * (2.0 * PI * phase).sin()).exp2()
+    }
+}
+
+/// What the global LFO moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LfoTarget {
+    /// Pitch of every synthesized note; depth in semitones
+    Pitch,
+    /// Output volume, dipping to `1 - depth` at the bottom of each cycle
+    Volume,
+    /// Cutoff of the output low-pass around its set value; depth in octaves
+    Filter,
+}
+
+/// Low-frequency oscillator shared by every note
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Lfo {
+    /// Cycles per second (Hz)
+    pub rate: f32,
+    /// Amount of movement, in the target's unit
+    pub depth: f32,
+    pub target: LfoTarget,
+}
+
+impl Lfo {
+    /// Sine position from -1.0 to 1.0 at an output time (seconds)
+    #[inline]
+    pub fn value(&self, time: f64) -> f32 {
+        sine_at(self.rate, time)
+    }
+}
+
+/// Sine of an oscillator at `rate` Hz, `time` seconds in
+///
+/// The cycle count is reduced to its fraction in f64 before going back to
+/// f32, so the phase stays exact however long the output has been running.
+fn sine_at(rate: f32, time: f64) -> f32 {
+    let phase = (f64::from(rate) * time).fract() as f32;
+    (2.0 * PI * phase).sin()
+}
+
+/// Where a route takes its value from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModSource {
+    /// First matrix LFO, from -1.0 to 1.0
+    Lfo1,
+    /// Second matrix LFO, from -1.0 to 1.0
+    Lfo2,
+    /// The note's envelope level, from 0.0 to 1.0
+    Envelope,
+    /// Typing speed, from 0.0 to 1.0 at 120 words per minute or more
+    Wpm,
+    /// The key's column on the keyboard, from -1.0 (left) to 1.0 (right)
+    KeyPosition,
+}
+
+/// What a route moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModTarget {
+    /// Pitch (semitones)
+    Pitch,
+    /// Amplitude, as a fraction of the note's volume added or taken away
+    Amp,
+    /// Cutoff of the note's low-pass (octaves)
+    Filter,
+    /// Stereo position, added to the note's pan
+    Pan,
+}
+
+/// One connection of the matrix: `amount` times the source's value is added
+/// to the target
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModRoute {
+    pub source: ModSource,
+    pub target: ModTarget,
+    pub amount: f32,
+}
+
+/// Sources routed to note targets
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModMatrix {
+    /// Rate of LFO1 (Hz)
+    pub lfo1_rate: f32,
+    /// Rate of LFO2 (Hz)
+    pub lfo2_rate: f32,
+    pub routes: Vec<ModRoute>,
+}
+
+/// Sum of the routes reaching each target for one note
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModOutputs {
+    pub pitch: f32,
+    pub amp: f32,
+    pub filter: f32,
+    pub pan: f32,
+}
+
+impl ModMatrix {
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// Values of LFO1 and LFO2 at an output time (seconds)
+    pub fn lfo_values(&self, time: f64) -> [f32; 2] {
+        [self.lfo1_rate, self.lfo2_rate].map(|rate| sine_at(rate, time))
+    }
+
+    /// Add up the routes, reading each source's value from `source_value`
+    pub fn evaluate(&self, source_value: impl Fn(ModSource) -> f32) -> ModOutputs {
+        let mut outputs = ModOutputs::default();
+        for route in &self.routes {
+            let value = route.amount * source_value(route.source);
+            match route.target {
+                ModTarget::Pitch => outputs.pitch += value,
+                ModTarget::Amp => outputs.amp += value,
+                ModTarget::Filter => outputs.filter += value,
+                ModTarget::Pan => outputs.pan += value,
+            }
+        }
+        outputs
+    }
+}
+
+/// Value of the WPM source for a typing speed
+pub fn wpm_source(wpm: f32) -> f32 {
+    (wpm / MAX_MOD_WPM).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vibrato_ratio() {
+        let vibrato = Vibrato {
+            rate: 1.0,
+            depth: 1200.0,
+        };
+        assert_eq!(vibrato.ratio(0.0), 1.0);
+        // A quarter cycle in is the top of the wobble: an octave up at 1200 cents
+        assert!((vibrato.ratio(0.25) - 2.0).abs() < 1e-4);
+        assert!((vibrato.ratio(0.75) - 0.5).abs() < 1e-4);
+
+        assert_eq!(
+            Vibrato::for_waveform(Waveform::Natural),
+            Some(NATURAL_VIBRATO)
+        );
+        assert_eq!(Vibrato::for_waveform(Waveform::Cyberpunk), None);
+    }
+
+    #[test]
+    fn test_lfo_deserialize() {
+        let lfo: Lfo =
+            serde_json::from_str(r#"{ "rate": 0.3, "depth": 0.1, "target": "volume" }"#).unwrap();
+        assert_eq!(lfo.target, LfoTarget::Volume);
+        assert!((lfo.value(1.0 / 1.2) - 1.0).abs() < 1e-4);
+        // Still on time an hour in, whole cycles later
+        assert!((lfo.value(3600.0 + 1.0 / 1.2) - 1.0).abs() < 1e-4);
+
+        let vibrato: Vibrato = serde_json::from_str(r#"{ "depth": 10 }"#).unwrap();
+        assert_eq!(vibrato.rate, 5.0);
+    }
+
+    #[test]
+    fn test_mod_matrix() {
+        let matrix: ModMatrix = serde_json::from_str(
+            r#"{ "lfo1_rate": 1.0, "routes": [
+                { "source": "key_position", "target": "pan", "amount": 0.5 },
+                { "source": "envelope", "target": "filter", "amount": -2.0 },
+                { "source": "wpm", "target": "amp", "amount": 0.2 },
+                { "source": "lfo1", "target": "pitch", "amount": 1.0 },
+                { "source": "lfo1", "target": "pitch", "amount": 0.5 }
+            ] }"#,
+        )
+        .unwrap();
+        assert_eq!(matrix.lfo2_rate, 0.0);
+        let [lfo1, lfo2] = matrix.lfo_values(0.25);
+        assert!((lfo1 - 1.0).abs() < 1e-4);
+        assert_eq!(lfo2, 0.0);
+        assert!((matrix.lfo_values(36_000.25)[0] - 1.0).abs() < 1e-4);
+
+        let outputs = matrix.evaluate(|source| match source {
+            ModSource::Lfo1 => lfo1,
+            ModSource::Lfo2 => lfo2,
+            ModSource::Envelope => 0.5,
+            ModSource::Wpm => wpm_source(60.0),
+            ModSource::KeyPosition => -1.0,
+        });
+        assert!((outputs.pitch - 1.5).abs() < 1e-4);
+        assert_eq!(outputs.amp, 0.1);
+        assert_eq!(outputs.filter, -1.0);
+        assert_eq!(outputs.pan, -0.5);
+        assert_eq!(wpm_source(500.0), 1.0);
+    }
+}
@@ -0,0 +1,205 @@
+//! Notification sounds module
+//!
+//! This module defines named notification sounds (e.g. "build-success",
+//! "test-fail") that scripts, shell aliases and CI hooks play on a running
+//! daemon with `codebeats notify <name>`. Each is a short melody of notes
+//! and chords, or a named sample, played through the same audio engine as
+//! the keys. A `notifications.json` settings file adds to or replaces the
+//! built-in sounds.
+
+use crate::audio_engine::AudioState;
+use crate::keyboard_mapping::{midi_to_frequency, note_to_midi};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// A sound played by name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    /// Steps played one after another: a note ("C5"), a chord of notes joined
+    /// with "+" ("C4+E4+G4"), or "-" for a rest
+    #[serde(default)]
+    pub notes: Vec<String>,
+    /// Seconds each step lasts
+    #[serde(default = "default_step")]
+    pub step: f32,
+    /// Sample from the config's `samples` (or "fart") played along with the notes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<String>,
+    /// Playback volume (0.0 to 1.0)
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+}
+
+fn default_step() -> f32 {
+    0.12
+}
+
+fn default_volume() -> f32 {
+    0.7
+}
+
+/// Notification sounds by name
+pub type NotificationMap = HashMap<String, Notification>;
+
+impl Notification {
+    /// A melody of notes or chords, each lasting `step` seconds
+    pub fn melody(notes: &[&str], step: f32) -> Self {
+        Self {
+            notes: notes.iter().map(|note| note.to_string()).collect(),
+            step,
+            sample: None,
+            volume: default_volume(),
+        }
+    }
+
+    /// Frequencies sounding in each step (empty for rests)
+    pub fn steps(&self) -> Result<Vec<Vec<f32>>, String> {
+        self.notes
+            .iter()
+            .map(|step| {
+                if step.trim() == "-" {
+                    return Ok(Vec::new());
+                }
+                step.split('+')
+                    .map(|note| {
+                        note_to_midi(note.trim())
+                            .map(midi_to_frequency)
+                            .ok_or_else(|| format!("Invalid note '{}'", note.trim()))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Schedule the melody on the engine `delay` seconds from now (the sample is up to the caller)
+    ///
+    /// Notes are identified by `id` so a notification replaying over itself
+    /// restarts its notes instead of stacking them.
+    pub fn schedule(&self, id: &str, delay: f32, state: &mut AudioState) -> Result<(), String> {
+        for (index, frequencies) in self.steps()?.into_iter().enumerate() {
+            for (voice, frequency) in frequencies.into_iter().enumerate() {
+                state.schedule_note(
+                    &format!("notify:{}:{}:{}", id, index, voice),
+                    frequency,
+                    self.volume,
+                    delay + index as f32 * self.step,
+                    self.step * 0.9,
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Built-in notification sounds
+pub fn default_notifications() -> NotificationMap {
+    HashMap::from([
+        (
+            "build-success".to_string(),
+            Notification::melody(&["C5", "E5", "G5", "C6"], 0.1),
+        ),
+        (
+            "build-fail".to_string(),
+            Notification::melody(&["E4", "-", "C4+F#4"], 0.15),
+        ),
+        (
+            "test-pass".to_string(),
+            Notification::melody(&["G5", "C5+E5+G5"], 0.15),
+        ),
+        (
+            "test-fail".to_string(),
+            Notification::melody(&["A4", "F4", "D4+G#4"], 0.15),
+        ),
+        // Played by the Git hooks (`codebeats git-hook install`)
+        (
+            "pre-commit".to_string(),
+            Notification::melody(&["G5"], 0.08),
+        ),
+        (
+            "commit".to_string(),
+            Notification::melody(&["C5", "G5", "C6"], 0.1),
+        ),
+        (
+            "push".to_string(),
+            Notification::melody(&["C5", "E5", "G5", "C6", "E6+G6"], 0.08),
+        ),
+    ])
+}
+
+/// Load notification sounds from a JSON file, on top of the built-in ones
+pub fn load_notifications<P: AsRef<Path>>(
+    path: P,
+) -> Result<NotificationMap, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let loaded: NotificationMap = serde_json::from_str(&content)?;
+    for (name, notification) in &loaded {
+        notification
+            .steps()
+            .map_err(|e| format!("Notification '{}': {}", name, e))?;
+    }
+
+    let mut notifications = default_notifications();
+    notifications.extend(loaded);
+    Ok(notifications)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::waveforms::Waveform;
+
+    #[test]
+    fn test_default_notifications() {
+        for (name, notification) in default_notifications() {
+            assert!(notification.steps().is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_steps() {
+        let notification = Notification::melody(&["A4", "-", "C4 + E4"], 0.1);
+        let steps = notification.steps().unwrap();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0], vec![440.0]);
+        assert!(steps[1].is_empty());
+        assert_eq!(steps[2].len(), 2);
+
+        assert!(Notification::melody(&["H4"], 0.1).steps().is_err());
+    }
+
+    #[test]
+    fn test_schedule() {
+        let mut state = AudioState::new(1000.0, Waveform::Electronic, 1.0, 1200.0);
+        let notification = Notification::melody(&["C4+E4", "G4"], 0.1);
+        notification.schedule("chime", 0.0, &mut state).unwrap();
+
+        let mut block = [0.0; 50];
+        state.fill_block(&mut block);
+        assert_eq!(state.active_voice_count(), 2);
+        for _ in 0..3 {
+            state.fill_block(&mut block);
+        }
+        assert!(state.note("notify:chime:1:0").is_some());
+    }
+
+    #[test]
+    fn test_load_notifications() {
+        let path = std::env::temp_dir().join(format!(
+            "codebeats-notifications-{}.json",
+            std::process::id()
+        ));
+        fs::write(
+            &path,
+            r#"{ "deploy": { "notes": ["C5", "G5"], "step": 0.2 }, "test-pass": { "sample": "chime" } }"#,
+        )
+        .unwrap();
+        let notifications = load_notifications(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(notifications["deploy"].step, 0.2);
+        assert_eq!(notifications["test-pass"].sample.as_deref(), Some("chime"));
+        assert!(notifications.contains_key("build-fail"));
+    }
+}
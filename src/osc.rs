@@ -0,0 +1,316 @@
+//! OSC input module
+//!
+//! This module listens for Open Sound Control messages over UDP so external
+//! controllers (TouchOSC, Lemur, ...) can perform the engine live. Messages
+//! are translated into IPC messages and handled by the engine like `ctl`
+//! commands; OSC has no replies, so errors are dropped. Supported addresses:
+//! - `/codebeats/volume f` - master volume (0.0-1.0)
+//! - `/codebeats/filter_cutoff f` - output low-pass cutoff in Hz
+//! - `/codebeats/waveform s|i` - waveform by name or by index
+//! - `/codebeats/transpose i|f` - transposition in semitones
+//! - `/codebeats/mute i|f` - mute when non-zero
+
+use crate::ipc::{IpcMessage, IpcRequest};
+use crate::waveforms;
+use std::net::UdpSocket;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Default address for OSC input (all interfaces, so tablets can reach it)
+pub const DEFAULT_OSC_ADDRESS: &str = "0.0.0.0:9000";
+
+/// An argument of an OSC message
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    Str(String),
+}
+
+impl OscArg {
+    /// Numeric value of the argument, if it has one
+    fn as_f32(&self) -> Option<f32> {
+        match self {
+            OscArg::Int(value) => Some(*value as f32),
+            OscArg::Float(value) => Some(*value),
+            OscArg::Str(value) => value.parse().ok(),
+        }
+    }
+}
+
+/// A decoded OSC message
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+impl OscMessage {
+    /// Translate the message into an engine command
+    pub fn to_ipc_message(&self) -> Result<IpcMessage, Box<dyn std::error::Error>> {
+        let arg = self
+            .args
+            .first()
+            .ok_or_else(|| format!("{} needs an argument", self.address))?;
+        // NaN or infinity would get past the engine's clamping, so reject them here
+        let number = || {
+            arg.as_f32()
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| format!("{} needs a finite number", self.address))
+        };
+
+        let message = match self.address.as_str() {
+            "/codebeats/volume" => IpcMessage::SetVolume { volume: number()? },
+            "/codebeats/filter_cutoff" => IpcMessage::SetFilterCutoff { cutoff: number()? },
+            "/codebeats/transpose" => IpcMessage::SetTranspose {
+                semitones: number()?.round() as i32,
+            },
+            "/codebeats/mute" => IpcMessage::SetMuted {
+                muted: number()? != 0.0,
+            },
+            "/codebeats/waveform" => {
+                let waveform = match arg {
+                    OscArg::Str(name) => name.clone(),
+                    _ => {
+                        let index = number()?.max(0.0) as usize;
+                        waveforms::get_all_waveforms()
+                            .get(index)
+                            .map(|(name, _)| name.to_string())
+                            .ok_or_else(|| format!("No waveform at index {}", index))?
+                    }
+                };
+                IpcMessage::SetWaveform { waveform }
+            }
+            address => return Err(format!("Unknown OSC address '{}'", address).into()),
+        };
+        Ok(message)
+    }
+}
+
+/// UDP listener receiving OSC messages on a background thread
+pub struct OscServer {
+    address: String,
+    requests: Receiver<IpcRequest>,
+}
+
+impl OscServer {
+    /// Start listening on the address (e.g. "0.0.0.0:9000")
+    pub fn start(address: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let socket = UdpSocket::bind(address)?;
+        let (sender, requests) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut buffer = [0u8; 65536];
+            while let Ok((len, _)) = socket.recv_from(&mut buffer) {
+                let Ok(messages) = parse_packet(&buffer[..len]) else {
+                    continue;
+                };
+                for message in messages {
+                    if let Ok(message) = message.to_ipc_message() {
+                        let (request, _) = IpcRequest::new(message);
+                        if sender.send(request).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            address: address.to_string(),
+            requests,
+        })
+    }
+
+    /// Address this server listens on
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Take the next pending request without blocking
+    pub fn try_next(&self) -> Option<IpcRequest> {
+        self.requests.try_recv().ok()
+    }
+}
+
+/// Decode an OSC packet (a message or a bundle of them)
+pub fn parse_packet(data: &[u8]) -> Result<Vec<OscMessage>, Box<dyn std::error::Error>> {
+    let mut reader = OscReader { data, pos: 0 };
+
+    if data.starts_with(b"#bundle\0") {
+        reader.pos = 16; // "#bundle\0" plus the 8-byte time tag (ignored)
+        let mut messages = Vec::new();
+        while reader.pos < data.len() {
+            let size = reader.read_i32()?;
+            let size = usize::try_from(size).map_err(|_| "negative bundle element size")?;
+            let element = reader.take(size)?;
+            messages.extend(parse_packet(element)?);
+        }
+        return Ok(messages);
+    }
+
+    let address = reader.read_string()?;
+    if !address.starts_with('/') {
+        return Err(format!("Invalid OSC address '{}'", address).into());
+    }
+
+    // Old implementations may omit the type tag string
+    let tags = if reader.pos < data.len() {
+        reader.read_string()?
+    } else {
+        ",".to_string()
+    };
+    let Some(tags) = tags.strip_prefix(',') else {
+        return Err("Missing OSC type tags".into());
+    };
+
+    let mut args = Vec::new();
+    for tag in tags.chars() {
+        args.push(match tag {
+            'i' => OscArg::Int(reader.read_i32()?),
+            'f' => OscArg::Float(f32::from_bits(reader.read_i32()? as u32)),
+            's' => OscArg::Str(reader.read_string()?),
+            'T' => OscArg::Int(1),
+            'F' => OscArg::Int(0),
+            tag => return Err(format!("Unsupported OSC type tag '{}'", tag).into()),
+        });
+    }
+
+    Ok(vec![OscMessage { address, args }])
+}
+
+/// Cursor over OSC data, where every field is padded to 4 bytes
+struct OscReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> OscReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Box<dyn std::error::Error>> {
+        let end = self.pos.checked_add(len).ok_or("OSC packet too large")?;
+        let bytes = self.data.get(self.pos..end).ok_or("Truncated OSC packet")?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_i32(&mut self) -> Result<i32, Box<dyn std::error::Error>> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_string(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        let rest = &self.data[self.pos..];
+        let len = rest
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or("Unterminated OSC string")?;
+        let text = String::from_utf8(rest[..len].to_vec())?;
+        self.take((len + 4) & !3)?; // the terminator plus padding to 4 bytes
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode a string with OSC padding
+    fn osc_string(text: &str) -> Vec<u8> {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.push(0);
+        while !bytes.len().is_multiple_of(4) {
+            bytes.push(0);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_parse_message() {
+        let mut packet = osc_string("/codebeats/volume");
+        packet.extend(osc_string(",f"));
+        packet.extend(0.5f32.to_be_bytes());
+
+        let messages = parse_packet(&packet).unwrap();
+        assert_eq!(
+            messages,
+            vec![OscMessage {
+                address: "/codebeats/volume".to_string(),
+                args: vec![OscArg::Float(0.5)],
+            }]
+        );
+        assert_eq!(
+            messages[0].to_ipc_message().unwrap(),
+            IpcMessage::SetVolume { volume: 0.5 }
+        );
+
+        assert!(parse_packet(&packet[..packet.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn test_parse_bundle() {
+        let mut first = osc_string("/codebeats/waveform");
+        first.extend(osc_string(",s"));
+        first.extend(osc_string("bass"));
+        let mut second = osc_string("/codebeats/transpose");
+        second.extend(osc_string(",i"));
+        second.extend((-12i32).to_be_bytes());
+
+        let mut packet = osc_string("#bundle");
+        packet.extend([0, 0, 0, 0, 0, 0, 0, 1]);
+        for element in [&first, &second] {
+            packet.extend((element.len() as i32).to_be_bytes());
+            packet.extend(element);
+        }
+
+        let messages: Vec<IpcMessage> = parse_packet(&packet)
+            .unwrap()
+            .iter()
+            .map(|message| message.to_ipc_message().unwrap())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                IpcMessage::SetWaveform {
+                    waveform: "bass".to_string()
+                },
+                IpcMessage::SetTranspose { semitones: -12 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_waveform_by_index() {
+        let message = OscMessage {
+            address: "/codebeats/waveform".to_string(),
+            args: vec![OscArg::Float(1.0)],
+        };
+        assert_eq!(
+            message.to_ipc_message().unwrap(),
+            IpcMessage::SetWaveform {
+                waveform: waveforms::get_all_waveforms()[1].0.to_string()
+            }
+        );
+
+        let unknown = OscMessage {
+            address: "/other".to_string(),
+            args: vec![OscArg::Int(1)],
+        };
+        assert!(unknown.to_ipc_message().is_err());
+    }
+
+    #[test]
+    fn test_rejects_non_finite_numbers() {
+        for (address, arg) in [
+            ("/codebeats/volume", OscArg::Float(f32::NAN)),
+            ("/codebeats/volume", OscArg::Str("NaN".to_string())),
+            ("/codebeats/filter_cutoff", OscArg::Float(f32::INFINITY)),
+            ("/codebeats/transpose", OscArg::Str("-inf".to_string())),
+        ] {
+            let message = OscMessage {
+                address: address.to_string(),
+                args: vec![arg],
+            };
+            assert!(message.to_ipc_message().is_err(), "{:?}", message);
+        }
+    }
+}
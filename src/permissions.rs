@@ -0,0 +1,144 @@
+//! Permission module
+//!
+//! macOS only delivers key state to applications the user has allowed, and
+//! otherwise reports no keys at all rather than an error. This module checks
+//! the permissions up front so the user learns what to enable:
+//! - macOS: Input Monitoring (`IOHIDCheckAccess`) and Accessibility
+//!   (`AXIsProcessTrusted`), granted to the terminal or app running CodeBeats
+//! - Other platforms: nothing to check
+
+use std::fmt;
+
+/// A permission needed to read the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    InputMonitoring,
+    Accessibility,
+}
+
+impl Permission {
+    /// URL that opens this permission's pane in System Settings
+    pub fn settings_url(&self) -> &'static str {
+        match self {
+            Permission::InputMonitoring => {
+                "x-apple.systempreferences:com.apple.preference.security?Privacy_ListenEvent"
+            }
+            Permission::Accessibility => {
+                "x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility"
+            }
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Permission::InputMonitoring => "Input Monitoring",
+            Permission::Accessibility => "Accessibility",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Permissions needed to read the keyboard that haven't been granted
+#[cfg(target_os = "macos")]
+pub fn missing_permissions() -> Vec<Permission> {
+    #[link(name = "IOKit", kind = "framework")]
+    unsafe extern "C" {
+        fn IOHIDCheckAccess(request: u32) -> u32;
+        fn IOHIDRequestAccess(request: u32) -> bool;
+    }
+    #[link(name = "ApplicationServices", kind = "framework")]
+    unsafe extern "C" {
+        fn AXIsProcessTrusted() -> bool;
+    }
+
+    const LISTEN_EVENT: u32 = 1;
+    const ACCESS_GRANTED: u32 = 0;
+    const ACCESS_UNKNOWN: u32 = 2;
+
+    let mut missing = Vec::new();
+
+    // SAFETY: these only read (or, when undecided, ask for) this process's permissions
+    let input_monitoring = match unsafe { IOHIDCheckAccess(LISTEN_EVENT) } {
+        ACCESS_GRANTED => true,
+        // Never asked: show the system prompt, which also adds the app to the list
+        ACCESS_UNKNOWN => unsafe { IOHIDRequestAccess(LISTEN_EVENT) },
+        _ => false,
+    };
+    if !input_monitoring {
+        missing.push(Permission::InputMonitoring);
+    }
+    // SAFETY: takes no arguments and only reads this process's trust flag
+    if !unsafe { AXIsProcessTrusted() } {
+        missing.push(Permission::Accessibility);
+    }
+    missing
+}
+
+/// Permissions needed to read the keyboard that haven't been granted
+#[cfg(not(target_os = "macos"))]
+pub fn missing_permissions() -> Vec<Permission> {
+    Vec::new()
+}
+
+/// Explanation of what's missing and how to grant it
+pub fn guidance(missing: &[Permission]) -> String {
+    let names: Vec<String> = missing.iter().map(|p| p.to_string()).collect();
+    format!(
+        "CodeBeats can't read the keyboard without the {} permission{}. Open System Settings → \
+         Privacy & Security, enable your terminal (or CodeBeats) under {}, then restart it.",
+        names.join(" and "),
+        if missing.len() == 1 { "" } else { "s" },
+        names.join(" and ")
+    )
+}
+
+/// Show a dialog explaining what's missing, with a button that opens the
+/// first missing permission's pane in System Settings
+#[cfg(target_os = "macos")]
+pub fn show_dialog(missing: &[Permission]) {
+    let Some(first) = missing.first() else {
+        return;
+    };
+
+    let script = format!(
+        "display dialog \"{}\" with title \"CodeBeats\" buttons {{\"Quit\", \"Open System Settings\"}} \
+         default button \"Open System Settings\" with icon caution",
+        guidance(missing).replace('"', "\\\"")
+    );
+    let clicked_open = std::process::Command::new("osascript")
+        .args(["-e", &script])
+        .output()
+        .is_ok_and(|output| {
+            String::from_utf8_lossy(&output.stdout).contains("Open System Settings")
+        });
+    if clicked_open {
+        let _ = std::process::Command::new("open")
+            .arg(first.settings_url())
+            .status();
+    }
+}
+
+/// Show a dialog explaining what's missing (nothing to show on this platform)
+#[cfg(not(target_os = "macos"))]
+pub fn show_dialog(_missing: &[Permission]) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guidance() {
+        assert_eq!(
+            guidance(&[Permission::InputMonitoring]),
+            "CodeBeats can't read the keyboard without the Input Monitoring permission. Open \
+             System Settings → Privacy & Security, enable your terminal (or CodeBeats) under \
+             Input Monitoring, then restart it."
+        );
+        assert!(
+            guidance(&[Permission::InputMonitoring, Permission::Accessibility])
+                .contains("Input Monitoring and Accessibility permissions")
+        );
+    }
+}
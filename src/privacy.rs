@@ -0,0 +1,144 @@
+//! Privacy mode module
+//!
+//! This module detects when the user is typing into a secure context (such as
+//! a password field) so the engine can stay silent and not leak the length or
+//! rhythm of what is being typed:
+//! - macOS: the system-wide secure event input flag (`IsSecureEventInputEnabled`)
+//! - Linux: running password prompt programs (pinentry, ssh-askpass, ...),
+//!   scanned for on a background thread so key handling never walks /proc
+//! - Other platforms: no detection
+
+#[cfg(target_os = "linux")]
+use std::sync::Arc;
+#[cfg(target_os = "linux")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+/// How often the (comparatively expensive) process scan runs on Linux
+#[cfg(target_os = "linux")]
+const PROCESS_SCAN_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Process names of password prompts that indicate secure input on Linux
+#[cfg(any(target_os = "linux", test))]
+const PASSWORD_PROMPT_PROCESSES: &[&str] = &[
+    "pinentry",
+    "ssh-askpass",
+    "ksshaskpass",
+    "gnome-ssh-askpass",
+    "gcr-prompter",
+    "lxqt-openssh-askpass",
+];
+
+/// Detects secure input contexts, polling in the background where detection is costly
+pub struct SecureInputMonitor {
+    #[cfg(target_os = "linux")]
+    prompt_running: Arc<AtomicBool>,
+    #[cfg(target_os = "linux")]
+    stop: Arc<AtomicBool>,
+}
+
+impl Default for SecureInputMonitor {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+impl SecureInputMonitor {
+    /// Start detecting secure input (on Linux, polling for password prompts)
+    #[cfg(target_os = "linux")]
+    pub fn start() -> Self {
+        let prompt_running = Arc::new(AtomicBool::new(false));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let prompt_running_clone = prompt_running.clone();
+        let stop_clone = stop.clone();
+        std::thread::spawn(move || {
+            while !stop_clone.load(Ordering::Relaxed) {
+                prompt_running_clone.store(password_prompt_running(), Ordering::Relaxed);
+                std::thread::sleep(PROCESS_SCAN_INTERVAL);
+            }
+        });
+
+        Self {
+            prompt_running,
+            stop,
+        }
+    }
+
+    /// Start detecting secure input
+    #[cfg(not(target_os = "linux"))]
+    pub fn start() -> Self {
+        Self {}
+    }
+
+    /// Check whether secure input is currently active
+    #[cfg(target_os = "macos")]
+    pub fn is_active(&self) -> bool {
+        #[link(name = "Carbon", kind = "framework")]
+        unsafe extern "C" {
+            fn IsSecureEventInputEnabled() -> u8;
+        }
+
+        // SAFETY: takes no arguments and only reads a system-wide flag
+        unsafe { IsSecureEventInputEnabled() != 0 }
+    }
+
+    /// Whether a password prompt was running at the last poll
+    #[cfg(target_os = "linux")]
+    pub fn is_active(&self) -> bool {
+        self.prompt_running.load(Ordering::Relaxed)
+    }
+
+    /// Check whether secure input is currently active
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    pub fn is_active(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for SecureInputMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Scan /proc for a running password prompt
+#[cfg(target_os = "linux")]
+fn password_prompt_running() -> bool {
+    any_process_running(is_password_prompt)
+}
+
+/// Scan /proc for a running process whose name satisfies the predicate
+#[cfg(target_os = "linux")]
+pub(crate) fn any_process_running(predicate: impl Fn(&str) -> bool) -> bool {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return false;
+    };
+
+    entries.flatten().any(|entry| {
+        std::fs::read_to_string(entry.path().join("comm")).is_ok_and(|name| predicate(name.trim()))
+    })
+}
+
+/// Check whether a process name belongs to a password prompt
+#[cfg(any(target_os = "linux", test))]
+fn is_password_prompt(process_name: &str) -> bool {
+    PASSWORD_PROMPT_PROCESSES
+        .iter()
+        .any(|prompt| process_name.starts_with(prompt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_prompt_names() {
+        assert!(is_password_prompt("pinentry-gnome3"));
+        assert!(is_password_prompt("ssh-askpass"));
+        assert!(!is_password_prompt("bash"));
+        assert!(!is_password_prompt("codebeats"));
+    }
+}
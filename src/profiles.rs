@@ -0,0 +1,98 @@
+//! Application profiles module
+//!
+//! This module loads the profiles file that maps focused applications to
+//! keyboard configurations, so the engine can switch sounds automatically
+//! (e.g. a programming config in the terminal, English in a chat app), and
+//! lists applications in which the engine stays silent.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// A mapping from an application to the keyboard configuration used in it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppProfile {
+    /// Application name to match (case-insensitive substring, e.g. "terminal")
+    pub app: String,
+    /// Embedded config name or JSON file path
+    pub config: String,
+}
+
+/// Contents of a profiles file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfilesConfig {
+    /// Config used when no profile matches (None keeps the startup config)
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Profiles checked in order; the first match wins
+    #[serde(default)]
+    pub profiles: Vec<AppProfile>,
+    /// Applications (names or bundle IDs) in which CodeBeats goes silent
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub muted_apps: Vec<String>,
+}
+
+impl ProfilesConfig {
+    /// Load profiles from a JSON file
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let profiles: ProfilesConfig = serde_json::from_str(&content)?;
+        Ok(profiles)
+    }
+
+    /// Config to use for an application, falling back to the default
+    pub fn config_for_app(&self, app: &str) -> Option<&str> {
+        self.profiles
+            .iter()
+            .find(|profile| app_matches(app, &profile.app))
+            .map(|profile| profile.config.as_str())
+            .or(self.default.as_deref())
+    }
+}
+
+/// Check whether an application name contains a pattern, ignoring case
+pub fn app_matches(app: &str, pattern: &str) -> bool {
+    app.to_lowercase().contains(&pattern.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_for_app() {
+        let json = r#"{
+            "default": "general",
+            "profiles": [
+                { "app": "Terminal", "config": "rust" },
+                { "app": "slack", "config": "english" }
+            ]
+        }"#;
+        let profiles: ProfilesConfig = serde_json::from_str(json).unwrap();
+
+        assert_eq!(profiles.config_for_app("gnome-terminal"), Some("rust"));
+        assert_eq!(profiles.config_for_app("Slack"), Some("english"));
+        assert_eq!(profiles.config_for_app("Firefox"), Some("general"));
+
+        let no_default = ProfilesConfig {
+            default: None,
+            ..profiles
+        };
+        assert_eq!(no_default.config_for_app("Firefox"), None);
+    }
+
+    #[test]
+    fn test_muted_apps() {
+        let json = r#"{ "muted_apps": ["1Password", "com.apple.keychainaccess"] }"#;
+        let profiles: ProfilesConfig = serde_json::from_str(json).unwrap();
+        assert!(profiles.profiles.is_empty());
+        assert_eq!(profiles.muted_apps.len(), 2);
+
+        assert!(app_matches(
+            "Keychain Access (com.apple.keychainaccess)",
+            &profiles.muted_apps[1]
+        ));
+        assert!(app_matches("1password", &profiles.muted_apps[0]));
+        assert!(!app_matches("Terminal", &profiles.muted_apps[0]));
+    }
+}
@@ -0,0 +1,242 @@
+//! Community config registry module
+//!
+//! This module fetches the index of community-contributed keyboard configs
+//! and installs them into the user config directory, where they can be used
+//! by name like the embedded ones. The index is a JSON file served over HTTPS
+//! (downloads need the `registry` feature); a local path works too, e.g. for
+//! a mirror:
+//!
+//! ```json
+//! { "configs": [
+//!   { "name": "lofi-rust", "description": "Mellow Rust mapping", "url": "configs/lofi-rust.json" }
+//! ] }
+//! ```
+//!
+//! Relative URLs are resolved against the index URL.
+
+use crate::keyboard_config::{ConfigFormat, KeyboardConfig};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Index used when no other registry is given
+pub const DEFAULT_REGISTRY_URL: &str =
+    "https://raw.githubusercontent.com/guiyuanju/codebeats-configs/main/index.json";
+
+/// A config listed in the registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryEntry {
+    /// Name the config is installed and used under
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Where to download the config (absolute, or relative to the index)
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl RegistryEntry {
+    /// Check whether the entry matches a search query (case-insensitive,
+    /// against the name, description and tags)
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.name.to_lowercase().contains(&query)
+            || self.description.to_lowercase().contains(&query)
+            || self.tags.iter().any(|tag| tag.to_lowercase() == query)
+    }
+}
+
+/// Contents of a registry index file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegistryIndex {
+    #[serde(default)]
+    pub configs: Vec<RegistryEntry>,
+    /// URL the index was fetched from, for resolving relative entry URLs
+    #[serde(skip)]
+    pub source: String,
+}
+
+impl RegistryIndex {
+    /// Fetch and parse the index at a URL or local path
+    pub fn fetch(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut index: RegistryIndex = serde_json::from_str(&fetch_text(url)?)
+            .map_err(|e| format!("Invalid registry index '{}': {}", url, e))?;
+        index.source = url.to_string();
+        Ok(index)
+    }
+
+    /// Entries matching a search query, or every entry for an empty query
+    pub fn search(&self, query: &str) -> Vec<&RegistryEntry> {
+        self.configs
+            .iter()
+            .filter(|entry| query.is_empty() || entry.matches(query))
+            .collect()
+    }
+
+    /// Find an entry by its exact name
+    pub fn find(&self, name: &str) -> Option<&RegistryEntry> {
+        self.configs.iter().find(|entry| entry.name == name)
+    }
+
+    /// Download an entry's config into a directory, checking that it loads
+    /// Existing files are kept unless `overwrite` is set; returns the written path
+    pub fn install(
+        &self,
+        entry: &RegistryEntry,
+        dir: &Path,
+        overwrite: bool,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        check_name(&entry.name)?;
+        let url = resolve_url(&self.source, &entry.url);
+        let format = ConfigFormat::from_path(Path::new(&url));
+        let content = fetch_text(&url)?;
+        KeyboardConfig::parse(&content, format)
+            .map_err(|e| format!("'{}' is not a valid config: {}", entry.name, e))?;
+
+        let extension = match format {
+            ConfigFormat::Json => "json",
+            ConfigFormat::Toml => "toml",
+        };
+        let path = dir.join(format!("{}.{}", entry.name, extension));
+        if path.exists() && !overwrite {
+            return Err(format!(
+                "'{}' already exists (use --force to replace it)",
+                path.display()
+            )
+            .into());
+        }
+        fs::create_dir_all(dir)?;
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+}
+
+/// Reject names that could escape the config directory or can't be used with `--language`
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid config name '{}' in registry", name))
+    }
+}
+
+/// Resolve an entry URL relative to the index URL
+fn resolve_url(base: &str, url: &str) -> String {
+    if url.contains("://") || Path::new(url).is_absolute() {
+        return url.to_string();
+    }
+    match base.rfind(['/', '\\']) {
+        Some(slash) => format!("{}/{}", &base[..slash], url),
+        None => url.to_string(),
+    }
+}
+
+/// Read a URL (http/https) or local file as text
+fn fetch_text(url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        download(url)
+    } else {
+        fs::read_to_string(url.strip_prefix("file://").unwrap_or(url))
+            .map_err(|e| format!("Could not read '{}': {}", url, e).into())
+    }
+}
+
+#[cfg(feature = "registry")]
+fn download(url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| format!("Could not fetch '{}': {}", url, e))?;
+    Ok(response.into_string()?)
+}
+
+#[cfg(not(feature = "registry"))]
+fn download(_url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    Err("CodeBeats was built without the 'registry' feature".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> RegistryIndex {
+        let mut index: RegistryIndex = serde_json::from_str(
+            r#"{ "configs": [
+                { "name": "lofi-rust", "description": "Mellow Rust mapping", "url": "configs/lofi-rust.json", "tags": ["rust"] },
+                { "name": "drums", "description": "Home row drum kit", "url": "https://example.com/drums.toml" }
+            ] }"#,
+        )
+        .unwrap();
+        index.source = "https://example.com/registry/index.json".to_string();
+        index
+    }
+
+    #[test]
+    fn test_search() {
+        let index = index();
+        assert_eq!(index.search("").len(), 2);
+        assert_eq!(index.search("RUST")[0].name, "lofi-rust");
+        assert_eq!(index.search("drum")[0].name, "drums");
+        assert!(index.search("haskell").is_empty());
+        assert!(index.find("drums").is_some());
+    }
+
+    #[test]
+    fn test_resolve_url() {
+        let index = index();
+        assert_eq!(
+            resolve_url(&index.source, "configs/lofi-rust.json"),
+            "https://example.com/registry/configs/lofi-rust.json"
+        );
+        assert_eq!(
+            resolve_url(&index.source, "https://example.com/drums.toml"),
+            "https://example.com/drums.toml"
+        );
+    }
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("lofi-rust").is_ok());
+        assert!(check_name("../evil").is_err());
+        assert!(check_name("").is_err());
+    }
+
+    #[test]
+    fn test_install_from_local_registry() {
+        let dir = std::env::temp_dir().join(format!("codebeats-registry-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("mine.toml"),
+            "version = \"1.0\"\ndescription = \"Mine\"\n[mappings.A]\nnote = \"C4\"\nvolume = 0.3\n",
+        )
+        .unwrap();
+        let index = RegistryIndex {
+            configs: vec![RegistryEntry {
+                name: "mine".to_string(),
+                description: String::new(),
+                url: "mine.toml".to_string(),
+                author: None,
+                tags: Vec::new(),
+            }],
+            source: dir.join("index.json").display().to_string(),
+        };
+
+        let installed = dir.join("installed");
+        let path = index.install(&index.configs[0], &installed, false).unwrap();
+        assert_eq!(path, installed.join("mine.toml"));
+        assert_eq!(
+            KeyboardConfig::load_from_file(&path).unwrap().description,
+            "Mine"
+        );
+        assert!(index.install(&index.configs[0], &installed, false).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
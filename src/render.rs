@@ -0,0 +1,145 @@
+//! Offline rendering module
+//!
+//! This module "types" a piece of text through a configuration without an
+//! audio device or keyboard, and writes the result to a WAV file. Time is
+//! driven purely by the number of samples generated, so the same text,
+//! config, and options always render the same audio.
+
+use crate::CodeBeatsConfig;
+use crate::headless::HeadlessEngine;
+use crate::keyboard_mapping::virtual_keycode_for_char;
+use std::path::Path;
+
+/// Default sample rate for rendered files
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+/// Timing of an offline render
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderOptions {
+    /// Sample rate of the output in Hz
+    pub sample_rate: u32,
+    /// Seconds each key is held down
+    pub key_duration: f32,
+    /// Seconds between releasing a key and pressing the next one
+    pub gap: f32,
+    /// Seconds rendered after the last key so the final notes can ring out
+    pub tail: f32,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            key_duration: 0.14,
+            gap: 0.07,
+            tail: 1.0,
+        }
+    }
+}
+
+/// Render text typed through a configuration to mono samples
+/// Characters without a key (e.g. non-ASCII) are rendered as a pause
+pub fn render_text(
+    config: &CodeBeatsConfig,
+    text: &str,
+    options: &RenderOptions,
+) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+    let mut engine = HeadlessEngine::new(config, options.sample_rate)?;
+    for warning in engine.set_keyboard_config(config.keyboard_config.clone()) {
+        eprintln!("⚠️ {}", warning);
+    }
+
+    let frames = |seconds: f32| (seconds * options.sample_rate as f32).round() as usize;
+    let mut output = Vec::new();
+    let mut generate = |engine: &mut HeadlessEngine, count: usize| {
+        let start = output.len();
+        output.resize(start + count, 0.0);
+        engine.process(&mut output[start..]);
+    };
+
+    for c in text.chars() {
+        if let Some(key) = virtual_keycode_for_char(c) {
+            engine.key_down(&key);
+            generate(&mut engine, frames(options.key_duration));
+            engine.key_up(&key);
+        } else {
+            generate(&mut engine, frames(options.key_duration));
+        }
+        generate(&mut engine, frames(options.gap));
+    }
+    generate(&mut engine, frames(options.tail));
+
+    Ok(output)
+}
+
+/// Write mono samples to a 16-bit WAV file
+pub fn write_wav<P: AsRef<Path>>(
+    path: P,
+    samples: &[f32],
+    sample_rate: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for &sample in samples {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded_configs;
+
+    fn test_config() -> CodeBeatsConfig {
+        CodeBeatsConfig {
+            keyboard_config: embedded_configs::get_default_config().unwrap(),
+            ..CodeBeatsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_render_is_deterministic() {
+        let config = test_config();
+        let options = RenderOptions::default();
+
+        let first = render_text(&config, "fn main() {}", &options).unwrap();
+        let second = render_text(&config, "fn main() {}", &options).unwrap();
+        assert_eq!(first, second);
+        assert!(first.iter().any(|sample| sample.abs() > 0.001));
+    }
+
+    #[test]
+    fn test_render_length() {
+        let config = test_config();
+        let options = RenderOptions {
+            sample_rate: 8000,
+            key_duration: 0.1,
+            gap: 0.05,
+            tail: 0.5,
+        };
+
+        // Unmapped characters still take up a key's worth of time
+        let samples = render_text(&config, "aé", &options).unwrap();
+        assert_eq!(samples.len(), 2 * (800 + 400) + 4000);
+    }
+
+    #[test]
+    fn test_write_wav() {
+        let path =
+            std::env::temp_dir().join(format!("codebeats-render-{}.wav", std::process::id()));
+        write_wav(&path, &[0.0, 0.5, -0.5, 2.0], 8000).unwrap();
+
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec().sample_rate, 8000);
+        let samples: Vec<i16> = reader.samples::<i16>().map(|s| s.unwrap()).collect();
+        assert_eq!(samples, vec![0, 16383, -16383, i16::MAX]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,284 @@
+//! Rhythm game module
+//!
+//! This module scores typing against a target rhythm for `codebeats rhythm`:
+//! a pattern of beats repeats for a number of 4/4 bars after a one-bar
+//! count-in, and each key press is judged by how close it lands to the
+//! nearest target (perfect, good, ok, or a miss). The terminal UI shows the
+//! pattern scrolling toward a hit line while the keys play their notes as usual.
+
+/// Beats in a bar (patterns are in 4/4)
+pub const BEATS_PER_BAR: f32 = 4.0;
+
+/// Largest timing error (seconds) for each judgement
+const PERFECT_WINDOW: f32 = 0.035;
+const GOOD_WINDOW: f32 = 0.08;
+const HIT_WINDOW: f32 = 0.15;
+
+/// A one-bar rhythm, as beat positions within the bar
+#[derive(Debug, Clone, PartialEq)]
+pub struct RhythmPattern {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub beats: &'static [f32],
+}
+
+/// Built-in patterns, roughly from easiest to hardest
+pub const PATTERNS: &[RhythmPattern] = &[
+    RhythmPattern {
+        name: "quarters",
+        description: "Every beat",
+        beats: &[0.0, 1.0, 2.0, 3.0],
+    },
+    RhythmPattern {
+        name: "eighths",
+        description: "Twice per beat",
+        beats: &[0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 3.0, 3.5],
+    },
+    RhythmPattern {
+        name: "offbeats",
+        description: "Between the beats",
+        beats: &[0.5, 1.5, 2.5, 3.5],
+    },
+    RhythmPattern {
+        name: "clave",
+        description: "3-2 son clave",
+        beats: &[0.0, 0.75, 1.5, 2.5, 3.0],
+    },
+    RhythmPattern {
+        name: "swing",
+        description: "Swung eighths",
+        beats: &[0.0, 0.667, 1.0, 1.667, 2.0, 2.667, 3.0, 3.667],
+    },
+    RhythmPattern {
+        name: "gallop",
+        description: "Eighth and two sixteenths",
+        beats: &[
+            0.0, 0.5, 0.75, 1.0, 1.5, 1.75, 2.0, 2.5, 2.75, 3.0, 3.5, 3.75,
+        ],
+    },
+];
+
+/// Find a built-in pattern by name
+pub fn find_pattern(name: &str) -> Option<&'static RhythmPattern> {
+    PATTERNS.iter().find(|pattern| pattern.name == name)
+}
+
+/// How well a target was hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Judgement {
+    Perfect,
+    Good,
+    Ok,
+    Miss,
+}
+
+impl Judgement {
+    /// Judgement for a timing error in seconds
+    fn for_error(error: f32) -> Self {
+        match error.abs() {
+            e if e <= PERFECT_WINDOW => Judgement::Perfect,
+            e if e <= GOOD_WINDOW => Judgement::Good,
+            e if e <= HIT_WINDOW => Judgement::Ok,
+            _ => Judgement::Miss,
+        }
+    }
+
+    /// Points scored
+    pub fn points(self) -> u32 {
+        match self {
+            Judgement::Perfect => 300,
+            Judgement::Good => 100,
+            Judgement::Ok => 50,
+            Judgement::Miss => 0,
+        }
+    }
+}
+
+/// A game in progress; times are seconds since the start of the count-in
+#[derive(Debug, Clone)]
+pub struct RhythmGame {
+    beat_seconds: f32,
+    targets: Vec<f32>,
+    judgements: Vec<Option<Judgement>>,
+    /// Timing errors of the hits (positive is late)
+    errors: Vec<f32>,
+    /// Presses not near any target
+    stray_presses: u32,
+    score: u32,
+    combo: u32,
+    max_combo: u32,
+}
+
+impl RhythmGame {
+    /// A game of `bars` repeats of a pattern at a tempo
+    pub fn new(pattern: &RhythmPattern, bpm: f32, bars: u32) -> Self {
+        let beat_seconds = 60.0 / bpm.max(1.0);
+        let targets: Vec<f32> = (1..=bars)
+            .flat_map(|bar| {
+                pattern
+                    .beats
+                    .iter()
+                    .map(move |beat| (bar as f32 * BEATS_PER_BAR + beat) * beat_seconds)
+            })
+            .collect();
+        Self {
+            beat_seconds,
+            judgements: vec![None; targets.len()],
+            targets,
+            errors: Vec::new(),
+            stray_presses: 0,
+            score: 0,
+            combo: 0,
+            max_combo: 0,
+        }
+    }
+
+    /// Seconds per beat
+    pub fn beat_seconds(&self) -> f32 {
+        self.beat_seconds
+    }
+
+    /// Times of the count-in clicks
+    pub fn count_in(&self) -> Vec<f32> {
+        (0..BEATS_PER_BAR as usize)
+            .map(|beat| beat as f32 * self.beat_seconds)
+            .collect()
+    }
+
+    /// Target times, and their judgements so far
+    pub fn targets(&self) -> impl Iterator<Item = (f32, Option<Judgement>)> + '_ {
+        self.targets
+            .iter()
+            .copied()
+            .zip(self.judgements.iter().copied())
+    }
+
+    /// Judge a key press against the earliest open target in reach
+    ///
+    /// Returns None for a press near no target, which breaks the combo.
+    pub fn press(&mut self, time: f32) -> Option<Judgement> {
+        self.update(time);
+        let Some(index) = (0..self.targets.len()).find(|&index| {
+            self.judgements[index].is_none() && (time - self.targets[index]).abs() <= HIT_WINDOW
+        }) else {
+            self.stray_presses += 1;
+            self.combo = 0;
+            return None;
+        };
+
+        let error = time - self.targets[index];
+        let judgement = Judgement::for_error(error);
+        self.judgements[index] = Some(judgement);
+        self.errors.push(error);
+        self.score += judgement.points();
+        self.combo += 1;
+        self.max_combo = self.max_combo.max(self.combo);
+        Some(judgement)
+    }
+
+    /// Mark targets whose window has passed as missed
+    pub fn update(&mut self, time: f32) {
+        for (target, judgement) in self.targets.iter().zip(self.judgements.iter_mut()) {
+            if judgement.is_none() && time - target > HIT_WINDOW {
+                *judgement = Some(Judgement::Miss);
+                self.combo = 0;
+            }
+        }
+    }
+
+    /// Whether every target has been hit or missed
+    pub fn is_finished(&self) -> bool {
+        self.judgements.iter().all(Option::is_some)
+    }
+
+    /// Seconds until the last target's window closes
+    pub fn duration(&self) -> f32 {
+        self.targets.last().map_or(0.0, |last| last + HIT_WINDOW)
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn combo(&self) -> u32 {
+        self.combo
+    }
+
+    pub fn max_combo(&self) -> u32 {
+        self.max_combo
+    }
+
+    pub fn stray_presses(&self) -> u32 {
+        self.stray_presses
+    }
+
+    /// Number of targets judged a given way
+    pub fn count(&self, judgement: Judgement) -> usize {
+        self.judgements
+            .iter()
+            .filter(|&&judged| judged == Some(judgement))
+            .count()
+    }
+
+    /// Score as a percentage of a perfect run
+    pub fn accuracy(&self) -> f32 {
+        let best = self.targets.len() as u32 * Judgement::Perfect.points();
+        if best == 0 {
+            return 0.0;
+        }
+        100.0 * self.score as f32 / best as f32
+    }
+
+    /// Average timing error of the hits in seconds (positive is late)
+    pub fn mean_error(&self) -> Option<f32> {
+        if self.errors.is_empty() {
+            return None;
+        }
+        Some(self.errors.iter().sum::<f32>() / self.errors.len() as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_targets() {
+        let game = RhythmGame::new(find_pattern("offbeats").unwrap(), 120.0, 2);
+        let targets: Vec<f32> = game.targets().map(|(time, _)| time).collect();
+        // One bar of count-in (2 s at 120 bpm), then half a beat in
+        assert_eq!(targets.len(), 8);
+        assert_eq!(targets[0], 2.25);
+        assert_eq!(targets[4], 4.25);
+        assert_eq!(game.count_in(), vec![0.0, 0.5, 1.0, 1.5]);
+        assert!(PATTERNS.iter().all(|pattern| {
+            pattern
+                .beats
+                .iter()
+                .all(|beat| (0.0..BEATS_PER_BAR).contains(beat))
+        }));
+    }
+
+    #[test]
+    fn test_press() {
+        let mut game = RhythmGame::new(find_pattern("quarters").unwrap(), 60.0, 1);
+        // Targets at 4, 5, 6 and 7 seconds
+        assert_eq!(game.press(4.01), Some(Judgement::Perfect));
+        assert_eq!(game.press(4.5), None);
+        assert_eq!(game.combo(), 0);
+        assert_eq!(game.press(4.94), Some(Judgement::Good));
+        assert_eq!(game.press(6.12), Some(Judgement::Ok));
+        assert_eq!(game.combo(), 2);
+        assert!(!game.is_finished());
+
+        game.update(7.2);
+        assert!(game.is_finished());
+        assert_eq!(game.count(Judgement::Miss), 1);
+        assert_eq!(game.combo(), 0);
+        assert_eq!(game.max_combo(), 2);
+        assert_eq!(game.score(), 450);
+        assert_eq!(game.stray_presses(), 1);
+        assert!((game.accuracy() - 37.5).abs() < 1e-4);
+        assert!((game.mean_error().unwrap() - 0.07 / 3.0).abs() < 1e-4);
+    }
+}
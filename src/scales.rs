@@ -0,0 +1,304 @@
+//! Musical scales module
+//!
+//! This module lets a keyboard config declare a scale instead of (or on top
+//! of) listing every key. With `auto_assign` enabled, keys missing from
+//! `mappings` get notes from the scale: the most frequently typed keys get
+//! the lowest degrees, wrapping around after the configured number of octaves.
+
+use crate::keyboard_mapping::{midi_to_note, note_to_midi};
+use serde::{Deserialize, Serialize};
+
+/// Keys in roughly the order they are typed when writing code and prose
+pub const KEY_FREQUENCY_ORDER: &[&str] = &[
+    "Space",
+    "E",
+    "T",
+    "A",
+    "O",
+    "I",
+    "N",
+    "S",
+    "R",
+    "H",
+    "L",
+    "D",
+    "C",
+    "U",
+    "M",
+    "F",
+    "P",
+    "G",
+    "W",
+    "Y",
+    "B",
+    "V",
+    "K",
+    "X",
+    "J",
+    "Q",
+    "Z",
+    "Enter",
+    "Dot",
+    "Comma",
+    "Semicolon",
+    "LeftParen",
+    "RightParen",
+    "Equal",
+    "Minus",
+    "Underscore",
+    "Slash",
+    "Apostrophe",
+    "DoubleQuote",
+    "Colon",
+    "LeftBrace",
+    "RightBrace",
+    "LeftBracket",
+    "RightBracket",
+    "Key0",
+    "Key1",
+    "Key2",
+    "Key3",
+    "Key4",
+    "Key5",
+    "Key6",
+    "Key7",
+    "Key8",
+    "Key9",
+    "Tab",
+    "Backspace",
+    "LessThan",
+    "GreaterThan",
+    "Ampersand",
+    "Pipe",
+    "Asterisk",
+    "Plus",
+    "Exclamation",
+    "Question",
+    "Hash",
+    "Dollar",
+    "At",
+    "Percent",
+    "Caret",
+    "Tilde",
+    "Grave",
+    "BackSlash",
+];
+
+/// Scale modes available for automatic note assignment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScaleMode {
+    Major,
+    Minor,
+    #[default]
+    Pentatonic,
+    MinorPentatonic,
+    Blues,
+    Dorian,
+    Chromatic,
+}
+
+impl ScaleMode {
+    /// Semitones above the root for each degree of one octave
+    pub fn intervals(&self) -> &'static [i32] {
+        match self {
+            ScaleMode::Major => &[0, 2, 4, 5, 7, 9, 11],
+            ScaleMode::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            ScaleMode::Pentatonic => &[0, 2, 4, 7, 9],
+            ScaleMode::MinorPentatonic => &[0, 3, 5, 7, 10],
+            ScaleMode::Blues => &[0, 3, 5, 6, 7, 10],
+            ScaleMode::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            ScaleMode::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+        }
+    }
+}
+
+impl std::str::FromStr for ScaleMode {
+    type Err = String;
+
+    /// Parse a mode name such as "dorian", "minor pentatonic" or "major_pentatonic"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s
+            .split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join("_")
+            .to_lowercase();
+        match name.as_str() {
+            "major" | "ionian" => Ok(ScaleMode::Major),
+            "minor" | "natural_minor" | "aeolian" => Ok(ScaleMode::Minor),
+            "pentatonic" | "major_pentatonic" => Ok(ScaleMode::Pentatonic),
+            "minor_pentatonic" => Ok(ScaleMode::MinorPentatonic),
+            "blues" => Ok(ScaleMode::Blues),
+            "dorian" => Ok(ScaleMode::Dorian),
+            "chromatic" => Ok(ScaleMode::Chromatic),
+            _ => Err(format!("Unknown scale mode '{}'", s)),
+        }
+    }
+}
+
+/// Semitones above the root ordered from most to least consonant
+/// (unison, fifth, thirds, sixths, fourth, then the dissonant intervals)
+const CONSONANCE_ORDER: [i32; 12] = [0, 7, 4, 3, 9, 8, 5, 2, 10, 11, 1, 6];
+
+/// A scale declared by a keyboard config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScaleConfig {
+    /// Scale mode, e.g. "major" or "minor_pentatonic"
+    #[serde(default)]
+    pub mode: ScaleMode,
+    /// Lowest note of the scale
+    #[serde(default = "default_root")]
+    pub root: String,
+    /// Octaves the assigned notes span before wrapping back to the root
+    #[serde(default = "default_octaves")]
+    pub octaves: u32,
+    /// Volume of automatically assigned keys (0.0 to 1.0)
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+}
+
+fn default_root() -> String {
+    "C4".to_string()
+}
+
+fn default_octaves() -> u32 {
+    2
+}
+
+fn default_volume() -> f32 {
+    0.3
+}
+
+impl ScaleConfig {
+    /// Note for a scale degree counted from the root (0 is the root itself)
+    pub fn note_for_degree(&self, degree: usize) -> Option<String> {
+        let root = note_to_midi(&self.root)?;
+        let intervals = self.mode.intervals();
+        let octave = (degree / intervals.len()) % self.octaves.max(1) as usize;
+        let midi_note = root + intervals[degree % intervals.len()] + 12 * octave as i32;
+        Some(midi_to_note(midi_note))
+    }
+
+    /// Every note of the scale across its octaves, most consonant with the root first
+    /// (lower octaves first among equally consonant notes)
+    pub fn notes_by_consonance(&self) -> Vec<String> {
+        let Some(root) = note_to_midi(&self.root) else {
+            return Vec::new();
+        };
+        let mut offsets: Vec<i32> = (0..self.octaves.max(1) as i32)
+            .flat_map(|octave| {
+                self.mode
+                    .intervals()
+                    .iter()
+                    .map(move |interval| interval + 12 * octave)
+            })
+            .collect();
+        offsets.sort_by_key(|offset| {
+            let rank = CONSONANCE_ORDER
+                .iter()
+                .position(|interval| *interval == offset % 12);
+            (rank, *offset)
+        });
+        offsets
+            .into_iter()
+            .map(|offset| midi_to_note(root + offset))
+            .collect()
+    }
+
+    /// Note for a key that has no mapping: the keys are ranked by typing frequency,
+    /// skipping those in `is_mapped`, and each takes the next scale degree
+    pub fn note_for_key(&self, key_name: &str, is_mapped: impl Fn(&str) -> bool) -> Option<String> {
+        let degree = KEY_FREQUENCY_ORDER
+            .iter()
+            .filter(|key| !is_mapped(key))
+            .position(|key| *key == key_name)?;
+        self.note_for_degree(degree)
+    }
+}
+
+impl std::str::FromStr for ScaleConfig {
+    type Err = String;
+
+    /// Parse a root and mode such as "D dorian" or "C3 minor pentatonic"
+    /// (the root defaults to octave 4)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (root, mode) = s
+            .split_once(char::is_whitespace)
+            .unwrap_or((s, "pentatonic"));
+        let root = if root.ends_with(|c: char| c.is_ascii_digit()) {
+            root.to_string()
+        } else {
+            format!("{}4", root)
+        };
+        if note_to_midi(&root).is_none() {
+            return Err(format!("Scale root '{}' is not a note", root));
+        }
+
+        Ok(ScaleConfig {
+            mode: mode.parse()?,
+            root,
+            octaves: default_octaves(),
+            volume: default_volume(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scale(mode: ScaleMode) -> ScaleConfig {
+        serde_json::from_value(serde_json::json!({ "mode": mode, "root": "A3" })).unwrap()
+    }
+
+    #[test]
+    fn test_note_for_degree() {
+        let pentatonic = scale(ScaleMode::MinorPentatonic);
+        assert_eq!(pentatonic.octaves, 2);
+        assert_eq!(pentatonic.note_for_degree(0).as_deref(), Some("A3"));
+        assert_eq!(pentatonic.note_for_degree(1).as_deref(), Some("C4"));
+        assert_eq!(pentatonic.note_for_degree(5).as_deref(), Some("A4"));
+        // Wraps back to the root after two octaves
+        assert_eq!(pentatonic.note_for_degree(10).as_deref(), Some("A3"));
+    }
+
+    #[test]
+    fn test_note_for_key() {
+        let major = scale(ScaleMode::Major);
+        assert_eq!(
+            major.note_for_key("Space", |_| false).as_deref(),
+            Some("A3")
+        );
+        assert_eq!(major.note_for_key("T", |_| false).as_deref(), Some("C#4"));
+
+        // Mapped keys give up their place in the ranking
+        let mapped = |key: &str| key == "Space" || key == "E";
+        assert_eq!(major.note_for_key("T", mapped).as_deref(), Some("A3"));
+        assert_eq!(major.note_for_key("LShift", mapped), None);
+    }
+
+    #[test]
+    fn test_parse_scale() {
+        let dorian: ScaleConfig = "D dorian".parse().unwrap();
+        assert_eq!(dorian.mode, ScaleMode::Dorian);
+        assert_eq!(dorian.root, "D4");
+
+        let pentatonic: ScaleConfig = "C3 major pentatonic".parse().unwrap();
+        assert_eq!(pentatonic.mode, ScaleMode::Pentatonic);
+        assert_eq!(pentatonic.root, "C3");
+
+        assert!("H dorian".parse::<ScaleConfig>().is_err());
+        assert!("C lydian".parse::<ScaleConfig>().is_err());
+    }
+
+    #[test]
+    fn test_notes_by_consonance() {
+        let major: ScaleConfig = "C major".parse().unwrap();
+        let notes = major.notes_by_consonance();
+        assert_eq!(notes.len(), 14);
+        assert_eq!(&notes[..4], ["C4", "C5", "G4", "G5"]);
+        assert_eq!(notes.last().map(String::as_str), Some("B5"));
+    }
+}
@@ -1,149 +1,299 @@
 //! Input sequence detection module for Easter eggs
 //!
 //! This module provides functionality to detect specific input sequences
-//! from keyboard input, such as the Japanese Easter egg sequence.
+//! from keyboard input, such as the Japanese Easter egg sequence and any
+//! additional sequences declared in a keyboard configuration.
 
-use device_query::Keycode;
+use crate::keycode::Keycode;
 use std::collections::VecDeque;
+use std::str::FromStr;
 
 /// Maximum length of input history to keep for sequence detection
 const MAX_HISTORY_LENGTH: usize = 50;
 
-/// Input sequence detector for Easter eggs
-pub struct SequenceDetector {
-    /// History of recent key inputs (excluding spaces and modifiers)
-    input_history: VecDeque<char>,
-    /// Target sequence to detect (as lowercase chars)
-    target_sequence: Vec<char>,
+/// Name of the built-in Japanese Easter egg sequence
+pub const DEFAULT_SEQUENCE_NAME: &str = "oppokokoppokosuttenten";
+
+/// A sequence to detect along with its anti-spam state
+struct TargetSequence {
+    /// Name reported when the sequence is detected
+    name: String,
+    /// Target sequence to detect
+    keys: Vec<Keycode>,
+    /// Whether only letter keys count (typed words ignore spaces, digits, etc.)
+    letters_only: bool,
     /// Whether the sequence was recently triggered (to avoid spam)
     recently_triggered: bool,
     /// Counter to reset the recently_triggered flag after some inputs
     reset_counter: usize,
 }
 
+impl TargetSequence {
+    fn new(name: &str, keys: Vec<Keycode>, letters_only: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            keys,
+            letters_only,
+            recently_triggered: false,
+            reset_counter: 0,
+        }
+    }
+
+    /// Whether a key takes part in matching this sequence
+    fn tracks(&self, keycode: Keycode) -> bool {
+        if self.letters_only {
+            letter_to_char(keycode).is_some()
+        } else {
+            !is_modifier(keycode)
+        }
+    }
+}
+
+/// Input sequence detector for Easter eggs
+pub struct SequenceDetector {
+    /// History of recent key inputs (excluding modifiers)
+    input_history: VecDeque<Keycode>,
+    /// Sequences to detect, checked in insertion order
+    targets: Vec<TargetSequence>,
+}
+
+impl Default for SequenceDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SequenceDetector {
     /// Create a new sequence detector
     pub fn new() -> Self {
         // Japanese "おっぽこ　こっぽこ　すってんてん" in romaji: "oppokokoppokosuttenten"
-        let target = "oppokokoppokosuttenten";
+        let target = DEFAULT_SEQUENCE_NAME;
+        let keys = target
+            .chars()
+            .filter_map(|ch| Keycode::from_str(&ch.to_ascii_uppercase().to_string()).ok())
+            .collect();
 
         Self {
             input_history: VecDeque::with_capacity(MAX_HISTORY_LENGTH),
-            target_sequence: target.chars().collect(),
-            recently_triggered: false,
-            reset_counter: 0,
+            targets: vec![TargetSequence::new(target, keys, true)],
         }
     }
 
-    /// Process a key input and check for sequence match
-    pub fn process_input(&mut self, keycode: Keycode) -> bool {
-        // Convert keycode to character, ignoring non-letter keys and spaces
-        if let Some(ch) = self.keycode_to_char(keycode) {
-            // Add to history
-            self.input_history.push_back(ch);
-
-            // Trim history if too long
-            if self.input_history.len() > MAX_HISTORY_LENGTH {
-                self.input_history.pop_front();
-            }
-
-            // Reset recently_triggered flag after enough inputs
-            self.reset_counter += 1;
-            if self.reset_counter > self.target_sequence.len() {
-                self.recently_triggered = false;
-                self.reset_counter = 0;
-            }
+    /// Add a sequence of letters to detect, replacing any sequence with the same name
+    ///
+    /// Letter sequences ignore non-letter keys, so spaces or punctuation typed
+    /// in between don't break the match.
+    pub fn add_sequence(
+        &mut self,
+        name: &str,
+        keys: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let keys = keys
+            .chars()
+            .filter(|ch| !ch.is_whitespace())
+            .map(|ch| {
+                Some(ch)
+                    .filter(char::is_ascii_alphabetic)
+                    .and_then(|ch| Keycode::from_str(&ch.to_ascii_uppercase().to_string()).ok())
+                    .ok_or_else(|| format!("Sequence '{}' contains unsupported key '{}'", name, ch))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.insert_target(TargetSequence::new(name, keys, true))
+    }
 
-            // Check for sequence match (only if not recently triggered)
-            if !self.recently_triggered {
-                if self.check_sequence_match() {
-                    self.recently_triggered = true;
-                    self.reset_counter = 0;
-                    return true;
-                }
-            }
+    /// Add a sequence of arbitrary keys to detect (e.g. Up, Up, Down, Down, ..., B, A)
+    ///
+    /// Every non-modifier key counts, so the keys must be pressed exactly in order.
+    pub fn add_key_sequence(
+        &mut self,
+        name: &str,
+        keys: Vec<Keycode>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(key) = keys.iter().find(|key| is_modifier(**key)) {
+            return Err(format!("Sequence '{}' contains modifier key '{:?}'", name, key).into());
         }
 
-        false
+        self.insert_target(TargetSequence::new(name, keys, false))
     }
 
-    /// Convert keycode to lowercase character, filtering out non-letters and spaces
-    fn keycode_to_char(&self, keycode: Keycode) -> Option<char> {
-        match keycode {
-            // Letters
-            Keycode::A => Some('a'),
-            Keycode::B => Some('b'),
-            Keycode::C => Some('c'),
-            Keycode::D => Some('d'),
-            Keycode::E => Some('e'),
-            Keycode::F => Some('f'),
-            Keycode::G => Some('g'),
-            Keycode::H => Some('h'),
-            Keycode::I => Some('i'),
-            Keycode::J => Some('j'),
-            Keycode::K => Some('k'),
-            Keycode::L => Some('l'),
-            Keycode::M => Some('m'),
-            Keycode::N => Some('n'),
-            Keycode::O => Some('o'),
-            Keycode::P => Some('p'),
-            Keycode::Q => Some('q'),
-            Keycode::R => Some('r'),
-            Keycode::S => Some('s'),
-            Keycode::T => Some('t'),
-            Keycode::U => Some('u'),
-            Keycode::V => Some('v'),
-            Keycode::W => Some('w'),
-            Keycode::X => Some('x'),
-            Keycode::Y => Some('y'),
-            Keycode::Z => Some('z'),
-
-            // Ignore all other keys (numbers, symbols, spaces, modifiers, etc.)
-            _ => None,
+    /// Validate and store a target, replacing any target with the same name
+    fn insert_target(&mut self, target: TargetSequence) -> Result<(), Box<dyn std::error::Error>> {
+        if target.keys.is_empty() {
+            return Err(format!("Sequence '{}' has no keys", target.name).into());
         }
+        if target.keys.len() > MAX_HISTORY_LENGTH {
+            return Err(format!(
+                "Sequence '{}' is longer than {} keys",
+                target.name, MAX_HISTORY_LENGTH
+            )
+            .into());
+        }
+
+        self.targets.retain(|existing| existing.name != target.name);
+        self.targets.push(target);
+        Ok(())
     }
 
-    /// Check if the current input history contains the target sequence
-    fn check_sequence_match(&self) -> bool {
-        let history_len = self.input_history.len();
-        let target_len = self.target_sequence.len();
+    /// Names of all sequences being detected
+    pub fn sequence_names(&self) -> Vec<&str> {
+        self.targets
+            .iter()
+            .map(|target| target.name.as_str())
+            .collect()
+    }
+
+    /// Process a key input and check for sequence match
+    pub fn process_input(&mut self, keycode: Keycode) -> bool {
+        self.detect(keycode).is_some()
+    }
+
+    /// Process a key input and return the name of the sequence it completes, if any
+    pub fn detect(&mut self, keycode: Keycode) -> Option<String> {
+        // Modifiers never take part in sequences
+        if is_modifier(keycode) {
+            return None;
+        }
+
+        // Add to history
+        self.input_history.push_back(keycode);
 
-        if history_len < target_len {
-            return false;
+        // Trim history if too long
+        if self.input_history.len() > MAX_HISTORY_LENGTH {
+            self.input_history.pop_front();
         }
 
-        // Check if the last N characters match the target sequence
-        for i in 0..target_len {
-            let history_idx = history_len - target_len + i;
-            if self.input_history[history_idx] != self.target_sequence[i] {
-                return false;
+        let mut detected = None;
+        for target in self.targets.iter_mut() {
+            if !target.tracks(keycode) {
+                continue;
+            }
+
+            // Reset recently_triggered flag after enough inputs
+            target.reset_counter += 1;
+            if target.reset_counter > target.keys.len() {
+                target.recently_triggered = false;
+                target.reset_counter = 0;
+            }
+
+            // Check for sequence match (only if not recently triggered)
+            if detected.is_none()
+                && !target.recently_triggered
+                && Self::check_sequence_match(&self.input_history, target)
+            {
+                target.recently_triggered = true;
+                target.reset_counter = 0;
+                detected = Some(target.name.clone());
             }
         }
 
-        true
+        detected
+    }
+
+    /// Check if the keys the target tracks in the input history end with its sequence
+    fn check_sequence_match(input_history: &VecDeque<Keycode>, target: &TargetSequence) -> bool {
+        // Compare backwards from the most recent input, skipping untracked keys
+        let mut recent = input_history
+            .iter()
+            .rev()
+            .filter(|key| target.tracks(**key));
+        target
+            .keys
+            .iter()
+            .rev()
+            .all(|key| recent.next() == Some(key))
     }
 
     /// Get the current input history as a string (for debugging)
     #[allow(dead_code)]
     pub fn get_history_string(&self) -> String {
-        self.input_history.iter().collect()
+        self.input_history
+            .iter()
+            .filter_map(|key| letter_to_char(*key))
+            .collect()
     }
 
-    /// Get the target sequence as a string (for debugging)
+    /// Get the built-in target sequence as a string (for debugging)
     #[allow(dead_code)]
     pub fn get_target_string(&self) -> String {
-        self.target_sequence.iter().collect()
+        self.targets
+            .first()
+            .map(|target| {
+                target
+                    .keys
+                    .iter()
+                    .filter_map(|key| letter_to_char(*key))
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
     /// Reset the detector state
     #[allow(dead_code)]
     pub fn reset(&mut self) {
         self.input_history.clear();
-        self.recently_triggered = false;
-        self.reset_counter = 0;
+        for target in self.targets.iter_mut() {
+            target.recently_triggered = false;
+            target.reset_counter = 0;
+        }
     }
 }
 
+/// Convert a letter keycode to its lowercase character
+fn letter_to_char(keycode: Keycode) -> Option<char> {
+    match keycode {
+        // Letters
+        Keycode::A => Some('a'),
+        Keycode::B => Some('b'),
+        Keycode::C => Some('c'),
+        Keycode::D => Some('d'),
+        Keycode::E => Some('e'),
+        Keycode::F => Some('f'),
+        Keycode::G => Some('g'),
+        Keycode::H => Some('h'),
+        Keycode::I => Some('i'),
+        Keycode::J => Some('j'),
+        Keycode::K => Some('k'),
+        Keycode::L => Some('l'),
+        Keycode::M => Some('m'),
+        Keycode::N => Some('n'),
+        Keycode::O => Some('o'),
+        Keycode::P => Some('p'),
+        Keycode::Q => Some('q'),
+        Keycode::R => Some('r'),
+        Keycode::S => Some('s'),
+        Keycode::T => Some('t'),
+        Keycode::U => Some('u'),
+        Keycode::V => Some('v'),
+        Keycode::W => Some('w'),
+        Keycode::X => Some('x'),
+        Keycode::Y => Some('y'),
+        Keycode::Z => Some('z'),
+
+        // Ignore all other keys (numbers, symbols, spaces, modifiers, etc.)
+        _ => None,
+    }
+}
+
+/// Check if a keycode is a modifier key (ignored by sequence detection)
+fn is_modifier(keycode: Keycode) -> bool {
+    matches!(
+        keycode,
+        Keycode::LShift
+            | Keycode::RShift
+            | Keycode::LControl
+            | Keycode::RControl
+            | Keycode::LAlt
+            | Keycode::RAlt
+            | Keycode::LMeta
+            | Keycode::RMeta
+            | Keycode::Command
+            | Keycode::LOption
+            | Keycode::ROption
+            | Keycode::CapsLock
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -156,17 +306,15 @@ mod tests {
     }
 
     #[test]
-    fn test_keycode_to_char() {
-        let detector = SequenceDetector::new();
-
+    fn test_letter_to_char() {
         // Test letter conversion
-        assert_eq!(detector.keycode_to_char(Keycode::A), Some('a'));
-        assert_eq!(detector.keycode_to_char(Keycode::Z), Some('z'));
+        assert_eq!(letter_to_char(Keycode::A), Some('a'));
+        assert_eq!(letter_to_char(Keycode::Z), Some('z'));
 
         // Test ignored keys
-        assert_eq!(detector.keycode_to_char(Keycode::Space), None);
-        assert_eq!(detector.keycode_to_char(Keycode::Enter), None);
-        assert_eq!(detector.keycode_to_char(Keycode::Key1), None);
+        assert_eq!(letter_to_char(Keycode::Space), None);
+        assert_eq!(letter_to_char(Keycode::Enter), None);
+        assert_eq!(letter_to_char(Keycode::Key1), None);
     }
 
     #[test]
@@ -338,4 +486,78 @@ mod tests {
 
         assert_eq!(trigger_count, 1, "Should only trigger once to prevent spam");
     }
+
+    #[test]
+    fn test_custom_sequences() {
+        let mut detector = SequenceDetector::new();
+        detector.add_sequence("hi", "H I").unwrap();
+        assert_eq!(detector.sequence_names(), vec![DEFAULT_SEQUENCE_NAME, "hi"]);
+
+        assert_eq!(detector.detect(Keycode::H), None);
+        assert_eq!(detector.detect(Keycode::I), Some("hi".to_string()));
+
+        // Invalid sequences are rejected
+        assert!(detector.add_sequence("empty", " ").is_err());
+        assert!(detector.add_sequence("digits", "a1").is_err());
+    }
+
+    #[test]
+    fn test_add_sequence_replaces_same_name() {
+        let mut detector = SequenceDetector::new();
+        detector.add_sequence("combo", "ab").unwrap();
+        detector.add_sequence("combo", "cd").unwrap();
+        assert_eq!(detector.sequence_names().len(), 2);
+
+        detector.detect(Keycode::A);
+        assert_eq!(detector.detect(Keycode::B), None);
+        detector.detect(Keycode::C);
+        assert_eq!(detector.detect(Keycode::D), Some("combo".to_string()));
+    }
+
+    #[test]
+    fn test_konami_key_sequence() {
+        let konami = vec![
+            Keycode::Up,
+            Keycode::Up,
+            Keycode::Down,
+            Keycode::Down,
+            Keycode::Left,
+            Keycode::Right,
+            Keycode::Left,
+            Keycode::Right,
+            Keycode::B,
+            Keycode::A,
+        ];
+        let mut detector = SequenceDetector::new();
+        detector.add_key_sequence("konami", konami.clone()).unwrap();
+
+        // Modifiers are ignored, but other keys in between break the combo
+        detector.detect(Keycode::LShift);
+        let results: Vec<_> = konami.iter().map(|key| detector.detect(*key)).collect();
+        assert_eq!(results.last().unwrap().as_deref(), Some("konami"));
+        assert!(results[..9].iter().all(Option::is_none));
+
+        for key in &konami[..9] {
+            detector.detect(*key);
+        }
+        detector.detect(Keycode::Space);
+        assert_eq!(detector.detect(Keycode::A), None);
+
+        assert!(
+            detector
+                .add_key_sequence("modifier", vec![Keycode::LControl, Keycode::A])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_letter_sequence_ignores_other_keys() {
+        let mut detector = SequenceDetector::new();
+        detector.add_sequence("hi", "hi").unwrap();
+
+        detector.detect(Keycode::H);
+        detector.detect(Keycode::Up);
+        detector.detect(Keycode::Space);
+        assert_eq!(detector.detect(Keycode::I), Some("hi".to_string()));
+    }
 }
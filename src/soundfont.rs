@@ -0,0 +1,896 @@
+//! SoundFont (SF2) loading and playback
+//!
+//! This module parses SoundFont 2 files and turns a selected preset into a
+//! playable instrument. Notes are rendered by resampling the preset's sample
+//! zones, giving instant access to realistic instrument sounds alongside the
+//! synthesized waveforms.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+// Generator operators understood by the player (SF2 spec section 8.1.2)
+const GEN_START_ADDRS_OFFSET: u16 = 0;
+const GEN_END_ADDRS_OFFSET: u16 = 1;
+const GEN_STARTLOOP_ADDRS_OFFSET: u16 = 2;
+const GEN_ENDLOOP_ADDRS_OFFSET: u16 = 3;
+const GEN_START_ADDRS_COARSE_OFFSET: u16 = 4;
+const GEN_END_ADDRS_COARSE_OFFSET: u16 = 12;
+const GEN_INSTRUMENT: u16 = 41;
+const GEN_KEY_RANGE: u16 = 43;
+const GEN_VEL_RANGE: u16 = 44;
+const GEN_STARTLOOP_ADDRS_COARSE_OFFSET: u16 = 45;
+const GEN_INITIAL_ATTENUATION: u16 = 48;
+const GEN_ENDLOOP_ADDRS_COARSE_OFFSET: u16 = 50;
+const GEN_COARSE_TUNE: u16 = 51;
+const GEN_FINE_TUNE: u16 = 52;
+const GEN_SAMPLE_ID: u16 = 53;
+const GEN_SAMPLE_MODES: u16 = 54;
+const GEN_OVERRIDING_ROOT_KEY: u16 = 58;
+
+// Sample types for the two halves of a stereo pair
+const SAMPLE_TYPE_RIGHT: u16 = 2;
+const SAMPLE_TYPE_LEFT: u16 = 4;
+
+/// Bank/program pair identifying a preset inside a SoundFont
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct PresetId {
+    pub bank: u16,
+    pub program: u16,
+}
+
+impl std::str::FromStr for PresetId {
+    type Err = String;
+
+    /// Parse "bank:program" or just "program" (bank 0)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |part: &str| {
+            part.trim()
+                .parse::<u16>()
+                .map_err(|_| format!("Invalid preset: {}", s))
+        };
+
+        match s.split_once(':') {
+            Some((bank, program)) => Ok(PresetId {
+                bank: parse(bank)?,
+                program: parse(program)?,
+            }),
+            None => Ok(PresetId {
+                bank: 0,
+                program: parse(s)?,
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for PresetId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.bank, self.program)
+    }
+}
+
+/// SoundFont file and preset chosen as the engine's sound source
+#[derive(Clone, Debug, PartialEq)]
+pub struct SoundFontSelection {
+    pub path: String,
+    pub preset: PresetId,
+}
+
+impl SoundFontSelection {
+    /// Load the selected preset from its file
+    pub fn load_preset(&self) -> Result<SoundFontPreset, Box<dyn std::error::Error>> {
+        let soundfont = SoundFont::load_from_file(&self.path)?;
+        soundfont.preset(self.preset).ok_or_else(|| {
+            format!(
+                "Preset {} not found in SoundFont '{}'",
+                self.preset, self.path
+            )
+            .into()
+        })
+    }
+}
+
+/// Name and location of a preset, for listing
+#[derive(Clone, Debug)]
+pub struct PresetInfo {
+    pub name: String,
+    pub id: PresetId,
+}
+
+/// How a sample zone loops during playback
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LoopMode {
+    NoLoop,
+    Continuous,
+    UntilRelease,
+}
+
+#[derive(Clone, Debug)]
+struct SampleHeader {
+    start: u32,
+    end: u32,
+    loop_start: u32,
+    loop_end: u32,
+    sample_rate: u32,
+    original_pitch: u8,
+    pitch_correction: i8,
+    sample_type: u16,
+}
+
+/// Generator list of a preset or instrument zone
+#[derive(Clone, Debug, Default)]
+struct Zone {
+    generators: Vec<(u16, u16)>,
+}
+
+impl Zone {
+    fn get(&self, oper: u16) -> Option<u16> {
+        self.generators
+            .iter()
+            .rev()
+            .find(|(op, _)| *op == oper)
+            .map(|(_, amount)| *amount)
+    }
+
+    fn get_signed(&self, oper: u16) -> Option<i32> {
+        self.get(oper).map(|amount| amount as i16 as i32)
+    }
+
+    fn get_range(&self, oper: u16) -> Option<(u8, u8)> {
+        self.get(oper)
+            .map(|amount| ((amount & 0xFF) as u8, (amount >> 8) as u8))
+    }
+}
+
+/// Look up a generator in a zone, falling back to the global zone
+fn lookup(zone: &Zone, global: Option<&Zone>, oper: u16) -> Option<u16> {
+    zone.get(oper).or_else(|| global.and_then(|g| g.get(oper)))
+}
+
+fn lookup_signed(zone: &Zone, global: Option<&Zone>, oper: u16) -> i32 {
+    zone.get_signed(oper)
+        .or_else(|| global.and_then(|g| g.get_signed(oper)))
+        .unwrap_or(0)
+}
+
+fn lookup_range(zone: &Zone, global: Option<&Zone>, oper: u16) -> (u8, u8) {
+    zone.get_range(oper)
+        .or_else(|| global.and_then(|g| g.get_range(oper)))
+        .unwrap_or((0, 127))
+}
+
+/// Split a zone list into its optional global zone and the regular zones
+fn split_global(zones: &[Zone], terminal_oper: u16) -> (Option<&Zone>, &[Zone]) {
+    match zones.first() {
+        Some(first) if first.get(terminal_oper).is_none() => (Some(first), &zones[1..]),
+        _ => (None, zones),
+    }
+}
+
+struct PresetRecord {
+    info: PresetInfo,
+    zones: Vec<Zone>,
+}
+
+struct InstrumentRecord {
+    zones: Vec<Zone>,
+}
+
+/// A fully resolved playable region of a preset
+#[derive(Clone, Debug)]
+struct Region {
+    key_range: (u8, u8),
+    vel_range: (u8, u8),
+    start: usize,
+    end: usize,
+    loop_start: usize,
+    loop_end: usize,
+    loop_mode: LoopMode,
+    root_key: u8,
+    tune_cents: f32,
+    gain: f32,
+    sample_rate: u32,
+}
+
+impl Region {
+    fn matches(&self, key: u8, velocity: u8) -> bool {
+        (self.key_range.0..=self.key_range.1).contains(&key)
+            && (self.vel_range.0..=self.vel_range.1).contains(&velocity)
+    }
+}
+
+/// A parsed SoundFont 2 file
+pub struct SoundFont {
+    presets: Vec<PresetRecord>,
+    instruments: Vec<InstrumentRecord>,
+    samples: Vec<SampleHeader>,
+    data: Arc<Vec<f32>>,
+}
+
+impl SoundFont {
+    /// Load a SoundFont from an .sf2 file
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let bytes = fs::read(path)?;
+        Self::load_from_bytes(&bytes)
+    }
+
+    /// Load a SoundFont from byte data (SF2 format)
+    pub fn load_from_bytes(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let (id, body) = read_chunk(data, 0)?.ok_or("Empty SoundFont file")?;
+        if &id != b"RIFF" || body.len() < 4 || &body[..4] != b"sfbk" {
+            return Err("Not a SoundFont 2 file".into());
+        }
+
+        let mut sample_bytes: &[u8] = &[];
+        let mut pdta: Vec<Chunk> = Vec::new();
+
+        for (id, chunk) in chunks(&body[4..])? {
+            if &id != b"LIST" || chunk.len() < 4 {
+                continue;
+            }
+            let list_type = &chunk[..4];
+            for (sub_id, sub_chunk) in chunks(&chunk[4..])? {
+                match list_type {
+                    b"sdta" if &sub_id == b"smpl" => sample_bytes = sub_chunk,
+                    b"pdta" => pdta.push((sub_id, sub_chunk)),
+                    _ => {}
+                }
+            }
+        }
+
+        let find = |name: &[u8; 4]| -> Result<&[u8], String> {
+            pdta.iter()
+                .find(|(id, _)| id == name)
+                .map(|(_, chunk)| *chunk)
+                .ok_or_else(|| {
+                    format!(
+                        "SoundFont is missing the '{}' chunk",
+                        String::from_utf8_lossy(name)
+                    )
+                })
+        };
+
+        let generators = |chunk: &[u8]| -> Vec<(u16, u16)> {
+            chunk
+                .chunks_exact(4)
+                .map(|g| (read_u16(g, 0), read_u16(g, 2)))
+                .collect()
+        };
+
+        let pgen = generators(find(b"pgen")?);
+        let igen = generators(find(b"igen")?);
+        let pbag = bag_indices(find(b"pbag")?);
+        let ibag = bag_indices(find(b"ibag")?);
+
+        // Presets: the final record is a terminator that only bounds the bag list
+        let phdr = find(b"phdr")?;
+        let preset_records: Vec<&[u8]> = phdr.chunks_exact(38).collect();
+        let mut presets = Vec::new();
+        for pair in preset_records.windows(2) {
+            let (record, next) = (pair[0], pair[1]);
+            presets.push(PresetRecord {
+                info: PresetInfo {
+                    name: read_name(&record[..20]),
+                    id: PresetId {
+                        program: read_u16(record, 20),
+                        bank: read_u16(record, 22),
+                    },
+                },
+                zones: collect_zones(
+                    &pbag,
+                    &pgen,
+                    read_u16(record, 24) as usize,
+                    read_u16(next, 24) as usize,
+                ),
+            });
+        }
+
+        let inst = find(b"inst")?;
+        let inst_records: Vec<&[u8]> = inst.chunks_exact(22).collect();
+        let mut instruments = Vec::new();
+        for pair in inst_records.windows(2) {
+            instruments.push(InstrumentRecord {
+                zones: collect_zones(
+                    &ibag,
+                    &igen,
+                    read_u16(pair[0], 20) as usize,
+                    read_u16(pair[1], 20) as usize,
+                ),
+            });
+        }
+
+        let samples = find(b"shdr")?
+            .chunks_exact(46)
+            .map(|record| SampleHeader {
+                start: read_u32(record, 20),
+                end: read_u32(record, 24),
+                loop_start: read_u32(record, 28),
+                loop_end: read_u32(record, 32),
+                sample_rate: read_u32(record, 36),
+                original_pitch: record[40],
+                pitch_correction: record[41] as i8,
+                sample_type: read_u16(record, 44),
+            })
+            .collect();
+
+        // 16-bit signed PCM, normalized to -1.0..1.0
+        let data = sample_bytes
+            .chunks_exact(2)
+            .map(|s| i16::from_le_bytes([s[0], s[1]]) as f32 / 32768.0)
+            .collect();
+
+        Ok(SoundFont {
+            presets,
+            instruments,
+            samples,
+            data: Arc::new(data),
+        })
+    }
+
+    /// List all presets in the SoundFont, sorted by bank and program
+    pub fn presets(&self) -> Vec<PresetInfo> {
+        let mut presets: Vec<PresetInfo> = self.presets.iter().map(|p| p.info.clone()).collect();
+        presets.sort_by_key(|p| (p.id.bank, p.id.program));
+        presets
+    }
+
+    /// Resolve a preset into a playable instrument
+    pub fn preset(&self, id: PresetId) -> Option<SoundFontPreset> {
+        let preset = self.presets.iter().find(|p| p.info.id == id)?;
+        let (preset_global, preset_zones) = split_global(&preset.zones, GEN_INSTRUMENT);
+        let mut regions = Vec::new();
+
+        for preset_zone in preset_zones {
+            let Some(instrument) = preset_zone
+                .get(GEN_INSTRUMENT)
+                .and_then(|i| self.instruments.get(i as usize))
+            else {
+                continue;
+            };
+
+            let preset_keys = lookup_range(preset_zone, preset_global, GEN_KEY_RANGE);
+            let preset_vels = lookup_range(preset_zone, preset_global, GEN_VEL_RANGE);
+            // Preset-level tuning and attenuation are offsets added to the instrument's values
+            let preset_tune = lookup_signed(preset_zone, preset_global, GEN_COARSE_TUNE) * 100
+                + lookup_signed(preset_zone, preset_global, GEN_FINE_TUNE);
+            let preset_attenuation =
+                lookup_signed(preset_zone, preset_global, GEN_INITIAL_ATTENUATION);
+
+            let (inst_global, inst_zones) = split_global(&instrument.zones, GEN_SAMPLE_ID);
+            for zone in inst_zones {
+                if let Some(region) = self.resolve_region(
+                    zone,
+                    inst_global,
+                    preset_keys,
+                    preset_vels,
+                    preset_tune,
+                    preset_attenuation,
+                ) {
+                    regions.push(region);
+                }
+            }
+        }
+
+        Some(SoundFontPreset {
+            name: preset.info.name.clone(),
+            id,
+            regions: Arc::new(regions),
+            data: self.data.clone(),
+        })
+    }
+
+    /// Combine an instrument zone with its preset context into a region
+    fn resolve_region(
+        &self,
+        zone: &Zone,
+        global: Option<&Zone>,
+        preset_keys: (u8, u8),
+        preset_vels: (u8, u8),
+        preset_tune: i32,
+        preset_attenuation: i32,
+    ) -> Option<Region> {
+        let header = self.samples.get(zone.get(GEN_SAMPLE_ID)? as usize)?;
+
+        let intersect = |a: (u8, u8), b: (u8, u8)| (a.0.max(b.0), a.1.min(b.1));
+        let key_range = intersect(lookup_range(zone, global, GEN_KEY_RANGE), preset_keys);
+        let vel_range = intersect(lookup_range(zone, global, GEN_VEL_RANGE), preset_vels);
+        if key_range.0 > key_range.1 || vel_range.0 > vel_range.1 {
+            return None;
+        }
+
+        let offset = |fine: u16, coarse: u16| {
+            (lookup_signed(zone, global, fine) + lookup_signed(zone, global, coarse) * 32768) as i64
+        };
+        let clamp_addr = |base: u32, delta: i64| (base as i64 + delta).max(0) as usize;
+        let data_len = self.data.len();
+
+        let start = clamp_addr(
+            header.start,
+            offset(GEN_START_ADDRS_OFFSET, GEN_START_ADDRS_COARSE_OFFSET),
+        )
+        .min(data_len);
+        let end = clamp_addr(
+            header.end,
+            offset(GEN_END_ADDRS_OFFSET, GEN_END_ADDRS_COARSE_OFFSET),
+        )
+        .min(data_len);
+        let loop_start = clamp_addr(
+            header.loop_start,
+            offset(
+                GEN_STARTLOOP_ADDRS_OFFSET,
+                GEN_STARTLOOP_ADDRS_COARSE_OFFSET,
+            ),
+        );
+        let loop_end = clamp_addr(
+            header.loop_end,
+            offset(GEN_ENDLOOP_ADDRS_OFFSET, GEN_ENDLOOP_ADDRS_COARSE_OFFSET),
+        );
+        if start >= end {
+            return None;
+        }
+
+        let mut loop_mode = match lookup(zone, global, GEN_SAMPLE_MODES).unwrap_or(0) & 3 {
+            1 => LoopMode::Continuous,
+            3 => LoopMode::UntilRelease,
+            _ => LoopMode::NoLoop,
+        };
+        if loop_start >= loop_end || loop_start < start || loop_end > end {
+            loop_mode = LoopMode::NoLoop;
+        }
+
+        // -1 (the default) means "use the sample's original pitch"
+        let root_key = lookup(zone, global, GEN_OVERRIDING_ROOT_KEY)
+            .map(|key| key as i16)
+            .filter(|key| (0..=127).contains(key))
+            .map(|key| key as u8)
+            .unwrap_or(header.original_pitch.min(127));
+
+        let tune_cents = (lookup_signed(zone, global, GEN_COARSE_TUNE) * 100
+            + lookup_signed(zone, global, GEN_FINE_TUNE)
+            + preset_tune
+            + header.pitch_correction as i32) as f32;
+
+        // Attenuation is expressed in centibels
+        let attenuation_cb =
+            (lookup_signed(zone, global, GEN_INITIAL_ATTENUATION) + preset_attenuation).max(0);
+        let mut gain = 10.0_f32.powf(-(attenuation_cb as f32) / 200.0);
+        if header.sample_type & (SAMPLE_TYPE_LEFT | SAMPLE_TYPE_RIGHT) != 0 {
+            // Both halves of a stereo pair sound together in the mono mix
+            gain *= 0.5;
+        }
+
+        Some(Region {
+            key_range,
+            vel_range,
+            start,
+            end,
+            loop_start,
+            loop_end,
+            loop_mode,
+            root_key,
+            tune_cents,
+            gain,
+            sample_rate: header.sample_rate.max(1),
+        })
+    }
+}
+
+/// A preset resolved into playable regions, cheap to clone and share
+#[derive(Clone, Debug)]
+pub struct SoundFontPreset {
+    pub name: String,
+    pub id: PresetId,
+    regions: Arc<Vec<Region>>,
+    data: Arc<Vec<f32>>,
+}
+
+impl SoundFontPreset {
+    /// Start a voice for the given MIDI key and velocity
+    ///
+    /// Returns None if no region of the preset covers the key.
+    pub fn start_voice(
+        &self,
+        key: u8,
+        velocity: u8,
+        output_sample_rate: f32,
+    ) -> Option<SoundFontVoice> {
+        let layers: Vec<VoiceLayer> = self
+            .regions
+            .iter()
+            .filter(|region| region.matches(key, velocity))
+            .map(|region| {
+                let semitones = key as f32 - region.root_key as f32 + region.tune_cents / 100.0;
+                let step = 2.0_f64.powf(semitones as f64 / 12.0) * region.sample_rate as f64
+                    / output_sample_rate as f64;
+                VoiceLayer {
+                    region: region.clone(),
+                    position: region.start as f64,
+                    step,
+                    finished: false,
+                }
+            })
+            .collect();
+
+        if layers.is_empty() {
+            None
+        } else {
+            Some(SoundFontVoice {
+                data: self.data.clone(),
+                layers,
+                released: false,
+            })
+        }
+    }
+
+    /// Number of playable regions in this preset
+    pub fn region_count(&self) -> usize {
+        self.regions.len()
+    }
+}
+
+/// Playback state of one region within a voice
+#[derive(Clone, Debug)]
+struct VoiceLayer {
+    region: Region,
+    position: f64,
+    step: f64,
+    finished: bool,
+}
+
+/// A sounding SoundFont note, mixing every region that matched the key
+#[derive(Clone, Debug)]
+pub struct SoundFontVoice {
+    data: Arc<Vec<f32>>,
+    layers: Vec<VoiceLayer>,
+    released: bool,
+}
+
+impl SoundFontVoice {
+    /// Shift the voice's pitch by the given cents, for pitches between keys
+    pub fn detune(&mut self, cents: f32) {
+        let ratio = 2.0_f64.powf(cents as f64 / 1200.0);
+        for layer in &mut self.layers {
+            layer.step *= ratio;
+        }
+    }
+
+    /// Render the next output sample and advance playback
+    pub fn next_sample(&mut self) -> f32 {
+        let mut output = 0.0;
+
+        for layer in self.layers.iter_mut().filter(|l| !l.finished) {
+            let region = &layer.region;
+            let looping = match region.loop_mode {
+                LoopMode::Continuous => true,
+                LoopMode::UntilRelease => !self.released,
+                LoopMode::NoLoop => false,
+            };
+
+            let index = layer.position as usize;
+            if index >= region.end {
+                layer.finished = true;
+                continue;
+            }
+
+            // Linear interpolation, wrapping to the loop start while looping
+            let next_index = if looping && index + 1 >= region.loop_end {
+                region.loop_start
+            } else {
+                index + 1
+            };
+            let current = self.data[index];
+            let next = if next_index < region.end {
+                self.data[next_index]
+            } else {
+                0.0
+            };
+            let frac = layer.position.fract() as f32;
+            output += (current + (next - current) * frac) * region.gain;
+
+            layer.position += layer.step;
+            if looping && layer.position >= region.loop_end as f64 {
+                layer.position -= (region.loop_end - region.loop_start) as f64;
+            }
+        }
+
+        output
+    }
+
+    /// Leave any loop-until-release section and play out the sample tail
+    pub fn release(&mut self) {
+        self.released = true;
+    }
+
+    /// Check if every region has played to its end
+    pub fn is_finished(&self) -> bool {
+        self.layers.iter().all(|l| l.finished)
+    }
+}
+
+/// Convert a frequency to the nearest MIDI key number
+pub fn frequency_to_midi_key(frequency: f32) -> u8 {
+    let key = 69.0 + 12.0 * (frequency.max(1.0) / 440.0).log2();
+    key.round().clamp(0.0, 127.0) as u8
+}
+
+/// A RIFF chunk id and its body
+type Chunk<'a> = ([u8; 4], &'a [u8]);
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn read_name(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).trim().to_string()
+}
+
+/// Read the RIFF chunk at `offset`, returning its id and body
+fn read_chunk(data: &[u8], offset: usize) -> Result<Option<Chunk<'_>>, String> {
+    if offset + 8 > data.len() {
+        return Ok(None);
+    }
+    let id = [
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ];
+    let size = read_u32(data, offset + 4) as usize;
+    let body_start = offset + 8;
+    let body_end = body_start
+        .checked_add(size)
+        .filter(|&end| end <= data.len())
+        .ok_or_else(|| format!("Truncated '{}' chunk", String::from_utf8_lossy(&id)))?;
+    Ok(Some((id, &data[body_start..body_end])))
+}
+
+/// Split a buffer into consecutive RIFF chunks
+fn chunks(data: &[u8]) -> Result<Vec<Chunk<'_>>, String> {
+    let mut result = Vec::new();
+    let mut offset = 0;
+    while let Some((id, body)) = read_chunk(data, offset)? {
+        // Chunks are padded to an even length
+        offset += 8 + body.len() + (body.len() & 1);
+        result.push((id, body));
+    }
+    Ok(result)
+}
+
+/// Generator start indices from a pbag/ibag chunk
+fn bag_indices(chunk: &[u8]) -> Vec<usize> {
+    chunk
+        .chunks_exact(4)
+        .map(|bag| read_u16(bag, 0) as usize)
+        .collect()
+}
+
+/// Build the zones for bag indices `first..last`
+fn collect_zones(bags: &[usize], gens: &[(u16, u16)], first: usize, last: usize) -> Vec<Zone> {
+    (first..last)
+        .filter_map(|bag| {
+            let gen_start = *bags.get(bag)?;
+            let gen_end = (*bags.get(bag + 1)?).min(gens.len());
+            Some(Zone {
+                generators: gens.get(gen_start..gen_end)?.to_vec(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        out.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            out.push(0);
+        }
+        out
+    }
+
+    fn list(list_type: &[u8; 4], subchunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = list_type.to_vec();
+        for sub in subchunks {
+            body.extend_from_slice(sub);
+        }
+        chunk(b"LIST", &body)
+    }
+
+    fn name(n: &str) -> Vec<u8> {
+        let mut out = n.as_bytes().to_vec();
+        out.resize(20, 0);
+        out
+    }
+
+    fn bags(indices: &[u16]) -> Vec<u8> {
+        indices
+            .iter()
+            .flat_map(|&i| [i.to_le_bytes(), 0u16.to_le_bytes()].concat())
+            .collect()
+    }
+
+    fn gens(list: &[(u16, u16)]) -> Vec<u8> {
+        list.iter()
+            .flat_map(|&(op, amount)| [op.to_le_bytes(), amount.to_le_bytes()].concat())
+            .collect()
+    }
+
+    fn sample_header(start: u32, end: u32, loop_start: u32, loop_end: u32, pitch: u8) -> Vec<u8> {
+        let mut out = name("sine");
+        for value in [start, end, loop_start, loop_end, 100] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend_from_slice(&[pitch, 0]);
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out
+    }
+
+    /// Build a minimal SF2 with one preset (0:5) whose instrument has two key-split zones
+    fn build_test_soundfont() -> Vec<u8> {
+        let pcm: Vec<u8> = (0..200i16).flat_map(|i| (i * 100).to_le_bytes()).collect();
+
+        let mut phdr = name("Test Piano");
+        phdr.extend_from_slice(&[5, 0, 0, 0, 0, 0]);
+        phdr.extend_from_slice(&[0; 12]);
+        phdr.extend(name("EOP"));
+        phdr.extend_from_slice(&[0, 0, 0, 0, 1, 0]);
+        phdr.extend_from_slice(&[0; 12]);
+
+        let mut inst = name("Split");
+        inst.extend_from_slice(&0u16.to_le_bytes());
+        inst.extend(name("EOI"));
+        inst.extend_from_slice(&2u16.to_le_bytes());
+
+        let mut shdr = sample_header(0, 100, 20, 80, 60);
+        shdr.extend(sample_header(100, 200, 0, 0, 72));
+        shdr.extend(name("EOS"));
+        shdr.extend_from_slice(&[0; 26]);
+
+        let pdta = list(
+            b"pdta",
+            &[
+                chunk(b"phdr", &phdr),
+                chunk(b"pbag", &bags(&[0, 1])),
+                chunk(b"pmod", &[0; 10]),
+                chunk(b"pgen", &gens(&[(GEN_INSTRUMENT, 0), (0, 0)])),
+                chunk(b"inst", &inst),
+                chunk(b"ibag", &bags(&[0, 3, 5])),
+                chunk(b"imod", &[0; 10]),
+                chunk(
+                    b"igen",
+                    &gens(&[
+                        (GEN_KEY_RANGE, 64 << 8),
+                        (GEN_SAMPLE_MODES, 1),
+                        (GEN_SAMPLE_ID, 0),
+                        (GEN_KEY_RANGE, 65 | (127 << 8)),
+                        (GEN_SAMPLE_ID, 1),
+                        (0, 0),
+                    ]),
+                ),
+                chunk(b"shdr", &shdr),
+            ],
+        );
+        let sdta = list(b"sdta", &[chunk(b"smpl", &pcm)]);
+        let info = list(b"INFO", &[chunk(b"ifil", &[2, 0, 1, 0])]);
+
+        let mut body = b"sfbk".to_vec();
+        body.extend(info);
+        body.extend(sdta);
+        body.extend(pdta);
+        chunk(b"RIFF", &body)
+    }
+
+    #[test]
+    fn test_preset_id_parsing() {
+        assert_eq!(
+            "0:5".parse::<PresetId>().unwrap(),
+            PresetId {
+                bank: 0,
+                program: 5
+            }
+        );
+        assert_eq!(
+            "128:0".parse::<PresetId>().unwrap(),
+            PresetId {
+                bank: 128,
+                program: 0
+            }
+        );
+        assert_eq!("7".parse::<PresetId>().unwrap().program, 7);
+        assert!("piano".parse::<PresetId>().is_err());
+    }
+
+    #[test]
+    fn test_load_and_list_presets() {
+        let soundfont = SoundFont::load_from_bytes(&build_test_soundfont()).unwrap();
+        let presets = soundfont.presets();
+        assert_eq!(presets.len(), 1);
+        assert_eq!(presets[0].name, "Test Piano");
+        assert_eq!(presets[0].id.to_string(), "0:5");
+
+        let preset = soundfont.preset(presets[0].id).unwrap();
+        assert_eq!(preset.region_count(), 2);
+        assert!(soundfont.preset(PresetId::default()).is_none());
+    }
+
+    #[test]
+    fn test_rejects_non_soundfont_data() {
+        assert!(SoundFont::load_from_bytes(b"RIFF\x04\x00\x00\x00WAVE").is_err());
+        assert!(SoundFont::load_from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn test_key_split_and_pitch() {
+        let soundfont = SoundFont::load_from_bytes(&build_test_soundfont()).unwrap();
+        let preset = soundfont
+            .preset(PresetId {
+                bank: 0,
+                program: 5,
+            })
+            .unwrap();
+
+        // Root key plays back at the sample's native rate (100 Hz sample at 100 Hz output)
+        let mut voice = preset.start_voice(60, 100, 100.0).unwrap();
+        assert_eq!(voice.next_sample(), 0.0);
+        assert!((voice.next_sample() - 100.0 / 32768.0).abs() < 1e-6);
+
+        // An octave above the upper zone's root skips every other frame
+        let mut high = preset.start_voice(84, 100, 100.0).unwrap();
+        let first = high.next_sample();
+        let second = high.next_sample();
+        assert!((first - 10000.0 / 32768.0).abs() < 1e-6);
+        assert!((second - 10200.0 / 32768.0).abs() < 1e-6);
+
+        // A detune of an octave doubles the playback step
+        let mut detuned = preset.start_voice(60, 100, 100.0).unwrap();
+        detuned.detune(1200.0);
+        assert!((detuned.layers[0].step - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_looping_and_release() {
+        let soundfont = SoundFont::load_from_bytes(&build_test_soundfont()).unwrap();
+        let preset = soundfont
+            .preset(PresetId {
+                bank: 0,
+                program: 5,
+            })
+            .unwrap();
+
+        // The lower zone loops continuously, so it never finishes
+        let mut looped = preset.start_voice(60, 100, 100.0).unwrap();
+        for _ in 0..1000 {
+            looped.next_sample();
+        }
+        assert!(!looped.is_finished());
+
+        // The upper zone is a one-shot
+        let mut one_shot = preset.start_voice(72, 100, 100.0).unwrap();
+        one_shot.release();
+        for _ in 0..101 {
+            one_shot.next_sample();
+        }
+        assert!(one_shot.is_finished());
+    }
+
+    #[test]
+    fn test_frequency_to_midi_key() {
+        assert_eq!(frequency_to_midi_key(440.0), 69);
+        assert_eq!(frequency_to_midi_key(261.63), 60);
+        assert_eq!(frequency_to_midi_key(0.0), 0);
+    }
+}
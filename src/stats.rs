@@ -0,0 +1,517 @@
+//! Session statistics module
+//!
+//! This module tracks typing and music statistics for the current session:
+//! keys and notes per minute (over a sliding window), the most-played notes,
+//! how often each key was pressed, and the session length. Front ends read them as a serializable snapshot.
+//! Finished sessions are appended to a history file in the user's data
+//! directory, which `codebeats stats` summarizes and `codebeats report`
+//! breaks down per day.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Window over which per-minute rates are measured
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Number of most-played notes included in a snapshot
+const TOP_NOTES: usize = 10;
+
+/// Seconds in a day
+const DAY_SECONDS: u64 = 24 * 60 * 60;
+
+/// Running statistics for one session
+#[derive(Debug, Clone)]
+pub struct SessionStats {
+    started: Instant,
+    total_keys: u64,
+    total_notes: u64,
+    easter_eggs: u64,
+    note_counts: HashMap<String, u64>,
+    key_counts: HashMap<String, u64>,
+    recent_keys: VecDeque<Instant>,
+    recent_notes: VecDeque<Instant>,
+}
+
+/// Statistics at a point in time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsSnapshot {
+    pub session_seconds: f32,
+    pub total_keys: u64,
+    pub total_notes: u64,
+    #[serde(default)]
+    pub easter_eggs: u64,
+    pub keys_per_minute: u32,
+    pub notes_per_minute: u32,
+    /// Most-played notes with their counts, most played first
+    pub top_notes: Vec<(String, u64)>,
+    /// Plays of each note, most played first
+    #[serde(default)]
+    pub note_counts: Vec<(String, u64)>,
+    /// Presses of each key (by mapping name, e.g. "A" or "Space"), most pressed first
+    #[serde(default)]
+    pub key_counts: Vec<(String, u64)>,
+}
+
+impl Default for SessionStats {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+impl SessionStats {
+    pub fn new(started: Instant) -> Self {
+        Self {
+            started,
+            total_keys: 0,
+            total_notes: 0,
+            easter_eggs: 0,
+            note_counts: HashMap::new(),
+            key_counts: HashMap::new(),
+            recent_keys: VecDeque::new(),
+            recent_notes: VecDeque::new(),
+        }
+    }
+
+    /// Record a key press (mapped or not)
+    pub fn record_key(&mut self, key: &str, now: Instant) {
+        self.total_keys += 1;
+        *self.key_counts.entry(key.to_string()).or_insert(0) += 1;
+        self.recent_keys.push_back(now);
+        trim_window(&mut self.recent_keys, now);
+    }
+
+    /// Record a played note (or sample, by key name)
+    pub fn record_note(&mut self, note: &str, now: Instant) {
+        self.total_notes += 1;
+        *self.note_counts.entry(note.to_string()).or_insert(0) += 1;
+        self.recent_notes.push_back(now);
+        trim_window(&mut self.recent_notes, now);
+    }
+
+    /// Record a triggered Easter egg sequence
+    pub fn record_easter_egg(&mut self) {
+        self.easter_eggs += 1;
+    }
+
+    /// Statistics as of `now`
+    pub fn snapshot(&self, now: Instant) -> StatsSnapshot {
+        let note_counts = sorted_counts(&self.note_counts);
+        let top_notes = note_counts.iter().take(TOP_NOTES).cloned().collect();
+
+        StatsSnapshot {
+            session_seconds: now.saturating_duration_since(self.started).as_secs_f32(),
+            total_keys: self.total_keys,
+            total_notes: self.total_notes,
+            easter_eggs: self.easter_eggs,
+            keys_per_minute: count_in_window(&self.recent_keys, now),
+            notes_per_minute: count_in_window(&self.recent_notes, now),
+            top_notes,
+            note_counts,
+            key_counts: sorted_counts(&self.key_counts),
+        }
+    }
+}
+
+/// A finished session as stored in the history file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    /// Session start as seconds since the Unix epoch
+    pub started_at: u64,
+    #[serde(flatten)]
+    pub stats: StatsSnapshot,
+    /// Config the session was started with
+    #[serde(default)]
+    pub config: Option<String>,
+    /// Waveform the session was started with
+    #[serde(default)]
+    pub waveform: Option<String>,
+}
+
+impl SessionRecord {
+    /// Record a session that ends now
+    pub fn ended_now(stats: StatsSnapshot) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Self {
+            started_at: now.saturating_sub(stats.session_seconds as u64),
+            stats,
+            config: None,
+            waveform: None,
+        }
+    }
+}
+
+/// Activity on one day (UTC)
+#[derive(Debug, Clone, PartialEq)]
+pub struct DaySummary {
+    /// Days since the Unix epoch
+    pub day: u64,
+    pub sessions: usize,
+    pub keystrokes: u64,
+    pub minutes: f32,
+    /// Config played longest that day
+    pub top_config: Option<String>,
+    /// Waveform played longest that day
+    pub top_waveform: Option<String>,
+}
+
+/// All recorded sessions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatsHistory {
+    pub sessions: Vec<SessionRecord>,
+}
+
+impl StatsHistory {
+    /// Load the history, treating a missing file as empty
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Save the history, creating its directory if needed
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Combined statistics over every session (rates are left at zero)
+    ///
+    /// Sessions recorded before full note counts were kept only add their top notes.
+    pub fn totals(&self) -> StatsSnapshot {
+        let mut note_counts: HashMap<String, u64> = HashMap::new();
+        let mut key_counts: HashMap<String, u64> = HashMap::new();
+        let mut totals = StatsSnapshot {
+            session_seconds: 0.0,
+            total_keys: 0,
+            total_notes: 0,
+            easter_eggs: 0,
+            keys_per_minute: 0,
+            notes_per_minute: 0,
+            top_notes: Vec::new(),
+            note_counts: Vec::new(),
+            key_counts: Vec::new(),
+        };
+
+        for session in &self.sessions {
+            totals.session_seconds += session.stats.session_seconds;
+            totals.total_keys += session.stats.total_keys;
+            totals.total_notes += session.stats.total_notes;
+            totals.easter_eggs += session.stats.easter_eggs;
+            let session_notes = if session.stats.note_counts.is_empty() {
+                &session.stats.top_notes
+            } else {
+                &session.stats.note_counts
+            };
+            for (note, count) in session_notes {
+                *note_counts.entry(note.clone()).or_insert(0) += count;
+            }
+            for (key, count) in &session.stats.key_counts {
+                *key_counts.entry(key.clone()).or_insert(0) += count;
+            }
+        }
+
+        totals.note_counts = sorted_counts(&note_counts);
+        totals.top_notes = totals.note_counts.iter().take(TOP_NOTES).cloned().collect();
+        totals.key_counts = sorted_counts(&key_counts);
+        totals
+    }
+
+    /// Activity per day, oldest first, for sessions started at or after `since`
+    /// (seconds since the Unix epoch)
+    pub fn daily_summaries(&self, since: u64) -> Vec<DaySummary> {
+        let mut days: BTreeMap<u64, DayTotals> = BTreeMap::new();
+        for session in self.sessions.iter().filter(|s| s.started_at >= since) {
+            let day = session.started_at / DAY_SECONDS;
+            let totals = days.entry(day).or_insert_with(|| DayTotals {
+                summary: DaySummary {
+                    day,
+                    sessions: 0,
+                    keystrokes: 0,
+                    minutes: 0.0,
+                    top_config: None,
+                    top_waveform: None,
+                },
+                config_seconds: HashMap::new(),
+                waveform_seconds: HashMap::new(),
+            });
+            totals.summary.sessions += 1;
+            totals.summary.keystrokes += session.stats.total_keys;
+            totals.summary.minutes += session.stats.session_seconds / 60.0;
+            let seconds = session.stats.session_seconds as u64;
+            if let Some(ref config) = session.config {
+                *totals.config_seconds.entry(config.clone()).or_insert(0) += seconds;
+            }
+            if let Some(ref waveform) = session.waveform {
+                *totals.waveform_seconds.entry(waveform.clone()).or_insert(0) += seconds;
+            }
+        }
+
+        days.into_values()
+            .map(|totals| {
+                let longest = |seconds| {
+                    sorted_counts(seconds)
+                        .into_iter()
+                        .next()
+                        .map(|(name, _)| name)
+                };
+                DaySummary {
+                    top_config: longest(&totals.config_seconds),
+                    top_waveform: longest(&totals.waveform_seconds),
+                    ..totals.summary
+                }
+            })
+            .collect()
+    }
+}
+
+/// A day's summary while it is being added up
+struct DayTotals {
+    summary: DaySummary,
+    /// Seconds played with each config and waveform
+    config_seconds: HashMap<String, u64>,
+    waveform_seconds: HashMap<String, u64>,
+}
+
+/// Parse a length of time such as "7d", "2w" or "12h"
+pub fn parse_period(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid period '{}' (use e.g. 12h, 7d or 2w)", text);
+    let text = text.trim();
+    let unit = text.chars().last().ok_or_else(invalid)?;
+    let count: u64 = text[..text.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| invalid())?;
+    let unit_seconds = match unit {
+        'h' => 60 * 60,
+        'd' => DAY_SECONDS,
+        'w' => 7 * DAY_SECONDS,
+        _ => return Err(invalid()),
+    };
+    Ok(Duration::from_secs(count * unit_seconds))
+}
+
+/// Date of a day since the Unix epoch, as YYYY-MM-DD
+pub fn format_day(day: u64) -> String {
+    // Civil-from-days conversion (Howard Hinnant's algorithm), for days after 1970
+    let z = day + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day_of_month)
+}
+
+/// Default location of the stats history file
+pub fn history_path() -> Option<PathBuf> {
+    let data_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+    };
+    data_dir.map(|dir| dir.join("codebeats").join("stats.json"))
+}
+
+/// Counts sorted most first, then by name
+fn sorted_counts(counts: &HashMap<String, u64>) -> Vec<(String, u64)> {
+    let mut sorted: Vec<(String, u64)> = counts
+        .iter()
+        .map(|(name, count)| (name.clone(), *count))
+        .collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sorted
+}
+
+/// Drop timestamps older than the rate window
+fn trim_window(times: &mut VecDeque<Instant>, now: Instant) {
+    while times
+        .front()
+        .is_some_and(|time| now.saturating_duration_since(*time) > RATE_WINDOW)
+    {
+        times.pop_front();
+    }
+}
+
+/// Count timestamps within the rate window before `now`
+fn count_in_window(times: &VecDeque<Instant>, now: Instant) -> u32 {
+    times
+        .iter()
+        .filter(|time| now.saturating_duration_since(**time) <= RATE_WINDOW)
+        .count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot() {
+        let start = Instant::now();
+        let mut stats = SessionStats::new(start);
+
+        for i in 0..5 {
+            let now = start + Duration::from_secs(i);
+            stats.record_key("A", now);
+            stats.record_note(if i % 2 == 0 { "C4" } else { "E4" }, now);
+        }
+        stats.record_key("F13", start + Duration::from_secs(5)); // unmapped key
+        stats.record_easter_egg();
+
+        let snapshot = stats.snapshot(start + Duration::from_secs(10));
+        assert_eq!(snapshot.session_seconds, 10.0);
+        assert_eq!(snapshot.total_keys, 6);
+        assert_eq!(snapshot.total_notes, 5);
+        assert_eq!(snapshot.easter_eggs, 1);
+        assert_eq!(snapshot.keys_per_minute, 6);
+        assert_eq!(snapshot.notes_per_minute, 5);
+        assert_eq!(
+            snapshot.top_notes,
+            vec![("C4".to_string(), 3), ("E4".to_string(), 2)]
+        );
+        assert_eq!(
+            snapshot.key_counts,
+            vec![("A".to_string(), 5), ("F13".to_string(), 1)]
+        );
+
+        // Rates only count the last minute; totals keep everything
+        let later = stats.snapshot(start + Duration::from_secs(64));
+        assert_eq!(later.keys_per_minute, 2);
+        assert_eq!(later.notes_per_minute, 1);
+        assert_eq!(later.total_keys, 6);
+    }
+
+    #[test]
+    fn test_history() {
+        // Only the first note makes each session's top list
+        let session = |keys: u64, notes: Vec<(&str, u64)>| SessionRecord {
+            started_at: 0,
+            config: None,
+            waveform: None,
+            stats: StatsSnapshot {
+                session_seconds: 60.0,
+                total_keys: keys,
+                total_notes: keys,
+                easter_eggs: 1,
+                keys_per_minute: 0,
+                notes_per_minute: 0,
+                top_notes: vec![(notes[0].0.to_string(), notes[0].1)],
+                note_counts: notes
+                    .into_iter()
+                    .map(|(note, count)| (note.to_string(), count))
+                    .collect(),
+                key_counts: vec![("A".to_string(), keys)],
+            },
+        };
+        let history = StatsHistory {
+            sessions: vec![
+                session(10, vec![("C4", 6), ("E4", 4)]),
+                session(5, vec![("E4", 5)]),
+            ],
+        };
+
+        let totals = history.totals();
+        assert_eq!(totals.session_seconds, 120.0);
+        assert_eq!(totals.total_keys, 15);
+        assert_eq!(totals.easter_eggs, 2);
+        assert_eq!(totals.top_notes[0], ("E4".to_string(), 9));
+        assert_eq!(totals.key_counts, vec![("A".to_string(), 15)]);
+
+        let path =
+            std::env::temp_dir().join(format!("codebeats-stats-{}.json", std::process::id()));
+        history.save_to_file(&path).unwrap();
+        let loaded = StatsHistory::load_from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.sessions, history.sessions);
+
+        assert!(
+            StatsHistory::load_from_file(&path)
+                .unwrap()
+                .sessions
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_daily_summaries() {
+        let session = |started_at, minutes: f32, config: &str, waveform: &str| SessionRecord {
+            started_at,
+            stats: StatsSnapshot {
+                session_seconds: minutes * 60.0,
+                total_keys: 100,
+                total_notes: 90,
+                easter_eggs: 0,
+                keys_per_minute: 0,
+                notes_per_minute: 0,
+                top_notes: Vec::new(),
+                note_counts: Vec::new(),
+                key_counts: Vec::new(),
+            },
+            config: Some(config.to_string()),
+            waveform: Some(waveform.to_string()),
+        };
+        let day = DAY_SECONDS;
+        let history = StatsHistory {
+            sessions: vec![
+                session(day, 30.0, "rust", "natural"),
+                session(10 * day, 10.0, "rust", "natural"),
+                session(10 * day + 3600, 20.0, "python", "bass"),
+                session(11 * day, 5.0, "go", "saw"),
+            ],
+        };
+
+        let summaries = history.daily_summaries(10 * day);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].day, 10);
+        assert_eq!(summaries[0].sessions, 2);
+        assert_eq!(summaries[0].keystrokes, 200);
+        assert_eq!(summaries[0].minutes, 30.0);
+        assert_eq!(summaries[0].top_config.as_deref(), Some("python"));
+        assert_eq!(summaries[0].top_waveform.as_deref(), Some("bass"));
+        assert_eq!(summaries[1].top_config.as_deref(), Some("go"));
+    }
+
+    #[test]
+    fn test_parse_period() {
+        assert_eq!(parse_period("7d"), Ok(Duration::from_secs(7 * DAY_SECONDS)));
+        assert_eq!(
+            parse_period("2w"),
+            Ok(Duration::from_secs(14 * DAY_SECONDS))
+        );
+        assert_eq!(parse_period("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert!(parse_period("7").is_err());
+        assert!(parse_period("d").is_err());
+        assert!(parse_period("7y").is_err());
+        assert!(parse_period("7é").is_err());
+    }
+
+    #[test]
+    fn test_format_day() {
+        assert_eq!(format_day(0), "1970-01-01");
+        assert_eq!(format_day(59), "1970-03-01");
+        assert_eq!(format_day(20_742), "2026-10-16");
+    }
+}
@@ -0,0 +1,545 @@
+//! Terminal UI module
+//!
+//! This module (enabled by the default `tui` feature) runs the engine with a
+//! full-screen terminal display built on ratatui: the currently sounding
+//! notes, a VU meter, the active config and waveform, session stats, recent
+//! log messages, and hotkey hints. Press `q` or `Esc` in the terminal to quit.
+//! It also hosts the rhythm game (`codebeats rhythm`), which scrolls a target
+//! pattern toward a hit line and scores the keys' timing, and the typing
+//! tutor (`codebeats practice`), which sounds each typed character's verdict.
+
+use crate::CodeBeatsEngine;
+use crate::events::EngineEvent;
+use crate::notifications::Notification;
+use crate::rhythm::{BEATS_PER_BAR, Judgement, RhythmGame, RhythmPattern};
+use crate::stats::StatsSnapshot;
+use crate::tutor::{self, TypingTutor};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, List, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often the screen is redrawn
+const FRAME_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often the engine polls the keyboard
+const INPUT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Number of log lines kept for display
+const LOG_LINES: usize = 100;
+
+/// Seconds of upcoming rhythm shown ahead of the hit line
+const LANE_AHEAD: f32 = 3.0;
+
+/// Run the engine with the terminal UI until the user quits, returning the session stats
+pub fn run(mut engine: CodeBeatsEngine) -> Result<StatsSnapshot, Box<dyn std::error::Error>> {
+    // Engine logs go to the log pane instead of stdout
+    let logs: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let logs_clone = logs.clone();
+    engine.set_log_callback(move |message| {
+        if let Ok(mut logs) = logs_clone.lock() {
+            logs.push(message.to_string());
+            let excess = logs.len().saturating_sub(LOG_LINES);
+            logs.drain(..excess);
+        }
+    });
+
+    let events = engine.subscribe_events();
+    engine.start()?;
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut engine, &events, &logs);
+    ratatui::restore();
+
+    engine.stop();
+    result.map(|()| engine.stats())
+}
+
+/// Keys currently sounding, mapped to the note they play
+type SoundingNotes = BTreeMap<String, String>;
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    engine: &mut CodeBeatsEngine,
+    events: &std::sync::mpsc::Receiver<EngineEvent>,
+    logs: &Mutex<Vec<String>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut sounding = SoundingNotes::new();
+    let mut last_frame = Instant::now() - FRAME_INTERVAL;
+
+    loop {
+        if engine.shutdown_requested() || !engine.process_input() {
+            return Ok(());
+        }
+
+        for event in events.try_iter() {
+            match event {
+                EngineEvent::NoteOn { key, note, .. } => {
+                    let note = note.unwrap_or_else(|| "sample".to_string());
+                    sounding.insert(key, note);
+                }
+                EngineEvent::NoteOff { key } => {
+                    sounding.remove(&key);
+                }
+                EngineEvent::EasterEgg { .. } => {}
+            }
+        }
+
+        if last_frame.elapsed() >= FRAME_INTERVAL {
+            let logs = logs.lock().map(|logs| logs.clone()).unwrap_or_default();
+            terminal.draw(|frame| draw(frame, engine, &sounding, &logs))?;
+            last_frame = Instant::now();
+        }
+
+        if event::poll(INPUT_INTERVAL)?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            let ctrl_c =
+                key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, engine: &CodeBeatsEngine, sounding: &SoundingNotes, logs: &[String]) {
+    let config = engine.get_config();
+    let [header, meter, notes, stats, log, hints] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(3),
+        Constraint::Length(4),
+        Constraint::Length(3),
+        Constraint::Min(3),
+        Constraint::Length(4),
+    ])
+    .areas(frame.area());
+
+    let state = if engine.is_paused() {
+        "⏸ paused".yellow()
+    } else if engine.is_muted() {
+        "🔇 muted".yellow()
+    } else {
+        "▶ playing".green()
+    };
+    let title = Line::from(vec![
+        config.keyboard_config.description.clone().bold(),
+        format!(
+            "  •  {}  •  volume {:.0}%  •  ",
+            config.waveform,
+            config.volume * 100.0
+        )
+        .into(),
+        state,
+    ]);
+    frame.render_widget(
+        Paragraph::new(title).block(Block::bordered().title(" CodeBeats ")),
+        header,
+    );
+
+    let level = engine.output_level();
+    let peak = level.peak();
+    let color = match peak {
+        p if p >= 1.0 => Color::Red,
+        p if p >= 0.7 => Color::Yellow,
+        _ => Color::Green,
+    };
+    frame.render_widget(
+        Gauge::default()
+            .block(Block::bordered().title(" Level "))
+            .gauge_style(Style::default().fg(color))
+            .ratio(level.rms().clamp(0.0, 1.0) as f64)
+            .label(format!("rms {:.2}  peak {:.2}", level.rms(), peak)),
+        meter,
+    );
+
+    let notes_text = sounding
+        .iter()
+        .map(|(key, note)| format!("{} → {}", key, note))
+        .collect::<Vec<_>>()
+        .join("   ");
+    frame.render_widget(
+        Paragraph::new(notes_text)
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title(" Sounding ")),
+        notes,
+    );
+
+    let snapshot = engine.stats();
+    let top_note = snapshot
+        .top_notes
+        .first()
+        .map(|(note, count)| format!("{} ({})", note, count))
+        .unwrap_or_else(|| "-".to_string());
+    frame.render_widget(
+        Paragraph::new(format!(
+            "{} keys/min  •  {} notes/min  •  {} keys total  •  top note {}",
+            snapshot.keys_per_minute, snapshot.notes_per_minute, snapshot.total_keys, top_note
+        ))
+        .block(Block::bordered().title(" Stats ")),
+        stats,
+    );
+
+    let visible = log.height.saturating_sub(2) as usize;
+    let start = logs.len().saturating_sub(visible);
+    frame.render_widget(
+        List::new(logs[start..].iter().map(String::as_str)).block(Block::bordered().title(" Log ")),
+        log,
+    );
+
+    let mut hotkeys: Vec<String> = config
+        .hotkeys
+        .iter()
+        .map(|(combo, action)| format!("{}: {}", combo, action))
+        .collect();
+    hotkeys.sort();
+    if let Some(ref combo) = config.pause_hotkey {
+        hotkeys.insert(0, format!("{}: pause", combo));
+    }
+    hotkeys.push("q/Esc: quit".to_string());
+    frame.render_widget(
+        Paragraph::new(hotkeys.join("   "))
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title(" Hotkeys ")),
+        hints,
+    );
+}
+
+/// Play the rhythm game until it ends or the user quits, returning the scored game
+///
+/// A count-in bar of clicks leads in (clicks continue on every beat with
+/// `metronome`); any key that plays a note or sample counts as a press.
+pub fn run_rhythm(
+    mut engine: CodeBeatsEngine,
+    pattern: &RhythmPattern,
+    bpm: f32,
+    bars: u32,
+    metronome: bool,
+) -> Result<RhythmGame, Box<dyn std::error::Error>> {
+    let mut game = RhythmGame::new(pattern, bpm, bars);
+    // Keep engine messages from drawing over the screen
+    engine.set_log_callback(|_| {});
+    let events = engine.subscribe_events();
+    engine.start()?;
+
+    let beats = if metronome {
+        (game.duration() / game.beat_seconds()) as usize
+    } else {
+        BEATS_PER_BAR as usize
+    };
+    for beat in 0..beats {
+        let note = if beat % BEATS_PER_BAR as usize == 0 {
+            "C6"
+        } else {
+            "G5"
+        };
+        let mut click = Notification::melody(&[note], 0.05);
+        click.volume = 0.5;
+        engine.play_melody("click", &click, beat as f32 * game.beat_seconds())?;
+    }
+
+    let mut terminal = ratatui::init();
+    let result = rhythm_loop(&mut terminal, &mut engine, &events, &mut game, pattern, bpm);
+    ratatui::restore();
+
+    engine.stop();
+    result.map(|()| game)
+}
+
+fn rhythm_loop(
+    terminal: &mut DefaultTerminal,
+    engine: &mut CodeBeatsEngine,
+    events: &std::sync::mpsc::Receiver<EngineEvent>,
+    game: &mut RhythmGame,
+    pattern: &RhythmPattern,
+    bpm: f32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let start = Instant::now();
+    // The last press and its judgement (None for a stray press)
+    let mut last_press: Option<Option<Judgement>> = None;
+    let mut last_frame = Instant::now() - FRAME_INTERVAL;
+
+    loop {
+        if engine.shutdown_requested() || !engine.process_input() {
+            return Ok(());
+        }
+
+        let now = start.elapsed().as_secs_f32();
+        for event in events.try_iter() {
+            if matches!(event, EngineEvent::NoteOn { .. }) {
+                last_press = Some(game.press(now));
+            }
+        }
+        game.update(now);
+        // Linger a moment so the last judgement shows
+        if game.is_finished() && now > game.duration() + 1.0 {
+            return Ok(());
+        }
+
+        if last_frame.elapsed() >= FRAME_INTERVAL {
+            terminal.draw(|frame| draw_rhythm(frame, game, pattern, bpm, now, last_press))?;
+            last_frame = Instant::now();
+        }
+
+        if event::poll(INPUT_INTERVAL)?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            let ctrl_c =
+                key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if ctrl_c || key.code == KeyCode::Esc {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn judgement_color(judgement: Option<Judgement>) -> Color {
+    match judgement {
+        None => Color::White,
+        Some(Judgement::Perfect) => Color::Green,
+        Some(Judgement::Good) => Color::Cyan,
+        Some(Judgement::Ok) => Color::Yellow,
+        Some(Judgement::Miss) => Color::Red,
+    }
+}
+
+fn draw_rhythm(
+    frame: &mut Frame,
+    game: &RhythmGame,
+    pattern: &RhythmPattern,
+    bpm: f32,
+    now: f32,
+    last_press: Option<Option<Judgement>>,
+) {
+    let [header, lane, score, hints] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(4),
+        Constraint::Length(3),
+        Constraint::Length(3),
+    ])
+    .areas(frame.area());
+
+    let first_target = game.targets().next().map_or(0.0, |(time, _)| time);
+    let state = if now < first_target {
+        let beats_left = ((first_target - now) / game.beat_seconds()).ceil();
+        format!("count-in {:.0}", beats_left).yellow()
+    } else {
+        "▶ play".green()
+    };
+    frame.render_widget(
+        Paragraph::new(Line::from(vec![
+            format!("{} ({})", pattern.name, pattern.description).bold(),
+            format!("  •  {:.0} bpm  •  ", bpm).into(),
+            state,
+        ]))
+        .block(Block::bordered().title(" Rhythm ")),
+        header,
+    );
+
+    // Columns scroll right to left; targets are hit as they reach the line
+    let width = lane.width.saturating_sub(2) as usize;
+    let hit_column = width / 8;
+    let seconds_per_column = LANE_AHEAD / width.saturating_sub(hit_column).max(1) as f32;
+    let column_of = |time: f32| {
+        let column = hit_column as f32 + (time - now) / seconds_per_column;
+        (column >= 0.0 && column < width as f32).then_some(column as usize)
+    };
+
+    let mut grid = vec![' '; width];
+    let first_beat = (now / game.beat_seconds()).floor() as i64 - 1;
+    let last_beat = ((now + LANE_AHEAD) / game.beat_seconds()).ceil() as i64;
+    for beat in first_beat.max(0)..=last_beat {
+        if let Some(column) = column_of(beat as f32 * game.beat_seconds()) {
+            grid[column] = if beat % BEATS_PER_BAR as i64 == 0 {
+                '|'
+            } else {
+                '·'
+            };
+        }
+    }
+    let mut targets: Vec<Span> = (0..width)
+        .map(|column| if column == hit_column { "│" } else { " " }.into())
+        .collect();
+    for (time, judgement) in game.targets() {
+        if let Some(column) = column_of(time) {
+            targets[column] = Span::styled("●", Style::default().fg(judgement_color(judgement)));
+        }
+    }
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from(grid.into_iter().collect::<String>()).dark_gray(),
+            Line::from(targets),
+        ])
+        .block(Block::bordered()),
+        lane,
+    );
+
+    let last = match last_press {
+        Some(Some(judgement)) => Span::styled(
+            format!("{:?}", judgement).to_uppercase(),
+            Style::default().fg(judgement_color(Some(judgement))).bold(),
+        ),
+        Some(None) => "off beat".red(),
+        None => "-".into(),
+    };
+    frame.render_widget(
+        Paragraph::new(Line::from(vec![
+            format!(
+                "score {}  •  combo {} (best {})  •  accuracy {:.0}%  •  last ",
+                game.score(),
+                game.combo(),
+                game.max_combo(),
+                game.accuracy()
+            )
+            .into(),
+            last,
+        ]))
+        .block(Block::bordered().title(" Score ")),
+        score,
+    );
+
+    frame.render_widget(
+        Paragraph::new("Type any key as each ● reaches the line   Esc: quit")
+            .block(Block::bordered().title(" How to play ")),
+        hints,
+    );
+}
+
+/// Run the typing tutor on a reference text until it is typed or the user quits
+///
+/// Characters are read from the terminal rather than the global keyboard, so
+/// the keys sound only their correct/mistake feedback.
+pub fn run_practice(
+    mut engine: CodeBeatsEngine,
+    text: &str,
+) -> Result<TypingTutor, Box<dyn std::error::Error>> {
+    let mut tutor = TypingTutor::new(text);
+    engine.set_log_callback(|_| {});
+    engine.start()?;
+
+    let mut terminal = ratatui::init();
+    let result = practice_loop(&mut terminal, &mut engine, &mut tutor);
+    ratatui::restore();
+
+    engine.stop();
+    result.map(|()| tutor)
+}
+
+fn practice_loop(
+    terminal: &mut DefaultTerminal,
+    engine: &mut CodeBeatsEngine,
+    tutor: &mut TypingTutor,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let start = Instant::now();
+    loop {
+        if engine.shutdown_requested() {
+            return Ok(());
+        }
+        terminal.draw(|frame| draw_practice(frame, tutor))?;
+        if tutor.is_finished() {
+            // Let the last note ring before leaving the screen
+            std::thread::sleep(Duration::from_millis(400));
+            return Ok(());
+        }
+
+        if !event::poll(FRAME_INTERVAL)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let typed = match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Esc => return Ok(()),
+            KeyCode::Char(c) => c,
+            KeyCode::Enter => '\n',
+            KeyCode::Tab => '\t',
+            _ => continue,
+        };
+
+        let Some(expected) = tutor.expected() else {
+            continue;
+        };
+        let feedback = tutor.type_char(typed, start.elapsed().as_secs_f32());
+        let root = tutor::root_note(expected, &engine.get_config().keyboard_config);
+        engine.play_melody("tutor", &tutor::feedback_melody(feedback, root), 0.0)?;
+    }
+}
+
+fn draw_practice(frame: &mut Frame, tutor: &TypingTutor) {
+    let [text_area, stats, hints] = Layout::vertical([
+        Constraint::Min(3),
+        Constraint::Length(3),
+        Constraint::Length(3),
+    ])
+    .areas(frame.area());
+
+    // Typed text in green, the cursor highlighted (red after a mistake), the rest dimmed
+    let (typed, remaining) = tutor.split();
+    let mut lines: Vec<Line> = vec![Line::default()];
+    let mut push = |text: &str, style: Style| {
+        for (index, part) in text.split('\n').enumerate() {
+            if index > 0 {
+                lines.push(Line::default());
+            }
+            if !part.is_empty() {
+                let line = lines.last_mut().expect("at least one line");
+                line.spans.push(Span::styled(part.to_string(), style));
+            }
+        }
+    };
+    push(&typed, Style::default().fg(Color::Green));
+    let mut remaining = remaining.chars();
+    if let Some(current) = remaining.next() {
+        let cursor_color = if tutor.missed_current() {
+            Color::Red
+        } else {
+            Color::Gray
+        };
+        // Show line breaks and spaces so the cursor stays visible
+        let shown = match current {
+            '\n' => "⏎\n".to_string(),
+            ' ' => "␣".to_string(),
+            '\t' => "⇥".to_string(),
+            c => c.to_string(),
+        };
+        push(
+            &shown,
+            Style::default().fg(Color::Black).bg(cursor_color).bold(),
+        );
+    }
+    push(
+        &remaining.collect::<String>(),
+        Style::default().fg(Color::DarkGray),
+    );
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Practice ")),
+        text_area,
+    );
+
+    frame.render_widget(
+        Paragraph::new(format!(
+            "{:.0} wpm  •  {:.0}% accuracy  •  {} mistakes",
+            tutor.wpm(),
+            tutor.accuracy(),
+            tutor.mistakes()
+        ))
+        .block(Block::bordered().title(" Stats ")),
+        stats,
+    );
+    frame.render_widget(
+        Paragraph::new("Type the highlighted character (indentation is skipped)   Esc: quit")
+            .block(Block::bordered().title(" How to play ")),
+        hints,
+    );
+}
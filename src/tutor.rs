@@ -0,0 +1,188 @@
+//! Typing tutor module
+//!
+//! This module drives `codebeats practice`, an audio-first typing trainer:
+//! the typist copies a reference text, and every key answers with a sound.
+//! A correct key plays its configured note with a perfect fifth above it
+//! (consonant); a wrong key plays the expected note against a minor second
+//! and a tritone (dissonant), and the position stays put until the right
+//! key is typed. Indentation after a line break is skipped automatically.
+
+use crate::keyboard_config::KeyboardConfig;
+use crate::keyboard_mapping::{
+    get_frequency_and_volume_with_config_virtual, midi_to_note, note_to_midi,
+    virtual_keycode_for_char,
+};
+use crate::notifications::Notification;
+
+/// Text practiced when none is given
+pub const DEFAULT_TEXT: &str = "fn main() {\n    let notes = [\"C4\", \"E4\", \"G4\"];\n    for note in notes {\n        println!(\"{}\", note);\n    }\n}";
+
+/// MIDI note used for characters without a mapped note (C4)
+const FALLBACK_ROOT: i32 = 60;
+
+/// How a typed character compared to the reference
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feedback {
+    Correct,
+    Mistake,
+}
+
+/// Progress through a reference text; times are in seconds
+#[derive(Debug, Clone)]
+pub struct TypingTutor {
+    text: Vec<char>,
+    position: usize,
+    correct: u32,
+    mistakes: u32,
+    /// Whether the current character has been mistyped
+    missed_current: bool,
+    first_key: Option<f32>,
+    last_key: f32,
+}
+
+impl TypingTutor {
+    pub fn new(text: &str) -> Self {
+        let text: Vec<char> = text.replace("\r\n", "\n").trim_end().chars().collect();
+        let mut tutor = Self {
+            text,
+            position: 0,
+            correct: 0,
+            mistakes: 0,
+            missed_current: false,
+            first_key: None,
+            last_key: 0.0,
+        };
+        tutor.skip_indentation();
+        tutor
+    }
+
+    /// Skip spaces and tabs at the start of a line
+    fn skip_indentation(&mut self) {
+        let line_start = self.position == 0 || self.text[self.position - 1] == '\n';
+        if line_start {
+            while matches!(self.text.get(self.position), Some(' ' | '\t')) {
+                self.position += 1;
+            }
+        }
+    }
+
+    /// Compare a typed character (`'\n'` for Enter) with the expected one
+    pub fn type_char(&mut self, c: char, time: f32) -> Feedback {
+        self.first_key.get_or_insert(time);
+        self.last_key = time;
+        if self.expected() != Some(c) {
+            self.mistakes += 1;
+            self.missed_current = true;
+            return Feedback::Mistake;
+        }
+
+        self.correct += 1;
+        self.position += 1;
+        self.missed_current = false;
+        self.skip_indentation();
+        Feedback::Correct
+    }
+
+    /// The character to type next, or None when finished
+    pub fn expected(&self) -> Option<char> {
+        self.text.get(self.position).copied()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.text.len()
+    }
+
+    /// The reference text, split at the current position
+    pub fn split(&self) -> (String, String) {
+        (
+            self.text[..self.position].iter().collect(),
+            self.text[self.position..].iter().collect(),
+        )
+    }
+
+    /// Whether the current character has been mistyped
+    pub fn missed_current(&self) -> bool {
+        self.missed_current
+    }
+
+    pub fn mistakes(&self) -> u32 {
+        self.mistakes
+    }
+
+    /// Percentage of keys typed correctly
+    pub fn accuracy(&self) -> f32 {
+        let typed = self.correct + self.mistakes;
+        if typed == 0 {
+            return 100.0;
+        }
+        100.0 * self.correct as f32 / typed as f32
+    }
+
+    /// Words (five characters) per minute between the first and latest key
+    pub fn wpm(&self) -> f32 {
+        let minutes = self
+            .first_key
+            .map_or(0.0, |first| (self.last_key - first) / 60.0);
+        if minutes <= 0.0 {
+            return 0.0;
+        }
+        self.correct as f32 / 5.0 / minutes
+    }
+}
+
+/// MIDI note a character plays in a keyboard config
+pub fn root_note(c: char, config: &KeyboardConfig) -> i32 {
+    virtual_keycode_for_char(c)
+        .and_then(|key| get_frequency_and_volume_with_config_virtual(&key, config))
+        .and_then(|(_, _, note)| note_to_midi(&note))
+        .unwrap_or(FALLBACK_ROOT)
+}
+
+/// Sound answering a key: a fifth when correct, a clashing cluster when not
+pub fn feedback_melody(feedback: Feedback, root: i32) -> Notification {
+    let intervals: &[i32] = match feedback {
+        Feedback::Correct => &[0, 7],
+        Feedback::Mistake => &[0, 1, 6],
+    };
+    let chord: Vec<String> = intervals
+        .iter()
+        .map(|interval| midi_to_note(root + interval))
+        .collect();
+    Notification::melody(&[&chord.join("+")], 0.15)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_char() {
+        let mut tutor = TypingTutor::new("ab\n  c\r\n");
+        assert_eq!(tutor.type_char('a', 0.0), Feedback::Correct);
+        assert_eq!(tutor.type_char('x', 1.0), Feedback::Mistake);
+        assert!(tutor.missed_current());
+        assert_eq!(tutor.expected(), Some('b'));
+        assert_eq!(tutor.type_char('b', 2.0), Feedback::Correct);
+        assert_eq!(tutor.type_char('\n', 3.0), Feedback::Correct);
+        // The indentation is skipped
+        assert_eq!(tutor.split(), ("ab\n  ".to_string(), "c".to_string()));
+        assert_eq!(tutor.type_char('c', 12.0), Feedback::Correct);
+
+        assert!(tutor.is_finished());
+        assert_eq!(tutor.mistakes(), 1);
+        assert_eq!(tutor.accuracy(), 80.0);
+        // 4 characters in 12 seconds
+        assert!((tutor.wpm() - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_feedback_melody() {
+        assert_eq!(feedback_melody(Feedback::Correct, 60).notes, vec!["C4+G4"]);
+        assert_eq!(
+            feedback_melody(Feedback::Mistake, 69).notes,
+            vec!["A4+A#4+D#5"]
+        );
+        assert_eq!(root_note('€', &KeyboardConfig::default()), FALLBACK_ROOT);
+        assert_eq!(root_note('s', &KeyboardConfig::default()), 79);
+    }
+}
@@ -0,0 +1,139 @@
+//! User configuration directory module
+//!
+//! This module finds the configs and settings users keep in the platform's
+//! config directory:
+//! - Linux: `$XDG_CONFIG_HOME/codebeats` (default `~/.config/codebeats`)
+//! - macOS: `~/Library/Application Support/codebeats`
+//! - Windows: `%APPDATA%\codebeats`
+//!
+//! Keyboard configs live in its `configs` subdirectory as JSON or TOML
+//! files and are used by file name. A user config named like an embedded one
+//! is merged onto it, so it only needs to list what it changes; settings files
+//! such as `hotkeys.json` and `profiles.json` sit in the directory itself.
+
+use crate::embedded_configs;
+use crate::keyboard_config::{ConfigFormat, KeyboardConfig};
+use std::fs;
+use std::path::PathBuf;
+
+/// File extensions recognized for user keyboard configs, in lookup order
+const CONFIG_EXTENSIONS: &[&str] = &["json", "toml"];
+
+/// The CodeBeats directory in the platform's config directory
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|dir| dir.join("codebeats"))
+}
+
+/// Directory holding the user's keyboard configs
+pub fn configs_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("configs"))
+}
+
+/// Path of a settings file in the config directory (e.g. "hotkeys.json"), if it exists
+pub fn settings_file(name: &str) -> Option<PathBuf> {
+    config_dir()
+        .map(|dir| dir.join(name))
+        .filter(|path| path.is_file())
+}
+
+/// Path of the user config with the given name, if there is one
+pub fn find_user_config(name: &str) -> Option<PathBuf> {
+    let dir = configs_dir()?;
+    CONFIG_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|path| path.is_file())
+}
+
+/// Names of the user's configs, sorted
+pub fn user_config_names() -> Vec<String> {
+    let Some(entries) = configs_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| CONFIG_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        })
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Check whether a config name refers to an embedded or user config
+pub fn config_exists(name: &str) -> bool {
+    embedded_configs::config_exists(name) || find_user_config(name).is_some()
+}
+
+/// Names of every embedded and user config, sorted
+pub fn all_config_names() -> Vec<String> {
+    let mut names: Vec<String> = embedded_configs::get_config_names()
+        .into_iter()
+        .map(str::to_string)
+        .chain(user_config_names())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Load a config by name, preferring the user's version
+///
+/// A user config that shares its name with an embedded config is merged onto
+/// the embedded one (see `KeyboardConfig::merge_json`).
+pub fn load_config(name: &str) -> Result<KeyboardConfig, Box<dyn std::error::Error>> {
+    let Some(path) = find_user_config(name) else {
+        return embedded_configs::load_config(name);
+    };
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read '{}': {}", path.display(), e))?;
+    let format = ConfigFormat::from_path(&path);
+    let Some(base) = embedded_configs::get_config_source(name) else {
+        let mut config = KeyboardConfig::parse(&content, format)?;
+        if let Some(dir) = path.parent() {
+            config.resolve_sample_paths(dir);
+        }
+        return Ok(config);
+    };
+
+    let overlay = KeyboardConfig::parse_value(&content, format)?;
+    let merged = KeyboardConfig::merge_overlay(base, overlay).map_err(|e| {
+        format!(
+            "Could not merge '{}' onto '{}': {}",
+            path.display(),
+            name,
+            e
+        )
+    })?;
+    let mut config: KeyboardConfig = serde_json::from_value(merged)?;
+    if let Some(dir) = path.parent() {
+        config.resolve_sample_paths(dir);
+    }
+    Ok(config)
+}
+
+/// JSON source of a config by name, with any user override merged in
+pub fn config_source(name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    match find_user_config(name) {
+        Some(_) => Ok(serde_json::to_string_pretty(&load_config(name)?)?),
+        None => embedded_configs::get_config_source(name)
+            .map(str::to_string)
+            .ok_or_else(|| format!("Configuration '{}' not found", name).into()),
+    }
+}
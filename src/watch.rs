@@ -0,0 +1,132 @@
+//! Compiler output sonification module
+//!
+//! This module turns a build's output into sound for `codebeats watch -- <command>`:
+//! each warning or error line plays a short dissonant motif that climbs and
+//! grows louder as diagnostics pile up (errors lower and harsher than
+//! warnings), and a successful build resolves with a dominant-to-tonic cadence.
+//! It recognizes rustc/cargo diagnostics (`warning: ...`, `error[E0308]: ...`)
+//! and the `file:line:col: error: ...` form of gcc, clang and many linters.
+
+use crate::keyboard_mapping::midi_to_note;
+use crate::notifications::Notification;
+
+/// A kind of compiler diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    Warning,
+    Error,
+}
+
+/// Diagnostics after which the motifs stop escalating
+const MAX_TENSION: usize = 12;
+
+/// The kind of diagnostic a line of output starts, if any
+///
+/// Summary lines ("generated 3 warnings", "could not compile") are skipped so
+/// each diagnostic is counted once.
+pub fn parse_diagnostic(line: &str) -> Option<DiagnosticKind> {
+    let line = line.trim_start();
+    let summaries = [
+        ") generated ",
+        "could not compile",
+        "aborting due to",
+        "build failed",
+    ];
+    if summaries.iter().any(|summary| line.contains(summary)) {
+        return None;
+    }
+
+    if line.starts_with("warning:") || line.starts_with("warning[") || line.contains(": warning:") {
+        Some(DiagnosticKind::Warning)
+    } else if line.starts_with("error:") || line.starts_with("error[") || line.contains(": error:")
+    {
+        Some(DiagnosticKind::Error)
+    } else {
+        None
+    }
+}
+
+/// Motif for the `count`th diagnostic of a kind (starting at 1)
+///
+/// Warnings rub a minor second high up; errors add a tritone down low. Each
+/// further diagnostic starts a semitone higher and a little louder.
+pub fn diagnostic_motif(kind: DiagnosticKind, count: usize) -> Notification {
+    let tension = count.clamp(1, MAX_TENSION) as i32 - 1;
+    let (root, intervals, step): (i32, &[i32], f32) = match kind {
+        DiagnosticKind::Warning => (72, &[0, 1], 0.08),
+        DiagnosticKind::Error => (55, &[0, 1, 6], 0.12),
+    };
+    let root = root + tension;
+    let chord: Vec<String> = intervals
+        .iter()
+        .map(|interval| midi_to_note(root + interval))
+        .collect();
+
+    let mut motif = Notification::melody(&[&midi_to_note(root), &chord.join("+")], step);
+    motif.volume = 0.4 + 0.04 * tension as f32;
+    motif
+}
+
+/// Cadence played when the command succeeds
+pub fn resolution_chord() -> Notification {
+    Notification::melody(&["G3+B3+D4+F4", "C3+E4+G4+C5"], 0.3)
+}
+
+/// Seconds a melody takes to play
+pub fn melody_length(melody: &Notification) -> f32 {
+    melody.notes.len() as f32 * melody.step
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_diagnostic() {
+        use DiagnosticKind::*;
+        assert_eq!(
+            parse_diagnostic("warning: unused variable: `x`"),
+            Some(Warning)
+        );
+        assert_eq!(
+            parse_diagnostic("error[E0308]: mismatched types"),
+            Some(Error)
+        );
+        assert_eq!(
+            parse_diagnostic("src/main.c:3:5: error: expected ';'"),
+            Some(Error)
+        );
+        assert_eq!(
+            parse_diagnostic("lib.c:9:1: warning: unused function"),
+            Some(Warning)
+        );
+        assert_eq!(
+            parse_diagnostic("warning: `app` (bin \"app\") generated 2 warnings"),
+            None
+        );
+        assert_eq!(
+            parse_diagnostic(
+                "error: could not compile `app` (bin \"app\") due to 1 previous error"
+            ),
+            None
+        );
+        assert_eq!(parse_diagnostic("   Compiling app v0.1.0"), None);
+        assert_eq!(parse_diagnostic("  --> src/main.rs:2:9"), None);
+    }
+
+    #[test]
+    fn test_diagnostic_motif() {
+        let first = diagnostic_motif(DiagnosticKind::Warning, 1);
+        assert_eq!(first.notes, vec!["C5", "C5+C#5"]);
+        let third = diagnostic_motif(DiagnosticKind::Error, 3);
+        assert_eq!(third.notes, vec!["A3", "A3+A#3+D#4"]);
+        assert!(third.volume > first.volume);
+
+        // Escalation stops at MAX_TENSION
+        assert_eq!(
+            diagnostic_motif(DiagnosticKind::Error, 50),
+            diagnostic_motif(DiagnosticKind::Error, MAX_TENSION)
+        );
+        assert!(resolution_chord().steps().is_ok());
+    }
+}
@@ -6,9 +6,16 @@
 //! - Saw and square waves for electronic music
 //! - Cyberpunk analog synthesizer emulation
 //! - Bass with deep low frequencies
+//! - Harmonic overtone series with a configurable rolloff
 
 use std::f32::consts::PI;
 
+/// Overtone rolloff of `harmonic` when none is given (each overtone at 1/n amplitude)
+pub const DEFAULT_HARMONIC_ROLLOFF: f32 = 1.0;
+
+/// Overtones summed by the harmonic waveform (fewer when they'd pass Nyquist)
+const HARMONIC_OVERTONES: u32 = 16;
+
 /// Available waveform types
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Waveform {
@@ -24,28 +31,44 @@ pub enum Waveform {
     Cyberpunk,
     /// Triangle wave for smooth electronic sound
     Triangle,
-    /// Realistic fart sound synthesis with turbulence and frequency sweeps
+    /// Realistic fart sound synthesis with body resonance and turbulence
     Fart,
     /// Deep bass with rich low frequencies and powerful sub-bass
     Bass,
+    /// Overtone series: the nth harmonic has amplitude 1/n^rolloff, so larger
+    /// rolloffs sound mellower and smaller ones brighter
+    Harmonic { rolloff: f32 },
 }
 
 impl Waveform {
+    /// The next waveform in `get_all_waveforms` order, wrapping around
+    pub fn next(&self) -> Self {
+        let all = get_all_waveforms();
+        let name = self.to_string();
+        let name = name.split(':').next().unwrap_or_default();
+        let index = all.iter().position(|(n, _)| *n == name).unwrap_or(0);
+        all[(index + 1) % all.len()]
+            .0
+            .parse()
+            .unwrap_or(Waveform::Electronic)
+    }
+
     /// Generate a single audio sample for this waveform
     pub fn generate_sample(&self, phase: f32, frequency: f32, sample_rate: f32) -> f32 {
         let base_phase = phase * 2.0 * PI;
 
         match self {
             Waveform::Electronic => self.generate_sine(base_phase),
-            Waveform::Natural => self.generate_natural_piano(phase, base_phase, sample_rate),
+            Waveform::Natural => self.generate_natural_piano(base_phase),
             Waveform::Saw => self.generate_sawtooth(phase),
             Waveform::Square => self.generate_square(phase),
-            Waveform::Cyberpunk => {
-                self.generate_cyberpunk(phase, base_phase, frequency, sample_rate)
-            }
+            Waveform::Cyberpunk => self.generate_cyberpunk(base_phase),
             Waveform::Triangle => self.generate_triangle(phase),
             Waveform::Fart => self.generate_fart(phase, base_phase, frequency, sample_rate),
-            Waveform::Bass => self.generate_bass(phase, base_phase, frequency, sample_rate),
+            Waveform::Bass => self.generate_bass(base_phase),
+            Waveform::Harmonic { rolloff } => {
+                self.generate_harmonic(base_phase, frequency, sample_rate, *rolloff)
+            }
         }
     }
 
@@ -59,6 +82,7 @@ impl Waveform {
             // Clean electronic envelopes
             Waveform::Electronic => (0.005, 0.05, 0.7, 0.2),
             Waveform::Triangle => (0.005, 0.05, 0.7, 0.2),
+            Waveform::Harmonic { .. } => (0.005, 0.05, 0.7, 0.2),
 
             // Sharp attack for electronic percussion
             Waveform::Saw => (0.001, 0.02, 0.6, 0.1),
@@ -78,24 +102,16 @@ impl Waveform {
     }
 
     /// Natural piano with harmonics and vibrato
-    fn generate_natural_piano(&self, phase: f32, base_phase: f32, _sample_rate: f32) -> f32 {
+    ///
+    /// Its vibrato is applied to the note's pitch by the engine (see `modulation`).
+    fn generate_natural_piano(&self, base_phase: f32) -> f32 {
         // Harmonic series for piano-like tone
         let fundamental = base_phase.sin();
         let second_harmonic = (base_phase * 2.0).sin() * 0.3;
         let third_harmonic = (base_phase * 3.0).sin() * 0.2;
         let fourth_harmonic = (base_phase * 4.0).sin() * 0.1;
 
-        // Subtle vibrato
-        let vibrato_rate = 4.5;
-        let vibrato_depth = 0.02;
-        let vibrato = (phase * vibrato_rate * 2.0 * PI).sin() * vibrato_depth;
-        let modulated_fundamental = (base_phase * (1.0 + vibrato)).sin();
-
-        // Mix harmonics with slight vibrato
-        (modulated_fundamental * 0.7 + fundamental * 0.3)
-            + second_harmonic
-            + third_harmonic
-            + fourth_harmonic
+        fundamental + second_harmonic + third_harmonic + fourth_harmonic
     }
 
     /// Sawtooth wave generation
@@ -109,15 +125,9 @@ impl Waveform {
     }
 
     /// Cyberpunk 2049 style analog synthesizer
-    fn generate_cyberpunk(
-        &self,
-        phase: f32,
-        base_phase: f32,
-        frequency: f32,
-        sample_rate: f32,
-    ) -> f32 {
-        let time = phase * sample_rate / frequency;
-
+    ///
+    /// Slow movement comes from the config's global LFO (see `modulation`).
+    fn generate_cyberpunk(&self, base_phase: f32) -> f32 {
         // Multiple analog oscillators with slight detuning
         let osc1 = base_phase.sin();
         let osc2 = (base_phase * 1.003).sin(); // Slightly detuned
@@ -126,21 +136,11 @@ impl Waveform {
         // Sub-oscillator (one octave down)
         let sub_osc = (base_phase * 0.5).sin() * 0.3;
 
-        // LFO modulation
-        let lfo_rate = 0.3;
-        let lfo = (time * lfo_rate * 2.0 * PI).sin();
-        let lfo_mod = lfo * 0.1 + 1.0;
-
         // Mix oscillators
         let mixed = (osc1 + osc2 * 0.8 + osc3 * 0.6) / 2.4 + sub_osc;
 
-        // Apply LFO modulation
-        let modulated = mixed * lfo_mod;
-
         // Soft analog-style clipping
-        let clipped = modulated.tanh() * 0.8;
-
-        clipped
+        mixed.tanh() * 0.8
     }
 
     /// Triangle wave generation
@@ -153,23 +153,24 @@ impl Waveform {
         }
     }
 
-    /// Realistic fart sound synthesis with turbulence and frequency sweeps
+    /// Realistic fart sound synthesis with body resonance and turbulence
     ///
     /// This implementation creates a more realistic fart sound by focusing on:
     /// - Strong fundamental tones in the 40-150Hz range
     /// - Natural harmonic series for organic timbre
     /// - Body cavity resonance simulation
-    /// - Gentle frequency sweeps for natural pitch variations
     /// - Tonal emphasis with minimal filtered turbulence
+    ///
+    /// Slow sweeps and swells come from the modulation matrix (see `modulation`).
     fn generate_fart(&self, phase: f32, base_phase: f32, frequency: f32, sample_rate: f32) -> f32 {
         let time = phase * sample_rate / frequency;
 
         // Force low frequency range (40-150Hz) for realistic fart sounds
-        let fart_freq = (frequency * 0.15).max(40.0).min(150.0);
+        let fart_freq = (frequency * 0.15).clamp(40.0, 150.0);
         let fart_phase = base_phase * fart_freq / frequency;
 
         // 1. Strong fundamental tone (main component)
-        let fundamental = fart_phase.sin() * 0.8;
+        let fundamental = fart_phase.sin();
 
         // 2. Harmonic series for natural timbre
         let harmonic2 = (fart_phase * 2.0).sin() * 0.4;
@@ -179,34 +180,22 @@ impl Waveform {
         // 3. Sub-bass for body resonance
         let sub_bass = (fart_phase * 0.5).sin() * 0.3;
 
-        // 4. Gentle frequency sweep (much more subtle)
-        let sweep_amount = (time * 0.4).sin() * 0.05; // Very small modulation
-        let swept_fundamental = (fart_phase * (1.0 + sweep_amount)).sin() * 0.2;
-
-        // 5. Body cavity formant simulation (resonant filtering effect)
+        // 4. Body cavity formant simulation (resonant filtering effect)
         let formant_freq1 = 80.0; // First formant around 80Hz
         let formant_freq2 = 120.0; // Second formant around 120Hz
         let formant1 = (fart_phase * formant_freq1 / fart_freq).sin() * 0.3;
         let formant2 = (fart_phase * formant_freq2 / fart_freq).sin() * 0.2;
 
-        // 6. Very gentle breath-like texture (much less harsh)
-        let breath_rate = 2.0;
-        let breath_mod = (time * breath_rate).sin() * 0.02 + 0.98; // Subtle amplitude variation
-
-        // 7. Minimal filtered turbulence (not harsh noise)
+        // 5. Minimal filtered turbulence (not harsh noise)
         let turbulence_seed = (time * 50.0).fract();
         let gentle_turbulence = (turbulence_seed * 100.0).sin() * 0.05; // Very quiet
 
         // Mix tonal components (emphasis on harmonics)
-        let tonal_mix =
-            fundamental + harmonic2 + harmonic3 + harmonic4 + sub_bass + swept_fundamental;
+        let tonal_mix = fundamental + harmonic2 + harmonic3 + harmonic4 + sub_bass;
         let formant_mix = tonal_mix + formant1 + formant2;
 
-        // Apply gentle breath modulation
-        let modulated = formant_mix * breath_mod;
-
         // Add minimal turbulence and normalize
-        let final_output = (modulated + gentle_turbulence) * 0.4;
+        let final_output = (formant_mix + gentle_turbulence) * 0.4;
 
         // Apply gentle limiting to prevent harsh peaks
         final_output.tanh()
@@ -218,16 +207,14 @@ impl Waveform {
     /// - Strong fundamental with sub-bass emphasis
     /// - Rich harmonic content for thickness
     /// - Slight saturation for analog warmth
-    /// - Low-frequency modulation for movement
-    fn generate_bass(&self, phase: f32, base_phase: f32, frequency: f32, sample_rate: f32) -> f32 {
-        let time = phase * sample_rate / frequency;
-
-        // Emphasize lower frequencies by shifting frequency down
-        let _bass_freq = frequency * 0.5; // One octave down for deeper bass
+    ///
+    /// Movement comes from the modulation matrix (see `modulation`).
+    fn generate_bass(&self, base_phase: f32) -> f32 {
+        // Emphasize lower frequencies by shifting one octave down
         let bass_phase = base_phase * 0.5;
 
         // 1. Strong fundamental (main bass tone)
-        let fundamental = bass_phase.sin() * 1.0;
+        let fundamental = bass_phase.sin() * 1.3;
 
         // 2. Sub-bass (octave below fundamental) - very important for bass
         let sub_bass = (bass_phase * 0.5).sin() * 0.8;
@@ -240,33 +227,46 @@ impl Waveform {
         // 4. Very low sub-harmonic for rumble (two octaves below)
         let sub_harmonic = (bass_phase * 0.25).sin() * 0.6;
 
-        // 5. Slow LFO for movement and life
-        let lfo_rate = 0.1; // Very slow modulation
-        let lfo = (time * lfo_rate * 2.0 * PI).sin();
-        let lfo_mod = lfo * 0.05 + 1.0; // Subtle amplitude modulation
-
-        // 6. Slight pitch modulation for analog character
-        let pitch_lfo = (time * 0.08 * 2.0 * PI).sin() * 0.002; // Very subtle pitch variation
-        let modulated_fundamental = (bass_phase * (1.0 + pitch_lfo)).sin() * 0.3;
-
-        // 7. Mix all components with emphasis on low end
+        // 5. Mix all components with emphasis on low end
         let bass_mix = fundamental
             + sub_bass
             + sub_harmonic
             + harmonic2 * 0.8
             + harmonic3 * 0.6
-            + harmonic4 * 0.4
-            + modulated_fundamental;
+            + harmonic4 * 0.4;
 
-        // Apply LFO modulation
-        let modulated = bass_mix * lfo_mod;
+        // 6. Gentle saturation for analog warmth (not too much distortion)
+        let saturated = bass_mix.tanh() * 0.9;
 
-        // 8. Gentle saturation for analog warmth (not too much distortion)
-        let saturated = modulated.tanh() * 0.9;
-
-        // 9. Final amplitude scaling - bass should be powerful but not overwhelming
+        // 7. Final amplitude scaling - bass should be powerful but not overwhelming
         saturated * 0.7
     }
+
+    /// Overtone series with amplitudes falling off as 1/n^rolloff
+    ///
+    /// Overtones at or above Nyquist are left out to avoid aliasing, and the
+    /// sum is scaled to the loudness of a sine so rolloffs can be compared.
+    fn generate_harmonic(
+        &self,
+        base_phase: f32,
+        frequency: f32,
+        sample_rate: f32,
+        rolloff: f32,
+    ) -> f32 {
+        let nyquist = sample_rate / 2.0;
+        let mut sum = 0.0;
+        let mut power = 0.0;
+        for n in 1..=HARMONIC_OVERTONES {
+            let n = n as f32;
+            if n > 1.0 && n * frequency >= nyquist {
+                break;
+            }
+            let amplitude = n.powf(-rolloff);
+            sum += (base_phase * n).sin() * amplitude;
+            power += amplitude * amplitude;
+        }
+        sum / power.sqrt()
+    }
 }
 
 impl std::str::FromStr for Waveform {
@@ -282,7 +282,19 @@ impl std::str::FromStr for Waveform {
             "triangle" => Ok(Waveform::Triangle),
             "fart" => Ok(Waveform::Fart),
             "bass" => Ok(Waveform::Bass),
-            _ => Err(format!("Unknown waveform: {}", s)),
+            "harmonic" => Ok(Waveform::Harmonic {
+                rolloff: DEFAULT_HARMONIC_ROLLOFF,
+            }),
+            name => match name.strip_prefix("harmonic:").map(str::parse::<f32>) {
+                Some(Ok(rolloff)) if (0.0..=4.0).contains(&rolloff) => {
+                    Ok(Waveform::Harmonic { rolloff })
+                }
+                Some(_) => Err(format!(
+                    "Invalid harmonic rolloff in '{}' (valid range: 0.0-4.0)",
+                    s
+                )),
+                None => Err(format!("Unknown waveform: {}", s)),
+            },
         }
     }
 }
@@ -298,6 +310,10 @@ impl std::fmt::Display for Waveform {
             Waveform::Triangle => "triangle",
             Waveform::Fart => "fart",
             Waveform::Bass => "bass",
+            Waveform::Harmonic { rolloff } if *rolloff != DEFAULT_HARMONIC_ROLLOFF => {
+                return write!(f, "harmonic:{}", rolloff);
+            }
+            Waveform::Harmonic { .. } => "harmonic",
         };
         write!(f, "{}", name)
     }
@@ -314,6 +330,10 @@ pub fn get_all_waveforms() -> Vec<(&'static str, &'static str)> {
         ("triangle", "Smooth triangular wave"),
         ("fart", "Realistic fart sound synthesis"),
         ("bass", "Deep bass with rich low frequencies"),
+        (
+            "harmonic",
+            "Overtone series (harmonic:<rolloff> sets how fast overtones fade)",
+        ),
     ]
 }
 
@@ -322,6 +342,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::manual_range_contains)]
     fn test_waveform_generation() {
         let waveforms = vec![
             Waveform::Natural,
@@ -332,6 +353,9 @@ mod tests {
             Waveform::Triangle,
             Waveform::Fart,
             Waveform::Bass,
+            Waveform::Harmonic {
+                rolloff: DEFAULT_HARMONIC_ROLLOFF,
+            },
         ];
 
         for waveform in waveforms {
@@ -385,6 +409,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::manual_range_contains)]
     fn test_adsr_params() {
         let waveforms = vec![
             Waveform::Natural,
@@ -395,6 +420,9 @@ mod tests {
             Waveform::Triangle,
             Waveform::Fart,
             Waveform::Bass,
+            Waveform::Harmonic {
+                rolloff: DEFAULT_HARMONIC_ROLLOFF,
+            },
         ];
 
         for waveform in waveforms {
@@ -507,4 +535,59 @@ mod tests {
             "Cyberpunk waveform should have variation between phases"
         );
     }
+
+    #[test]
+    fn test_harmonic_waveform() {
+        assert_eq!(
+            "harmonic:2".parse::<Waveform>().unwrap(),
+            Waveform::Harmonic { rolloff: 2.0 }
+        );
+        assert_eq!(
+            Waveform::Harmonic { rolloff: 2.0 }.to_string(),
+            "harmonic:2"
+        );
+        assert_eq!(
+            "harmonic".parse::<Waveform>().unwrap().to_string(),
+            "harmonic"
+        );
+        assert!("harmonic:9".parse::<Waveform>().is_err());
+        assert!("harmonic:loud".parse::<Waveform>().is_err());
+
+        // A steep rolloff leaves little but the fundamental
+        let steep = Waveform::Harmonic { rolloff: 4.0 };
+        let sine = Waveform::Electronic;
+        for i in 0..8 {
+            let phase = i as f32 / 8.0;
+            let difference = steep.generate_sample(phase, 440.0, 44100.0)
+                - sine.generate_sample(phase, 440.0, 44100.0);
+            assert!(difference.abs() < 0.1, "phase {}: {}", phase, difference);
+        }
+
+        // Near Nyquist only the fundamental remains
+        let flat = Waveform::Harmonic { rolloff: 0.0 };
+        let sample = flat.generate_sample(0.25, 15000.0, 44100.0);
+        assert!((sample - 1.0).abs() < 1e-4, "{}", sample);
+    }
+
+    #[test]
+    fn test_waveform_cycling() {
+        assert_eq!(Waveform::Natural.next(), Waveform::Electronic);
+        assert_eq!(
+            Waveform::Bass.next(),
+            Waveform::Harmonic {
+                rolloff: DEFAULT_HARMONIC_ROLLOFF
+            }
+        );
+        assert_eq!(
+            Waveform::Harmonic { rolloff: 2.0 }.next(),
+            Waveform::Natural
+        );
+
+        // Cycling visits every waveform once
+        let mut waveform = Waveform::Natural;
+        for _ in 0..get_all_waveforms().len() {
+            waveform = waveform.next();
+        }
+        assert_eq!(waveform, Waveform::Natural);
+    }
 }
@@ -0,0 +1,186 @@
+//! Web module
+//!
+//! This module runs CodeBeats in the browser: the synthesis plays through Web
+//! Audio (a `ScriptProcessorNode` pulling blocks from `HeadlessEngine`), and the
+//! page's key events play the keys of an embedded config. Keys are read from
+//! `KeyboardEvent.code`, so they follow key positions like the desktop app.
+//!
+//! Build with `cargo rustc --crate-type cdylib --target wasm32-unknown-unknown
+//! --no-default-features --features web`, run `wasm-bindgen --target web` on the
+//! output and serve `web/index.html` (see README).
+
+use crate::headless::HeadlessEngine;
+use crate::keyboard_mapping::VirtualKeycode;
+use crate::keycode::keycode_for_dom_code;
+use crate::{CodeBeatsConfig, embedded_configs, waveforms::Waveform};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{AudioContext, AudioProcessingEvent, KeyboardEvent, ScriptProcessorNode};
+
+/// Frames rendered per Web Audio callback (smaller reacts faster but may crackle)
+const BUFFER_SIZE: u32 = 1024;
+
+/// CodeBeats playing in a web page
+///
+/// Creating one starts the audio and listens for key events on the window;
+/// browsers only allow audio after a user gesture, so create it from a click
+/// handler.
+#[wasm_bindgen]
+pub struct WebEngine {
+    player: Rc<RefCell<HeadlessEngine>>,
+    context: AudioContext,
+    processor: ScriptProcessorNode,
+    _on_audio: Closure<dyn FnMut(AudioProcessingEvent)>,
+    on_key_down: Closure<dyn FnMut(KeyboardEvent)>,
+    on_key_up: Closure<dyn FnMut(KeyboardEvent)>,
+}
+
+#[wasm_bindgen]
+impl WebEngine {
+    /// Start playing with an embedded config (e.g. "rust") and a waveform (e.g. "natural")
+    #[wasm_bindgen(constructor)]
+    pub fn new(config_name: &str, waveform: &str) -> Result<WebEngine, JsValue> {
+        let waveform: Waveform = waveform
+            .parse()
+            .map_err(|e: String| JsValue::from_str(&e))?;
+        let keyboard_config = embedded_configs::load_config(config_name)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        let context = AudioContext::new()?;
+        let config = CodeBeatsConfig {
+            waveform,
+            ..CodeBeatsConfig::default()
+        };
+        let mut engine = HeadlessEngine::new(&config, context.sample_rate() as u32)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        for warning in engine.set_keyboard_config(keyboard_config) {
+            web_sys::console::warn_1(&JsValue::from_str(&warning));
+        }
+        let player = Rc::new(RefCell::new(engine));
+
+        let processor = context
+            .create_script_processor_with_buffer_size_and_number_of_input_channels_and_number_of_output_channels(
+                BUFFER_SIZE,
+                0,
+                1,
+            )?;
+        let audio_player = player.clone();
+        let mut block = vec![0.0; BUFFER_SIZE as usize];
+        let on_audio =
+            Closure::<dyn FnMut(AudioProcessingEvent)>::new(move |event: AudioProcessingEvent| {
+                audio_player.borrow_mut().process(&mut block);
+                if let Ok(output) = event.output_buffer() {
+                    for channel in 0..output.number_of_channels() {
+                        let _ = output.copy_to_channel(&mut block, channel as i32);
+                    }
+                }
+            });
+        processor.set_onaudioprocess(Some(on_audio.as_ref().unchecked_ref()));
+        processor.connect_with_audio_node(&context.destination())?;
+
+        let key_player = player.clone();
+        let on_key_down = Closure::<dyn FnMut(KeyboardEvent)>::new(move |event: KeyboardEvent| {
+            if !event.repeat() {
+                press(&mut key_player.borrow_mut(), &event.code());
+            }
+        });
+        let key_player = player.clone();
+        let on_key_up = Closure::<dyn FnMut(KeyboardEvent)>::new(move |event: KeyboardEvent| {
+            release(&mut key_player.borrow_mut(), &event.code());
+        });
+        let window = web_sys::window().ok_or_else(|| JsValue::from_str("No window"))?;
+        window.add_event_listener_with_callback("keydown", on_key_down.as_ref().unchecked_ref())?;
+        window.add_event_listener_with_callback("keyup", on_key_up.as_ref().unchecked_ref())?;
+
+        Ok(WebEngine {
+            player,
+            context,
+            processor,
+            _on_audio: on_audio,
+            on_key_down,
+            on_key_up,
+        })
+    }
+
+    /// Names of the embedded configs
+    #[wasm_bindgen(js_name = configNames)]
+    pub fn config_names() -> Vec<String> {
+        embedded_configs::get_config_names()
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    /// Press a key by its `KeyboardEvent.code` (e.g. "KeyA"), for on-screen keys
+    #[wasm_bindgen(js_name = keyDown)]
+    pub fn key_down(&self, code: &str) {
+        press(&mut self.player.borrow_mut(), code);
+    }
+
+    /// Release a key by its `KeyboardEvent.code`
+    #[wasm_bindgen(js_name = keyUp)]
+    pub fn key_up(&self, code: &str) {
+        release(&mut self.player.borrow_mut(), code);
+    }
+
+    /// Switch to another embedded config
+    #[wasm_bindgen(js_name = setConfig)]
+    pub fn set_config(&self, config_name: &str) -> Result<(), JsValue> {
+        let config = embedded_configs::load_config(config_name)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        for warning in self.player.borrow_mut().set_keyboard_config(config) {
+            web_sys::console::warn_1(&JsValue::from_str(&warning));
+        }
+        Ok(())
+    }
+
+    /// Switch the waveform (e.g. "natural", "square", "harmonic:1.5")
+    #[wasm_bindgen(js_name = setWaveform)]
+    pub fn set_waveform(&self, waveform: &str) -> Result<(), JsValue> {
+        let waveform: Waveform = waveform
+            .parse()
+            .map_err(|e: String| JsValue::from_str(&e))?;
+        self.player.borrow_mut().state_mut().set_waveform(waveform);
+        Ok(())
+    }
+
+    /// Set the master volume (0.0 to 1.0)
+    #[wasm_bindgen(js_name = setVolume)]
+    pub fn set_volume(&self, volume: f32) {
+        self.player.borrow_mut().state_mut().set_volume(volume);
+    }
+}
+
+/// Press the key with a `KeyboardEvent.code`
+fn press(engine: &mut HeadlessEngine, code: &str) {
+    if let Some(key) = keycode_for_dom_code(code) {
+        engine.key_down(&VirtualKeycode::Physical(key));
+    }
+}
+
+/// Release the key with a `KeyboardEvent.code`
+fn release(engine: &mut HeadlessEngine, code: &str) {
+    if let Some(key) = keycode_for_dom_code(code) {
+        engine.key_up(&VirtualKeycode::Physical(key));
+    }
+}
+
+impl Drop for WebEngine {
+    fn drop(&mut self) {
+        if let Some(window) = web_sys::window() {
+            let _ = window.remove_event_listener_with_callback(
+                "keydown",
+                self.on_key_down.as_ref().unchecked_ref(),
+            );
+            let _ = window.remove_event_listener_with_callback(
+                "keyup",
+                self.on_key_up.as_ref().unchecked_ref(),
+            );
+        }
+        self.processor.set_onaudioprocess(None);
+        let _ = self.processor.disconnect();
+        let _ = self.context.close();
+    }
+}